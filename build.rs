@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+// Parses every WGSL file in `src/`, so a typo fails `cargo build` instead of the first frame
+fn main() {
+    // The directory too, so an added shader gets parsed
+    println!("cargo:rerun-if-changed=src");
+
+    let mut shaders = Vec::new();
+    find_shaders(Path::new("src"), &mut shaders);
+
+    let mut failed = 0;
+
+    for path in &shaders {
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        let source = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e));
+
+        if let Err(error) = naga::front::wgsl::parse_str(&source) {
+            eprintln!(
+                "{}",
+                error.emit_to_string_with_path(&source, path.display().to_string())
+            );
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        panic!("{} of the WGSL shaders failed to parse", failed);
+    }
+}
+
+fn find_shaders(dir: &Path, shaders: &mut Vec<PathBuf>) {
+    let entries = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {}", dir.display(), e));
+
+    for entry in entries {
+        let path = entry.expect("a readable directory entry").path();
+
+        if path.is_dir() {
+            find_shaders(&path, shaders);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "wgsl")
+        {
+            shaders.push(path);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use wgpuing::{app::App, Vertex};
+
+// A hexagon, as 6 triangles around its center
+fn hexagon(radius: f32) -> Vec<Vertex> {
+    let corner = |i: usize| {
+        let angle = i as f32 * std::f32::consts::TAU / 6.;
+        let (sin, cos) = angle.sin_cos();
+
+        Vertex {
+            position: [cos * radius, sin * radius, 0.],
+            color: [(cos + 1.) / 2., (sin + 1.) / 2., 0.5],
+            tex_coords: [(cos + 1.) / 2., (1. - sin) / 2.],
+        }
+    };
+    let center = Vertex {
+        position: [0., 0., 0.],
+        color: [1., 1., 1.],
+        tex_coords: [0.5, 0.5],
+    };
+
+    (0..6)
+        .flat_map(|i| [center, corner(i), corner(i + 1)])
+        .collect()
+}
+
+// Draws a hexagon instead of the quad, and the untextured geometry with inverted colors.
+// The shader starts from the built-in one, so it keeps its bindings and optional entry points
+fn main() -> Result<(), String> {
+    let shader = wgpuing::DEFAULT_SHADER.replace(
+        "return vec4<f32>(in.color, 1.);",
+        "return vec4<f32>(1. - in.color, 1.);",
+    );
+
+    pollster::block_on(
+        App::new()
+            .with_vertices(&hexagon(0.4))
+            .with_shader(&shader)
+            .run(),
+    )
+}
@@ -0,0 +1,17 @@
+use wgpuing::input_map::{self, InputMap};
+use winit::keyboard::KeyCode;
+
+// Moves the triangle, and the rest of the geometry drawn with the transform, with WASD.
+// Binding them takes them from flying the scene camera, the rest of the default bindings stay
+fn main() -> Result<(), String> {
+    let mut input_map = InputMap::default();
+    input_map.bind(KeyCode::KeyA, input_map::MOVE_LEFT);
+    input_map.bind(KeyCode::KeyD, input_map::MOVE_RIGHT);
+    input_map.bind(KeyCode::KeyW, input_map::MOVE_UP);
+    input_map.bind(KeyCode::KeyS, input_map::MOVE_DOWN);
+
+    pollster::block_on(wgpuing::run_with_config(wgpuing::StateConfig {
+        input_map,
+        ..Default::default()
+    }))
+}
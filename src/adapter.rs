@@ -0,0 +1,110 @@
+/// Which kinds of GPUs are acceptable, finer grained than `wgpu::PowerPreference`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdapterPreference {
+    /// The most wanted first. Adapters of other types are never picked
+    pub device_types: &'static [wgpu::DeviceType],
+    /// Software adapters are slow enough to be mistaken for a hang,
+    /// so they're only picked when allowed, after all the `device_types`
+    pub allow_cpu: bool,
+    /// `HighPerformance` picks a discrete GPU and `LowPower` an integrated one
+    /// before the `device_types` order, where one is acceptable
+    pub power_preference: wgpu::PowerPreference,
+}
+
+impl Default for AdapterPreference {
+    fn default() -> AdapterPreference {
+        AdapterPreference {
+            device_types: &[
+                wgpu::DeviceType::DiscreteGpu,
+                wgpu::DeviceType::IntegratedGpu,
+                wgpu::DeviceType::VirtualGpu,
+                // What GL drivers often report, whatever the GPU
+                wgpu::DeviceType::Other,
+            ],
+            allow_cpu: false,
+            power_preference: wgpu::PowerPreference::None,
+        }
+    }
+}
+
+impl AdapterPreference {
+    // Lower is better. None if the type isn't acceptable at all
+    fn rank(&self, device_type: wgpu::DeviceType) -> Option<usize> {
+        if device_type == wgpu::DeviceType::Cpu && !self.allow_cpu {
+            return None;
+        }
+
+        self.device_types
+            .iter()
+            .position(|&preferred| preferred == device_type)
+            .or((device_type == wgpu::DeviceType::Cpu).then_some(self.device_types.len()))
+    }
+
+    // 0 for the type matching the power preference, sorted before the `rank`
+    fn power_rank(&self, device_type: wgpu::DeviceType) -> usize {
+        match (self.power_preference, device_type) {
+            (wgpu::PowerPreference::HighPerformance, wgpu::DeviceType::DiscreteGpu)
+            | (wgpu::PowerPreference::LowPower, wgpu::DeviceType::IntegratedGpu) => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// The most preferred adapter able to present to `surface`, or None if none is acceptable
+pub fn select_adapter(
+    instance: &wgpu::Instance,
+    surface: &wgpu::Surface,
+    preference: AdapterPreference,
+) -> Option<wgpu::Adapter> {
+    let mut candidates = Vec::new();
+
+    for adapter in instance.enumerate_adapters(wgpu::Backends::all()) {
+        let info = adapter.get_info();
+
+        if !adapter.is_surface_supported(surface) {
+            log::debug!(
+                "Skipping {} ({:?}): can't present to the window",
+                info.name,
+                info.backend
+            );
+            continue;
+        }
+
+        match preference.rank(info.device_type) {
+            Some(rank) => candidates.push((rank, adapter)),
+            None => log::debug!(
+                "Skipping {} ({:?}): {:?} adapters aren't allowed",
+                info.name,
+                info.backend,
+                info.device_type
+            ),
+        }
+    }
+
+    // Stable, so the backends keep the order wgpu enumerates them in
+    candidates.sort_by_key(|(rank, adapter)| {
+        (preference.power_rank(adapter.get_info().device_type), *rank)
+    });
+
+    let (rank, adapter) = candidates.into_iter().next()?;
+    let info = adapter.get_info();
+
+    log::info!(
+        "Using {} ({:?}, {:?}): {}",
+        info.name,
+        info.device_type,
+        info.backend,
+        if preference.power_rank(info.device_type) == 0 {
+            format!("the {:?} power preference", preference.power_preference)
+        } else if rank == 0 {
+            "the most preferred type".to_owned()
+        } else {
+            format!(
+                "no adapter of the more preferred types {:?}",
+                &preference.device_types[..rank.min(preference.device_types.len())]
+            )
+        }
+    );
+
+    Some(adapter)
+}
@@ -0,0 +1,45 @@
+/// Picks how the window is composited with what's behind it
+pub struct AlphaModeSelector;
+
+impl AlphaModeSelector {
+    /// A mode blending the window with the desktop when `want_transparent` and the surface supports one,
+    /// premultiplied being preferred. Otherwise `Opaque`, or whatever the surface does if it can't be opaque
+    pub fn preferred(
+        caps: &wgpu::SurfaceCapabilities,
+        want_transparent: bool,
+    ) -> wgpu::CompositeAlphaMode {
+        let transparent_modes: &[wgpu::CompositeAlphaMode] = if want_transparent {
+            &[
+                wgpu::CompositeAlphaMode::PreMultiplied,
+                wgpu::CompositeAlphaMode::PostMultiplied,
+            ]
+        } else {
+            &[]
+        };
+
+        let mode = transparent_modes
+            .iter()
+            .chain(&[wgpu::CompositeAlphaMode::Opaque])
+            .find(|mode| caps.alpha_modes.contains(mode))
+            .copied()
+            .unwrap_or(caps.alpha_modes[0]);
+
+        if want_transparent && !Self::is_transparent(mode) {
+            log::warn!(
+                "The surface can't be transparent, using {:?}. Supported modes: {:?}",
+                mode,
+                caps.alpha_modes
+            );
+        }
+
+        mode
+    }
+
+    /// Whether the alpha written to the surface shows what's behind the window
+    pub fn is_transparent(mode: wgpu::CompositeAlphaMode) -> bool {
+        matches!(
+            mode,
+            wgpu::CompositeAlphaMode::PreMultiplied | wgpu::CompositeAlphaMode::PostMultiplied
+        )
+    }
+}
@@ -0,0 +1,287 @@
+use std::time::Duration;
+
+use crate::resources::AtlasId;
+use crate::texture_atlas::UvRect;
+
+/// What happens after the last frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlaybackMode {
+    /// Starts over from the first frame
+    #[default]
+    Loop,
+    /// Plays backwards to the first frame, then forwards again
+    PingPong,
+}
+
+/// Plays a sprite sheet: a texture split into a grid of equally sized frames,
+/// read left to right, then top to bottom.
+/// The frame only depends on the time played, not on how many frames were rendered
+#[derive(Clone, Debug)]
+pub struct AnimatedSprite {
+    columns: u32,
+    rows: u32,
+    frames_per_second: f32,
+    mode: PlaybackMode,
+    // Seconds, in f64 so the frames don't drift after a long time
+    elapsed: f64,
+}
+
+impl AnimatedSprite {
+    pub fn new(
+        columns: u32,
+        rows: u32,
+        frames_per_second: f32,
+        mode: PlaybackMode,
+    ) -> AnimatedSprite {
+        assert!(
+            columns > 0 && rows > 0,
+            "the sprite sheet has no frames: {}x{}",
+            columns,
+            rows
+        );
+
+        AnimatedSprite {
+            columns,
+            rows,
+            frames_per_second,
+            mode,
+            elapsed: 0.,
+        }
+    }
+
+    pub fn frames_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    pub fn frames_per_second(&self) -> f32 {
+        self.frames_per_second
+    }
+
+    /// The current frame is kept, only the following ones come faster or slower
+    pub fn set_frames_per_second(&mut self, frames_per_second: f32) {
+        let frame_time = self.elapsed * self.frames_per_second as f64;
+
+        self.frames_per_second = frames_per_second;
+        self.elapsed = if frames_per_second > 0. {
+            frame_time / frames_per_second as f64
+        } else {
+            0.
+        };
+    }
+
+    /// Moves the playback forward by the time the last frame took
+    pub fn advance(&mut self, dt: Duration) {
+        self.elapsed += dt.as_secs_f64();
+    }
+
+    pub fn restart(&mut self) {
+        self.elapsed = 0.;
+    }
+
+    /// Index of the frame in the sheet
+    pub fn frame(&self) -> u32 {
+        let frames_count = self.frames_count() as u64;
+        let step = (self.elapsed * self.frames_per_second as f64).max(0.) as u64;
+
+        let frame = match self.mode {
+            PlaybackMode::Loop => step % frames_count,
+            // E.g. 0 1 2 3 2 1 0 1 for 4 frames, the ends aren't shown twice
+            PlaybackMode::PingPong if frames_count > 1 => {
+                let period = 2 * (frames_count - 1);
+                let position = step % period;
+
+                position.min(period - position)
+            }
+            PlaybackMode::PingPong => 0,
+        };
+
+        frame as u32
+    }
+
+    /// The current frame in the sheet's UVs
+    pub fn uv_rect(&self) -> UvRect {
+        let frame = self.frame();
+        let scale = [1. / self.columns as f32, 1. / self.rows as f32];
+
+        UvRect {
+            offset: [
+                (frame % self.columns) as f32 * scale[0],
+                (frame / self.columns) as f32 * scale[1],
+            ],
+            scale,
+        }
+    }
+}
+
+/// An animation made of regions of a texture atlas, in any order and of any size
+#[derive(Clone, Debug)]
+pub struct AtlasedSprite {
+    pub atlas: AtlasId,
+    pub frames: Vec<UvRect>,
+    pub fps: f32,
+    /// Stops on the last frame otherwise
+    pub looping: bool,
+}
+
+/// Plays an `AtlasedSprite`, frame by frame.
+/// Unlike `AnimatedSprite`, it can be paused and moved to any frame
+#[derive(Clone, Debug)]
+pub struct SpriteAnimator {
+    sprite: AtlasedSprite,
+    current_frame: usize,
+    // Seconds into the current frame
+    elapsed: f32,
+    playing: bool,
+}
+
+impl SpriteAnimator {
+    /// Playing from the first frame
+    pub fn new(sprite: AtlasedSprite) -> SpriteAnimator {
+        assert!(!sprite.frames.is_empty(), "the sprite has no frames");
+
+        SpriteAnimator {
+            sprite,
+            current_frame: 0,
+            elapsed: 0.,
+            playing: true,
+        }
+    }
+
+    pub fn sprite(&self) -> &AtlasedSprite {
+        &self.sprite
+    }
+
+    /// A sprite that isn't looping and has finished starts over
+    pub fn play(&mut self) {
+        if !self.sprite.looping && self.current_frame + 1 == self.sprite.frames.len() {
+            self.set_frame(0);
+        }
+
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn current_frame(&self) -> usize {
+        self.current_frame
+    }
+
+    /// Shown for the whole frame time, from now
+    pub fn set_frame(&mut self, frame: usize) {
+        assert!(
+            frame < self.sprite.frames.len(),
+            "the sprite has {} frames",
+            self.sprite.frames.len()
+        );
+
+        self.current_frame = frame;
+        self.elapsed = 0.;
+    }
+
+    /// Moves the playback forward by `dt` seconds, returning the frame to draw
+    pub fn update(&mut self, dt: f32) -> UvRect {
+        if self.playing && self.sprite.fps > 0. {
+            let frame_time = 1. / self.sprite.fps;
+            self.elapsed += dt;
+
+            // A long frame may skip several of the sprite's ones
+            let steps = (self.elapsed / frame_time) as usize;
+            self.elapsed -= steps as f32 * frame_time;
+
+            let frames_count = self.sprite.frames.len();
+            let frame = self.current_frame + steps;
+
+            if self.sprite.looping {
+                self.current_frame = frame % frames_count;
+            } else if frame + 1 >= frames_count {
+                self.current_frame = frames_count - 1;
+                self.elapsed = 0.;
+                self.playing = false;
+            } else {
+                self.current_frame = frame;
+            }
+        }
+
+        self.sprite.frames[self.current_frame]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 4 frames at 10 per second, a frame every 100ms
+    fn sprite(mode: PlaybackMode) -> AnimatedSprite {
+        AnimatedSprite::new(2, 2, 10., mode)
+    }
+
+    // The frames at the middle of each of the next `count` frame times
+    fn frames(sprite: &mut AnimatedSprite, count: usize) -> Vec<u32> {
+        sprite.advance(Duration::from_millis(50));
+
+        (0..count)
+            .map(|_| {
+                let frame = sprite.frame();
+                sprite.advance(Duration::from_millis(100));
+                frame
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_wraps_after_the_last_frame() {
+        let mut sprite = sprite(PlaybackMode::Loop);
+
+        assert_eq!(frames(&mut sprite, 6), [0, 1, 2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn ping_pong_turns_at_both_ends() {
+        let mut sprite = sprite(PlaybackMode::PingPong);
+
+        assert_eq!(frames(&mut sprite, 9), [0, 1, 2, 3, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn ping_pong_of_a_single_frame_stays_on_it() {
+        let mut sprite = AnimatedSprite::new(1, 1, 10., PlaybackMode::PingPong);
+
+        assert_eq!(frames(&mut sprite, 3), [0, 0, 0]);
+    }
+
+    #[test]
+    fn frames_are_read_left_to_right_then_top_to_bottom() {
+        let mut sprite = sprite(PlaybackMode::Loop);
+        sprite.advance(Duration::from_millis(50));
+
+        let offsets = (0..4)
+            .map(|_| {
+                let offset = sprite.uv_rect().offset;
+                sprite.advance(Duration::from_millis(100));
+                offset
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(offsets, [[0., 0.], [0.5, 0.], [0., 0.5], [0.5, 0.5]]);
+        assert_eq!(sprite.uv_rect().scale, [0.5, 0.5]);
+    }
+
+    #[test]
+    fn changing_the_speed_keeps_the_current_frame() {
+        let mut sprite = sprite(PlaybackMode::Loop);
+        sprite.advance(Duration::from_millis(250));
+        assert_eq!(sprite.frame(), 2);
+
+        sprite.set_frames_per_second(20.);
+        assert_eq!(sprite.frame(), 2);
+        // The next frame comes after 25ms at the new speed
+        sprite.advance(Duration::from_millis(30));
+        assert_eq!(sprite.frame(), 3);
+    }
+}
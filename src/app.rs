@@ -0,0 +1,51 @@
+use crate::{MeshData, StateConfig, Vertex};
+
+/// The window and its renderer, set up a piece at a time, e.g.
+/// `App::new().with_vertices(&vertices).with_shader(wgsl).run().await`.
+/// Without any, it draws the same as `run`. To drive a `State` from an event loop of your own,
+/// create it with a `StateBuilder` instead
+#[derive(Clone, Debug, Default)]
+pub struct App {
+    config: StateConfig,
+}
+
+impl From<StateConfig> for App {
+    fn from(config: StateConfig) -> App {
+        App { config }
+    }
+}
+
+impl App {
+    pub fn new() -> App {
+        App::default()
+    }
+
+    /// Replaces the mesh with a triangle list, every triangle being 3 consecutive vertices
+    pub fn with_vertices(self, vertices: &[Vertex]) -> App {
+        self.with_mesh(MeshData {
+            vertices: vertices.to_vec(),
+            indices: None,
+        })
+    }
+
+    pub fn with_mesh(mut self, mesh: MeshData) -> App {
+        self.config.mesh = mesh;
+        self
+    }
+
+    /// WGSL replacing `DEFAULT_SHADER`, see `StateConfig::shader` for the entry points it needs.
+    /// Checked when the app starts, `run` fails if it doesn't compile or its bindings differ
+    pub fn with_shader(mut self, wgsl: &str) -> App {
+        self.config.shader = Some(wgsl.to_owned());
+        self
+    }
+
+    pub fn config(&self) -> &StateConfig {
+        &self.config
+    }
+
+    /// Opens the window and renders until it's closed
+    pub async fn run(self) -> Result<(), String> {
+        crate::run_with_config(self.config).await
+    }
+}
@@ -0,0 +1,301 @@
+use crate::bindable::Bindable;
+use crate::shader_reflection::{ReflectedBinding, ShaderReflection};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BindGroupError {
+    /// The shader has nothing at the slot
+    UnknownBinding {
+        group: u32,
+        binding: u32,
+    },
+    /// The resource is of a different kind than the shader expects
+    MismatchedType {
+        binding: u32,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The buffer lacks the usage its binding needs
+    MissingUsage {
+        binding: u32,
+        usage: wgpu::BufferUsages,
+    },
+    /// The buffer is smaller than the shader type
+    BufferTooSmall {
+        binding: u32,
+        size: u64,
+        min_size: u64,
+    },
+    DuplicateBinding {
+        binding: u32,
+    },
+    MissingBinding {
+        binding: u32,
+    },
+}
+
+impl std::fmt::Display for BindGroupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindGroupError::UnknownBinding { group, binding } => write!(
+                f,
+                "the shader has no @group({}) @binding({})",
+                group, binding
+            ),
+            BindGroupError::MismatchedType {
+                binding,
+                expected,
+                found,
+            } => write!(
+                f,
+                "binding {} expects a {}, but got a {}",
+                binding, expected, found
+            ),
+            BindGroupError::MissingUsage { binding, usage } => write!(
+                f,
+                "the buffer at binding {} needs the {:?} usage",
+                binding, usage
+            ),
+            BindGroupError::BufferTooSmall {
+                binding,
+                size,
+                min_size,
+            } => write!(
+                f,
+                "the buffer at binding {} has {} bytes, but the shader reads {}",
+                binding, size, min_size
+            ),
+            BindGroupError::DuplicateBinding { binding } => {
+                write!(f, "binding {} is bound twice", binding)
+            }
+            BindGroupError::MissingBinding { binding } => {
+                write!(f, "nothing is bound to binding {}", binding)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BindGroupError {}
+
+/// Creates a bind group checking the resources against what the shader declares.
+/// The first mismatch is reported by `build`, so the calls can be chained
+pub struct BindGroupBuilder<'a> {
+    device: &'a wgpu::Device,
+    reflection: &'a ShaderReflection,
+    group: u32,
+    entries: Vec<wgpu::BindGroupEntry<'a>>,
+    error: Option<BindGroupError>,
+}
+
+impl<'a> BindGroupBuilder<'a> {
+    /// Builds `@group(0)`, see `for_group`
+    pub fn from_reflection(
+        reflection: &'a ShaderReflection,
+        device: &'a wgpu::Device,
+    ) -> BindGroupBuilder<'a> {
+        BindGroupBuilder {
+            device,
+            reflection,
+            group: 0,
+            entries: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn for_group(mut self, group: u32) -> BindGroupBuilder<'a> {
+        self.group = group;
+        self
+    }
+
+    /// The whole buffer
+    pub fn bind_buffer(self, slot: u32, buffer: &'a wgpu::Buffer) -> BindGroupBuilder<'a> {
+        self.bind_buffer_binding(slot, buffer.as_entire_buffer_binding())
+    }
+
+    /// A range of a buffer
+    pub fn bind_buffer_binding(
+        self,
+        slot: u32,
+        binding: wgpu::BufferBinding<'a>,
+    ) -> BindGroupBuilder<'a> {
+        let buffer = binding.buffer;
+        let size = binding
+            .size
+            .map_or(buffer.size().saturating_sub(binding.offset), |size| {
+                size.get()
+            });
+
+        self.bind(
+            slot,
+            "buffer",
+            wgpu::BindingResource::Buffer(binding),
+            |expected| {
+                let wgpu::BindingType::Buffer {
+                    ty,
+                    min_binding_size,
+                    ..
+                } = expected.ty
+                else {
+                    return Err(None);
+                };
+
+                let usage = match ty {
+                    wgpu::BufferBindingType::Uniform => wgpu::BufferUsages::UNIFORM,
+                    wgpu::BufferBindingType::Storage { .. } => wgpu::BufferUsages::STORAGE,
+                };
+                if !buffer.usage().contains(usage) {
+                    return Err(Some(BindGroupError::MissingUsage {
+                        binding: slot,
+                        usage,
+                    }));
+                }
+
+                if let Some(min_size) = min_binding_size {
+                    if size < min_size.get() {
+                        return Err(Some(BindGroupError::BufferTooSmall {
+                            binding: slot,
+                            size,
+                            min_size: min_size.get(),
+                        }));
+                    }
+                }
+
+                Ok(())
+            },
+        )
+    }
+
+    /// A sampled or a storage texture
+    pub fn bind_texture(self, slot: u32, view: &'a wgpu::TextureView) -> BindGroupBuilder<'a> {
+        self.bind(
+            slot,
+            "texture",
+            wgpu::BindingResource::TextureView(view),
+            |expected| match expected.ty {
+                wgpu::BindingType::Texture { .. } | wgpu::BindingType::StorageTexture { .. } => {
+                    Ok(())
+                }
+                _ => Err(None),
+            },
+        )
+    }
+
+    pub fn bind_sampler(self, slot: u32, sampler: &'a wgpu::Sampler) -> BindGroupBuilder<'a> {
+        self.bind(
+            slot,
+            "sampler",
+            wgpu::BindingResource::Sampler(sampler),
+            |expected| match expected.ty {
+                wgpu::BindingType::Sampler(_) => Ok(()),
+                _ => Err(None),
+            },
+        )
+    }
+
+    /// Anything `Bindable`, checked like the resources of the other `bind_` methods
+    pub fn bind_resource(self, slot: u32, resource: &'a impl Bindable) -> BindGroupBuilder<'a> {
+        match resource.binding_resource() {
+            wgpu::BindingResource::Buffer(binding) => self.bind_buffer_binding(slot, binding),
+            wgpu::BindingResource::TextureView(view) => self.bind_texture(slot, view),
+            wgpu::BindingResource::Sampler(sampler) => self.bind_sampler(slot, sampler),
+            other => {
+                let found = binding_kind(&resource.binding_type());
+
+                self.bind(slot, found, other, |expected| {
+                    if binding_kind(&expected.ty) == found {
+                        Ok(())
+                    } else {
+                        Err(None)
+                    }
+                })
+            }
+        }
+    }
+
+    /// `layout` must be the one the pipeline was created with,
+    /// e.g. from `ShaderReflection::create_bind_group_layout`
+    pub fn build(self, layout: &wgpu::BindGroupLayout) -> Result<wgpu::BindGroup, BindGroupError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        if let Some(missing) = self.group_bindings().find(|expected| {
+            !self
+                .entries
+                .iter()
+                .any(|entry| entry.binding == expected.binding)
+        }) {
+            return Err(BindGroupError::MissingBinding {
+                binding: missing.binding,
+            });
+        }
+
+        Ok(self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My reflected bind group"),
+            layout,
+            entries: &self.entries,
+        }))
+    }
+
+    fn group_bindings(&self) -> impl Iterator<Item = &'a ReflectedBinding> {
+        let group = self.group;
+
+        self.reflection
+            .bindings()
+            .iter()
+            .filter(move |binding| binding.group == group)
+    }
+
+    // `check` returns `Err(None)` when the kind of the resource doesn't match
+    fn bind(
+        mut self,
+        slot: u32,
+        found: &'static str,
+        resource: wgpu::BindingResource<'a>,
+        check: impl FnOnce(&ReflectedBinding) -> Result<(), Option<BindGroupError>>,
+    ) -> BindGroupBuilder<'a> {
+        if self.error.is_some() {
+            return self;
+        }
+
+        let Some(expected) = self
+            .group_bindings()
+            .find(|binding| binding.binding == slot)
+        else {
+            self.error = Some(BindGroupError::UnknownBinding {
+                group: self.group,
+                binding: slot,
+            });
+            return self;
+        };
+
+        if self.entries.iter().any(|entry| entry.binding == slot) {
+            self.error = Some(BindGroupError::DuplicateBinding { binding: slot });
+            return self;
+        }
+
+        match check(expected) {
+            Ok(()) => self.entries.push(wgpu::BindGroupEntry {
+                binding: slot,
+                resource,
+            }),
+            Err(error) => {
+                self.error = Some(error.unwrap_or(BindGroupError::MismatchedType {
+                    binding: slot,
+                    expected: binding_kind(&expected.ty),
+                    found,
+                }))
+            }
+        }
+
+        self
+    }
+}
+
+fn binding_kind(ty: &wgpu::BindingType) -> &'static str {
+    match ty {
+        wgpu::BindingType::Buffer { .. } => "buffer",
+        wgpu::BindingType::Sampler(_) => "sampler",
+        wgpu::BindingType::Texture { .. } | wgpu::BindingType::StorageTexture { .. } => "texture",
+        wgpu::BindingType::AccelerationStructure => "acceleration structure",
+    }
+}
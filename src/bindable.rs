@@ -0,0 +1,152 @@
+use crate::storage_buffer::StorageBuffer;
+use crate::texture::Texture;
+use crate::uniform_buffer::UniformBuffer;
+
+/// A resource that knows how it's bound, for `BindGroupBuilder::bind_resource`
+/// and to declare bind group layouts without spelling out the binding types
+pub trait Bindable {
+    fn binding_resource(&self) -> wgpu::BindingResource<'_>;
+
+    fn binding_type(&self) -> wgpu::BindingType;
+
+    fn layout_entry(
+        &self,
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: self.binding_type(),
+            count: None,
+        }
+    }
+}
+
+impl<T: bytemuck::Pod> Bindable for UniformBuffer<T> {
+    fn binding_resource(&self) -> wgpu::BindingResource<'_> {
+        self.binding()
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+        }
+    }
+}
+
+impl<T: bytemuck::Pod> Bindable for StorageBuffer<T> {
+    fn binding_resource(&self) -> wgpu::BindingResource<'_> {
+        self.binding()
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+        }
+    }
+}
+
+/// Sampled, not as a storage texture. Depth stencil textures are sampled for their depth
+impl Bindable for Texture {
+    fn binding_resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::TextureView(&self.view)
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        let view_dimension = match (
+            self.texture.dimension(),
+            self.texture.depth_or_array_layers(),
+        ) {
+            (wgpu::TextureDimension::D1, _) => wgpu::TextureViewDimension::D1,
+            (wgpu::TextureDimension::D2, 1) => wgpu::TextureViewDimension::D2,
+            (wgpu::TextureDimension::D2, _) => wgpu::TextureViewDimension::D2Array,
+            (wgpu::TextureDimension::D3, _) => wgpu::TextureViewDimension::D3,
+        };
+
+        // The aspect of the view, see `Texture::new`
+        let format = self.texture.format();
+        let aspect = format
+            .has_depth_aspect()
+            .then_some(wgpu::TextureAspect::DepthOnly);
+
+        wgpu::BindingType::Texture {
+            sample_type: format
+                .sample_type(aspect, None)
+                .expect("the aspect of the view can be sampled"),
+            view_dimension,
+            multisampled: self.texture.sample_count() > 1,
+        }
+    }
+}
+
+/// A filtering sampler. Samplers don't tell whether they compare or filter,
+/// non-filtering and comparison ones need their layout entry written by hand
+impl Bindable for wgpu::Sampler {
+    fn binding_resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Sampler(self)
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_stencil_texture_binds_its_depth() {
+        let Some((device, _)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        let texture = Texture::new(
+            &device,
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("My test depth stencil texture"),
+                size: wgpu::Extent3d {
+                    width: 4,
+                    height: 4,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Depth24PlusStencil8,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            }),
+        );
+
+        assert_eq!(
+            texture.binding_type(),
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Depth,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            }
+        );
+
+        // Both aspects in the view would be a validation error, panicking here
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My test layout"),
+            entries: &[texture.layout_entry(0, wgpu::ShaderStages::FRAGMENT)],
+        });
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My test bind group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: texture.binding_resource(),
+            }],
+        });
+        device.poll(wgpu::Maintain::Wait);
+    }
+}
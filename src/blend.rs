@@ -0,0 +1,58 @@
+/// How the fragments are combined with what's already in the target
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BlendMode {
+    #[default]
+    Replace,
+    Alpha,
+    /// Adds the fragment weighted by its alpha, for glows and light
+    Additive,
+    /// Blends by a fixed opacity instead of the fragment's alpha.
+    /// The opacity is the blend constant, set for the pass rather than baked into the pipeline
+    ConstantAlpha(f32),
+}
+
+impl BlendMode {
+    pub fn state(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Replace => wgpu::BlendState::REPLACE,
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+            BlendMode::ConstantAlpha(_) => {
+                let component = wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Constant,
+                    dst_factor: wgpu::BlendFactor::OneMinusConstant,
+                    operation: wgpu::BlendOperation::Add,
+                };
+
+                wgpu::BlendState {
+                    color: component,
+                    alpha: component,
+                }
+            }
+        }
+    }
+
+    /// What `set_blend_constant` must be called with before drawing, for the modes using it
+    pub fn constant(self) -> Option<wgpu::Color> {
+        match self {
+            BlendMode::ConstantAlpha(alpha) => {
+                let alpha = alpha.clamp(0., 1.) as f64;
+
+                Some(wgpu::Color {
+                    r: alpha,
+                    g: alpha,
+                    b: alpha,
+                    a: alpha,
+                })
+            }
+            _ => None,
+        }
+    }
+}
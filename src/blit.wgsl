@@ -0,0 +1,34 @@
+// Draws a render target over the whole viewport, as is or through an effect
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// A single triangle covering the whole viewport
+@vertex fn vs_main(
+    @builtin(vertex_index) vertex_index: u32
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2. - 1., 0., 1.);
+    out.uv = vec2<f32>(uv.x, 1. - uv.y);
+
+    return out;
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+@fragment fn fs_copy(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(source_texture, source_sampler, in.uv);
+}
+
+// Rec. 709 luma, of the linear color
+@fragment fn fs_grayscale(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, source_sampler, in.uv);
+    let luma = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+
+    return vec4<f32>(vec3<f32>(luma), color.a);
+}
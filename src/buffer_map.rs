@@ -0,0 +1,54 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// Resolves once `buffer` is mapped, to a view of all of it.
+/// The device is polled without blocking every time the future is polled,
+/// so awaiting it doesn't stall the thread the executor runs on
+pub fn map_buffer_async<'a>(
+    device: &'a wgpu::Device,
+    buffer: &'a wgpu::Buffer,
+    mode: wgpu::MapMode,
+) -> BufferMapFuture<'a> {
+    let slice = buffer.slice(..);
+    let result = Arc::new(Mutex::new(None));
+
+    let callback_result = Arc::clone(&result);
+    slice.map_async(mode, move |mapped| {
+        *callback_result.lock().unwrap() = Some(mapped);
+    });
+
+    BufferMapFuture {
+        device,
+        slice,
+        result,
+    }
+}
+
+/// See `map_buffer_async`. The buffer stays mapped until it's unmapped,
+/// after the returned view is dropped
+pub struct BufferMapFuture<'a> {
+    device: &'a wgpu::Device,
+    slice: wgpu::BufferSlice<'a>,
+    // Set by the `map_async` callback, which only runs from `device.poll`
+    result: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+}
+
+impl<'a> Future for BufferMapFuture<'a> {
+    type Output = Result<wgpu::BufferView<'a>, wgpu::BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.device.poll(wgpu::Maintain::Poll);
+
+        match self.result.lock().unwrap().take() {
+            Some(Ok(())) => Poll::Ready(Ok(self.slice.get_mapped_range())),
+            Some(Err(error)) => Poll::Ready(Err(error)),
+            // Nothing wakes the task when the GPU is done, so it asks to be polled again right away
+            None => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+}
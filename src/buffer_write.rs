@@ -0,0 +1,40 @@
+use std::marker::PhantomData;
+
+/// Staging memory for a `count` of `T` written to a buffer, filled in place instead of copied from a slice.
+/// The write is scheduled when it's dropped, and happens before the next submit
+pub struct TypedWriteView<'a, T: bytemuck::Pod> {
+    view: wgpu::QueueWriteBufferView<'a>,
+    element: PhantomData<T>,
+}
+
+impl<'a, T: bytemuck::Pod> TypedWriteView<'a, T> {
+    /// None when the write isn't valid, which is reported as a device error.
+    /// `count` values of `T` must make a multiple of `wgpu::COPY_BUFFER_ALIGNMENT` bytes
+    pub fn new(
+        queue: &'a wgpu::Queue,
+        buffer: &'a wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        count: usize,
+    ) -> Option<TypedWriteView<'a, T>> {
+        let size = wgpu::BufferSize::new((count * std::mem::size_of::<T>()) as u64)?;
+
+        Some(TypedWriteView {
+            view: queue.write_buffer_with(buffer, offset, size)?,
+            element: PhantomData,
+        })
+    }
+}
+
+impl<T: bytemuck::Pod> std::ops::Deref for TypedWriteView<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        bytemuck::cast_slice(&self.view)
+    }
+}
+
+impl<T: bytemuck::Pod> std::ops::DerefMut for TypedWriteView<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        bytemuck::cast_slice_mut(&mut self.view)
+    }
+}
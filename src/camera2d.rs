@@ -0,0 +1,143 @@
+use cgmath::{Matrix4, SquareMatrix, Vector2, Vector3, Vector4};
+
+/// Which way the world's Y axis goes on the screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum YAxis {
+    #[default]
+    Up,
+    /// As in images and tile maps, with the rows going down
+    Down,
+}
+
+/// An orthographic camera looking at a 2D world, one world unit per pixel at zoom 1
+#[derive(Clone, Copy, Debug)]
+pub struct Camera2D {
+    /// The world point at the center of the viewport
+    pub position: [f32; 2],
+    /// Above 1 shows the world bigger
+    pub zoom: f32,
+    /// Radians, counter-clockwise on the screen for `YAxis::Up`
+    pub rotation: f32,
+    /// In pixels
+    pub viewport: (u32, u32),
+    pub y_axis: YAxis,
+    /// Moves the camera by whole screen pixels only, so pixel art doesn't shimmer while panning
+    pub pixel_perfect: bool,
+}
+
+impl Camera2D {
+    pub fn new(viewport: (u32, u32)) -> Camera2D {
+        Camera2D {
+            position: [0., 0.],
+            zoom: 1.,
+            rotation: 0.,
+            viewport,
+            y_axis: YAxis::default(),
+            pixel_perfect: false,
+        }
+    }
+
+    /// World to clip space, column major
+    pub fn build_projection(&self) -> [[f32; 4]; 4] {
+        self.projection().into()
+    }
+
+    /// `screen` is in window pixels, from the top left corner
+    pub fn screen_to_world(&self, screen: [f32; 2]) -> [f32; 2] {
+        let (width, height) = self.viewport_size();
+        let clip = Vector4::new(
+            screen[0] / width * 2. - 1.,
+            1. - screen[1] / height * 2.,
+            0.,
+            1.,
+        );
+        let inverse = self
+            .projection()
+            .invert()
+            .expect("the zoom isn't 0, so the projection can be inverted");
+        let world = inverse * clip;
+
+        [world.x, world.y]
+    }
+
+    /// In window pixels, from the top left corner
+    pub fn world_to_screen(&self, world: [f32; 2]) -> [f32; 2] {
+        let (width, height) = self.viewport_size();
+        let clip = self.projection() * Vector4::new(world[0], world[1], 0., 1.);
+
+        [(clip.x + 1.) / 2. * width, (1. - clip.y) / 2. * height]
+    }
+
+    fn projection(&self) -> Matrix4<f32> {
+        let (width, height) = self.viewport_size();
+
+        let mut position = Vector2::from(self.position);
+        if self.pixel_perfect {
+            position = position.map(|coordinate| (coordinate * self.zoom).round() / self.zoom);
+        }
+
+        let y_sign = match self.y_axis {
+            YAxis::Up => 1.,
+            YAxis::Down => -1.,
+        };
+
+        Matrix4::from_nonuniform_scale(2. / width, 2. * y_sign / height, 1.)
+            * Matrix4::from_scale(self.zoom)
+            * Matrix4::from_angle_z(cgmath::Rad(-self.rotation))
+            * Matrix4::from_translation(Vector3::new(-position.x, -position.y, 0.))
+    }
+
+    // Never 0, for a minimized window
+    fn viewport_size(&self) -> (f32, f32) {
+        (self.viewport.0.max(1) as f32, self.viewport.1.max(1) as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 2], b: [f32; 2]) {
+        assert!(
+            (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3,
+            "{:?} isn't {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn converts_between_the_screen_and_the_world() {
+        let mut camera = Camera2D::new((800, 600));
+        camera.position = [100., 50.];
+        camera.zoom = 2.;
+
+        // The center of the viewport is the position, a pixel is half a world unit
+        assert_close(camera.screen_to_world([400., 300.]), [100., 50.]);
+        assert_close(camera.screen_to_world([410., 290.]), [105., 55.]);
+        assert_close(camera.world_to_screen([105., 55.]), [410., 290.]);
+
+        camera.y_axis = YAxis::Down;
+        assert_close(camera.screen_to_world([410., 290.]), [105., 45.]);
+        assert_close(camera.world_to_screen([105., 45.]), [410., 290.]);
+    }
+
+    #[test]
+    fn round_trips_with_rotation() {
+        let mut camera = Camera2D::new((800, 600));
+        camera.position = [-30., 12.5];
+        camera.zoom = 0.75;
+        camera.rotation = 0.4;
+
+        for y_axis in [YAxis::Up, YAxis::Down] {
+            camera.y_axis = y_axis;
+
+            for screen in [[0., 0.], [123., 456.], [800., 600.]] {
+                assert_close(
+                    camera.world_to_screen(camera.screen_to_world(screen)),
+                    screen,
+                );
+            }
+        }
+    }
+}
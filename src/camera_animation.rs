@@ -0,0 +1,313 @@
+use cgmath::{InnerSpace, Vector3};
+
+/// Where the camera is and looks at `time_secs` into the animation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraWaypoint {
+    pub position: [f32; 3],
+    pub target: [f32; 3],
+    pub up: [f32; 3],
+    pub time_secs: f32,
+}
+
+// A cubic Bézier curve per interpolated vector, from one waypoint to the next
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    start_time: f32,
+    duration: f32,
+    position: [Vector3<f32>; 4],
+    target: [Vector3<f32>; 4],
+    up: [Vector3<f32>; 2],
+}
+
+/// Flies the camera through waypoints along Catmull-Rom splines, so it passes through
+/// every one of them without stopping. The position and the target are interpolated independently.
+/// When the last waypoint is the same as the first, the curve is closed and loops seamlessly
+#[derive(Clone, Debug)]
+pub struct CameraAnimation {
+    segments: Vec<Segment>,
+    first: CameraWaypoint,
+    elapsed: f32,
+    playing: bool,
+    looping: bool,
+}
+
+impl CameraAnimation {
+    /// Takes at least one waypoint, in any order, they're sorted by time
+    pub fn new(mut waypoints: Vec<CameraWaypoint>) -> CameraAnimation {
+        assert!(
+            !waypoints.is_empty(),
+            "a camera animation needs at least one waypoint"
+        );
+
+        waypoints.sort_by(|a, b| a.time_secs.total_cmp(&b.time_secs));
+
+        let last = waypoints.len() - 1;
+        let closed = last > 1
+            && waypoints[0].position == waypoints[last].position
+            && waypoints[0].target == waypoints[last].target;
+
+        let positions: Vec<_> = waypoints
+            .iter()
+            .map(|w| Vector3::from(w.position))
+            .collect();
+        let targets: Vec<_> = waypoints.iter().map(|w| Vector3::from(w.target)).collect();
+        let times: Vec<_> = waypoints.iter().map(|w| w.time_secs).collect();
+
+        let position_tangents = tangents(&positions, &times, closed);
+        let target_tangents = tangents(&targets, &times, closed);
+
+        let segments = (0..last)
+            .map(|i| {
+                let duration = times[i + 1] - times[i];
+                // Hermite to Bézier, the tangents being per second
+                let controls = |points: &[Vector3<f32>], tangents: &[Vector3<f32>]| {
+                    [
+                        points[i],
+                        points[i] + tangents[i] * duration / 3.,
+                        points[i + 1] - tangents[i + 1] * duration / 3.,
+                        points[i + 1],
+                    ]
+                };
+
+                Segment {
+                    start_time: times[i],
+                    duration,
+                    position: controls(&positions, &position_tangents),
+                    target: controls(&targets, &target_tangents),
+                    up: [waypoints[i].up.into(), waypoints[i + 1].up.into()],
+                }
+            })
+            .collect();
+
+        CameraAnimation {
+            segments,
+            first: waypoints[0],
+            elapsed: waypoints[0].time_secs,
+            playing: false,
+            looping: false,
+        }
+    }
+
+    /// From the first waypoint to the last one
+    pub fn duration(&self) -> f32 {
+        self.segments.last().map_or(0., |last| {
+            last.start_time + last.duration - self.first.time_secs
+        })
+    }
+
+    /// The position and the target at `time_secs`, held at the first and the last waypoints outside of them
+    pub fn evaluate(&self, time_secs: f32) -> ([f32; 3], [f32; 3]) {
+        match self.segment_at(time_secs) {
+            Some((segment, s)) => (
+                bezier(&segment.position, s).into(),
+                bezier(&segment.target, s).into(),
+            ),
+            None => (self.first.position, self.first.target),
+        }
+    }
+
+    /// Linearly interpolated and normalized, for the cameras that can roll
+    pub fn up(&self, time_secs: f32) -> [f32; 3] {
+        match self.segment_at(time_secs) {
+            Some((segment, s)) => {
+                let [from, to] = segment.up;
+                let up = from + (to - from) * s;
+
+                if up.magnitude2() > 0. {
+                    up.normalize().into()
+                } else {
+                    to.into()
+                }
+            }
+            None => self.first.up,
+        }
+    }
+
+    /// Starts over from the first waypoint. `looping` starts over again once the last one is reached
+    pub fn play(&mut self, looping: bool) {
+        self.elapsed = self.first.time_secs;
+        self.playing = true;
+        self.looping = looping;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Moves the playback forward by `frame_time` seconds, and returns the position and the target there.
+    /// None when it isn't playing. Playing once, it stops at the last waypoint, which is returned
+    pub fn advance(&mut self, frame_time: f32) -> Option<([f32; 3], [f32; 3])> {
+        if !self.playing {
+            return None;
+        }
+
+        self.elapsed += frame_time;
+
+        let duration = self.duration();
+        let end = self.first.time_secs + duration;
+        if self.elapsed >= end {
+            if self.looping && duration > 0. {
+                self.elapsed = self.first.time_secs + (self.elapsed - end) % duration;
+            } else {
+                self.elapsed = end;
+                self.playing = false;
+            }
+        }
+
+        Some(self.evaluate(self.elapsed))
+    }
+
+    // The segment `time_secs` is in, and how far along it, in [0, 1]. None with a single waypoint
+    fn segment_at(&self, time_secs: f32) -> Option<(&Segment, f32)> {
+        let index = self
+            .segments
+            .partition_point(|segment| segment.start_time + segment.duration < time_secs)
+            .min(self.segments.len().checked_sub(1)?);
+        let segment = &self.segments[index];
+
+        let s = if segment.duration > 0. {
+            ((time_secs - segment.start_time) / segment.duration).clamp(0., 1.)
+        } else {
+            1.
+        };
+
+        Some((segment, s))
+    }
+}
+
+// Catmull-Rom tangents, per second so the uneven gaps between the waypoints don't kink the curve.
+// The ends of an open curve use their only neighbor, those of a closed one wrap around
+fn tangents(points: &[Vector3<f32>], times: &[f32], closed: bool) -> Vec<Vector3<f32>> {
+    let last = points.len() - 1;
+    let slope = |from: usize, to: usize, duration: f32| {
+        if duration > 0. {
+            (points[to] - points[from]) / duration
+        } else {
+            Vector3::new(0., 0., 0.)
+        }
+    };
+
+    (0..=last)
+        .map(|i| {
+            if closed && (i == 0 || i == last) {
+                // Before the first point comes the one before the last, the first and the last being the same
+                let duration = (times[last] - times[last - 1]) + (times[1] - times[0]);
+                slope(last - 1, 1, duration)
+            } else if i == 0 {
+                slope(0, 1.min(last), times[1.min(last)] - times[0])
+            } else if i == last {
+                slope(last - 1, last, times[last] - times[last - 1])
+            } else {
+                slope(i - 1, i + 1, times[i + 1] - times[i - 1])
+            }
+        })
+        .collect()
+}
+
+fn bezier(controls: &[Vector3<f32>; 4], s: f32) -> Vector3<f32> {
+    let t = 1. - s;
+
+    controls[0] * (t * t * t)
+        + controls[1] * (3. * t * t * s)
+        + controls[2] * (3. * t * s * s)
+        + controls[3] * (s * s * s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UP: [f32; 3] = [0., 1., 0.];
+
+    fn waypoint(position: [f32; 3], time_secs: f32) -> CameraWaypoint {
+        CameraWaypoint {
+            position,
+            target: [0., 0., 0.],
+            up: UP,
+            time_secs,
+        }
+    }
+
+    fn assert_near(actual: [f32; 3], expected: [f32; 3]) {
+        let distance = (Vector3::from(actual) - Vector3::from(expected)).magnitude();
+        assert!(distance < 1e-4, "{actual:?} is not {expected:?}");
+    }
+
+    // A bend with uneven gaps, given out of order
+    fn bend() -> CameraAnimation {
+        CameraAnimation::new(vec![
+            waypoint([4., 0., 2.], 3.),
+            waypoint([0., 0., 0.], 1.),
+            waypoint([2., 0., 0.], 2.),
+        ])
+    }
+
+    #[test]
+    fn passes_through_every_waypoint() {
+        let animation = bend();
+
+        assert_eq!(animation.duration(), 2.);
+        assert_near(animation.evaluate(1.).0, [0., 0., 0.]);
+        assert_near(animation.evaluate(2.).0, [2., 0., 0.]);
+        assert_near(animation.evaluate(3.).0, [4., 0., 2.]);
+        // Off the straight line between the waypoints
+        assert!(animation.evaluate(2.5).0[2] < 1.);
+    }
+
+    #[test]
+    fn holds_the_end_waypoints_outside_of_them() {
+        let animation = bend();
+
+        assert_near(animation.evaluate(0.).0, [0., 0., 0.]);
+        assert_near(animation.evaluate(10.).0, [4., 0., 2.]);
+    }
+
+    #[test]
+    fn moves_evenly_along_evenly_spaced_waypoints() {
+        let animation = CameraAnimation::new(vec![
+            waypoint([0., 0., 0.], 0.),
+            waypoint([1., 0., 0.], 1.),
+            waypoint([2., 0., 0.], 2.),
+        ]);
+
+        assert_near(animation.evaluate(0.5).0, [0.5, 0., 0.]);
+        assert_near(animation.evaluate(1.25).0, [1.25, 0., 0.]);
+    }
+
+    #[test]
+    fn plays_once_up_to_the_last_waypoint() {
+        let mut animation = bend();
+        assert_eq!(animation.advance(0.5), None);
+
+        animation.play(false);
+        assert_near(animation.advance(1.).unwrap().0, [2., 0., 0.]);
+        assert_near(animation.advance(5.).unwrap().0, [4., 0., 2.]);
+        assert!(!animation.is_playing());
+        assert_eq!(animation.advance(0.5), None);
+    }
+
+    #[test]
+    fn loops_back_to_the_first_waypoint() {
+        let mut animation = bend();
+
+        animation.play(true);
+        // One loop and a half
+        assert_near(animation.advance(3.).unwrap().0, [2., 0., 0.]);
+        assert!(animation.is_playing());
+    }
+
+    #[test]
+    fn interpolates_the_up_vector() {
+        let mut from = waypoint([0., 0., 0.], 0.);
+        from.up = [1., 0., 0.];
+        let animation = CameraAnimation::new(vec![from, waypoint([1., 0., 0.], 1.)]);
+
+        assert_near(animation.up(0.), [1., 0., 0.]);
+        assert_near(animation.up(0.5), [0.5_f32.sqrt(), 0.5_f32.sqrt(), 0.]);
+        assert_near(animation.up(1.), UP);
+    }
+}
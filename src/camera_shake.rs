@@ -0,0 +1,75 @@
+/// Jitters the camera for explosions and impacts, fading out over its duration.
+/// The offset follows smooth value noise instead of jumping to a new random spot every frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraShake {
+    duration_secs: f32,
+    // The largest offset at the start, in world units
+    intensity: f32,
+    // How many times per second the offset changes direction, roughly
+    frequency: f32,
+    elapsed: f32,
+    seed: u32,
+}
+
+impl CameraShake {
+    pub fn new(duration_secs: f32, intensity: f32, frequency: f32) -> CameraShake {
+        CameraShake {
+            duration_secs: duration_secs.max(0.),
+            intensity,
+            frequency,
+            elapsed: 0.,
+            seed: 0,
+        }
+    }
+
+    /// Shakes sharing a seed move the same way
+    pub fn with_seed(mut self, seed: u32) -> CameraShake {
+        self.seed = seed;
+        self
+    }
+
+    /// Advances by `frame_time` seconds and returns the offset to add to the camera position.
+    /// Zero once finished
+    pub fn update(&mut self, frame_time: f32) -> [f32; 3] {
+        self.elapsed = (self.elapsed + frame_time).min(self.duration_secs);
+        if self.is_finished() {
+            return [0.; 3];
+        }
+
+        // Quadratic, so the shake settles instead of stopping abruptly
+        let remaining = 1. - self.elapsed / self.duration_secs;
+        let intensity = self.intensity * remaining * remaining;
+
+        let t = self.elapsed * self.frequency;
+        [0, 1, 2]
+            .map(|axis| value_noise(t, self.seed.wrapping_mul(3).wrapping_add(axis)) * intensity)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration_secs
+    }
+}
+
+// Random in [-1, 1] at the integers, smoothly interpolated between them
+fn value_noise(t: f32, seed: u32) -> f32 {
+    let floor = t.floor();
+    let fraction = t - floor;
+    let smooth = fraction * fraction * (3. - 2. * fraction);
+
+    let from = hash(floor as i32, seed);
+    let to = hash(floor as i32 + 1, seed);
+
+    from + (to - from) * smooth
+}
+
+// Integer hash to [-1, 1], well mixed enough that neighbors don't correlate
+fn hash(i: i32, seed: u32) -> f32 {
+    let mut x = (i as u32).wrapping_mul(0x9e37_79b1) ^ seed.wrapping_mul(0x85eb_ca77);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x2c1b_3c6d);
+    x ^= x >> 12;
+    x = x.wrapping_mul(0x297a_2d39);
+    x ^= x >> 15;
+
+    x as f32 / u32::MAX as f32 * 2. - 1.
+}
@@ -0,0 +1,529 @@
+use wgpu::util::DeviceExt;
+
+use crate::texture::Texture;
+use crate::texture_atlas::UvRect;
+
+// The triangles a circle is made of
+const CIRCLE_SEGMENTS: u32 = 32;
+
+/// In pixels, from the top left corner of the target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CanvasVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+impl CanvasVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x4,
+    ];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<CanvasVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Canvas2DParams {
+    viewport: [f32; 2],
+    _padding: [f32; 2],
+}
+
+// The vertices drawn with one texture. None is the white texel the shapes use
+struct Batch {
+    texture: Option<wgpu::BindGroup>,
+    vertices_count: u32,
+}
+
+/// Immediate mode 2D drawing: the shapes are collected between `begin_frame` and `end_frame`,
+/// then drawn in one draw call. Only switching between textures takes another one.
+/// Everything is in pixels, from the top left corner, drawn over what's already there
+pub struct Canvas2D {
+    pipeline: wgpu::RenderPipeline,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    white_bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    vertices: Vec<CanvasVertex>,
+    batches: Vec<Batch>,
+    viewport: [f32; 2],
+}
+
+impl Canvas2D {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Canvas2D {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My canvas shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("canvas2d.wgsl").into()),
+        });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My canvas params buffer"),
+            size: std::mem::size_of::<Canvas2DParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let params_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("My canvas params bind group layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My canvas params bind group"),
+            layout: &params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+        });
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("My canvas texture bind group layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("My canvas sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let white_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("My canvas white texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &[255; 4],
+        );
+        let white_bind_group = create_texture_bind_group(
+            device,
+            &texture_bind_group_layout,
+            &white_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            &sampler,
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My canvas pipeline layout"),
+            bind_group_layouts: &[&params_bind_group_layout, &texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My canvas pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[CanvasVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Canvas2D {
+            pipeline,
+            params_buffer,
+            params_bind_group,
+            texture_bind_group_layout,
+            sampler,
+            white_bind_group,
+            vertex_buffer: create_vertex_buffer(device, 0),
+            vertices: Vec::new(),
+            batches: Vec::new(),
+            viewport: [width.max(1) as f32, height.max(1) as f32],
+        }
+    }
+
+    /// The size of the target, for the shapes to be in its pixels
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = [width.max(1) as f32, height.max(1) as f32];
+    }
+
+    /// Forgets the shapes of the previous frame
+    pub fn begin_frame(&mut self) {
+        self.vertices.clear();
+        self.batches.clear();
+    }
+
+    pub fn draw_line(&mut self, a: [f32; 2], b: [f32; 2], width: f32, color: [f32; 4]) {
+        let direction = [b[0] - a[0], b[1] - a[1]];
+        let length = direction[0].hypot(direction[1]);
+        if length == 0. {
+            return;
+        }
+
+        // Half the width to the sides
+        let side = [
+            -direction[1] / length * width / 2.,
+            direction[0] / length * width / 2.,
+        ];
+
+        self.push_quad(
+            [
+                [a[0] + side[0], a[1] + side[1]],
+                [b[0] + side[0], b[1] + side[1]],
+                [b[0] - side[0], b[1] - side[1]],
+                [a[0] - side[0], a[1] - side[1]],
+            ],
+            UvRect::FULL,
+            color,
+        );
+    }
+
+    /// Filled
+    pub fn draw_circle(&mut self, center: [f32; 2], radius: f32, color: [f32; 4]) {
+        self.use_texture(None);
+
+        let point = |i: u32| {
+            let (sin, cos) = (i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU).sin_cos();
+
+            [center[0] + radius * cos, center[1] + radius * sin]
+        };
+
+        for i in 0..CIRCLE_SEGMENTS {
+            for position in [center, point(i), point(i + 1)] {
+                self.push_vertex(position, [0.5, 0.5], color);
+            }
+        }
+    }
+
+    /// The outline, a pixel wide and inside the rect
+    pub fn draw_rect(&mut self, rect: Rect, color: [f32; 4]) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = rect;
+        let edge = |x, y, width, height| Rect {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        self.fill_rect(edge(x, y, width, 1.), color);
+        self.fill_rect(edge(x, y + height - 1., width, 1.), color);
+        self.fill_rect(edge(x, y + 1., 1., height - 2.), color);
+        self.fill_rect(edge(x + width - 1., y + 1., 1., height - 2.), color);
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: [f32; 4]) {
+        self.push_quad(corners(rect), UvRect::FULL, color);
+    }
+
+    /// `src` is the part of the texture drawn, e.g. an atlas region. `tint` multiplies its colors
+    pub fn draw_texture(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &Texture,
+        dest: Rect,
+        src: UvRect,
+        tint: [f32; 4],
+    ) {
+        let bind_group = create_texture_bind_group(
+            device,
+            &self.texture_bind_group_layout,
+            &texture.view,
+            &self.sampler,
+        );
+
+        self.use_texture(Some(bind_group));
+        self.push_vertices_of_quad(corners(dest), src, tint);
+    }
+
+    /// Uploads the frame's shapes and draws them in `render_pass`, which must target
+    /// the format the canvas was created with, without a depth attachment
+    pub fn end_frame<'p>(
+        &'p mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        render_pass: &mut wgpu::RenderPass<'p>,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let size = std::mem::size_of_val(self.vertices.as_slice()) as wgpu::BufferAddress;
+        if self.vertex_buffer.size() < size {
+            self.vertex_buffer = create_vertex_buffer(device, size.next_power_of_two());
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::bytes_of(&Canvas2DParams {
+                viewport: self.viewport,
+                _padding: [0.; 2],
+            }),
+        );
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.params_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..size));
+
+        let mut first = 0;
+        for batch in &self.batches {
+            let texture = batch.texture.as_ref().unwrap_or(&self.white_bind_group);
+            render_pass.set_bind_group(1, texture, &[]);
+            render_pass.draw(first..first + batch.vertices_count, 0..1);
+
+            first += batch.vertices_count;
+        }
+    }
+
+    // A new batch only when the texture changes. Consecutive shapes always share one
+    fn use_texture(&mut self, texture: Option<wgpu::BindGroup>) {
+        let shapes_continue = texture.is_none()
+            && self
+                .batches
+                .last()
+                .is_some_and(|batch| batch.texture.is_none());
+
+        if !shapes_continue {
+            self.batches.push(Batch {
+                texture,
+                vertices_count: 0,
+            });
+        }
+    }
+
+    fn push_quad(&mut self, corners: [[f32; 2]; 4], uv: UvRect, color: [f32; 4]) {
+        self.use_texture(None);
+        self.push_vertices_of_quad(corners, uv, color);
+    }
+
+    // Clockwise from the top left, as `corners` returns them
+    fn push_vertices_of_quad(&mut self, corners: [[f32; 2]; 4], uv: UvRect, color: [f32; 4]) {
+        let [u0, v0] = uv.offset;
+        let [u1, v1] = [u0 + uv.scale[0], v0 + uv.scale[1]];
+        let uvs = [[u0, v0], [u1, v0], [u1, v1], [u0, v1]];
+
+        for i in [0, 1, 2, 0, 2, 3] {
+            self.push_vertex(corners[i], uvs[i], color);
+        }
+    }
+
+    fn push_vertex(&mut self, position: [f32; 2], uv: [f32; 2], color: [f32; 4]) {
+        self.vertices.push(CanvasVertex {
+            position,
+            uv,
+            color,
+        });
+        self.batches
+            .last_mut()
+            .expect("a batch is started before any vertex")
+            .vertices_count += 1;
+    }
+}
+
+fn corners(rect: Rect) -> [[f32; 2]; 4] {
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = rect;
+
+    [
+        [x, y],
+        [x + width, y],
+        [x + width, y + height],
+        [x, y + height],
+    ]
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("My canvas texture bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("My canvas vertex buffer"),
+        size,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{texel, SIZE};
+
+    #[test]
+    fn draws_shapes_and_textures_in_pixels() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        let (red, green, blue) = ([255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]);
+        let white = crate::tests::texture_of(&device, &queue, 1, &[[255; 4]]);
+        let target = crate::render_target::RenderTarget::new(
+            &device,
+            SIZE,
+            SIZE,
+            wgpu::TextureFormat::Rgba8Unorm,
+            crate::depth::DEPTH_FORMAT,
+        );
+        let half = SIZE as f32 / 2.;
+
+        let mut canvas = Canvas2D::new(&device, &queue, target.format(), SIZE, SIZE);
+        canvas.begin_frame();
+        canvas.fill_rect(
+            Rect {
+                x: 0.,
+                y: 0.,
+                width: half,
+                height: half,
+            },
+            [1., 0., 0., 1.],
+        );
+        // Tinted blue, a batch of its own between the shapes
+        canvas.draw_texture(
+            &device,
+            &white,
+            Rect {
+                x: half,
+                y: 0.,
+                width: half,
+                height: half,
+            },
+            UvRect::FULL,
+            [0., 0., 1., 1.],
+        );
+        canvas.draw_circle([half * 1.5, half * 1.5], half / 4., [0., 1., 0., 1.]);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My test encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("My test pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target.view(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            canvas.end_frame(&device, &queue, &mut render_pass);
+        }
+        queue.submit([encoder.finish()]);
+        let texels = pollster::block_on(crate::texture::readback(
+            &device,
+            &queue,
+            target.texture(),
+            0,
+            0,
+        ));
+
+        let (quarter, three_quarters) = (SIZE / 4, SIZE * 3 / 4);
+        assert_eq!(texel(&texels, [quarter, quarter]), red);
+        assert_eq!(texel(&texels, [three_quarters, quarter]), blue);
+        assert_eq!(texel(&texels, [three_quarters, three_quarters]), green);
+        // Outside of the circle
+        assert_eq!(texel(&texels, [SIZE / 2 + 2, SIZE - 2]), [0; 4]);
+        assert_eq!(texel(&texels, [quarter, three_quarters]), [0; 4]);
+    }
+}
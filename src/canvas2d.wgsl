@@ -0,0 +1,38 @@
+// Immediate mode 2D shapes and textures, all in one vertex buffer
+
+struct Canvas2DParams {
+    // In pixels, the positions go from the top left corner
+    viewport: vec2<f32>,
+}
+
+struct CanvasVertex {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Canvas2DParams;
+@group(1) @binding(0) var canvas_texture: texture_2d<f32>;
+@group(1) @binding(1) var canvas_sampler: sampler;
+
+@vertex fn vs_main(vertex: CanvasVertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let clip = vertex.position / params.viewport * 2. - 1.;
+    out.clip_position = vec4<f32>(clip.x, -clip.y, 0., 1.);
+    out.uv = vertex.uv;
+    out.color = vertex.color;
+
+    return out;
+}
+
+// The shapes sample a white texel, so only their color shows
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(canvas_texture, canvas_sampler, in.uv) * in.color;
+}
@@ -0,0 +1,138 @@
+use wgpu::util::DeviceExt;
+
+use crate::bind_group_builder::BindGroupBuilder;
+use crate::shader_reflection::ShaderReflection;
+use crate::uniform_buffer::UniformBuffer;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ColorCycleParams {
+    time: f32,
+    vertices_count: u32,
+}
+
+const VERTEX_SIZE: usize = 8 * std::mem::size_of::<f32>();
+
+/// Work recorded outside of the renderer: a compute pass cycling the colors of the vertices,
+/// then a copy of the result into a vertex buffer the renderer draws
+pub struct ColorCycle {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    params: UniformBuffer<ColorCycleParams>,
+    cycled_vertices_buffer: wgpu::Buffer,
+    vertices_count: u32,
+}
+
+impl ColorCycle {
+    /// `vertex_data` is packed 3 floats of position followed by 3 floats of color
+    pub fn new(device: &wgpu::Device, vertex_data: &[u8]) -> ColorCycle {
+        assert!(
+            vertex_data.len().is_multiple_of(VERTEX_SIZE),
+            "{} bytes aren't whole vertices of {} bytes",
+            vertex_data.len(),
+            VERTEX_SIZE
+        );
+
+        let shader_source = include_str!("color_cycle.wgsl");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My color cycle shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        let vertices_count = (vertex_data.len() / VERTEX_SIZE) as u32;
+        let params = UniformBuffer::new(
+            device,
+            "My color cycle params buffer",
+            ColorCycleParams {
+                time: 0.,
+                vertices_count,
+            },
+        );
+
+        let source_vertices_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My color cycle source vertices buffer"),
+            contents: vertex_data,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let cycled_vertices_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My color cycle vertices buffer"),
+            size: source_vertices_buffer.size(),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let reflection =
+            ShaderReflection::from_wgsl(shader_source).expect("the color cycle shader is valid");
+        let bind_group_layout = reflection.create_bind_group_layout(device, 0);
+
+        let bind_group = BindGroupBuilder::from_reflection(&reflection, device)
+            .bind_resource(0, &params)
+            .bind_buffer(1, &source_vertices_buffer)
+            .bind_buffer(2, &cycled_vertices_buffer)
+            .build(&bind_group_layout)
+            .expect("the color cycle buffers match the shader");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My color cycle pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("My color cycle pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        ColorCycle {
+            pipeline,
+            bind_group,
+            params,
+            cycled_vertices_buffer,
+            vertices_count,
+        }
+    }
+
+    /// Records the recoloring at `time` seconds into `target`, which must hold as many vertices
+    /// and be `COPY_DST`. Meant to be submitted before the frame drawing `target`
+    pub fn record(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        time: f32,
+        target: &wgpu::Buffer,
+    ) -> wgpu::CommandBuffer {
+        self.params.set(ColorCycleParams {
+            time,
+            vertices_count: self.vertices_count,
+        });
+        self.params.upload(queue);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My color cycle command encoder"),
+        });
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("My color cycle pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.vertices_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        drop(compute_pass);
+
+        encoder.copy_buffer_to_buffer(
+            &self.cycled_vertices_buffer,
+            0,
+            target,
+            0,
+            self.cycled_vertices_buffer.size(),
+        );
+
+        encoder.finish()
+    }
+}
@@ -0,0 +1,35 @@
+// Recolors vertices over time. The vertices are packed as 3 floats of position, 3 of color
+// and 2 of texture coordinates
+
+struct ColorCycleParams {
+    time: f32,
+    vertices_count: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: ColorCycleParams;
+@group(0) @binding(1) var<storage, read> source_vertices: array<f32>;
+@group(0) @binding(2) var<storage, read_write> cycled_vertices: array<f32>;
+
+const FLOATS_PER_VERTEX: u32 = 8u;
+
+@compute @workgroup_size(64) fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let vertex = id.x;
+    if vertex >= params.vertices_count {
+        return;
+    }
+
+    let base = vertex * FLOATS_PER_VERTEX;
+    for (var i = 0u; i < 3u; i++) {
+        cycled_vertices[base + i] = source_vertices[base + i];
+    }
+
+    // Each vertex runs through the hues, a third of the cycle apart
+    let phase = params.time + f32(vertex) * 2.094;
+    let color = 0.5 + 0.5 * cos(phase + vec3<f32>(0., 2.094, 4.189));
+    for (var i = 0u; i < 3u; i++) {
+        cycled_vertices[base + 3u + i] = color[i];
+    }
+    for (var i = 6u; i < FLOATS_PER_VERTEX; i++) {
+        cycled_vertices[base + i] = source_vertices[base + i];
+    }
+}
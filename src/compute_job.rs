@@ -0,0 +1,36 @@
+/// A compute shader dispatch, queued with `State::add_compute_job` and run once, in a compute pass
+/// recorded before the frame's render pass. What it writes, e.g. a `STORAGE | VERTEX` buffer,
+/// can be drawn by that frame
+pub struct ComputeJob {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    workgroups: (u32, u32, u32),
+}
+
+impl ComputeJob {
+    /// `bind_group` is set at group 0. `workgroups` is how many are dispatched along X, Y and Z
+    pub fn new(
+        pipeline: wgpu::ComputePipeline,
+        bind_group: wgpu::BindGroup,
+        workgroups: (u32, u32, u32),
+    ) -> ComputeJob {
+        ComputeJob {
+            pipeline,
+            bind_group,
+            workgroups,
+        }
+    }
+
+    pub fn workgroups(&self) -> (u32, u32, u32) {
+        self.workgroups
+    }
+
+    /// Records the dispatch, for running the job without `State`
+    pub fn dispatch<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        let (x, y, z) = self.workgroups;
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(x, y, z);
+    }
+}
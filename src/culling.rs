@@ -0,0 +1,388 @@
+use wgpu::util::DeviceExt;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Per-object input of the culling pass
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CullData {
+    /// xyz - center, w - radius
+    pub bounding_sphere: [f32; 4],
+    pub base_instance: u32,
+    // WGSL aligns the struct to 16 bytes
+    _padding: [u32; 3],
+}
+
+impl CullData {
+    pub fn new(bounding_sphere: [f32; 4], base_instance: u32) -> CullData {
+        CullData {
+            bounding_sphere,
+            base_instance,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Around all of `positions`, xyz - center, w - radius. Not the tightest one,
+/// the center is the middle of their bounding box
+pub fn bounding_sphere(positions: &[[f32; 3]]) -> [f32; 4] {
+    let Some(&first) = positions.first() else {
+        return [0.; 4];
+    };
+
+    let (min, max) = positions
+        .iter()
+        .fold((first, first), |(min, max), position| {
+            (
+                [0, 1, 2].map(|i| min[i].min(position[i])),
+                [0, 1, 2].map(|i| max[i].max(position[i])),
+            )
+        });
+    let center = [0, 1, 2].map(|i| (min[i] + max[i]) / 2.);
+    let radius = positions
+        .iter()
+        .map(|position| {
+            let offset = [0, 1, 2].map(|i| position[i] - center[i]);
+
+            (offset[0] * offset[0] + offset[1] * offset[1] + offset[2] * offset[2]).sqrt()
+        })
+        .fold(0., f32::max);
+
+    [center[0], center[1], center[2], radius]
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FrustumUniform {
+    // left, right, bottom, top, near, far
+    planes: [[f32; 4]; 6],
+}
+
+impl FrustumUniform {
+    // Gribb-Hartmann plane extraction. `view_proj` is column major, depth is in [0, 1]
+    fn from_view_proj(view_proj: [[f32; 4]; 4]) -> FrustumUniform {
+        let row = |i: usize| {
+            [
+                view_proj[0][i],
+                view_proj[1][i],
+                view_proj[2][i],
+                view_proj[3][i],
+            ]
+        };
+        let add = |a: [f32; 4], b: [f32; 4]| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]];
+        let sub = |a: [f32; 4], b: [f32; 4]| [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]];
+        let normalize = |p: [f32; 4]| {
+            let length = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+
+            [p[0] / length, p[1] / length, p[2] / length, p[3] / length]
+        };
+
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        FrustumUniform {
+            planes: [
+                normalize(add(w, x)),
+                normalize(sub(w, x)),
+                normalize(add(w, y)),
+                normalize(sub(w, y)),
+                normalize(z),
+                normalize(sub(w, z)),
+            ],
+        }
+    }
+}
+
+/// Culls bounding spheres against the view frustum on the GPU.
+/// The visible objects are counted right in the indirect draw arguments,
+/// so the render pass can draw them without reading anything back to the CPU.
+pub struct GpuFrustumCuller {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    frustum_buffer: wgpu::Buffer,
+    draw_args_buffer: wgpu::Buffer,
+    draw_indexed_args_buffer: wgpu::Buffer,
+    objects_count: u32,
+}
+
+impl GpuFrustumCuller {
+    /// `vertex_count` and `index_count` describe the mesh drawn for every visible object
+    pub fn new(
+        device: &wgpu::Device,
+        objects: &[CullData],
+        vertex_count: u32,
+        index_count: u32,
+    ) -> GpuFrustumCuller {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My culling shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("culling.wgsl").into()),
+        });
+
+        let frustum_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My frustum buffer"),
+            contents: bytemuck::cast_slice(&[FrustumUniform::from_view_proj(IDENTITY)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Bindings can't be empty. The padding is never culled, as nothing is dispatched without objects
+        let padding = [CullData::new([0.; 4], 0)];
+        let cull_data = if objects.is_empty() {
+            &padding[..]
+        } else {
+            objects
+        };
+        let cull_data_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My cull data buffer"),
+            contents: bytemuck::cast_slice(cull_data),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        // `instance_count` is zeroed and then counted by the shader every frame.
+        // Copied from to read the counts back
+        let indirect_usage = wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::INDIRECT
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC;
+
+        let draw_args_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My draw args buffer"),
+            contents: wgpu::util::DrawIndirectArgs {
+                vertex_count,
+                instance_count: 0,
+                first_vertex: 0,
+                first_instance: 0,
+            }
+            .as_bytes(),
+            usage: indirect_usage,
+        });
+
+        let draw_indexed_args_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("My draw indexed args buffer"),
+                contents: wgpu::util::DrawIndexedIndirectArgs {
+                    index_count,
+                    instance_count: 0,
+                    first_index: 0,
+                    base_vertex: 0,
+                    first_instance: 0,
+                }
+                .as_bytes(),
+                usage: indirect_usage,
+            });
+
+        let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My culling bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, false),
+                storage_entry(3, false),
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My culling bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: frustum_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: cull_data_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: draw_args_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: draw_indexed_args_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My culling pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("My culling pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        GpuFrustumCuller {
+            pipeline,
+            bind_group,
+            frustum_buffer,
+            draw_args_buffer,
+            draw_indexed_args_buffer,
+            objects_count: objects.len() as u32,
+        }
+    }
+
+    /// `view_proj` takes the positions of the bounding spheres to clip space
+    pub fn update_frustum(&self, queue: &wgpu::Queue, view_proj: [[f32; 4]; 4]) {
+        queue.write_buffer(
+            &self.frustum_buffer,
+            0,
+            bytemuck::cast_slice(&[FrustumUniform::from_view_proj(view_proj)]),
+        );
+    }
+
+    /// Records the culling pass. Must be called before the render pass that uses the args
+    pub fn cull(&self, encoder: &mut wgpu::CommandEncoder) {
+        // `instance_count` is the second u32 in both args structs
+        let u32_size = std::mem::size_of::<u32>() as wgpu::BufferAddress;
+        let instance_count_range = u32_size..2 * u32_size;
+
+        crate::State::clear_buffer(
+            encoder,
+            &self.draw_args_buffer,
+            Some(instance_count_range.clone()),
+        );
+        crate::State::clear_buffer(
+            encoder,
+            &self.draw_indexed_args_buffer,
+            Some(instance_count_range),
+        );
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("My culling pass"),
+            timestamp_writes: None,
+        });
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.objects_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// `wgpu::util::DrawIndirectArgs` for `draw_indirect`
+    pub fn draw_args(&self) -> &wgpu::Buffer {
+        &self.draw_args_buffer
+    }
+
+    /// `wgpu::util::DrawIndexedIndirectArgs` for `draw_indexed_indirect`
+    pub fn draw_indexed_args(&self) -> &wgpu::Buffer {
+        &self.draw_indexed_args_buffer
+    }
+}
+
+const IDENTITY: [[f32; 4]; 4] = [
+    [1., 0., 0., 0.],
+    [0., 1., 0., 0.],
+    [0., 0., 1., 0.],
+    [0., 0., 0., 1.],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn culls_without_objects() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        // A zero sized cull data binding would be a validation error, panicking here
+        let culler = GpuFrustumCuller::new(&device, &[], 3, 0);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My test encoder"),
+        });
+        culler.cull(&mut encoder);
+        queue.submit([encoder.finish()]);
+        device.poll(wgpu::Maintain::Wait);
+    }
+
+    // The `instance_count` of both args buffers after culling
+    fn instance_counts(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        culler: &GpuFrustumCuller,
+    ) -> [u32; 2] {
+        let u32_size = std::mem::size_of::<u32>() as wgpu::BufferAddress;
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My test readback buffer"),
+            size: 2 * u32_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My test encoder"),
+        });
+        culler.cull(&mut encoder);
+        encoder.copy_buffer_to_buffer(culler.draw_args(), u32_size, &readback, 0, u32_size);
+        encoder.copy_buffer_to_buffer(
+            culler.draw_indexed_args(),
+            u32_size,
+            &readback,
+            u32_size,
+            u32_size,
+        );
+        queue.submit([encoder.finish()]);
+
+        let mapped = pollster::block_on(crate::buffer_map::map_buffer_async(
+            device,
+            &readback,
+            wgpu::MapMode::Read,
+        ))
+        .unwrap();
+
+        bytemuck::pod_read_unaligned(&mapped)
+    }
+
+    #[test]
+    fn counts_the_spheres_in_the_frustum() {
+        let Some((device, queue)) =
+            crate::tests::device_with(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        else {
+            eprintln!("No adapter with compute shaders, skipping");
+            return;
+        };
+
+        let inside = CullData::new([0., 0., 0.5, 0.1], 0);
+        // Past the right plane, by more than its radius
+        let outside = CullData::new([1.5, 0., 0.5, 0.1], 1);
+        // Across the right plane
+        let crossing = CullData::new([1.05, 0., 0.5, 0.1], 2);
+
+        let culler = GpuFrustumCuller::new(&device, &[outside], 3, 6);
+        assert_eq!(instance_counts(&device, &queue, &culler), [0, 0]);
+
+        let culler = GpuFrustumCuller::new(&device, &[inside, outside, crossing], 3, 6);
+        assert_eq!(instance_counts(&device, &queue, &culler), [2, 2]);
+
+        // Moving everything 1 to the right in clip space leaves the inside sphere across the right plane
+        let mut view_proj = IDENTITY;
+        view_proj[3][0] = 1.;
+        culler.update_frustum(&queue, view_proj);
+        assert_eq!(instance_counts(&device, &queue, &culler), [1, 1]);
+    }
+}
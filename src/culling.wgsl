@@ -0,0 +1,54 @@
+struct CullData {
+    bounding_sphere: vec4<f32>, // xyz - center, w - radius
+    base_instance: u32,
+}
+
+struct Frustum {
+    planes: array<vec4<f32>, 6>,
+}
+
+// Same layout as `wgpu::util::DrawIndirectArgs`
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    first_vertex: u32,
+    first_instance: u32,
+}
+
+// Same layout as `wgpu::util::DrawIndexedIndirectArgs`
+struct DrawIndexedArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+@group(0) @binding(0) var<uniform> frustum: Frustum;
+@group(0) @binding(1) var<storage, read> objects: array<CullData>;
+@group(0) @binding(2) var<storage, read_write> draw_args: DrawArgs;
+@group(0) @binding(3) var<storage, read_write> draw_indexed_args: DrawIndexedArgs;
+
+@compute @workgroup_size(64) fn cs_main(
+    @builtin(global_invocation_id) id: vec3<u32>
+) {
+    let index = id.x;
+
+    if index >= arrayLength(&objects) {
+        return;
+    }
+
+    let sphere = objects[index].bounding_sphere;
+
+    // The sphere is culled if it's completely behind any of the planes
+    for (var i = 0u; i < 6u; i++) {
+        let plane = frustum.planes[i];
+
+        if dot(plane.xyz, sphere.xyz) + plane.w < -sphere.w {
+            return;
+        }
+    }
+
+    atomicAdd(&draw_args.instance_count, 1u);
+    atomicAdd(&draw_indexed_args.instance_count, 1u);
+}
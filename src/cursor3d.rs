@@ -0,0 +1,127 @@
+use bytemuck::Zeroable;
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+
+use crate::scene::{BakedVertex, SceneMesh};
+use crate::vertex_buffer::VertexBuffer;
+
+/// A marker in the world, e.g. where the mouse points at in the scene, for editor style picking.
+/// Its mesh is drawn as lines, every pair of indices being one, projected on the CPU like
+/// `Scene::bake`. Each line is colored after its direction, so the axes of `cross` are RGB
+pub struct Cursor3D {
+    mesh: SceneMesh,
+    size: f32,
+    position: Option<[f32; 3]>,
+    // Holds every line, those behind the camera are left out of the first `vertices_count`
+    vertex_buffer: VertexBuffer,
+    vertices_count: u32,
+}
+
+impl Cursor3D {
+    /// `mesh` is scaled by `size` around the position, e.g. `Cursor3D::cross()`
+    pub fn new(device: &wgpu::Device, mesh: SceneMesh, size: f32) -> Cursor3D {
+        let lines_vertices = mesh.indices.len() / 2 * 2;
+        let vertex_buffer = VertexBuffer::new(
+            device,
+            "My 3D cursor vertex buffer",
+            &vec![BakedVertex::zeroed(); lines_vertices.max(1)],
+        );
+
+        Cursor3D {
+            mesh,
+            size,
+            position: None,
+            vertex_buffer,
+            vertices_count: 0,
+        }
+    }
+
+    /// Three lines through the origin along the axes, a unit long each way
+    pub fn cross() -> SceneMesh {
+        SceneMesh {
+            positions: vec![
+                [-1., 0., 0.],
+                [1., 0., 0.],
+                [0., -1., 0.],
+                [0., 1., 0.],
+                [0., 0., -1.],
+                [0., 0., 1.],
+            ],
+            indices: vec![0, 1, 2, 3, 4, 5],
+        }
+    }
+
+    /// None when it points at nothing
+    pub fn position(&self) -> Option<[f32; 3]> {
+        self.position
+    }
+
+    /// Shown at `position` from the next `update` on. None hides it
+    pub fn set_position(&mut self, position: Option<[f32; 3]>) {
+        self.position = position;
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size;
+    }
+
+    /// Projects the lines to clip space, after the cursor or the camera moved.
+    /// Lines reaching behind the camera are dropped
+    pub fn update(&mut self, queue: &wgpu::Queue, view_projection: Matrix4<f32>) {
+        let Some(position) = self.position else {
+            self.vertices_count = 0;
+            return;
+        };
+        let position = Vector3::from(position);
+
+        let mut vertices = Vec::with_capacity(self.mesh.indices.len());
+        for line in self.mesh.indices.as_chunks::<2>().0 {
+            let [from, to] = line.map(|index| Vector3::from(self.mesh.positions[index as usize]));
+            let clip =
+                [from, to].map(|point| view_projection * (position + point * self.size).extend(1.));
+
+            if clip.iter().any(|point| point.w <= f32::EPSILON) {
+                continue;
+            }
+
+            let direction = to - from;
+            let color = if direction.magnitude2() > 0. {
+                let direction = direction.normalize();
+                [direction.x.abs(), direction.y.abs(), direction.z.abs()]
+            } else {
+                [1.; 3]
+            };
+
+            vertices.extend(clip.map(|point: Vector4<f32>| BakedVertex {
+                position: (point.truncate() / point.w).into(),
+                color,
+                tex_coords: [0., 0.],
+            }));
+        }
+
+        queue.write_buffer(
+            self.vertex_buffer.buffer(),
+            0,
+            bytemuck::cast_slice(&vertices),
+        );
+        self.vertices_count = vertices.len() as u32;
+    }
+
+    /// With a line list pipeline of `crate::Vertex`'s layout, set by the caller
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertices_count == 0 {
+            return;
+        }
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice());
+        render_pass.draw(0..self.vertices_count, 0..1);
+    }
+
+    /// Nothing to draw when it's hidden or behind the camera
+    pub fn is_visible(&self) -> bool {
+        self.vertices_count > 0
+    }
+}
@@ -0,0 +1,94 @@
+/// Anything commands can be grouped in for GPU capture tools like RenderDoc
+pub trait Debuggable {
+    fn push_debug_group(&mut self, label: &str);
+    fn pop_debug_group(&mut self);
+    fn insert_debug_marker(&mut self, label: &str);
+}
+
+impl Debuggable for wgpu::CommandEncoder {
+    fn push_debug_group(&mut self, label: &str) {
+        wgpu::CommandEncoder::push_debug_group(self, label);
+    }
+
+    fn pop_debug_group(&mut self) {
+        wgpu::CommandEncoder::pop_debug_group(self);
+    }
+
+    fn insert_debug_marker(&mut self, label: &str) {
+        wgpu::CommandEncoder::insert_debug_marker(self, label);
+    }
+}
+
+impl Debuggable for wgpu::RenderPass<'_> {
+    fn push_debug_group(&mut self, label: &str) {
+        wgpu::RenderPass::push_debug_group(self, label);
+    }
+
+    fn pop_debug_group(&mut self) {
+        wgpu::RenderPass::pop_debug_group(self);
+    }
+
+    fn insert_debug_marker(&mut self, label: &str) {
+        wgpu::RenderPass::insert_debug_marker(self, label);
+    }
+}
+
+impl Debuggable for wgpu::ComputePass<'_> {
+    fn push_debug_group(&mut self, label: &str) {
+        wgpu::ComputePass::push_debug_group(self, label);
+    }
+
+    fn pop_debug_group(&mut self) {
+        wgpu::ComputePass::pop_debug_group(self);
+    }
+
+    fn insert_debug_marker(&mut self, label: &str) {
+        wgpu::ComputePass::insert_debug_marker(self, label);
+    }
+}
+
+/// Names the commands recorded while it's alive. Derefs to the encoder or the pass,
+/// so the recording goes on through it. Does nothing in release builds
+pub struct DebugScope<'a, D: Debuggable> {
+    target: &'a mut D,
+}
+
+impl<'a, D: Debuggable> DebugScope<'a, D> {
+    pub fn new(target: &'a mut D, label: &str) -> DebugScope<'a, D> {
+        #[cfg(debug_assertions)]
+        target.push_debug_group(label);
+        #[cfg(not(debug_assertions))]
+        let _ = label;
+
+        DebugScope { target }
+    }
+
+    /// A single named point inside the scope
+    pub fn insert_marker(&mut self, label: &str) {
+        #[cfg(debug_assertions)]
+        self.target.insert_debug_marker(label);
+        #[cfg(not(debug_assertions))]
+        let _ = label;
+    }
+}
+
+impl<D: Debuggable> std::ops::Deref for DebugScope<'_, D> {
+    type Target = D;
+
+    fn deref(&self) -> &D {
+        self.target
+    }
+}
+
+impl<D: Debuggable> std::ops::DerefMut for DebugScope<'_, D> {
+    fn deref_mut(&mut self) -> &mut D {
+        self.target
+    }
+}
+
+impl<D: Debuggable> Drop for DebugScope<'_, D> {
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        self.target.pop_debug_group();
+    }
+}
@@ -0,0 +1,350 @@
+use crate::render_pass_builder::{PassCompatibilityError, PipelineTargets};
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+/// The format of the depth texture once the stencil is enabled
+pub const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+/// The format of the depth texture once the stencil is enabled with reversed Z, still a float one.
+/// Needs `Features::DEPTH32FLOAT_STENCIL8`
+pub const REVERSED_Z_STENCIL_FORMAT: wgpu::TextureFormat =
+    wgpu::TextureFormat::Depth32FloatStencil8;
+
+/// How the pipelines test and write the stencil buffer, against `reference`,
+/// which the render pass sets with `set_stencil_reference`.
+/// The parts of a `wgpu::StencilState`, which isn't `Copy`
+#[derive(Clone, Copy, Debug)]
+pub struct StencilConfig {
+    pub front: wgpu::StencilFaceState,
+    pub back: wgpu::StencilFaceState,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    pub reference: u32,
+}
+
+impl StencilConfig {
+    /// Marks every drawn fragment with 1, e.g. to mask where a mirror is
+    pub fn write_one() -> StencilConfig {
+        StencilConfig::new(wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Always,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Replace,
+        })
+    }
+
+    /// Draws only where the stencil is 1, leaving it as is
+    pub fn read_equals_one() -> StencilConfig {
+        StencilConfig::new(wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Equal,
+            ..wgpu::StencilFaceState::IGNORE
+        })
+    }
+
+    /// Draws only where the stencil isn't 1, e.g. around what `write_one` masked
+    pub fn read_not_one() -> StencilConfig {
+        StencilConfig::new(wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::NotEqual,
+            ..wgpu::StencilFaceState::IGNORE
+        })
+    }
+
+    /// Neither tests nor writes, as without the stencil
+    pub fn always_pass() -> StencilConfig {
+        StencilConfig::new(wgpu::StencilFaceState::IGNORE)
+    }
+
+    // Both faces alike, against 1
+    fn new(face: wgpu::StencilFaceState) -> StencilConfig {
+        StencilConfig {
+            front: face,
+            back: face,
+            read_mask: 0xff,
+            write_mask: 0xff,
+            reference: 1,
+        }
+    }
+
+    pub fn state(&self) -> wgpu::StencilState {
+        wgpu::StencilState {
+            front: self.front,
+            back: self.back,
+            read_mask: self.read_mask,
+            write_mask: self.write_mask,
+        }
+    }
+}
+
+/// How the depth buffer is cleared and tested.
+/// The clear value must be reachable by the compare function, otherwise no fragment ever passes
+#[derive(Clone, Copy, Debug)]
+pub struct DepthConfig {
+    clear_depth: f32,
+    depth_compare: wgpu::CompareFunction,
+    stencil: Option<StencilConfig>,
+    // Keeps the depth a float with the stencil too, see `format`
+    reversed_z: bool,
+}
+
+impl DepthConfig {
+    pub fn new(
+        clear_depth: f32,
+        depth_compare: wgpu::CompareFunction,
+    ) -> Result<DepthConfig, DepthConfigError> {
+        if !(0.0..=1.0).contains(&clear_depth) {
+            return Err(DepthConfigError::ClearDepthOutOfRange(clear_depth));
+        }
+
+        // E.g. nothing is less than 0.0, so clearing to 0.0 with `Less` hides everything
+        let mismatched = match depth_compare {
+            wgpu::CompareFunction::Less => clear_depth == 0.0,
+            wgpu::CompareFunction::Greater => clear_depth == 1.0,
+            _ => false,
+        };
+
+        if mismatched {
+            return Err(DepthConfigError::MismatchedClearDepth {
+                clear_depth,
+                depth_compare,
+            });
+        }
+
+        Ok(DepthConfig {
+            clear_depth,
+            depth_compare,
+            stencil: None,
+            reversed_z: false,
+        })
+    }
+
+    /// 0.0 at the far plane, tested with `Greater`. The depth stays `Depth32Float`
+    /// with the stencil, which then needs `Features::DEPTH32FLOAT_STENCIL8`
+    pub fn reversed_z() -> DepthConfig {
+        DepthConfig {
+            clear_depth: 0.0,
+            depth_compare: wgpu::CompareFunction::Greater,
+            stencil: None,
+            reversed_z: true,
+        }
+    }
+
+    pub fn clear_depth(&self) -> f32 {
+        self.clear_depth
+    }
+
+    pub fn depth_compare(&self) -> wgpu::CompareFunction {
+        self.depth_compare
+    }
+
+    /// Enables the stencil, switching the depth texture to `DEPTH_STENCIL_FORMAT`,
+    /// or `REVERSED_Z_STENCIL_FORMAT` with reversed Z
+    pub fn stencil(self, stencil: StencilConfig) -> DepthConfig {
+        DepthConfig {
+            stencil: Some(stencil),
+            ..self
+        }
+    }
+
+    pub fn stencil_config(&self) -> Option<StencilConfig> {
+        self.stencil
+    }
+
+    /// For the pipelines writing the depth. The ones only testing it ignore the stencil
+    pub fn stencil_state(&self) -> wgpu::StencilState {
+        self.stencil
+            .map_or(wgpu::StencilState::default(), |stencil| stencil.state())
+    }
+
+    /// Fixed point depth would lose the precision reversed Z is for
+    pub fn format(&self) -> wgpu::TextureFormat {
+        match (self.stencil, self.reversed_z) {
+            (Some(_), false) => DEPTH_STENCIL_FORMAT,
+            (Some(_), true) => REVERSED_Z_STENCIL_FORMAT,
+            (None, _) => DEPTH_FORMAT,
+        }
+    }
+
+    /// What the device needs for `format`
+    pub fn required_features(&self) -> wgpu::Features {
+        self.format().required_features()
+    }
+}
+
+impl Default for DepthConfig {
+    // 1.0 at the far plane, tested with `LessEqual`, so the flat geometry all at the same depth
+    // is still drawn in submission order
+    fn default() -> DepthConfig {
+        DepthConfig {
+            clear_depth: 1.0,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: None,
+            reversed_z: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DepthConfigError {
+    ClearDepthOutOfRange(f32),
+    MismatchedClearDepth {
+        clear_depth: f32,
+        depth_compare: wgpu::CompareFunction,
+    },
+}
+
+impl std::fmt::Display for DepthConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DepthConfigError::ClearDepthOutOfRange(clear_depth) => {
+                write!(f, "clear depth {} is outside of [0, 1]", clear_depth)
+            }
+            DepthConfigError::MismatchedClearDepth {
+                clear_depth,
+                depth_compare,
+            } => write!(
+                f,
+                "clear depth {} never passes the {:?} depth test",
+                clear_depth, depth_compare
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DepthConfigError {}
+
+/// The depth texture must always match the size of the surface.
+/// Its sample count must match the color targets it's used with,
+/// otherwise `begin_render_pass` fails validation once MSAA is enabled
+pub struct DepthTexture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl DepthTexture {
+    /// `format` is `DepthConfig::format`
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        format: wgpu::TextureFormat,
+    ) -> DepthTexture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("My depth texture"),
+            size: wgpu::Extent3d {
+                // The window can be zero sized before the first resize
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        DepthTexture { texture, view }
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.texture.sample_count()
+    }
+
+    /// Catches a mismatch before wgpu does, with a clearer message. `color_sample_count` is
+    /// the one of the color attachment drawn to with it, the multisampled one with MSAA
+    pub fn check_sample_count(
+        &self,
+        color_sample_count: u32,
+        pipeline: &PipelineTargets,
+    ) -> Result<(), PassCompatibilityError> {
+        for attachment in [color_sample_count, self.sample_count()] {
+            if attachment != pipeline.sample_count {
+                return Err(PassCompatibilityError::SampleCount {
+                    attachment,
+                    pipeline: pipeline.sample_count,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reversed_z_keeps_a_float_depth_with_the_stencil() {
+        let stencil = StencilConfig::write_one();
+
+        assert_eq!(DepthConfig::default().format(), DEPTH_FORMAT);
+        assert_eq!(
+            DepthConfig::default().stencil(stencil).format(),
+            DEPTH_STENCIL_FORMAT
+        );
+        assert_eq!(DepthConfig::reversed_z().format(), DEPTH_FORMAT);
+        assert_eq!(
+            DepthConfig::reversed_z().stencil(stencil).format(),
+            wgpu::TextureFormat::Depth32FloatStencil8
+        );
+
+        assert_eq!(
+            DepthConfig::default().stencil(stencil).required_features(),
+            wgpu::Features::empty()
+        );
+        assert_eq!(
+            DepthConfig::reversed_z()
+                .stencil(stencil)
+                .required_features(),
+            wgpu::Features::DEPTH32FLOAT_STENCIL8
+        );
+    }
+
+    #[test]
+    fn check_sample_count_catches_a_mismatch() {
+        let Some((device, _)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        let depth_texture = DepthTexture::new(&device, 16, 16, 1, DEPTH_FORMAT);
+        let pipeline = PipelineTargets {
+            color_formats: vec![Some(wgpu::TextureFormat::Rgba8Unorm)],
+            depth_format: Some(DEPTH_FORMAT),
+            sample_count: 4,
+        };
+
+        // The depth texture was left single sampled while MSAA was turned on
+        assert_eq!(
+            depth_texture.check_sample_count(4, &pipeline),
+            Err(PassCompatibilityError::SampleCount {
+                attachment: 1,
+                pipeline: 4,
+            })
+        );
+        assert_eq!(
+            depth_texture.check_sample_count(1, &pipeline),
+            Err(PassCompatibilityError::SampleCount {
+                attachment: 1,
+                pipeline: 4,
+            })
+        );
+        assert_eq!(
+            depth_texture.check_sample_count(
+                1,
+                &PipelineTargets {
+                    sample_count: 1,
+                    ..pipeline
+                }
+            ),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,125 @@
+/// Draw calls ordered by their depth along the view direction.
+/// Opaque ones go front to back, so the depth test rejects the hidden fragments
+/// before they are shaded, transparent ones back to front, so they blend correctly
+pub struct DrawQueue<T> {
+    view_direction: [f32; 3],
+    // xyz - center, w - radius
+    items: Vec<([f32; 4], T)>,
+}
+
+impl<T> DrawQueue<T> {
+    /// `view_direction` needs not be normalized, only the order of the depths matters
+    pub fn new(view_direction: [f32; 3]) -> DrawQueue<T> {
+        DrawQueue {
+            view_direction,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn set_view_direction(&mut self, view_direction: [f32; 3]) {
+        self.view_direction = view_direction;
+    }
+
+    pub fn push(&mut self, bounding_sphere: [f32; 4], item: T) {
+        self.items.push((bounding_sphere, item));
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// In the sorted order
+    pub fn items(&self) -> impl Iterator<Item = &T> {
+        self.items.iter().map(|(_, item)| item)
+    }
+
+    /// Ascending depth. The sort is stable, so items at the same depth keep their order
+    pub fn sort_opaque_front_to_back(&mut self, camera_pos: [f32; 3]) {
+        let direction = self.view_direction;
+
+        self.items.sort_by(|(a, _), (b, _)| {
+            depth(a, camera_pos, direction).total_cmp(&depth(b, camera_pos, direction))
+        });
+    }
+
+    /// Descending depth
+    pub fn sort_transparent_back_to_front(&mut self, camera_pos: [f32; 3]) {
+        let direction = self.view_direction;
+
+        self.items.sort_by(|(a, _), (b, _)| {
+            depth(b, camera_pos, direction).total_cmp(&depth(a, camera_pos, direction))
+        });
+    }
+}
+
+// The sphere center projected onto the view direction
+fn depth(bounding_sphere: &[f32; 4], camera_pos: [f32; 3], view_direction: [f32; 3]) -> f32 {
+    (0..3)
+        .map(|i| (bounding_sphere[i] - camera_pos[i]) * view_direction[i])
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Along Z from the origin, `name` at `z`
+    fn queue(items: &[(&'static str, f32)]) -> DrawQueue<&'static str> {
+        let mut queue = DrawQueue::new([0., 0., 1.]);
+        for &(name, z) in items {
+            queue.push([0., 0., z, 1.], name);
+        }
+
+        queue
+    }
+
+    #[test]
+    fn sorts_opaque_front_to_back() {
+        let mut queue = queue(&[("far", 5.), ("near", 1.), ("middle", 3.)]);
+
+        queue.sort_opaque_front_to_back([0.; 3]);
+
+        assert!(queue.items().copied().eq(["near", "middle", "far"]));
+    }
+
+    #[test]
+    fn sorts_transparent_back_to_front() {
+        let mut queue = queue(&[("near", 1.), ("far", 5.), ("middle", 3.)]);
+
+        queue.sort_transparent_back_to_front([0.; 3]);
+
+        assert!(queue.items().copied().eq(["far", "middle", "near"]));
+    }
+
+    #[test]
+    fn keeps_the_order_of_items_at_the_same_depth() {
+        let mut queue = queue(&[("b", 2.), ("a", 1.), ("c", 2.), ("d", 2.)]);
+
+        queue.sort_opaque_front_to_back([0.; 3]);
+        assert!(queue.items().copied().eq(["a", "b", "c", "d"]));
+
+        queue.sort_transparent_back_to_front([0.; 3]);
+        assert!(queue.items().copied().eq(["b", "c", "d", "a"]));
+    }
+
+    #[test]
+    fn measures_the_depth_from_the_camera_along_the_view_direction() {
+        // Beside the camera the depth is zero, whatever the distance
+        let mut queue = DrawQueue::new([0., 0., -2.]);
+        queue.push([0., 0., -4., 1.], "ahead");
+        queue.push([100., 0., 1., 1.], "beside");
+        queue.push([0., 0., 2., 1.], "behind");
+
+        queue.sort_opaque_front_to_back([0., 0., 1.]);
+
+        assert!(queue.items().copied().eq(["behind", "beside", "ahead"]));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::vertex_buffer::VertexBuffer;
+use crate::vertex_layout::{VertexLayout, VertexLayoutError};
+
+/// Bit masks of the layers a drawable belongs to
+pub const LAYER_OPAQUE: u32 = 1 << 0;
+pub const LAYER_TRANSPARENT: u32 = 1 << 1;
+pub const ALL_LAYERS: u32 = u32::MAX;
+
+/// A vertex buffer together with the flags deciding whether it's drawn
+pub struct Drawable {
+    vertex_buffer: VertexBuffer,
+    layer_mask: u32,
+    visible: bool,
+}
+
+impl Drawable {
+    pub fn new<V: bytemuck::Pod>(
+        device: &wgpu::Device,
+        label: &str,
+        vertices: &[V],
+        layer_mask: u32,
+    ) -> Drawable {
+        Drawable {
+            vertex_buffer: VertexBuffer::new(device, label, vertices),
+            layer_mask,
+            visible: true,
+        }
+    }
+
+    /// Vertices described by a `VertexLayout` instead of a Rust struct
+    pub fn with_layout(
+        device: &wgpu::Device,
+        label: &str,
+        vertex_data: &[u8],
+        layout: &VertexLayout,
+        layer_mask: u32,
+    ) -> Result<Drawable, VertexLayoutError> {
+        layout.validate(vertex_data)?;
+
+        Ok(Drawable {
+            vertex_buffer: VertexBuffer::from_bytes(
+                device,
+                label,
+                vertex_data,
+                layout.stride() as u32,
+            ),
+            layer_mask,
+            visible: true,
+        })
+    }
+
+    pub fn vertex_buffer(&self) -> &VertexBuffer {
+        &self.vertex_buffer
+    }
+
+    /// Replaces the vertices, e.g. to animate them. The count can't change
+    pub fn write_vertices(&self, queue: &wgpu::Queue, vertex_data: &[u8]) {
+        self.vertex_buffer.write(queue, vertex_data);
+    }
+
+    pub fn vertices_count(&self) -> u32 {
+        self.vertex_buffer.vertex_count()
+    }
+
+    pub fn layer_mask(&self) -> u32 {
+        self.layer_mask
+    }
+
+    pub fn set_layer_mask(&mut self, layer_mask: u32) {
+        self.layer_mask = layer_mask;
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Visible and in at least one of the active layers
+    pub fn is_rendered(&self, active_layers: u32) -> bool {
+        self.visible && self.layer_mask & active_layers != 0
+    }
+}
@@ -0,0 +1,77 @@
+use crate::uniform_buffer::UniformBuffer;
+
+/// A uniform in two buffers taking turns, so the CPU writes one frame's value
+/// while the GPU may still be reading the previous frame's.
+/// Bind groups are created for both buffers, see `binding`, and the one of `current` is used
+pub struct DualBuffer<T: bytemuck::Pod> {
+    bufs: [UniformBuffer<T>; 2],
+    current: usize,
+}
+
+impl<T: bytemuck::Pod> DualBuffer<T> {
+    pub fn new(device: &wgpu::Device, label: &str, initial: T) -> DualBuffer<T> {
+        DualBuffer {
+            bufs: [
+                UniformBuffer::new(device, label, initial),
+                UniformBuffer::new(device, label, initial),
+            ],
+            current: 0,
+        }
+    }
+
+    /// Moves to the other buffer. Called at the start of each frame, before `write`
+    pub fn swap(&mut self) {
+        self.current = 1 - self.current;
+    }
+
+    /// 0 or 1
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn write(&mut self, queue: &wgpu::Queue, value: T) {
+        let buffer = &mut self.bufs[self.current];
+
+        buffer.set(value);
+        buffer.upload(queue);
+    }
+
+    /// The buffer written this frame
+    pub fn read_binding(&self) -> wgpu::BindingResource<'_> {
+        self.binding(self.current)
+    }
+
+    /// Either buffer, to create the bind groups of both up front
+    pub fn binding(&self, index: usize) -> wgpu::BindingResource<'_> {
+        self.bufs[index].binding()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_go_to_the_current_buffer_only() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let mut buffer = DualBuffer::new(&device, "My test dual buffer", 0_u32);
+
+        buffer.write(&queue, 1);
+        assert_eq!(buffer.current(), 0);
+        assert_eq!([*buffer.bufs[0].value(), *buffer.bufs[1].value()], [1, 0]);
+
+        buffer.swap();
+        buffer.write(&queue, 2);
+        assert_eq!(buffer.current(), 1);
+        // The previous frame's value is left for the GPU
+        assert_eq!([*buffer.bufs[0].value(), *buffer.bufs[1].value()], [1, 2]);
+
+        buffer.swap();
+        buffer.write(&queue, 3);
+        assert_eq!(buffer.current(), 0);
+        assert_eq!([*buffer.bufs[0].value(), *buffer.bufs[1].value()], [3, 2]);
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::{Arc, Mutex};
+
+/// A GPU failure happening while running, as opposed to while initializing
+#[derive(Debug)]
+pub enum GpuError {
+    /// Only the ones that can't be recovered from by reconfiguring the surface
+    Surface(wgpu::SurfaceError),
+    DeviceLost(String),
+    /// A validation or out of memory error nothing was waiting for with an error scope
+    Uncaptured(wgpu::Error),
+}
+
+impl GpuError {
+    /// Whether rendering can't go on after it
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            GpuError::Surface(wgpu::SurfaceError::OutOfMemory) | GpuError::DeviceLost(_) => true,
+            GpuError::Surface(_) => false,
+            GpuError::Uncaptured(error) => matches!(error, wgpu::Error::OutOfMemory { .. }),
+        }
+    }
+}
+
+impl std::fmt::Display for GpuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GpuError::Surface(error) => write!(f, "surface error: {}", error),
+            GpuError::DeviceLost(message) => write!(f, "the device was lost: {}", message),
+            GpuError::Uncaptured(error) => write!(f, "uncaptured GPU error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+/// How the GPU errors are surfaced
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ErrorPolicy {
+    /// Fail fast, for development
+    Panic,
+    /// Every error ends `run` with it
+    #[default]
+    ReturnResult,
+    /// Every error is handed to the function, and only the fatal ones end `run`
+    Callback(fn(&GpuError)),
+}
+
+/// Applies the policy to the errors of the frames and of the wgpu callbacks.
+/// The errors ending `run` are kept until `take_pending` is called.
+/// Clones share the pending errors
+#[derive(Clone, Debug)]
+pub struct ErrorReporter {
+    policy: ErrorPolicy,
+    pending: Arc<Mutex<Vec<GpuError>>>,
+}
+
+impl ErrorReporter {
+    pub fn new(policy: ErrorPolicy) -> ErrorReporter {
+        ErrorReporter {
+            policy,
+            pending: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Routes the uncaptured errors and the loss of `device` to `report`
+    pub fn install(&self, device: &wgpu::Device) {
+        let reporter = self.clone();
+        device.on_uncaptured_error(Box::new(move |error| {
+            reporter.report(GpuError::Uncaptured(error))
+        }));
+
+        let reporter = self.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // Dropping the device at exit also calls back
+            if let wgpu::DeviceLostReason::Unknown | wgpu::DeviceLostReason::Destroyed = reason {
+                reporter.report(GpuError::DeviceLost(message));
+            }
+        });
+    }
+
+    pub fn report(&self, error: GpuError) {
+        match self.policy {
+            ErrorPolicy::Panic => panic!("{}", error),
+            ErrorPolicy::ReturnResult => self.push(error),
+            ErrorPolicy::Callback(callback) => {
+                callback(&error);
+
+                if error.is_fatal() {
+                    self.push(error);
+                }
+            }
+        }
+    }
+
+    /// The oldest error that must end `run`, if any
+    pub fn take_pending(&self) -> Option<GpuError> {
+        let mut pending = self.pending.lock().unwrap();
+
+        (!pending.is_empty()).then(|| pending.remove(0))
+    }
+
+    fn push(&self, error: GpuError) {
+        self.pending.lock().unwrap().push(error);
+    }
+}
@@ -0,0 +1,63 @@
+use cgmath::{InnerSpace, Vector3};
+
+// The velocity is multiplied by it every 1/60 of a second once no key is held
+const DAMPING: f32 = 0.9;
+// Below this speed, the coasting camera stops
+const STOP_SPEED: f32 = 1e-3;
+
+/// Moves with some inertia, speeding up while a direction is held and coasting to a stop after,
+/// instead of starting and stopping dead. It only tracks the velocity, the moving is up to the caller
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlyCamera {
+    /// World units per second
+    pub velocity: [f32; 3],
+    /// World units per second, per second
+    pub acceleration: f32,
+    /// World units per second
+    pub max_speed: f32,
+}
+
+impl FlyCamera {
+    pub fn new(acceleration: f32, max_speed: f32) -> FlyCamera {
+        FlyCamera {
+            velocity: [0.; 3],
+            acceleration,
+            max_speed,
+        }
+    }
+
+    /// Advances by `frame_time` seconds, speeding up along `direction`, or slowing down
+    /// when it's zero. Only its direction matters. Returns how far the camera moved
+    pub fn update(&mut self, direction: [f32; 3], frame_time: f32) -> [f32; 3] {
+        let direction = Vector3::from(direction);
+        let mut velocity = Vector3::from(self.velocity);
+
+        if direction.magnitude2() > 0. {
+            velocity += direction.normalize() * self.acceleration * frame_time;
+
+            if velocity.magnitude() > self.max_speed {
+                velocity = velocity.normalize_to(self.max_speed);
+            }
+        } else {
+            // Frame rate independent, the same speed is lost per second at any frame time
+            velocity *= DAMPING.powf(frame_time * 60.);
+
+            if velocity.magnitude() < STOP_SPEED {
+                velocity = Vector3::new(0., 0., 0.);
+            }
+        }
+
+        self.velocity = velocity.into();
+
+        (velocity * frame_time).into()
+    }
+
+    /// Stops dead, e.g. when the camera is moved some other way
+    pub fn stop(&mut self) {
+        self.velocity = [0.; 3];
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.velocity != [0.; 3]
+    }
+}
@@ -0,0 +1,303 @@
+use wgpu::util::DeviceExt;
+
+/// Width and height of the built-in font glyphs, in font pixels
+pub const GLYPH_SIZE: (u32, u32) = (3, 5);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct HudVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+impl HudVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<HudVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Screen space overlay composited over the world every frame, whatever the camera.
+/// The world is drawn by the scene passes, the HUD only by what's collected here since the last `render`.
+/// Coordinates are in pixels from the top left corner. There is no depth test,
+/// the draws are blended in the order they were collected, and the depth buffer isn't touched
+pub struct HudLayer {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    vertices: Vec<HudVertex>,
+}
+
+impl HudLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> HudLayer {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My HUD shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("hud.wgsl").into()),
+        });
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My HUD uniform buffer"),
+            contents: bytemuck::cast_slice(&projection(width, height)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My HUD bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My HUD bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My HUD pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My HUD pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[HudVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // The quads are wound either way depending on how they're given
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        HudLayer {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            vertex_buffer: create_vertex_buffer(device, 0),
+            vertices: Vec::new(),
+        }
+    }
+
+    pub fn resize(&mut self, queue: &wgpu::Queue, width: u32, height: u32) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&projection(width, height)),
+        );
+    }
+
+    /// An untextured sprite
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        self.quad(
+            [
+                [x, y],
+                [x + width, y],
+                [x + width, y + height],
+                [x, y + height],
+            ],
+            color,
+        );
+    }
+
+    pub fn line(&mut self, from: [f32; 2], to: [f32; 2], thickness: f32, color: [f32; 4]) {
+        let direction = [to[0] - from[0], to[1] - from[1]];
+        let length = direction[0].hypot(direction[1]);
+
+        if length == 0. {
+            return;
+        }
+
+        let offset = [
+            -direction[1] / length * thickness / 2.,
+            direction[0] / length * thickness / 2.,
+        ];
+
+        self.quad(
+            [
+                [from[0] + offset[0], from[1] + offset[1]],
+                [to[0] + offset[0], to[1] + offset[1]],
+                [to[0] - offset[0], to[1] - offset[1]],
+                [from[0] - offset[0], from[1] - offset[1]],
+            ],
+            color,
+        );
+    }
+
+    /// Text in the built-in font, `scale` being the size of a font pixel.
+    /// Only digits, letters, `:`, `.`, `-` and spaces are drawn, other characters leave a gap
+    pub fn text(&mut self, x: f32, y: f32, scale: f32, text: &str, color: [f32; 4]) {
+        let advance = (GLYPH_SIZE.0 + 1) as f32 * scale;
+
+        for (i, character) in text.chars().enumerate() {
+            let Some(rows) = glyph(character) else {
+                continue;
+            };
+
+            let glyph_x = x + i as f32 * advance;
+
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..GLYPH_SIZE.0 {
+                    if bits & (1 << (GLYPH_SIZE.0 - 1 - column)) != 0 {
+                        self.rect(
+                            glyph_x + column as f32 * scale,
+                            y + row as f32 * scale,
+                            scale,
+                            scale,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws everything collected over `view` and starts collecting anew.
+    /// Must come after the world passes, as the view is loaded, not cleared
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let size = std::mem::size_of_val(self.vertices.as_slice()) as wgpu::BufferAddress;
+        if self.vertex_buffer.size() < size {
+            self.vertex_buffer = create_vertex_buffer(device, size.next_power_of_two());
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My HUD pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..size));
+        render_pass.draw(0..self.vertices.len() as u32, 0..1);
+
+        drop(render_pass);
+
+        self.vertices.clear();
+    }
+
+    // The corners in order around the quad
+    fn quad(&mut self, corners: [[f32; 2]; 4], color: [f32; 4]) {
+        for index in [0, 1, 2, 0, 2, 3] {
+            self.vertices.push(HudVertex {
+                position: corners[index],
+                color,
+            });
+        }
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("My HUD vertex buffer"),
+        size,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+// Pixels to clip space, y pointing down
+fn projection(width: u32, height: u32) -> [[f32; 4]; 4] {
+    cgmath::ortho(0., width.max(1) as f32, height.max(1) as f32, 0., -1., 1.).into()
+}
+
+// The rows from the top, the leftmost pixel being the highest bit. Letters are upper case only
+fn glyph(character: char) -> Option<[u8; 5]> {
+    Some(match character.to_ascii_uppercase() {
+        '0' | 'O' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' | 'S' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b111, 0b100, 0b100, 0b100, 0b111],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b111, 0b100, 0b101, 0b101, 0b111],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b111],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'P' => [0b111, 0b101, 0b111, 0b100, 0b100],
+        'Q' => [0b111, 0b101, 0b101, 0b111, 0b001],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        ' ' => [0; 5],
+        _ => return None,
+    })
+}
@@ -0,0 +1,32 @@
+// Screen space overlay drawn over the scene. Positions are in pixels, the origin being the top left corner
+
+struct HudUniforms {
+    projection: mat4x4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> hud: HudUniforms;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex fn vs_main(
+    vertex: VertexInput
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.color = vertex.color;
+    out.clip_position = hud.projection * vec4<f32>(vertex.position, 0., 1.);
+
+    return out;
+}
+
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
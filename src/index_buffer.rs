@@ -0,0 +1,143 @@
+use wgpu::util::DeviceExt;
+
+use crate::Indices;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexError {
+    /// The first index referencing a vertex past the end
+    OutOfBounds(u32),
+    /// The count of the indices of a triangle list, not a multiple of 3
+    PartialTriangle(usize),
+}
+
+impl std::fmt::Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexError::OutOfBounds(index) => {
+                write!(f, "the index {} is past the last vertex", index)
+            }
+            IndexError::PartialTriangle(count) => {
+                write!(f, "{} indices don't make whole triangles", count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+/// Indices checked against the vertex count, only in debug builds for the 32 bit ones of `new`.
+/// Out of bounds indices are undefined behavior on some GPUs instead of a validation error
+pub struct IndexBuffer {
+    buffer: wgpu::Buffer,
+    indices_count: u32,
+    format: wgpu::IndexFormat,
+}
+
+impl IndexBuffer {
+    /// Of the buffers of `new`
+    pub const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32;
+
+    /// Release builds don't look at the indices and never fail
+    pub fn new(
+        device: &wgpu::Device,
+        indices: &[u32],
+        vertex_count: u32,
+    ) -> Result<IndexBuffer, IndexError> {
+        #[cfg(debug_assertions)]
+        if let Some(&index) = indices.iter().find(|&&index| index >= vertex_count) {
+            return Err(IndexError::OutOfBounds(index));
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = vertex_count;
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My index buffer"),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        Ok(IndexBuffer {
+            buffer,
+            indices_count: indices.len() as u32,
+            format: Self::FORMAT,
+        })
+    }
+
+    /// Indices from outside, e.g. of a `MeshData`, in their own format.
+    /// Checked in release builds too, and for whole triangles
+    pub fn triangle_list(
+        device: &wgpu::Device,
+        indices: &Indices,
+        vertex_count: u32,
+    ) -> Result<IndexBuffer, IndexError> {
+        if !indices.len().is_multiple_of(3) {
+            return Err(IndexError::PartialTriangle(indices.len()));
+        }
+
+        let out_of_bounds = match indices {
+            Indices::U16(indices) => indices
+                .iter()
+                .map(|&index| index as u32)
+                .find(|&index| index >= vertex_count),
+            Indices::U32(indices) => indices.iter().copied().find(|&index| index >= vertex_count),
+        };
+        if let Some(index) = out_of_bounds {
+            return Err(IndexError::OutOfBounds(index));
+        }
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My index buffer"),
+            contents: indices.as_bytes(),
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        Ok(IndexBuffer {
+            buffer,
+            indices_count: indices.len() as u32,
+            format: indices.format(),
+        })
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn indices_count(&self) -> u32 {
+        self.indices_count
+    }
+
+    pub fn format(&self) -> wgpu::IndexFormat {
+        self.format
+    }
+
+    pub fn set<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>) {
+        render_pass.set_index_buffer(self.buffer.slice(..), self.format);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triangle_list_checks_the_indices() {
+        let Some((device, _)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        let quad =
+            IndexBuffer::triangle_list(&device, &Indices::U16(vec![0, 1, 2, 0, 2, 3]), 4).unwrap();
+        assert_eq!(quad.indices_count(), 6);
+        assert_eq!(quad.format(), wgpu::IndexFormat::Uint16);
+
+        assert_eq!(
+            IndexBuffer::triangle_list(&device, &Indices::U32(vec![0, 1, 2, 0, 2, 4]), 4).err(),
+            Some(IndexError::OutOfBounds(4))
+        );
+        assert_eq!(
+            IndexBuffer::triangle_list(&device, &Indices::U16(vec![0, 1, 2, 0]), 4).err(),
+            Some(IndexError::PartialTriangle(4))
+        );
+    }
+}
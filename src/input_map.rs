@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+/// Ends the app. Bound to Escape by default
+pub const QUIT: &str = "quit";
+/// Move the geometry of the main pipelines around while held, see `State::set_transform`.
+/// Unbound by default
+pub const MOVE_LEFT: &str = "move_left";
+pub const MOVE_RIGHT: &str = "move_right";
+pub const MOVE_UP: &str = "move_up";
+pub const MOVE_DOWN: &str = "move_down";
+/// Pan the scene camera along the ground while held. Bound to the arrow keys by default
+pub const PAN_LEFT: &str = "pan_left";
+pub const PAN_RIGHT: &str = "pan_right";
+pub const PAN_FORWARD: &str = "pan_forward";
+pub const PAN_BACK: &str = "pan_back";
+/// Fly the scene camera along the view while held, once `CYCLE_CAMERA_MODE` gets to flying.
+/// Bound to WASD by default
+pub const FLY_LEFT: &str = "fly_left";
+pub const FLY_RIGHT: &str = "fly_right";
+pub const FLY_FORWARD: &str = "fly_forward";
+pub const FLY_BACK: &str = "fly_back";
+/// Shows and hides the opaque geometry. 1 by default
+pub const TOGGLE_OPAQUE_LAYER: &str = "toggle_opaque_layer";
+/// Shows and hides the transparent geometry. 2 by default
+pub const TOGGLE_TRANSPARENT_LAYER: &str = "toggle_transparent_layer";
+/// Switches the order-independent transparency technique. O by default
+pub const SWITCH_OIT: &str = "switch_oit";
+/// Switches between the sRGB and the linear swapchain views, the latter looking darker.
+/// G by default
+pub const TOGGLE_SRGB_VIEW: &str = "toggle_srgb_view";
+/// Makes every frame slow. L by default
+pub const TOGGLE_SLOW_FRAMES: &str = "toggle_slow_frames";
+/// Exports the mesh to mesh.obj. E by default
+pub const EXPORT_OBJ: &str = "export_obj";
+/// Imports mesh.obj back. I by default
+pub const IMPORT_OBJ: &str = "import_obj";
+/// Spins the triangle, leaving a fading trail. T by default
+pub const TOGGLE_TRAILS: &str = "toggle_trails";
+/// Cycles the joins of the thick line. J by default
+pub const CYCLE_LINE_JOIN: &str = "cycle_line_join";
+/// Saves the frame to screenshot.png. F12 by default
+pub const SCREENSHOT: &str = "screenshot";
+/// Enters and exits exclusive fullscreen. F11 by default
+pub const TOGGLE_FULLSCREEN: &str = "toggle_fullscreen";
+/// Logs the pipeline statistics of the main pass. P by default
+pub const LOG_PIPELINE_STATS: &str = "log_pipeline_stats";
+/// Puts the scene camera back where it started. Home by default
+pub const RESET_CAMERA: &str = "reset_camera";
+/// Cycles the scene camera between panning, orbiting with the mouse and flying. M by default
+pub const CYCLE_CAMERA_MODE: &str = "cycle_camera_mode";
+/// Draws the opaque geometry as a wireframe, where supported. F by default
+pub const TOGGLE_WIREFRAME: &str = "toggle_wireframe";
+/// Shakes the scene camera. K by default
+pub const SHAKE_CAMERA: &str = "shake_camera";
+/// Switches between the grid of instanced triangles and 10 000 scattered ones. N by default
+pub const SWITCH_INSTANCES: &str = "switch_instances";
+/// Outlines the squares sharing the first material. H by default
+pub const TOGGLE_SELECTION: &str = "toggle_selection";
+/// Renders the opaque geometry offscreen and shows it in grayscale. R by default
+pub const TOGGLE_GRAYSCALE: &str = "toggle_grayscale";
+/// Switches the texture of the textured geometry. X by default
+pub const SWITCH_TEXTURE: &str = "switch_texture";
+/// Starts and stops a tour around what the scene camera looks at. V by default
+pub const TOGGLE_CAMERA_TOUR: &str = "toggle_camera_tour";
+/// Makes the material squares translucent. B by default
+pub const TOGGLE_MATERIAL_BLEND: &str = "toggle_material_blend";
+/// Cycles the colors of the transparent triangle. C by default
+pub const TOGGLE_COLOR_CYCLE: &str = "toggle_color_cycle";
+
+// What `InputMap::default` binds
+const DEFAULT_BINDINGS: [(KeyCode, &str); 32] = [
+    (KeyCode::Escape, QUIT),
+    (KeyCode::ArrowLeft, PAN_LEFT),
+    (KeyCode::ArrowRight, PAN_RIGHT),
+    (KeyCode::ArrowUp, PAN_FORWARD),
+    (KeyCode::ArrowDown, PAN_BACK),
+    (KeyCode::KeyA, FLY_LEFT),
+    (KeyCode::KeyD, FLY_RIGHT),
+    (KeyCode::KeyW, FLY_FORWARD),
+    (KeyCode::KeyS, FLY_BACK),
+    (KeyCode::Digit1, TOGGLE_OPAQUE_LAYER),
+    (KeyCode::Digit2, TOGGLE_TRANSPARENT_LAYER),
+    (KeyCode::KeyO, SWITCH_OIT),
+    (KeyCode::KeyG, TOGGLE_SRGB_VIEW),
+    (KeyCode::KeyL, TOGGLE_SLOW_FRAMES),
+    (KeyCode::KeyE, EXPORT_OBJ),
+    (KeyCode::KeyI, IMPORT_OBJ),
+    (KeyCode::KeyT, TOGGLE_TRAILS),
+    (KeyCode::KeyJ, CYCLE_LINE_JOIN),
+    (KeyCode::F12, SCREENSHOT),
+    (KeyCode::F11, TOGGLE_FULLSCREEN),
+    (KeyCode::KeyP, LOG_PIPELINE_STATS),
+    (KeyCode::Home, RESET_CAMERA),
+    (KeyCode::KeyM, CYCLE_CAMERA_MODE),
+    (KeyCode::KeyF, TOGGLE_WIREFRAME),
+    (KeyCode::KeyK, SHAKE_CAMERA),
+    (KeyCode::KeyN, SWITCH_INSTANCES),
+    (KeyCode::KeyH, TOGGLE_SELECTION),
+    (KeyCode::KeyR, TOGGLE_GRAYSCALE),
+    (KeyCode::KeyX, SWITCH_TEXTURE),
+    (KeyCode::KeyV, TOGGLE_CAMERA_TOUR),
+    (KeyCode::KeyB, TOGGLE_MATERIAL_BLEND),
+    (KeyCode::KeyC, TOGGLE_COLOR_CYCLE),
+];
+
+/// Which keys are held, and the named actions they trigger, so the keys can be remapped
+/// without touching what the actions do. A key triggers a single action, an action may
+/// be triggered by several keys
+#[derive(Clone, Debug)]
+pub struct InputMap {
+    pressed: HashMap<KeyCode, bool>,
+    actions: HashMap<KeyCode, String>,
+}
+
+impl InputMap {
+    /// Nothing bound, not even `QUIT`, leaving only the mouse controls
+    pub fn empty() -> InputMap {
+        InputMap {
+            pressed: HashMap::new(),
+            actions: HashMap::new(),
+        }
+    }
+
+    /// Replaces whatever action `key` triggered
+    pub fn bind(&mut self, key: KeyCode, action: &str) {
+        self.actions.insert(key, action.to_owned());
+    }
+
+    /// The action `key` triggered, if any
+    pub fn unbind(&mut self, key: KeyCode) -> Option<String> {
+        self.actions.remove(&key)
+    }
+
+    /// Every key triggering `action`
+    pub fn unbind_action(&mut self, action: &str) {
+        self.actions.retain(|_, bound| bound != action);
+    }
+
+    pub fn action(&self, key: KeyCode) -> Option<&str> {
+        self.actions.get(&key).map(String::as_str)
+    }
+
+    /// Held since the last `Pressed` event of the key
+    pub fn is_pressed(&self, key: KeyCode) -> bool {
+        self.pressed.get(&key).copied().unwrap_or(false)
+    }
+
+    /// Whether any of the keys triggering `action` is held
+    pub fn is_action_pressed(&self, action: &str) -> bool {
+        self.actions
+            .iter()
+            .any(|(key, bound)| bound == action && self.is_pressed(*key))
+    }
+
+    /// Keeps track of the held keys. Key repeats and other events change nothing
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state,
+                    physical_key: PhysicalKey::Code(key_code),
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.pressed
+                .insert(*key_code, *state == ElementState::Pressed);
+        }
+    }
+
+    /// Whether `event` presses a key triggering `action`
+    pub fn triggers(&self, event: &WindowEvent, action: &str) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key: PhysicalKey::Code(key_code),
+                        ..
+                    },
+                ..
+            } => self.action(*key_code) == Some(action),
+            _ => false,
+        }
+    }
+}
+
+impl Default for InputMap {
+    /// The built-in controls, on the keys their actions name. The `MOVE_*` actions are left unbound
+    fn default() -> InputMap {
+        let mut input_map = InputMap::empty();
+        for (key, action) in DEFAULT_BINDINGS {
+            input_map.bind(key, action);
+        }
+
+        input_map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_a_key_takes_it_from_its_default_action() {
+        let mut input_map = InputMap::default();
+        assert_eq!(input_map.action(KeyCode::KeyW), Some(FLY_FORWARD));
+
+        input_map.bind(KeyCode::KeyW, MOVE_UP);
+        assert_eq!(input_map.action(KeyCode::KeyW), Some(MOVE_UP));
+        assert!(!input_map
+            .actions
+            .values()
+            .any(|action| action == FLY_FORWARD));
+        assert_eq!(input_map.action(KeyCode::Escape), Some(QUIT));
+    }
+}
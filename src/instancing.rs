@@ -0,0 +1,49 @@
+/// Where one copy of an instanced mesh is drawn. Read by `vs_instanced` of shader.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceData {
+    /// Column major, applied before the camera and the transform
+    pub model: [[f32; 4]; 4],
+}
+
+impl InstanceData {
+    pub const IDENTITY: InstanceData = InstanceData {
+        model: [
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ],
+    };
+
+    /// Scaled, then rotated around Z by `angle` radians, then moved to `position`
+    pub fn new(position: [f32; 3], angle: f32, scale: f32) -> InstanceData {
+        let (sin, cos) = angle.sin_cos();
+
+        InstanceData {
+            model: [
+                [cos * scale, sin * scale, 0., 0.],
+                [-sin * scale, cos * scale, 0., 0.],
+                [0., 0., scale, 0.],
+                [position[0], position[1], position[2], 1.],
+            ],
+        }
+    }
+
+    // A vec4 per column, after the attributes of the vertices
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        3 => Float32x4,
+        4 => Float32x4,
+        5 => Float32x4,
+        6 => Float32x4,
+    ];
+
+    /// The second vertex buffer of an instanced pipeline, advanced once per instance
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceData>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
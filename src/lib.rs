@@ -1,231 +1,3915 @@
 use winit::{
     event::{ElementState, Event, KeyEvent, WindowEvent},
     event_loop::EventLoop,
-    keyboard::{KeyCode, PhysicalKey},
+    keyboard::PhysicalKey,
     window::{Window, WindowBuilder},
 };
 
-use wgpu::util::DeviceExt;
+pub mod adapter;
+pub mod alpha_mode;
+pub mod animated_sprite;
+pub mod app;
+pub mod bind_group_builder;
+pub mod bindable;
+pub mod blend;
+pub mod buffer_map;
+pub mod buffer_write;
+pub mod camera2d;
+pub mod camera_animation;
+pub mod camera_shake;
+pub mod canvas2d;
+pub mod color_cycle;
+pub mod compute_job;
+pub mod culling;
+pub mod cursor3d;
+pub mod debug_scope;
+pub mod depth;
+pub mod draw_queue;
+pub mod drawable;
+pub mod dual_buffer;
+pub mod error_policy;
+pub mod fly_camera;
+pub mod hud;
+pub mod index_buffer;
+pub mod input_map;
+pub mod instancing;
+pub mod linked_list_oit;
+pub mod material;
+pub mod mesh_streams;
+pub mod msaa;
+pub mod obj;
+pub mod orbit_camera;
+pub mod parallax;
+pub mod pipeline_stats;
+pub mod point_sprite;
+pub mod ragdoll;
+pub mod ray;
+pub mod render_pass_builder;
+pub mod render_target;
+pub mod resources;
+pub mod ring_buffer;
+pub mod scene;
+pub mod screenshot;
+pub mod selection_highlight;
+pub mod shader_debug;
+pub mod shader_reflection;
+pub mod shape2d;
+pub mod skeleton;
+pub mod skinning;
+pub mod sprite;
+pub mod storage_buffer;
+pub mod strip;
+pub mod texture;
+pub mod texture_atlas;
+pub mod texture_format;
+pub mod texture_pool;
+pub mod tile_map;
+pub mod trails;
+pub mod uniform_buffer;
+pub mod upscale;
+pub mod vertex_buffer;
+pub mod vertex_layout;
+pub mod viewport_label;
+pub mod wboit;
+pub mod wide_line;
+
+// Default of `StateConfig::sample_count`. The color target, the depth texture
+// and the pipelines drawing into them must always share it
+const SAMPLE_COUNT: u32 = 4;
+
+const WINDOW_TITLE: &str = "wgpuing";
+
+// Per-pixel fragment budget of the linked list OIT
+const MAX_TRANSPARENT_FRAGMENTS: u32 = 8;
+
+// How opaque the background of a transparent window is
+const TRANSPARENT_BACKGROUND_ALPHA: f64 = 0.5;
+
+// How fast the arrow keys pan the scene camera, in world units per second
+const PAN_SPEED: f32 = 2.;
+// Holding Ctrl divides the panning speed by it, holding Shift multiplies it
+const PAN_SPEED_MODIFIER: f32 = 4.;
+// The actions panning the scene camera, and how far to the right and forward
+const PAN_ACTIONS: [(&str, [f32; 2]); 4] = [
+    (input_map::PAN_LEFT, [-1., 0.]),
+    (input_map::PAN_RIGHT, [1., 0.]),
+    (input_map::PAN_FORWARD, [0., 1.]),
+    (input_map::PAN_BACK, [0., -1.]),
+];
+// Scrolling by pixels, e.g. on a touchpad, zooms the orbit camera by a line per this many
+const PIXELS_PER_SCROLL_LINE: f32 = 20.;
+// The fly camera reaches its top speed, in world units per second, in half a second
+const FLY_MAX_SPEED: f32 = 4.;
+const FLY_ACCELERATION: f32 = FLY_MAX_SPEED * 2.;
+// The actions flying the scene camera, and which way along the view
+const FLY_ACTIONS: [(&str, [f32; 2]); 4] = [
+    (input_map::FLY_LEFT, [-1., 0.]),
+    (input_map::FLY_RIGHT, [1., 0.]),
+    (input_map::FLY_FORWARD, [0., 1.]),
+    (input_map::FLY_BACK, [0., -1.]),
+];
+// The actions moving the transform, and which way in clip space
+const MOVE_ACTIONS: [(&str, [f32; 2]); 4] = [
+    (input_map::MOVE_LEFT, [-1., 0.]),
+    (input_map::MOVE_RIGHT, [1., 0.]),
+    (input_map::MOVE_UP, [0., 1.]),
+    (input_map::MOVE_DOWN, [0., -1.]),
+];
+// In clip space units per second
+const MOVE_SPEED: f32 = 1.;
+
+// Which order-independent transparency technique `render` uses. Toggled with `O`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OitMode {
+    // Approximate, but cheap
+    Weighted,
+    // Exact, but needs a lot of memory
+    LinkedList,
+}
+
+// How the scene camera is moved. Switched with `M`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CameraMode {
+    // Held arrow keys move it, see `pan_camera`
+    Panning,
+    // Dragging the mouse circles around what it looks at
+    Orbit,
+    // Held WASD keys speed it up along the view, see `fly_camera`
+    Fly,
+}
+
+/// Where command buffers recorded outside of the renderer go in the frame's submission
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubmitOrder {
+    /// The frame sees what they wrote
+    BeforeFrame,
+    /// They see what the frame wrote
+    AfterFrame,
+}
+
+/// The shader of the opaque and transparent pipelines, see `StateConfig::shader`
+pub const DEFAULT_SHADER: &str = include_str!("shader.wgsl");
+
+// What a custom shader must have at least, the rest of the entry points of `DEFAULT_SHADER`
+// are optional, see `ShaderEntryPoints`
+const SHADER_ENTRY_POINTS: [&str; 2] = ["vs_main", "fs_main"];
+
+// Which of the optional entry points of `DEFAULT_SHADER` the shader has. The pipelines of
+// the missing ones aren't created, and what they draw is skipped
+#[derive(Clone, Copy, Debug)]
+struct ShaderEntryPoints {
+    // `fs_color`, or `fs_main` sampling the diffuse texture when the shader has none
+    color: &'static str,
+    // `vs_instanced`, for the instanced pipeline
+    instanced: bool,
+    // `fs_transparent`, for the WBOIT pipeline
+    transparent: bool,
+    // `fs_transparent_linked_list`, for the linked list pipeline
+    linked_list: bool,
+}
+
+impl ShaderEntryPoints {
+    fn of(reflection: &shader_reflection::ShaderReflection) -> ShaderEntryPoints {
+        ShaderEntryPoints {
+            color: if reflection.has_entry_point("fs_color") {
+                "fs_color"
+            } else {
+                "fs_main"
+            },
+            instanced: reflection.has_entry_point("vs_instanced"),
+            transparent: reflection.has_entry_point("fs_transparent"),
+            linked_list: reflection.has_entry_point("fs_transparent_linked_list"),
+        }
+    }
+}
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    position: [f32; 3],
-    color: [f32; 3],
+pub struct Vertex {
+    /// In clip space
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    /// From the top left corner of the texture
+    pub tex_coords: [f32; 2],
 }
 
-impl Vertex {
-    fn desc() -> wgpu::VertexBufferLayout<'static> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
+// What `vs_main` transforms the vertices with, at group 0 of the opaque and transparent pipelines
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    // The depth of the near plane, 1 with reversed Z, for the shaders weighting by the distance
+    near_depth: f32,
+    _padding: [f32; 3],
+}
+
+impl CameraUniform {
+    // The vertices are already in clip space
+    const IDENTITY: CameraUniform = CameraUniform {
+        view_proj: IDENTITY_MATRIX,
+        near_depth: 0.,
+        _padding: [0.; 3],
+    };
+
+    // Clip space vertices drawn with `DepthConfig::reversed_z`: Z becomes 1 - Z,
+    // so the nearest ones are at 1 and pass the `Greater` test against the 0 clear value
+    const REVERSED_Z: CameraUniform = CameraUniform {
+        view_proj: [
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., -1., 0.],
+            [0., 0., 1., 1.],
+        ],
+        near_depth: 1.,
+        _padding: [0.; 3],
+    };
+
+    // Vertices already projected with reversed Z, like the baked scene
+    const REVERSED_Z_PROJECTED: CameraUniform = CameraUniform {
+        view_proj: IDENTITY_MATRIX,
+        near_depth: 1.,
+        _padding: [0.; 3],
+    };
+
+    // For the clip space geometry, and the geometry projected by the scene camera
+    fn for_depth(reversed_z: bool) -> (CameraUniform, CameraUniform) {
+        if reversed_z {
+            (
+                CameraUniform::REVERSED_Z,
+                CameraUniform::REVERSED_Z_PROJECTED,
+            )
+        } else {
+            (CameraUniform::IDENTITY, CameraUniform::IDENTITY)
+        }
+    }
+}
+
+const IDENTITY_MATRIX: [[f32; 4]; 4] = [
+    [1., 0., 0., 0.],
+    [0., 1., 0., 0.],
+    [0., 0., 1., 0.],
+    [0., 0., 0., 1.],
+];
+
+/// Indices into `MeshData::vertices`. 16 bits are enough up to 65536 vertices, at half the size
+#[derive(Clone, Debug)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    fn format(&self) -> wgpu::IndexFormat {
+        match self {
+            Indices::U16(_) => wgpu::IndexFormat::Uint16,
+            Indices::U32(_) => wgpu::IndexFormat::Uint32,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => bytemuck::cast_slice(indices),
+            Indices::U32(indices) => bytemuck::cast_slice(indices),
+        }
+    }
+}
+
+/// A triangle list, drawn with the opaque geometry
+#[derive(Clone, Debug)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    /// Every triangle is 3 consecutive vertices when None
+    pub indices: Option<Indices>,
+}
+
+impl Default for MeshData {
+    // The quad, sharing its corners
+    fn default() -> MeshData {
+        MeshData {
+            vertices: QUAD_VERTICES.to_vec(),
+            indices: Some(Indices::U16(INDICES.to_vec())),
         }
     }
 }
 
+impl Vertex {
+    fn layout() -> vertex_layout::VertexLayout {
+        use vertex_layout::VertexAttributeKind;
+
+        vertex_layout::VertexLayout::new(&[
+            (VertexAttributeKind::Position, wgpu::VertexFormat::Float32x3),
+            (VertexAttributeKind::Color, wgpu::VertexFormat::Float32x3),
+            (VertexAttributeKind::Uv, wgpu::VertexFormat::Float32x2),
+        ])
+        .expect("the vertex layout is valid")
+    }
+}
+
 const VERTICES: &[Vertex] = &[
     Vertex {
         position: [0., 0.5, 0.],
         color: [1., 0., 0.],
+        tex_coords: [0.5, 0.],
     },
     Vertex {
         position: [-0.5, -0.5, 0.],
         color: [0., 1., 0.],
+        tex_coords: [0., 1.],
     },
     Vertex {
         position: [0.5, -0.5, 0.],
         color: [0., 0., 1.],
+        tex_coords: [1., 1.],
     },
 ];
 
-// Just a helper struct that holds everything we need
-struct State<'a> {
-    surface: wgpu::Surface<'a>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    surface_config: wgpu::SurfaceConfiguration,
-    window_size: winit::dpi::PhysicalSize<u32>,
-    window: &'a Window,
-    clear_color: wgpu::Color,
-    render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    vertices_count: u32,
+// A quad on the right, the default `MeshData`. Its 4 corners are shared by the 2 triangles through INDICES
+const QUAD_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [0.6, -0.15, 0.],
+        color: [1., 1., 0.],
+        tex_coords: [0., 1.],
+    },
+    Vertex {
+        position: [0.9, -0.15, 0.],
+        color: [0., 1., 1.],
+        tex_coords: [1., 1.],
+    },
+    Vertex {
+        position: [0.9, 0.15, 0.],
+        color: [1., 0., 1.],
+        tex_coords: [1., 0.],
+    },
+    Vertex {
+        position: [0.6, 0.15, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [0., 0.],
+    },
+];
+
+const INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
+
+// Drawn behind the opaque triangle, so it's only visible around it
+const TRANSPARENT_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-0.75, 0.25, 0.5],
+        color: [1., 1., 0.],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0., -0.75, 0.5],
+        color: [0., 1., 1.],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.75, 0.25, 0.5],
+        color: [1., 0., 1.],
+        tex_coords: [0., 0.],
+    },
+];
+
+// Two quads in the bottom corners, each a separate strip of the same index buffer
+const STRIP_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-0.9, -0.6, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [-0.9, -0.9, 0.],
+        color: [0.5, 0.5, 0.5],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [-0.6, -0.6, 0.],
+        color: [0.5, 0.5, 0.5],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [-0.6, -0.9, 0.],
+        color: [0., 0., 0.],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.6, -0.6, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.6, -0.9, 0.],
+        color: [0.5, 0.5, 0.5],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.9, -0.6, 0.],
+        color: [0.5, 0.5, 0.5],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.9, -0.9, 0.],
+        color: [0., 0., 0.],
+        tex_coords: [0., 0.],
+    },
+];
+
+// A quad at the bottom, between the strips, showing the whole diffuse texture
+const TEXTURED_QUAD_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-0.15, -0.9, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [0., 1.],
+    },
+    Vertex {
+        position: [0.15, -0.9, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [1., 1.],
+    },
+    Vertex {
+        position: [0.15, -0.6, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [1., 0.],
+    },
+    Vertex {
+        position: [-0.15, -0.9, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [0., 1.],
+    },
+    Vertex {
+        position: [0.15, -0.6, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [1., 0.],
+    },
+    Vertex {
+        position: [-0.15, -0.6, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [0., 0.],
+    },
+];
+
+// Two overlapping triangles in the top right, the farther one drawn last.
+// The depth test keeps the closer one in front (the farther one with `--reversed-z`)
+const OCCLUSION_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [0.6, 0.2, 0.2],
+        color: [1., 0.5, 0.],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.85, 0.2, 0.2],
+        color: [1., 0.5, 0.],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.725, 0.45, 0.2],
+        color: [1., 0.5, 0.],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.7, 0.25, 0.6],
+        color: [0., 0.6, 0.6],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.95, 0.25, 0.6],
+        color: [0., 0.6, 0.6],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.825, 0.5, 0.6],
+        color: [0., 0.6, 0.6],
+        tex_coords: [0., 0.],
+    },
+];
+
+const STRIPS: &[&[u16]] = &[&[0, 1, 2, 3], &[4, 5, 6, 7]];
+
+// The mesh drawn instanced, around the origin and a unit across
+const INSTANCED_TRIANGLE_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-0.5, -0.4, 0.],
+        color: [1., 0.3, 0.6],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0.5, -0.4, 0.],
+        color: [0.3, 1., 0.6],
+        tex_coords: [0., 0.],
+    },
+    Vertex {
+        position: [0., 0.5, 0.],
+        color: [0.3, 0.6, 1.],
+        tex_coords: [0., 0.],
+    },
+];
+const DEMO_INSTANCES_COUNT: usize = 10_000;
+
+// The instances drawn from the start: 10 by 10 in the top left corner
+const INSTANCE_GRID_SIZE: usize = 10;
+const INSTANCE_GRID_ORIGIN: [f32; 2] = [-0.92, 0.22];
+const INSTANCE_GRID_SPACING: f32 = 0.04;
+
+fn instance_grid() -> Vec<instancing::InstanceData> {
+    (0..INSTANCE_GRID_SIZE * INSTANCE_GRID_SIZE)
+        .map(|i| {
+            let (column, row) = (i % INSTANCE_GRID_SIZE, i / INSTANCE_GRID_SIZE);
+            let [x, y] = INSTANCE_GRID_ORIGIN;
+            let position = [
+                x + column as f32 * INSTANCE_GRID_SPACING,
+                y + row as f32 * INSTANCE_GRID_SPACING,
+                0.,
+            ];
+
+            instancing::InstanceData::new(position, 0., INSTANCE_GRID_SPACING * 0.8)
+        })
+        .collect()
 }
 
-impl<'a> State<'a> {
-    async fn new(window: &'a Window) -> State<'a> {
-        // 1. Get the device and queue
-        // Instance of wgpu. Used to work with wgpu and access the api.
-        let wgpu_instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+// Scattered all over the screen, at random depths, angles and sizes. Always the same ones
+fn random_instances(count: usize) -> Vec<instancing::InstanceData> {
+    // xorshift32, to [0, 1)
+    let mut state = 0x2545_f491_u32;
+    let mut random = || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state >> 8) as f32 / (1 << 24) as f32
+    };
 
-        // Surface - is the part of the window we draw to. A "canvas"
-        let surface = wgpu_instance.create_surface(window).unwrap();
+    (0..count)
+        .map(|_| {
+            let position = [random() * 2. - 1., random() * 2. - 1., 0.1 + random() * 0.8];
+            let angle = random() * std::f32::consts::TAU;
+            let scale = 0.01 + random() * 0.03;
 
-        // A handle to GPU. Needed to get the device
-        let adapter = wgpu_instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+            instancing::InstanceData::new(position, angle, scale)
+        })
+        .collect()
+}
 
-        // TODO: What is device and queue
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
-                    label: Some("My device"),
-                },
-                None,
-            )
-            .await
-            .unwrap();
+// Squares on the left, by material color: two share the red one
+const MATERIAL_SQUARES: &[([f32; 4], &[[f32; 2]])] = &[
+    ([1., 0.3, 0.3, 1.], &[[-0.95, -0.05], [-0.8, -0.05]]),
+    ([0.3, 0.3, 1., 1.], &[[-0.65, -0.05]]),
+];
+const MATERIAL_SQUARE_SIZE: f32 = 0.1;
 
-        // 2. Configuring the surface
-        let surface_caps = surface.get_capabilities(&adapter);
-        let window_size = window.inner_size();
+// Two counter-clockwise triangles, white so the material color shows as is
+fn square(x: f32, y: f32, size: f32) -> [Vertex; 6] {
+    [
+        [x, y],
+        [x + size, y],
+        [x + size, y + size],
+        [x, y],
+        [x + size, y + size],
+        [x, y + size],
+    ]
+    .map(|[x, y]| Vertex {
+        position: [x, y, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [0., 0.],
+    })
+}
 
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_caps
-                .formats
-                .iter()
-                .find(|f| f.is_srgb())
-                .copied()
-                .unwrap_or(surface_caps.formats[0]),
-            width: window_size.width,
-            height: window_size.height,
-            present_mode: wgpu::PresentMode::AutoVsync,
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: Vec::new(),
-            desired_maximum_frame_latency: 2,
-        };
+// The outline `H` draws around the selected material squares, and its width in pixels
+const SELECTION_COLOR: [f32; 4] = [1., 0.8, 0., 1.];
+const SELECTION_THICKNESS: f32 = 3.;
 
-        // 3. Load shaders
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("My shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
+// Under the FPS counter, tells whether `R` shows the frame in grayscale
+const RENDER_MODE_LABEL_POSITION: [f32; 2] = [12., 44.];
+const RENDER_MODE_LABEL_FONT_SIZE: f32 = 15.;
 
-        // 4. Create render pipeline layout
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("My pipeline layout"),
-                bind_group_layouts: &[],
-                push_constant_ranges: &[],
-            });
+// Dots circling around the walking sprite
+const POINT_SPRITES_COUNT: usize = 12;
+const POINT_SPRITE_SIZE: f32 = 10.;
+const POINT_SPRITE_COLOR: [f32; 4] = [1., 0.9, 0.4, 0.8];
 
-        // 5. Create render pipeline
-        // Render pipeline describes what actions GPU must perform on data
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("My render pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+fn point_sprite_positions(time: f32) -> Vec<[f32; 2]> {
+    (0..POINT_SPRITES_COUNT)
+        .map(|i| {
+            let angle = time + i as f32 * std::f32::consts::TAU / POINT_SPRITES_COUNT as f32;
+            let (sin, cos) = angle.sin_cos();
+
+            [-0.7 + 0.25 * cos, -0.6 + 0.25 * sin]
+        })
+        .collect()
+}
+
+// A sine wave across the top of the screen, drawn as a thick line
+fn wavy_line() -> Vec<[f32; 2]> {
+    const POINTS_COUNT: usize = 48;
+
+    (0..POINTS_COUNT)
+        .map(|i| {
+            let x = -0.9 + 1.8 * i as f32 / (POINTS_COUNT - 1) as f32;
+
+            [x, 0.7 + 0.1 * (x * 9.).sin()]
+        })
+        .collect()
+}
+
+// The impulse `K` shakes the scene camera with: seconds, world units and changes per second
+const DEMO_SHAKE: (f32, f32, f32) = (0.8, 0.15, 18.);
+
+// A ribbon below the triangle, its vertices written by a compute job, see sine_wave.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SineWaveParams {
+    origin: [f32; 2],
+    length: f32,
+    amplitude: f32,
+    periods: f32,
+    thickness: f32,
+    samples_count: u32,
+    _padding: u32,
+}
+
+const SINE_WAVE: SineWaveParams = SineWaveParams {
+    origin: [-0.45, -0.4],
+    length: 0.9,
+    amplitude: 0.06,
+    periods: 3.,
+    thickness: 0.02,
+    samples_count: 96,
+    _padding: 0,
+};
+
+// Writes the `SINE_WAVE` strip into `vertices`, which holds two vertices per sample
+fn sine_wave_job(device: &wgpu::Device, vertices: &wgpu::Buffer) -> compute_job::ComputeJob {
+    const WORKGROUP_SIZE: u32 = 64;
+
+    let shader_source = include_str!("sine_wave.wgsl");
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("My sine wave shader"),
+        source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+    });
+
+    let params =
+        uniform_buffer::UniformBuffer::new(device, "My sine wave params buffer", SINE_WAVE);
+
+    let reflection = shader_reflection::ShaderReflection::from_wgsl(shader_source)
+        .expect("the sine wave shader is valid");
+    let bind_group_layout = reflection.create_bind_group_layout(device, 0);
+    let bind_group = bind_group_builder::BindGroupBuilder::from_reflection(&reflection, device)
+        .bind_resource(0, &params)
+        .bind_buffer(1, vertices)
+        .build(&bind_group_layout)
+        .expect("the sine wave buffers match the shader");
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("My sine wave pipeline layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("My sine wave pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "cs_main",
+    });
+
+    compute_job::ComputeJob::new(
+        pipeline,
+        bind_group,
+        (SINE_WAVE.samples_count.div_ceil(WORKGROUP_SIZE), 1, 1),
+    )
+}
+
+// Half the width of the 3D cursor's cross, in world units
+const CURSOR_SIZE: f32 = 0.15;
+
+// Seconds from one waypoint of the camera tour to the next
+const CAMERA_TOUR_STEP: f32 = 2.;
+
+// Circles around where `camera` looks, rising and dipping, and back to where it started
+fn camera_tour(camera: &scene::Camera) -> camera_animation::CameraAnimation {
+    const WAYPOINTS_COUNT: usize = 6;
+
+    let [x, y, z] = camera.eye;
+    let [target_x, target_y, target_z] = camera.target;
+    let (dx, dz) = (x - target_x, z - target_z);
+    // Looking straight down, the camera circles at the height it's at instead
+    let radius = match dx.hypot(dz) {
+        radius if radius > 0. => radius,
+        _ => (y - target_y).abs().max(1.),
+    };
+    let start_angle = dx.atan2(dz);
+
+    let mut waypoints: Vec<_> = (0..WAYPOINTS_COUNT)
+        .map(|i| {
+            let angle = start_angle + i as f32 * std::f32::consts::TAU / WAYPOINTS_COUNT as f32;
+            let (sin, cos) = angle.sin_cos();
+            let height = match i {
+                0 => y,
+                _ if i % 2 == 0 => y + radius * 0.5,
+                _ => y - radius * 0.5,
+            };
+
+            camera_animation::CameraWaypoint {
+                position: [target_x + radius * sin, height, target_z + radius * cos],
+                target: camera.target,
+                up: [0., 1., 0.],
+                time_secs: i as f32 * CAMERA_TOUR_STEP,
+            }
+        })
+        .collect();
+    // Back to the first one, so the loop is seamless
+    waypoints.push(camera_animation::CameraWaypoint {
+        time_secs: WAYPOINTS_COUNT as f32 * CAMERA_TOUR_STEP,
+        ..waypoints[0]
+    });
+
+    camera_animation::CameraAnimation::new(waypoints)
+}
+
+const WAVY_LINE_STYLE: wide_line::WideLineStyle = wide_line::WideLineStyle {
+    width: 12.,
+    join: wide_line::JoinStyle::Miter,
+    miter_limit: 4.,
+    color: [1., 0.6, 0., 1.],
+};
+
+// The walking sprite sheet: frames of 16x16 pixels side by side
+const WALKER_FRAME_SIZE: u32 = 16;
+// How far the feet are from the middle in each frame
+const WALKER_STRIDES: [i32; 4] = [3, 1, -1, -3];
+
+// A white stick figure swinging its arms and legs. Returns the width, the height and the RGBA pixels
+fn walking_sheet() -> (u32, u32, Vec<u8>) {
+    let (width, height) = (
+        WALKER_FRAME_SIZE * WALKER_STRIDES.len() as u32,
+        WALKER_FRAME_SIZE,
+    );
+    let mut pixels = vec![0; (width * height * 4) as usize];
+
+    let mut line = |(x0, y0): (i32, i32), (x1, y1): (i32, i32)| {
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+
+        for step in 0..=steps {
+            let x = x0 + (x1 - x0) * step / steps;
+            let y = y0 + (y1 - y0) * step / steps;
+            let offset = ((y as u32 * width + x as u32) * 4) as usize;
+
+            pixels[offset..offset + 4].copy_from_slice(&[255; 4]);
+        }
+    };
+
+    for (frame, stride) in WALKER_STRIDES.iter().enumerate() {
+        let x = (frame as u32 * WALKER_FRAME_SIZE) as i32 + 8;
+
+        // Head
+        line((x - 1, 1), (x + 1, 1));
+        line((x - 1, 3), (x + 1, 3));
+        line((x - 1, 1), (x - 1, 3));
+        line((x + 1, 1), (x + 1, 3));
+        // Body
+        line((x, 4), (x, 10));
+        // Arms swing against the legs
+        line((x, 6), (x - stride, 9));
+        line((x, 6), (x + stride, 9));
+        // Legs
+        line((x, 10), (x + stride, 15));
+        line((x, 10), (x - stride, 15));
+    }
+
+    (width, height, pixels)
+}
+
+// Sent by the OBJ import thread
+enum ImportEvent {
+    Progress(f32),
+    Done(Result<obj::ObjMesh, obj::ObjError>),
+}
+
+/// Where `State::render` and `State::update` run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Threading {
+    /// Everything runs on the event loop thread
+    #[default]
+    SingleThreaded,
+    /// A dedicated render thread. The event loop only forwards the events to it,
+    /// so a slow frame doesn't block e.g. dragging the window
+    RenderThread,
+}
+
+/// What happens to a window event, decided by `StateConfig::event_filter` before anything else sees it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventDisposition {
+    /// The app handled it, nothing else sees it
+    Consume,
+    /// Skips the interactive controls, but the built-in handling
+    /// (closing, the `input_map::QUIT` action, resizing) still applies
+    Ignore,
+    /// Handled as if there were no filter
+    Passthrough,
+}
+
+/// Everything that can be tuned before the `State` is created
+#[derive(Clone, Debug)]
+pub struct StateConfig {
+    pub threading: Threading,
+    /// Which GPUs may be used, software ones are rejected by default
+    pub adapter: adapter::AdapterPreference,
+    /// How the depth is tested, and the stencil. Its clear value is replaced by `clear_depth`
+    pub depth: depth::DepthConfig,
+    /// Overrides `depth` with `DepthConfig::reversed_z`, and the scene camera puts
+    /// the near plane at 1.0, so the float precision is spread evenly over the distance
+    /// instead of being spent near the camera. It takes a float depth format, so with the stencil
+    /// it's `depth::REVERSED_Z_STENCIL_FORMAT` and `State::new` fails where it isn't supported.
+    /// The geometry already in clip space has its depth flipped by the camera uniform to match
+    pub reversed_z: bool,
+    /// What the depth buffer is cleared to every frame, e.g. 0.0 with a `Greater` depth compare.
+    /// `State::new` fails if nothing passes the depth compare against it. 0.0 with `reversed_z`
+    pub clear_depth: f32,
+    /// What the stencil buffer is cleared to every frame, where the depth format has one
+    pub clear_stencil: u32,
+    /// Joint influences per vertex of the skinned meshes, see `skinning::SkinningPass`
+    pub max_influences: skinning::MaxInfluences,
+    /// How many frames the CPU may queue ahead of the GPU, within `FRAME_LATENCY_RANGE`.
+    /// 1 shows the input in the very next frame, but the CPU waits for the GPU every frame,
+    /// so any hitch drops a frame. 3 keeps a GPU bound renderer busy for the best throughput,
+    /// at the cost of the input showing up to 3 frames late
+    pub frame_latency: u32,
+    /// Falls back to `AutoVsync` when the surface doesn't support it
+    pub present_mode: wgpu::PresentMode,
+    /// None, or one the surface doesn't support, picks the first sRGB format of the surface,
+    /// or its first format when it has no sRGB one
+    pub surface_format: Option<wgpu::TextureFormat>,
+    /// MSAA samples per pixel, 1, 2, 4 or 8. 1 turns it off, an invalid one too with a warning.
+    /// One the adapter doesn't support falls back to the largest supported one below it
+    pub sample_count: u32,
+    /// Renders at this fixed width and height, scaled up to the window by a whole factor.
+    /// None renders at the window resolution
+    pub internal_resolution: Option<(u32, u32)>,
+    /// How the GPU errors happening while running are surfaced
+    pub error_policy: error_policy::ErrorPolicy,
+    /// Lets the desktop show through the background, where the surface supports it
+    pub transparent_window: bool,
+    /// Dxc compiles faster and reports clearer errors than Fxc,
+    /// but needs `dxcompiler.dll` and `dxil.dll` shipped with the app
+    pub dx12_shader_compiler: wgpu::Dx12Compiler,
+    /// Requesting OpenGL ES 3.0 instead of the latest minor version works around some old drivers
+    pub gles_minor_version: wgpu::Gles3MinorVersion,
+    /// Consulted for every window event, e.g. for a host app to take over the keyboard
+    pub event_filter: fn(&WindowEvent) -> EventDisposition,
+    /// The keys triggering the actions, e.g. `input_map::QUIT`. Sees the events
+    /// the filter doesn't consume, whether `State` handles them or not
+    pub input_map: input_map::InputMap,
+    /// A JSON scene drawn with the opaque geometry, see `scene::Scene::from_json`.
+    /// It's reloaded whenever the file changes
+    pub scene_path: Option<std::path::PathBuf>,
+    /// Squeezes the geometry by the aspect ratio of the frame, so it isn't stretched with the window.
+    /// Applied after the matrix of `State::set_transform`
+    pub correct_aspect_ratio: bool,
+    /// Drawn indexed when it has indices
+    pub mesh: MeshData,
+    /// WGSL replacing `DEFAULT_SHADER` for all the geometry drawn with `Vertex`.
+    /// It needs `vs_main`, `fs_main` and the same bind groups. Without `fs_color`, the untextured
+    /// geometry is drawn with `fs_main`. Without `vs_instanced`, `fs_transparent` or
+    /// `fs_transparent_linked_list`, the instances or the transparent geometry aren't drawn
+    pub shader: Option<String>,
+}
+
+pub const FRAME_LATENCY_RANGE: std::ops::RangeInclusive<u32> = 1..=3;
+
+/// Creates a `State` from a `StateConfig` tuned a setting at a time, e.g.
+/// `StateBuilder::default().present_mode(wgpu::PresentMode::Fifo).build(&window).await?`
+#[derive(Clone, Debug, Default)]
+pub struct StateBuilder {
+    config: StateConfig,
+}
+
+impl From<StateConfig> for StateBuilder {
+    fn from(config: StateConfig) -> StateBuilder {
+        StateBuilder { config }
+    }
+}
+
+impl StateBuilder {
+    pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> StateBuilder {
+        self.config.adapter.power_preference = power_preference;
+        self
+    }
+
+    pub fn present_mode(mut self, present_mode: wgpu::PresentMode) -> StateBuilder {
+        self.config.present_mode = present_mode;
+        self
+    }
+
+    pub fn surface_format(mut self, surface_format: wgpu::TextureFormat) -> StateBuilder {
+        self.config.surface_format = Some(surface_format);
+        self
+    }
+
+    pub fn frame_latency(mut self, frame_latency: u32) -> StateBuilder {
+        self.config.frame_latency = frame_latency;
+        self
+    }
+
+    pub fn sample_count(mut self, sample_count: u32) -> StateBuilder {
+        self.config.sample_count = sample_count;
+        self
+    }
+
+    pub fn config(&self) -> &StateConfig {
+        &self.config
+    }
+
+    pub async fn build(self, window: &Window) -> Result<State<'_>, StateError> {
+        State::new(window, self.config).await
+    }
+}
+
+/// Why a `State` couldn't be created, e.g. on a machine without a GPU
+#[derive(Debug)]
+pub enum StateError {
+    CreateSurface(wgpu::CreateSurfaceError),
+    /// None of the adapters of the `AdapterPreference` types can present to the window
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    /// `StateConfig::shader` doesn't compile
+    Shader(shader_reflection::ShaderReflectionError),
+    /// `StateConfig::shader` lacks `vs_main` or `fs_main`
+    MissingEntryPoint(&'static str),
+    /// The bindings of groups 0 to 2 of `StateConfig::shader` differ from `DEFAULT_SHADER`'s
+    ShaderBindings(bind_group_builder::BindGroupError),
+    /// `StateConfig::mesh` has indices past its vertices or partial triangles
+    Indices(index_buffer::IndexError),
+    /// The depth texture and the color target of the main pass differ in samples per pixel
+    DepthSampleCount(render_pass_builder::PassCompatibilityError),
+    /// `StateConfig::clear_depth` is out of range or never passes the depth compare
+    DepthConfig(depth::DepthConfigError),
+    /// The adapter lacks the features of the depth format, e.g. `StateConfig::reversed_z`
+    /// with the stencil
+    UnsupportedDepthFormat(wgpu::TextureFormat),
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::CreateSurface(error) => write!(f, "can't create the surface: {}", error),
+            StateError::NoAdapter => {
+                write!(f, "no GPU of the preferred types can draw to the window")
+            }
+            StateError::RequestDevice(error) => write!(f, "can't get the device: {}", error),
+            StateError::Shader(error) => write!(f, "the shader is invalid: {}", error),
+            StateError::MissingEntryPoint(name) => {
+                write!(f, "the shader has no `{}` entry point", name)
+            }
+            StateError::ShaderBindings(error) => {
+                write!(f, "the shader bindings don't match: {}", error)
+            }
+            StateError::Indices(error) => write!(f, "the mesh indices are invalid: {}", error),
+            StateError::DepthSampleCount(error) => {
+                write!(
+                    f,
+                    "the depth texture doesn't match the main pass: {}",
+                    error
+                )
+            }
+            StateError::DepthConfig(error) => write!(f, "the depth config is invalid: {}", error),
+            StateError::UnsupportedDepthFormat(format) => {
+                write!(f, "the GPU doesn't support {:?} depth textures", format)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StateError::CreateSurface(error) => Some(error),
+            StateError::NoAdapter => None,
+            StateError::RequestDevice(error) => Some(error),
+            StateError::Shader(error) => Some(error),
+            StateError::MissingEntryPoint(_) => None,
+            StateError::ShaderBindings(error) => Some(error),
+            StateError::Indices(error) => Some(error),
+            StateError::DepthSampleCount(error) => Some(error),
+            StateError::DepthConfig(error) => Some(error),
+            StateError::UnsupportedDepthFormat(_) => None,
+        }
+    }
+}
+
+impl Default for StateConfig {
+    fn default() -> StateConfig {
+        StateConfig {
+            threading: Threading::default(),
+            adapter: adapter::AdapterPreference::default(),
+            depth: depth::DepthConfig::default(),
+            reversed_z: false,
+            clear_depth: 1.0,
+            clear_stencil: 0,
+            max_influences: skinning::MaxInfluences::default(),
+            frame_latency: 2,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            surface_format: None,
+            sample_count: SAMPLE_COUNT,
+            internal_resolution: None,
+            error_policy: error_policy::ErrorPolicy::default(),
+            transparent_window: false,
+            dx12_shader_compiler: wgpu::Dx12Compiler::Fxc,
+            gles_minor_version: wgpu::Gles3MinorVersion::default(),
+            event_filter: |_| EventDisposition::Passthrough,
+            input_map: input_map::InputMap::default(),
+            scene_path: None,
+            correct_aspect_ratio: false,
+            mesh: MeshData::default(),
+            shader: None,
+        }
+    }
+}
+
+const TRIANGLE_LIST: wgpu::PrimitiveState = wgpu::PrimitiveState {
+    topology: wgpu::PrimitiveTopology::TriangleList,
+    strip_index_format: None,
+    front_face: wgpu::FrontFace::Ccw, // ------ - Don't render triangles that are not visible
+    cull_mode: Some(wgpu::Face::Back), // ---/
+    polygon_mode: wgpu::PolygonMode::Fill,
+    unclipped_depth: false,
+    conservative: false,
+};
+
+// The opaque pipelines of shader.wgsl drawing the polygons as lines, for debugging the geometry
+struct WireframePipelines {
+    render: wgpu::RenderPipeline,
+    textured: wgpu::RenderPipeline,
+    strip: wgpu::RenderPipeline,
+}
+
+// The opaque pipelines of shader.wgsl for `State::render_to_target`, single sampled
+struct OffscreenPipelines {
+    format: wgpu::TextureFormat,
+    render: wgpu::RenderPipeline,
+    textured: wgpu::RenderPipeline,
+}
+
+// None when the device doesn't have `POLYGON_MODE_LINE`
+#[allow(clippy::too_many_arguments)]
+fn create_wireframe_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    color_entry_point: &str,
+    vertex_layout: &vertex_layout::VertexLayout,
+    color_format: wgpu::TextureFormat,
+    depth_config: depth::DepthConfig,
+    sample_count: u32,
+) -> Option<WireframePipelines> {
+    if !device
+        .features()
+        .contains(wgpu::Features::POLYGON_MODE_LINE)
+    {
+        return None;
+    }
+
+    let create = |label: &str, fragment_entry_point: &str, primitive: wgpu::PrimitiveState| {
+        create_render_pipeline(
+            device,
+            label,
+            layout,
+            shader,
+            fragment_entry_point,
+            vertex_layout,
+            wgpu::PrimitiveState {
+                polygon_mode: wgpu::PolygonMode::Line,
+                ..primitive
+            },
+            color_format,
+            blend::BlendMode::Replace,
+            depth_config,
+            sample_count,
+        )
+    };
+
+    Some(WireframePipelines {
+        render: create(
+            "My wireframe render pipeline",
+            color_entry_point,
+            TRIANGLE_LIST,
+        ),
+        textured: create("My wireframe textured pipeline", "fs_main", TRIANGLE_LIST),
+        strip: create(
+            "My wireframe strip pipeline",
+            color_entry_point,
+            strip::primitive_state::<u16>(),
+        ),
+    })
+}
+
+// The texture and the sampler of group 2 of shader.wgsl, sampled by `fs_main`
+fn create_diffuse_bind_group(
+    device: &wgpu::Device,
+    reflection: &shader_reflection::ShaderReflection,
+    layout: &wgpu::BindGroupLayout,
+    texture: &texture::Texture,
+) -> Result<wgpu::BindGroup, bind_group_builder::BindGroupError> {
+    bind_group_builder::BindGroupBuilder::from_reflection(reflection, device)
+        .for_group(2)
+        .bind_resource(0, texture)
+        .bind_sampler(1, &texture.sampler)
+        .build(layout)
+}
+
+// What the pipelines of `create_render_pipeline` draw to, as they're created
+fn render_pipeline_targets(
+    color_format: wgpu::TextureFormat,
+    depth_config: depth::DepthConfig,
+    sample_count: u32,
+) -> render_pass_builder::PipelineTargets {
+    render_pass_builder::PipelineTargets {
+        color_formats: vec![Some(color_format)],
+        depth_format: Some(depth_config.format()),
+        sample_count,
+    }
+}
+
+// The opaque pipelines are rebuilt whenever the format of the target changes
+#[allow(clippy::too_many_arguments)]
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    vertex_layout: &vertex_layout::VertexLayout,
+    primitive: wgpu::PrimitiveState,
+    color_format: wgpu::TextureFormat,
+    blend_mode: blend::BlendMode,
+    depth_config: depth::DepthConfig,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[vertex_layout.buffer_layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(blend_mode.state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive,
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth_config.format(),
+            depth_write_enabled: true,
+            depth_compare: depth_config.depth_compare(),
+            stencil: depth_config.stencil_state(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+// Draws every vertex of the mesh once per `InstanceData`, in buffer slot 1, with `vs_instanced`
+#[allow(clippy::too_many_arguments)]
+fn create_instanced_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    vertex_layout: &vertex_layout::VertexLayout,
+    color_format: wgpu::TextureFormat,
+    depth_config: depth::DepthConfig,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("My instanced render pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_instanced",
+            buffers: &[
+                vertex_layout.buffer_layout(),
+                instancing::InstanceData::desc(),
+            ],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(blend::BlendMode::Replace.state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: TRIANGLE_LIST,
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth_config.format(),
+            depth_write_enabled: true,
+            depth_compare: depth_config.depth_compare(),
+            stencil: depth_config.stencil_state(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+// Lines drawn over everything, whatever the depth, for the 3D cursor
+#[allow(clippy::too_many_arguments)]
+fn create_cursor_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    vertex_layout: &vertex_layout::VertexLayout,
+    color_format: wgpu::TextureFormat,
+    depth_config: depth::DepthConfig,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("My 3D cursor pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[vertex_layout.buffer_layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(blend::BlendMode::Replace.state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::LineList,
+            cull_mode: None,
+            ..TRIANGLE_LIST
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth_config.format(),
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+// Transparent geometry is tested against the opaque depth, but doesn't write to it.
+// `targets` are the ones of the OIT pass it's drawn in
+#[allow(clippy::too_many_arguments)]
+fn create_transparent_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    targets: &[Option<wgpu::ColorTargetState>],
+    vertex_layout: &vertex_layout::VertexLayout,
+    depth_config: depth::DepthConfig,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[vertex_layout.buffer_layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry_point,
+            targets,
+        }),
+        primitive: wgpu::PrimitiveState {
+            cull_mode: None, // The back faces of transparent geometry are visible
+            ..TRIANGLE_LIST
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth_config.format(),
+            depth_write_enabled: false,
+            depth_compare: depth_config.depth_compare(),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+// Just a helper struct that holds everything we need
+pub struct State<'a> {
+    surface: wgpu::Surface<'a>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    surface_config: wgpu::SurfaceConfiguration,
+    window_size: winit::dpi::PhysicalSize<u32>,
+    window: &'a Window,
+    cursor_position: winit::dpi::PhysicalPosition<f64>,
+    clear_color: wgpu::Color,
+    surface_view_format: wgpu::TextureFormat,
+    shader: wgpu::ShaderModule,
+    shader_entry_points: ShaderEntryPoints,
+    camera_buffer: uniform_buffer::UniformBuffer<CameraUniform>,
+    camera_bind_group: wgpu::BindGroup,
+    // For the geometry the scene camera already projected, the baked scene and the 3D cursor
+    scene_camera_bind_group: wgpu::BindGroup,
+    // Set with `set_transform`, uploaded with the aspect ratio correction if any
+    transform: [[f32; 4]; 4],
+    transform_buffer: uniform_buffer::UniformBuffer<[[f32; 4]; 4]>,
+    transform_bind_group: wgpu::BindGroup,
+    // Of shader.wgsl, for the bind groups created after `new`
+    shader_reflection: shader_reflection::ShaderReflection,
+    diffuse_bind_group_layout: wgpu::BindGroupLayout,
+    // Set with `bind_texture`
+    diffuse_bind_group: wgpu::BindGroup,
+    // The bundled ones, and which of them `X` bound last
+    diffuse_textures: [texture::Texture; 2],
+    diffuse_texture_index: usize,
+    correct_aspect_ratio: bool,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    vertex_layout: vertex_layout::VertexLayout,
+    render_pipeline: wgpu::RenderPipeline,
+    // What the opaque pipelines were created for, checked against the attachments every frame
+    render_pipeline_targets: render_pass_builder::PipelineTargets,
+    textured_pipeline: wgpu::RenderPipeline,
+    // None where the polygons can't be drawn as lines
+    wireframe_pipelines: Option<WireframePipelines>,
+    // Draws the opaque geometry with the `wireframe_pipelines`, where there are some
+    wireframe: bool,
+    triangle: drawable::Drawable,
+    textured_quad: drawable::Drawable,
+    // None when the shader has no `vs_instanced`
+    instanced_pipeline: Option<wgpu::RenderPipeline>,
+    // Drawn once per instance of the `instance_buffer`
+    instanced_mesh: drawable::Drawable,
+    // Set with `set_instances`, None when there are none
+    instance_buffer: Option<vertex_buffer::VertexBuffer>,
+    // `StateConfig::max_influences`, the ones of the meshes of `set_skinned_mesh`
+    max_influences: skinning::MaxInfluences,
+    // Set with `set_skinned_mesh`, skinned every frame before the render passes
+    skinning: Option<skinning::SkinningPass>,
+    skinned_mesh_pipeline: skinning::SkinnedMeshPipeline,
+    cursor_pipeline: wgpu::RenderPipeline,
+    // Where the mouse points at in the scene, moved in `update`
+    cursor: cursor3d::Cursor3D,
+    // `StateConfig::mesh`. Drawn with `draw` when it has no indices
+    mesh: drawable::Drawable,
+    // What `mesh` was created from, for `export_obj`
+    mesh_data: MeshData,
+    // The indices of `mesh`, checked against its vertices
+    index_buffer: Option<index_buffer::IndexBuffer>,
+    // Shows the depth test deciding what's in front, whatever the drawing order
+    occlusion: drawable::Drawable,
+    strip_pipeline: wgpu::RenderPipeline,
+    strips: strip::StripMesh,
+    culler: culling::GpuFrustumCuller,
+    // Culls the mesh when it has indices, so it's drawn with `draw_indexed_indirect`
+    mesh_culler: Option<culling::GpuFrustumCuller>,
+    depth_config: depth::DepthConfig,
+    reversed_z: bool,
+    depth_texture: depth::DepthTexture,
+    // Of the depth texture and every pipeline drawing with it, see `set_sample_count`
+    sample_count: u32,
+    // Which sample counts `set_sample_count` accepts
+    sample_count_support: msaa::SampleCountSupport,
+    // None without MSAA, the frame being rendered into directly
+    msaa_target: Option<msaa::MsaaTarget>,
+    clear_depth: f32,
+    clear_stencil: u32,
+    // None when the shader has no `fs_transparent`
+    transparent_pipeline: Option<wgpu::RenderPipeline>,
+    transparent_triangle: drawable::Drawable,
+    wboit: wboit::WboitPass,
+    linked_list_pipeline_layout: wgpu::PipelineLayout,
+    // None when the shader has no `fs_transparent_linked_list`
+    linked_list_pipeline: Option<wgpu::RenderPipeline>,
+    linked_list_oit: linked_list_oit::LinkedListOit,
+    oit_mode: OitMode,
+    layer_mask: u32,
+    slow_frames: bool,
+    trails: trails::TrailsPass,
+    // Set when rendering at a fixed resolution instead of the window one
+    upscale: Option<upscale::UpscalePass>,
+    // Whether the previous frames fade away instead of being cleared. Toggled with `T`
+    trails_enabled: bool,
+    start_time: std::time::Instant,
+    errors: error_policy::ErrorReporter,
+    event_filter: fn(&WindowEvent) -> EventDisposition,
+    input_map: input_map::InputMap,
+    // Submitted with the next frame, in the order they were added
+    command_buffers_before: Vec<wgpu::CommandBuffer>,
+    command_buffers_after: Vec<wgpu::CommandBuffer>,
+    // Recolors the transparent triangle from a compute pass. Toggled with `C`
+    color_cycle: color_cycle::ColorCycle,
+    color_cycle_enabled: bool,
+    // Run before the next frame's render pass, see `add_compute_job`
+    compute_jobs: Vec<compute_job::ComputeJob>,
+    // Written by a compute job, drawn as a strip
+    sine_wave_buffer: wgpu::Buffer,
+    sprites: sprite::SpriteRenderer,
+    // Its join style is cycled with `J`
+    wide_line: wide_line::WideLine,
+    // Cycles through the walking sprite sheet
+    walker: animated_sprite::AnimatedSprite,
+    point_sprites: point_sprite::PointSpriteRenderer,
+    // Outlines the meshes of `resources` selected with `H`
+    selection_highlight: selection_highlight::SelectionHighlight,
+    // Draws render targets onto the surface, see `blit_to_screen`
+    blit: render_target::Blit,
+    // Created for the format of the last `render_to_target`
+    offscreen_pipelines: Option<OffscreenPipelines>,
+    // With `R`, the opaque geometry is rendered here and blitted to the surface in grayscale
+    grayscale_target: Option<render_target::RenderTarget>,
+    // Screen space draws, composited over the world
+    hud: hud::HudLayer,
+    // Drawn through the HUD after everything else on it, in the order they were added
+    viewport_labels: Vec<(viewport_label::LabelId, viewport_label::ViewportLabel)>,
+    next_label_id: u32,
+    // Tells whether `R` shows the frame in grayscale
+    render_mode_label: viewport_label::LabelId,
+    last_frame: std::time::Instant,
+    // Smoothed over the recent frames, so the HUD stays readable
+    fps: f32,
+    // Receives the progress of the running OBJ import, if any
+    import_events: Option<std::sync::mpsc::Receiver<ImportEvent>>,
+    scene_path: Option<std::path::PathBuf>,
+    scene: scene::Scene,
+    // The camera of the loaded scene, restored with `Home`
+    initial_camera: Option<scene::Camera>,
+    pan_speed: f32,
+    modifiers: winit::keyboard::ModifiersState,
+    camera_mode: CameraMode,
+    // Followed by the scene camera in `CameraMode::Orbit`
+    orbit_camera: orbit_camera::OrbitCamera,
+    // The button dragging the orbit camera, if any
+    orbit_drag: Option<winit::event::MouseButton>,
+    // Moves the scene camera in `CameraMode::Fly`
+    fly_camera: fly_camera::FlyCamera,
+    // Moves the scene camera instead of the `camera_mode` while it plays
+    camera_animation: Option<camera_animation::CameraAnimation>,
+    // Offsets the scene camera while the scene is baked, without moving it. See `shake_camera`
+    camera_shake: Option<camera_shake::CameraShake>,
+    // Each shake moves differently
+    camera_shakes_count: u32,
+    // When the loaded scene file was last modified, to notice the edits
+    scene_modified: Option<std::time::SystemTime>,
+    // The baked scene. None when there is nothing to draw
+    scene_drawable: Option<drawable::Drawable>,
+    // The indices of the scene objects in the order they are baked, front to back. See `update`
+    draw_order: Vec<usize>,
+    // Counts the shader invocations of the main pass, where supported. Logged with `P`
+    pipeline_stat_query: Option<pipeline_stats::PipelineStatQuery>,
+    // Meshes drawn with a material, see `assign_material`
+    resources: resources::ResourceManager,
+    material_table: material::MaterialTable,
+    material_shader: wgpu::ShaderModule,
+    material_pipeline_layout: wgpu::PipelineLayout,
+    material_pipeline: wgpu::RenderPipeline,
+    material_blend_mode: blend::BlendMode,
+    // Set for the whole pass, used by the pipelines blending with `BlendFactor::Constant`
+    blend_constant: wgpu::Color,
+}
+
+impl<'a> State<'a> {
+    async fn new(window: &'a Window, config: StateConfig) -> Result<State<'a>, StateError> {
+        let depth_config = if config.reversed_z {
+            depth::DepthConfig::reversed_z()
+        } else {
+            depth::DepthConfig::new(config.clear_depth, config.depth.depth_compare())
+                .map_err(StateError::DepthConfig)?
+        };
+        let depth_config = match config.depth.stencil_config() {
+            Some(stencil) => depth_config.stencil(stencil),
+            None => depth_config,
+        };
+
+        // 1. Get the device and queue
+        // Instance of wgpu. Used to work with wgpu and access the api.
+        let wgpu_instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            dx12_shader_compiler: config.dx12_shader_compiler,
+            gles_minor_version: config.gles_minor_version,
+            ..Default::default()
+        });
+
+        // Surface - is the part of the window we draw to. A "canvas"
+        let surface = wgpu_instance
+            .create_surface(window)
+            .map_err(StateError::CreateSurface)?;
+
+        // A handle to GPU. Needed to get the device
+        let adapter = adapter::select_adapter(&wgpu_instance, &surface, config.adapter)
+            .ok_or(StateError::NoAdapter)?;
+
+        if !adapter
+            .features()
+            .contains(depth_config.required_features())
+        {
+            return Err(StateError::UnsupportedDepthFormat(depth_config.format()));
+        }
+
+        log::info!(
+            "The best HDR format is {:?}, the best depth format is {:?}",
+            texture_format::TextureFormatSelector::best_hdr(&adapter),
+            texture_format::TextureFormatSelector::best_depth_format(&adapter)
+        );
+
+        // TODO: What is device and queue
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    // Compressed textures are only loaded, and the statistics only counted, where the adapter supports them
+                    // The adapter specific format features allow the sample counts other than 1 and 4.
+                    // The wireframe can only be toggled where the polygons can be drawn as lines.
+                    // The depth format was checked against the adapter already
+                    required_features: adapter.features()
+                        & (wgpu::Features::TEXTURE_COMPRESSION_BC
+                            | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+                            | wgpu::Features::TEXTURE_COMPRESSION_ASTC
+                            | wgpu::Features::PIPELINE_STATISTICS_QUERY
+                            | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                            | wgpu::Features::POLYGON_MODE_LINE)
+                        | depth_config.required_features(),
+                    required_limits: wgpu::Limits::default(),
+                    label: Some("My device"),
+                },
+                None,
+            )
+            .await
+            .map_err(StateError::RequestDevice)?;
+
+        let errors = error_policy::ErrorReporter::new(config.error_policy);
+        errors.install(&device);
+
+        // 2. Configuring the surface
+        let frame_latency = config
+            .frame_latency
+            .clamp(*FRAME_LATENCY_RANGE.start(), *FRAME_LATENCY_RANGE.end());
+        if frame_latency != config.frame_latency {
+            log::warn!(
+                "Frame latency {} is outside of {:?}, using {}",
+                config.frame_latency,
+                FRAME_LATENCY_RANGE,
+                frame_latency
+            );
+        }
+
+        let surface_caps = surface.get_capabilities(&adapter);
+        let window_size = window.inner_size();
+
+        let surface_format = Self::negotiate_surface_format(config.surface_format, &surface_caps);
+
+        // Both the sRGB and the linear views of the swapchain textures can be created.
+        // The sRGB one encodes the written colors, the linear one writes them as is
+        let view_formats = [
+            surface_format.add_srgb_suffix(),
+            surface_format.remove_srgb_suffix(),
+        ]
+        .into_iter()
+        .filter(|&format| format != surface_format)
+        .collect();
+
+        // Copying from the frames is needed by `read_pixel` and `screenshot`, but not every surface allows it
+        let surface_usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (surface_caps.usages & wgpu::TextureUsages::COPY_SRC);
+
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: surface_usage,
+            format: surface_format,
+            width: window_size.width,
+            height: window_size.height,
+            present_mode: Self::negotiate_present_mode(config.present_mode, &surface_caps),
+            alpha_mode: alpha_mode::AlphaModeSelector::preferred(
+                &surface_caps,
+                config.transparent_window,
+            ),
+            view_formats,
+            desired_maximum_frame_latency: frame_latency,
+        };
+
+        // The format of the view `render` targets
+        let surface_view_format = surface_config.format;
+
+        // Everything the main and the transparent passes render to, in either swapchain view
+        let sample_count_support = msaa::SampleCountSupport::new(
+            &adapter,
+            device.features(),
+            &[
+                surface_config.format.add_srgb_suffix(),
+                surface_config.format.remove_srgb_suffix(),
+                wboit::ACCUM_FORMAT,
+                wboit::REVEALAGE_FORMAT,
+            ],
+            depth_config.format(),
+        );
+        let sample_count = match msaa::validate_sample_count(config.sample_count) {
+            Ok(count) if sample_count_support.supports(count) => count,
+            Ok(count) => {
+                let supported = sample_count_support.clamp(count);
+                log::warn!(
+                    "MSAA x{} isn't supported by the adapter, falling back to x{}",
+                    count,
+                    supported
+                );
+                supported
+            }
+            Err(e) => {
+                log::warn!("{}, MSAA is off", e);
+                1
+            }
+        };
+
+        // 3. Load shaders
+        // Checked before wgpu sees it, so a broken custom shader is an error instead of a panic
+        let shader_source = config.shader.as_deref().unwrap_or(DEFAULT_SHADER);
+        let shader_reflection = shader_reflection::ShaderReflection::from_wgsl(shader_source)
+            .map_err(StateError::Shader)?;
+        if let Some(missing) = SHADER_ENTRY_POINTS
+            .iter()
+            .find(|entry_point| !shader_reflection.has_entry_point(entry_point))
+        {
+            return Err(StateError::MissingEntryPoint(missing));
+        }
+        let shader_entry_points = ShaderEntryPoints::of(&shader_reflection);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        // What the GL backend actually runs, for comparing with the other backends
+        if adapter.get_info().backend == wgpu::Backend::Gl && log::log_enabled!(log::Level::Debug) {
+            for (stage, entry) in [
+                (naga::ShaderStage::Vertex, "vs_main"),
+                (naga::ShaderStage::Fragment, "fs_main"),
+            ] {
+                match shader_debug::dump_glsl(shader_source, stage, entry) {
+                    Ok(glsl) => log::debug!("GLSL of {}:\n{}", entry, glsl),
+                    Err(e) => log::debug!("Failed to translate {} to GLSL: {}", entry, e),
+                }
+            }
+        }
+
+        // 4. Create the camera, transform and texture bind groups, and the render pipeline layout
+        let camera_bind_group_layout = shader_reflection.create_bind_group_layout(&device, 0);
+        let (camera, scene_camera) = CameraUniform::for_depth(config.reversed_z);
+        let camera_buffer = uniform_buffer::UniformBuffer::new(&device, "My camera buffer", camera);
+        let camera_bind_group =
+            bind_group_builder::BindGroupBuilder::from_reflection(&shader_reflection, &device)
+                .bind_resource(0, &camera_buffer)
+                .build(&camera_bind_group_layout)
+                .map_err(StateError::ShaderBindings)?;
+        // The scene is baked by its own camera, only the depth direction is left
+        let scene_camera_buffer =
+            uniform_buffer::UniformBuffer::new(&device, "My scene camera buffer", scene_camera);
+        let scene_camera_bind_group =
+            bind_group_builder::BindGroupBuilder::from_reflection(&shader_reflection, &device)
+                .bind_resource(0, &scene_camera_buffer)
+                .build(&camera_bind_group_layout)
+                .map_err(StateError::ShaderBindings)?;
+
+        let transform_bind_group_layout = shader_reflection.create_bind_group_layout(&device, 1);
+        let transform_buffer =
+            uniform_buffer::UniformBuffer::new(&device, "My transform buffer", IDENTITY_MATRIX);
+        let transform_bind_group =
+            bind_group_builder::BindGroupBuilder::from_reflection(&shader_reflection, &device)
+                .for_group(1)
+                .bind_resource(0, &transform_buffer)
+                .build(&transform_bind_group_layout)
+                .map_err(StateError::ShaderBindings)?;
+
+        // Sampled by the opaque triangle and the textured quad. `X` switches between them
+        let diffuse_textures = [
+            ("My checker texture", &include_bytes!("checker.png")[..]),
+            ("My bricks texture", &include_bytes!("bricks.png")[..]),
+        ]
+        .map(|(label, bytes)| {
+            texture::Texture::from_bytes(&device, &queue, bytes, label)
+                .expect("the bundled image is a valid PNG")
+        });
+        let diffuse_bind_group_layout = shader_reflection.create_bind_group_layout(&device, 2);
+        let diffuse_bind_group = create_diffuse_bind_group(
+            &device,
+            &shader_reflection,
+            &diffuse_bind_group_layout,
+            &diffuse_textures[0],
+        )
+        .map_err(StateError::ShaderBindings)?;
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("My pipeline layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    &transform_bind_group_layout,
+                    &diffuse_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        // 5. Create render pipeline
+        // Render pipeline describes what actions GPU must perform on data
+        let vertex_layout = Vertex::layout();
+        let render_pipeline = create_render_pipeline(
+            &device,
+            "My render pipeline",
+            &render_pipeline_layout,
+            &shader,
+            shader_entry_points.color,
+            &vertex_layout,
+            TRIANGLE_LIST,
+            surface_view_format,
+            blend::BlendMode::Replace,
+            depth_config,
+            sample_count,
+        );
+        let render_pipeline_targets =
+            render_pipeline_targets(surface_view_format, depth_config, sample_count);
+
+        // The opaque triangle, textured
+        let textured_pipeline = create_render_pipeline(
+            &device,
+            "My textured render pipeline",
+            &render_pipeline_layout,
+            &shader,
+            "fs_main",
+            &vertex_layout,
+            TRIANGLE_LIST,
+            surface_view_format,
+            blend::BlendMode::Replace,
+            depth_config,
+            sample_count,
+        );
+
+        // Same, but for the strips. The restart index splits them
+        let strip_pipeline = create_render_pipeline(
+            &device,
+            "My strip render pipeline",
+            &render_pipeline_layout,
+            &shader,
+            shader_entry_points.color,
+            &vertex_layout,
+            strip::primitive_state::<u16>(),
+            surface_view_format,
+            blend::BlendMode::Replace,
+            depth_config,
+            sample_count,
+        );
+
+        // The same three, drawing lines instead, switched to with `F`
+        let wireframe_pipelines = create_wireframe_pipelines(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            shader_entry_points.color,
+            &vertex_layout,
+            surface_view_format,
+            depth_config,
+            sample_count,
+        );
+
+        // Draws the copies of `instanced_mesh` set with `set_instances`, all in one call
+        let instanced_pipeline = shader_entry_points.instanced.then(|| {
+            create_instanced_pipeline(
+                &device,
+                &render_pipeline_layout,
+                &shader,
+                shader_entry_points.color,
+                &vertex_layout,
+                surface_view_format,
+                depth_config,
+                sample_count,
+            )
+        });
+
+        // Draws what the skinning pass writes, once `set_skinned_mesh` gives it a mesh
+        let skinned_mesh_pipeline = skinning::SkinnedMeshPipeline::new(
+            &device,
+            &camera_buffer,
+            &transform_buffer,
+            surface_view_format,
+            depth_config,
+            sample_count,
+        );
+
+        // The 3D cursor, in front of everything
+        let cursor_pipeline = create_cursor_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            shader_entry_points.color,
+            &vertex_layout,
+            surface_view_format,
+            depth_config,
+            sample_count,
+        );
+
+        // Transparent geometry is accumulated by the WBOIT pass
+        let transparent_pipeline = shader_entry_points.transparent.then(|| {
+            create_transparent_pipeline(
+                &device,
+                "My transparent render pipeline",
+                &render_pipeline_layout,
+                &shader,
+                "fs_transparent",
+                &wboit::WboitPass::color_targets(),
+                &vertex_layout,
+                depth_config,
+                sample_count,
+            )
+        });
+
+        // 6. Create vertex buffers
+        let triangle = drawable::Drawable::with_layout(
+            &device,
+            "My vertex buffer",
+            bytemuck::cast_slice(VERTICES),
+            &vertex_layout,
+            drawable::LAYER_OPAQUE,
+        )
+        .expect("the triangle matches the vertex layout");
+        let mesh = drawable::Drawable::new(
+            &device,
+            "My mesh vertex buffer",
+            &config.mesh.vertices,
+            drawable::LAYER_OPAQUE,
+        );
+        let textured_quad = drawable::Drawable::new(
+            &device,
+            "My textured quad vertex buffer",
+            TEXTURED_QUAD_VERTICES,
+            drawable::LAYER_OPAQUE,
+        );
+        let instanced_mesh = drawable::Drawable::new(
+            &device,
+            "My instanced mesh vertex buffer",
+            INSTANCED_TRIANGLE_VERTICES,
+            drawable::LAYER_OPAQUE,
+        );
+        // A copy of the mesh per instance, drawn in a single call
+        let instance_buffer =
+            vertex_buffer::VertexBuffer::new(&device, "My instance buffer", &instance_grid());
+        let cursor = cursor3d::Cursor3D::new(&device, cursor3d::Cursor3D::cross(), CURSOR_SIZE);
+        let occlusion = drawable::Drawable::new(
+            &device,
+            "My occlusion vertex buffer",
+            OCCLUSION_VERTICES,
+            drawable::LAYER_OPAQUE,
+        );
+        let index_buffer = config
+            .mesh
+            .indices
+            .as_ref()
+            .map(|indices| {
+                index_buffer::IndexBuffer::triangle_list(
+                    &device,
+                    indices,
+                    config.mesh.vertices.len() as u32,
+                )
+            })
+            .transpose()
+            .map_err(StateError::Indices)?;
+
+        let transparent_triangle = drawable::Drawable::with_layout(
+            &device,
+            "My transparent vertex buffer",
+            bytemuck::cast_slice(TRANSPARENT_VERTICES),
+            &vertex_layout,
+            drawable::LAYER_TRANSPARENT,
+        )
+        .expect("the transparent triangle matches the vertex layout");
+
+        let strips = strip::StripMesh::new(&device, "My strip buffer", STRIP_VERTICES, STRIPS);
+
+        // 7. Create frustum cullers
+        // Bounding sphere of the triangle. The frustum is the clip space until the first `update`
+        let culler = culling::GpuFrustumCuller::new(
+            &device,
+            &[culling::CullData::new([0., 0., 0., 0.71], 0)],
+            triangle.vertices_count(),
+            0,
+        );
+        let mesh_positions: Vec<[f32; 3]> = config
+            .mesh
+            .vertices
+            .iter()
+            .map(|vertex| vertex.position)
+            .collect();
+        let mesh_culler = index_buffer.as_ref().map(|index_buffer| {
+            culling::GpuFrustumCuller::new(
+                &device,
+                &[culling::CullData::new(
+                    culling::bounding_sphere(&mesh_positions),
+                    0,
+                )],
+                mesh.vertices_count(),
+                index_buffer.indices_count(),
+            )
+        });
+
+        // 8. Create the low resolution frame, when rendering at a fixed resolution.
+        // Everything the scene is rendered to is sized after it instead of the surface
+        let upscale = config.internal_resolution.map(|(width, height)| {
+            upscale::UpscalePass::new(&device, width, height, surface_view_format)
+        });
+        let (render_width, render_height) = upscale
+            .as_ref()
+            .map_or((surface_config.width, surface_config.height), |upscale| {
+                upscale.size()
+            });
+
+        // 9. Create depth texture, and the multisampled color texture resolved into the frame
+        let depth_texture = depth::DepthTexture::new(
+            &device,
+            render_width,
+            render_height,
+            sample_count,
+            depth_config.format(),
+        );
+        let msaa_target = msaa::MsaaTarget::new(
+            &device,
+            render_width,
+            render_height,
+            sample_count,
+            surface_view_format,
+        );
+
+        // 10. Create order-independent transparency targets
+        let wboit = wboit::WboitPass::new(
+            &device,
+            render_width,
+            render_height,
+            surface_view_format,
+            sample_count,
+        );
+
+        let linked_list_oit = linked_list_oit::LinkedListOit::new(
+            &device,
+            render_width,
+            render_height,
+            MAX_TRANSPARENT_FRAGMENTS,
+            surface_view_format,
+        );
+
+        let linked_list_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("My linked list pipeline layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    &transform_bind_group_layout,
+                    &diffuse_bind_group_layout,
+                    linked_list_oit.gather_bind_group_layout(),
+                ],
+                push_constant_ranges: &[],
+            });
+
+        // Same as the transparent pipeline, but pushes the fragments to the lists instead of blending
+        let linked_list_pipeline = shader_entry_points.linked_list.then(|| {
+            create_transparent_pipeline(
+                &device,
+                "My linked list render pipeline",
+                &linked_list_pipeline_layout,
+                &shader,
+                "fs_transparent_linked_list",
+                &[],
+                &vertex_layout,
+                depth_config,
+                sample_count,
+            )
+        });
+
+        // 11. Create trail frames
+        let trails = trails::TrailsPass::new(
+            &device,
+            render_width,
+            render_height,
+            surface_view_format,
+            depth_config.format(),
+            sample_count,
+        );
+
+        // 12. Create HUD layer, at the window resolution
+        let hud = hud::HudLayer::new(
+            &device,
+            surface_config.width,
+            surface_config.height,
+            surface_view_format,
+        );
+
+        // 13. Create the sprites
+        let (sheet_width, sheet_height, sheet_pixels) = walking_sheet();
+        let sprites = sprite::SpriteRenderer::new(
+            &device,
+            &queue,
+            surface_view_format,
+            sheet_width,
+            sheet_height,
+            &sheet_pixels,
+        );
+        let walker = animated_sprite::AnimatedSprite::new(
+            WALKER_STRIDES.len() as u32,
+            1,
+            8.,
+            animated_sprite::PlaybackMode::PingPong,
+        );
+        let mut point_sprites = point_sprite::PointSpriteRenderer::new(
+            &device,
+            surface_view_format,
+            render_width,
+            render_height,
+        );
+        point_sprites.set_sprite_size(POINT_SPRITE_SIZE);
+        point_sprites.set_color(POINT_SPRITE_COLOR);
+
+        // 14. Create the thick line
+        let wide_line = wide_line::WideLine::new(
+            &device,
+            surface_view_format,
+            render_width,
+            render_height,
+            &wavy_line(),
+            WAVY_LINE_STYLE,
+        );
+
+        // 15. Create the color cycling compute work, recorded outside of the frame
+        let color_cycle =
+            color_cycle::ColorCycle::new(&device, bytemuck::cast_slice(TRANSPARENT_VERTICES));
+
+        // The sine wave is written once, by a compute job run before the first frame
+        let sine_wave_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My sine wave vertex buffer"),
+            size: (SINE_WAVE.samples_count as usize * 2 * std::mem::size_of::<Vertex>())
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let sine_wave_job = sine_wave_job(&device, &sine_wave_buffer);
+
+        // 16. Create the pipeline statistics query
+        let pipeline_stat_query = pipeline_stats::PipelineStatQuery::new(&device);
+
+        // 17. Create the materials, and the meshes drawn with them
+        let material_source = include_str!("material.wgsl");
+        let material_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My material shader"),
+            source: wgpu::ShaderSource::Wgsl(material_source.into()),
+        });
+        let material_reflection = shader_reflection::ShaderReflection::from_wgsl(material_source)
+            .expect("the material shader is valid");
+        let material_bind_group_layout =
+            material_reflection.create_bind_group_layout(&device, material::MATERIAL_GROUP);
+        let material_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("My material pipeline layout"),
+                bind_group_layouts: &[&material_bind_group_layout, &camera_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let material_pipeline = create_render_pipeline(
+            &device,
+            "My material render pipeline",
+            &material_pipeline_layout,
+            &material_shader,
+            "fs_main",
+            &vertex_layout,
+            TRIANGLE_LIST,
+            surface_view_format,
+            blend::BlendMode::Replace,
+            depth_config,
+            sample_count,
+        );
+
+        let mut resources = resources::ResourceManager::new();
+        let mut material_assignments = Vec::new();
+        for &(color, squares) in MATERIAL_SQUARES {
+            let params = uniform_buffer::UniformBuffer::new(
+                &device,
+                "My material params buffer",
+                material::MaterialParams { color },
+            );
+            let bind_group = bind_group_builder::BindGroupBuilder::from_reflection(
+                &material_reflection,
+                &device,
+            )
+            .for_group(material::MATERIAL_GROUP)
+            .bind_resource(0, &params)
+            .build(&material_bind_group_layout)
+            .expect("the material params match the shader");
+            let material = resources.add_material(material::Material { bind_group });
+
+            for &[x, y] in squares {
+                let mesh = resources.add_mesh(drawable::Drawable::new(
+                    &device,
+                    "My material square vertex buffer",
+                    &square(x, y, MATERIAL_SQUARE_SIZE),
+                    drawable::LAYER_OPAQUE,
+                ));
+                material_assignments.push((mesh, material));
+            }
+        }
+
+        // Nothing is selected until `H`
+        let selection_highlight = selection_highlight::SelectionHighlight::new(
+            &device,
+            surface_view_format,
+            render_width,
+            render_height,
+            SELECTION_COLOR,
+            SELECTION_THICKNESS,
+        );
+
+        let blit = render_target::Blit::new(&device, surface_view_format);
+
+        let render_mode_label = viewport_label::ViewportLabel::new(
+            RENDER_MODE_LABEL_POSITION,
+            "COLOR".to_string(),
+            RENDER_MODE_LABEL_FONT_SIZE,
+            [1., 1., 1., 0.8],
+        );
+
+        let mut state = State {
+            window,
+            cursor_position: winit::dpi::PhysicalPosition::default(),
+            surface,
+            device,
+            queue,
+            surface_config,
+            window_size,
+            clear_color: wgpu::Color::BLACK,
+            surface_view_format,
+            shader,
+            shader_entry_points,
+            camera_buffer,
+            camera_bind_group,
+            scene_camera_bind_group,
+            transform: IDENTITY_MATRIX,
+            transform_buffer,
+            transform_bind_group,
+            shader_reflection,
+            diffuse_bind_group_layout,
+            diffuse_bind_group,
+            diffuse_textures,
+            diffuse_texture_index: 0,
+            correct_aspect_ratio: config.correct_aspect_ratio,
+            render_pipeline_layout,
+            vertex_layout,
+            render_pipeline,
+            render_pipeline_targets,
+            textured_pipeline,
+            wireframe_pipelines,
+            wireframe: false,
+            triangle,
+            mesh,
+            textured_quad,
+            instanced_pipeline,
+            instanced_mesh,
+            instance_buffer: Some(instance_buffer),
+            max_influences: config.max_influences,
+            skinning: None,
+            skinned_mesh_pipeline,
+            cursor_pipeline,
+            cursor,
+            occlusion,
+            mesh_data: config.mesh,
+            index_buffer,
+            strip_pipeline,
+            strips,
+            culler,
+            mesh_culler,
+            depth_config,
+            depth_texture,
+            sample_count,
+            sample_count_support,
+            msaa_target,
+            reversed_z: config.reversed_z,
+            clear_depth: depth_config.clear_depth(),
+            clear_stencil: config.clear_stencil,
+            transparent_pipeline,
+            transparent_triangle,
+            wboit,
+            linked_list_pipeline_layout,
+            linked_list_pipeline,
+            linked_list_oit,
+            oit_mode: OitMode::Weighted,
+            layer_mask: drawable::ALL_LAYERS,
+            slow_frames: false,
+            trails,
+            upscale,
+            trails_enabled: false,
+            start_time: std::time::Instant::now(),
+            errors,
+            event_filter: config.event_filter,
+            input_map: config.input_map,
+            command_buffers_before: Vec::new(),
+            command_buffers_after: Vec::new(),
+            color_cycle,
+            color_cycle_enabled: false,
+            compute_jobs: vec![sine_wave_job],
+            sine_wave_buffer,
+            sprites,
+            wide_line,
+            walker,
+            point_sprites,
+            selection_highlight,
+            blit,
+            offscreen_pipelines: None,
+            grayscale_target: None,
+            hud,
+            viewport_labels: vec![(viewport_label::LabelId(0), render_mode_label)],
+            next_label_id: 1,
+            render_mode_label: viewport_label::LabelId(0),
+            last_frame: std::time::Instant::now(),
+            fps: 0.,
+            import_events: None,
+            scene_path: config.scene_path,
+            scene: scene::Scene::default(),
+            initial_camera: None,
+            pan_speed: PAN_SPEED,
+            modifiers: winit::keyboard::ModifiersState::empty(),
+            camera_mode: CameraMode::Panning,
+            orbit_camera: orbit_camera::OrbitCamera::new([0.; 3], 1.),
+            orbit_drag: None,
+            fly_camera: fly_camera::FlyCamera::new(FLY_ACCELERATION, FLY_MAX_SPEED),
+            camera_animation: None,
+            camera_shake: None,
+            camera_shakes_count: 0,
+            scene_modified: None,
+            scene_drawable: None,
+            draw_order: Vec::new(),
+            pipeline_stat_query,
+            resources,
+            material_table: material::MaterialTable::default(),
+            material_shader,
+            material_pipeline_layout,
+            material_pipeline,
+            material_blend_mode: blend::BlendMode::Replace,
+            blend_constant: wgpu::Color::WHITE,
+        };
+
+        for (mesh, material) in material_assignments {
+            state.assign_material(mesh, material);
+        }
+
+        // 18. Load the scene, if any
+        state.reload_scene();
+
+        // The transform buffer was created without the correction
+        if state.correct_aspect_ratio {
+            state.upload_transform();
+        }
+
+        state.check_depth_texture()?;
+
+        Ok(state)
+    }
+
+    // `preferred` if the surface supports it, otherwise `AutoVsync`, which every surface does.
+    // The automatic modes are never listed by the surface, wgpu resolves them when configuring it.
+    // Rather than Fifo, so FifoRelaxed is used where the surface supports it
+    fn negotiate_present_mode(
+        preferred: wgpu::PresentMode,
+        caps: &wgpu::SurfaceCapabilities,
+    ) -> wgpu::PresentMode {
+        let automatic = matches!(
+            preferred,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        );
+        if automatic || caps.present_modes.contains(&preferred) {
+            log::info!("Using the {:?} present mode", preferred);
+            return preferred;
+        }
+
+        log::warn!(
+            "The {:?} present mode isn't supported, falling back to AutoVsync. Supported modes: {:?}",
+            preferred,
+            caps.present_modes
+        );
+
+        wgpu::PresentMode::AutoVsync
+    }
+
+    // `preferred` if the surface supports it, otherwise the first sRGB one, or just the first one
+    fn negotiate_surface_format(
+        preferred: Option<wgpu::TextureFormat>,
+        caps: &wgpu::SurfaceCapabilities,
+    ) -> wgpu::TextureFormat {
+        if let Some(preferred) = preferred {
+            if caps.formats.contains(&preferred) {
+                return preferred;
+            }
+
+            log::warn!(
+                "The {:?} surface format isn't supported, supported formats: {:?}",
+                preferred,
+                caps.formats
+            );
+        }
+
+        caps.formats
+            .iter()
+            .find(|f| f.is_srgb())
+            .copied()
+            .unwrap_or(caps.formats[0])
+    }
+
+    pub fn window(&self) -> &Window {
+        self.window
+    }
+
+    /// Reconfigures the surface and everything sized after it. Ignored while minimized.
+    /// Fails if the new depth texture doesn't match the main pass
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) -> Result<(), StateError> {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.window_size = new_size;
+            self.surface_config.width = new_size.width;
+            self.surface_config.height = new_size.height;
+            self.surface.configure(&self.device, &self.surface_config);
+            self.hud
+                .resize(&self.queue, new_size.width, new_size.height);
+            self.bake_scene();
+            if self.correct_aspect_ratio {
+                self.upload_transform();
+            }
+
+            // The fixed resolution frame is only scaled differently
+            if self.upscale.is_some() {
+                return Ok(());
+            }
+
+            self.depth_texture = depth::DepthTexture::new(
+                &self.device,
+                new_size.width,
+                new_size.height,
+                self.sample_count,
+                self.depth_config.format(),
+            );
+            self.msaa_target = msaa::MsaaTarget::new(
+                &self.device,
+                new_size.width,
+                new_size.height,
+                self.sample_count,
+                self.surface_view_format,
+            );
+            self.wboit
+                .resize(&self.device, new_size.width, new_size.height);
+            self.linked_list_oit
+                .resize(&self.device, new_size.width, new_size.height);
+            self.trails
+                .resize(&self.device, new_size.width, new_size.height);
+            self.wide_line.resize(new_size.width, new_size.height);
+            self.point_sprites.resize(new_size.width, new_size.height);
+            self.selection_highlight
+                .resize(&self.device, new_size.width, new_size.height);
+            if self.grayscale_target.is_some() {
+                self.grayscale_target = Some(self.new_render_target(
+                    new_size.width,
+                    new_size.height,
+                    self.surface_view_format,
+                ));
+            }
+
+            self.check_depth_texture()?;
+        }
+
+        Ok(())
+    }
+
+    // The depth texture has to be multisampled like the color target it's drawn with,
+    // otherwise the main pass fails validation
+    fn check_depth_texture(&self) -> Result<(), StateError> {
+        let color_sample_count = self
+            .msaa_target
+            .as_ref()
+            .map_or(1, msaa::MsaaTarget::sample_count);
+
+        self.depth_texture
+            .check_sample_count(color_sample_count, &self.render_pipeline_targets)
+            .map_err(StateError::DepthSampleCount)
+    }
+
+    /// Filled in place with `count` values, instead of building them in a `Vec` first and copying it.
+    /// Empty when the write isn't valid, the error being reported by the device
+    pub fn write_buffer_with<'b, T: bytemuck::Pod>(
+        &'b self,
+        buffer: &'b wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        count: usize,
+    ) -> Option<buffer_write::TypedWriteView<'b, T>> {
+        buffer_write::TypedWriteView::new(&self.queue, buffer, offset, count)
+    }
+
+    /// Zeroes the range of the buffer, all of it when None. E.g. atomic counters before a dispatch
+    pub fn clear_buffer(
+        encoder: &mut wgpu::CommandEncoder,
+        buffer: &wgpu::Buffer,
+        range: Option<std::ops::Range<wgpu::BufferAddress>>,
+    ) {
+        debug_assert!(
+            buffer.usage().contains(wgpu::BufferUsages::COPY_DST),
+            "only COPY_DST buffers can be cleared"
+        );
+
+        match range {
+            Some(range) => encoder.clear_buffer(buffer, range.start, Some(range.end - range.start)),
+            None => encoder.clear_buffer(buffer, 0, None),
+        }
+    }
+
+    /// Work recorded with its own encoder on the same device, submitted with the next frame.
+    /// Buffers of the same order keep the order they were added in
+    pub fn add_command_buffer(&mut self, command_buffer: wgpu::CommandBuffer, order: SubmitOrder) {
+        match order {
+            SubmitOrder::BeforeFrame => self.command_buffers_before.push(command_buffer),
+            SubmitOrder::AfterFrame => self.command_buffers_after.push(command_buffer),
+        }
+    }
+
+    /// Switches the monitor to its highest resolution mode, the window's one if `monitor` is None.
+    /// The surface is reconfigured right away instead of waiting for the resize event
+    pub fn enter_exclusive_fullscreen(
+        &mut self,
+        monitor: Option<winit::monitor::MonitorHandle>,
+    ) -> Result<(), StateError> {
+        let Some(monitor) = monitor.or_else(|| self.window.current_monitor()) else {
+            log::warn!("There is no monitor to go fullscreen on");
+            return Ok(());
+        };
+
+        let Some(video_mode) = monitor.video_modes().max_by_key(|mode| {
+            let size = mode.size();
+
+            (
+                size.width * size.height,
+                mode.refresh_rate_millihertz(),
+                mode.bit_depth(),
+            )
+        }) else {
+            log::warn!("{:?} has no video modes", monitor.name());
+            return Ok(());
+        };
+
+        log::info!(
+            "Entering exclusive fullscreen on {:?}: {}x{} at {} mHz",
+            monitor.name(),
+            video_mode.size().width,
+            video_mode.size().height,
+            video_mode.refresh_rate_millihertz()
+        );
+
+        let size = video_mode.size();
+        self.window
+            .set_fullscreen(Some(winit::window::Fullscreen::Exclusive(video_mode)));
+        self.resize(size)
+    }
+
+    pub fn exit_fullscreen(&mut self) -> Result<(), StateError> {
+        self.window.set_fullscreen(None);
+        self.resize(self.window.inner_size())
+    }
+
+    // The size of everything the scene is rendered to
+    fn render_size(&self) -> (u32, u32) {
+        self.upscale.as_ref().map_or(
+            (self.surface_config.width, self.surface_config.height),
+            |upscale| upscale.size(),
+        )
+    }
+
+    /// Sampled by the textured geometry from the next frame on, e.g. a `Texture::from_bytes`.
+    /// Fails for textures `fs_main` can't sample with a filtering sampler
+    pub fn bind_texture(
+        &mut self,
+        texture: &texture::Texture,
+    ) -> Result<(), bind_group_builder::BindGroupError> {
+        self.diffuse_bind_group = create_diffuse_bind_group(
+            &self.device,
+            &self.shader_reflection,
+            &self.diffuse_bind_group_layout,
+            texture,
+        )?;
+
+        Ok(())
+    }
+
+    /// Run once, in a compute pass before the next frame's render pass, in the order they were added
+    pub fn add_compute_job(&mut self, job: compute_job::ComputeJob) {
+        self.compute_jobs.push(job);
+    }
+
+    /// Text drawn over every frame from the next one on, over the rest of the HUD
+    pub fn add_label(&mut self, label: viewport_label::ViewportLabel) -> viewport_label::LabelId {
+        let id = viewport_label::LabelId(self.next_label_id);
+        self.next_label_id += 1;
+        self.viewport_labels.push((id, label));
+
+        id
+    }
+
+    /// None if it was already removed
+    pub fn remove_label(
+        &mut self,
+        id: viewport_label::LabelId,
+    ) -> Option<viewport_label::ViewportLabel> {
+        let index = self
+            .viewport_labels
+            .iter()
+            .position(|(label_id, _)| *label_id == id)?;
+
+        Some(self.viewport_labels.remove(index).1)
+    }
+
+    /// Does nothing if the label was removed
+    pub fn update_label_text(&mut self, id: viewport_label::LabelId, text: &str) {
+        if let Some((_, label)) = self
+            .viewport_labels
+            .iter_mut()
+            .find(|(label_id, _)| *label_id == id)
+        {
+            label.set_text(text);
+        }
+    }
+
+    /// Copies of the instanced triangle drawn from the next frame on, all in a single draw call.
+    /// The buffer is only recreated when the count changes, empty removes them all
+    pub fn set_instances(&mut self, instances: &[instancing::InstanceData]) {
+        match &self.instance_buffer {
+            _ if instances.is_empty() => self.instance_buffer = None,
+            Some(buffer) if buffer.vertex_count() as usize == instances.len() => {
+                buffer.write(&self.queue, bytemuck::cast_slice(instances))
+            }
+            _ => {
+                self.instance_buffer = Some(vertex_buffer::VertexBuffer::new(
+                    &self.device,
+                    "My instance buffer",
+                    instances,
+                ))
+            }
+        }
+    }
+
+    /// A triangle list skinned on the GPU every frame, then drawn with the opaque geometry and
+    /// the transform. Replaces the previous one, empty removes it. The joints are in the bind pose
+    /// until `update_joints`. Panics if a vertex has more influences than
+    /// `StateConfig::max_influences`
+    pub fn set_skinned_mesh(&mut self, vertices: &[skinning::SkinVertex], joints_count: u32) {
+        self.skinning = (!vertices.is_empty()).then(|| {
+            skinning::SkinningPass::new(&self.device, vertices, joints_count, self.max_influences)
+        });
+    }
+
+    /// Column major matrices of the joints of the skinned mesh, already multiplied by
+    /// the inverse bind matrices. Nothing happens without a skinned mesh
+    pub fn update_joints(&self, joint_matrices: &[[[f32; 4]; 4]]) {
+        if let Some(skinning) = &self.skinning {
+            skinning.update_joints(&self.queue, joint_matrices);
+        }
+    }
+
+    /// MSAA samples per pixel, 1, 2, 4 or 8, 1 turning it off, if the adapter supports it.
+    /// The depth texture, the multisampled targets and every pipeline drawing with them are rebuilt
+    pub fn set_sample_count(&mut self, count: u32) -> Result<(), msaa::SampleCountError> {
+        self.sample_count = self.sample_count_support.check(count)?;
+
+        let (render_width, render_height) = self.render_size();
+        self.depth_texture = depth::DepthTexture::new(
+            &self.device,
+            render_width,
+            render_height,
+            self.sample_count,
+            self.depth_config.format(),
+        );
+        self.transparent_pipeline = self.shader_entry_points.transparent.then(|| {
+            create_transparent_pipeline(
+                &self.device,
+                "My transparent render pipeline",
+                &self.render_pipeline_layout,
+                &self.shader,
+                "fs_transparent",
+                &wboit::WboitPass::color_targets(),
+                &self.vertex_layout,
+                self.depth_config,
+                self.sample_count,
+            )
+        });
+        self.linked_list_pipeline = self.shader_entry_points.linked_list.then(|| {
+            create_transparent_pipeline(
+                &self.device,
+                "My linked list render pipeline",
+                &self.linked_list_pipeline_layout,
+                &self.shader,
+                "fs_transparent_linked_list",
+                &[],
+                &self.vertex_layout,
+                self.depth_config,
+                self.sample_count,
+            )
+        });
+
+        // The opaque pipelines, the multisampled color target, the WBOIT targets and the trails
+        self.set_srgb_view(self.surface_view_format.is_srgb());
+
+        Ok(())
+    }
+
+    // Switches between the sRGB and the linear view of the swapchain.
+    // Everything that draws to the swapchain is rebuilt for the new format
+    fn set_srgb_view(&mut self, srgb: bool) {
+        self.surface_view_format = if srgb {
+            self.surface_config.format.add_srgb_suffix()
+        } else {
+            self.surface_config.format.remove_srgb_suffix()
+        };
+
+        self.render_pipeline = create_render_pipeline(
+            &self.device,
+            "My render pipeline",
+            &self.render_pipeline_layout,
+            &self.shader,
+            self.shader_entry_points.color,
+            &self.vertex_layout,
+            TRIANGLE_LIST,
+            self.surface_view_format,
+            blend::BlendMode::Replace,
+            self.depth_config,
+            self.sample_count,
+        );
+        self.render_pipeline_targets = render_pipeline_targets(
+            self.surface_view_format,
+            self.depth_config,
+            self.sample_count,
+        );
+        self.textured_pipeline = create_render_pipeline(
+            &self.device,
+            "My textured render pipeline",
+            &self.render_pipeline_layout,
+            &self.shader,
+            "fs_main",
+            &self.vertex_layout,
+            TRIANGLE_LIST,
+            self.surface_view_format,
+            blend::BlendMode::Replace,
+            self.depth_config,
+            self.sample_count,
+        );
+        self.strip_pipeline = create_render_pipeline(
+            &self.device,
+            "My strip render pipeline",
+            &self.render_pipeline_layout,
+            &self.shader,
+            self.shader_entry_points.color,
+            &self.vertex_layout,
+            strip::primitive_state::<u16>(),
+            self.surface_view_format,
+            blend::BlendMode::Replace,
+            self.depth_config,
+            self.sample_count,
+        );
+        self.wireframe_pipelines = create_wireframe_pipelines(
+            &self.device,
+            &self.render_pipeline_layout,
+            &self.shader,
+            self.shader_entry_points.color,
+            &self.vertex_layout,
+            self.surface_view_format,
+            self.depth_config,
+            self.sample_count,
+        );
+        self.instanced_pipeline = self.shader_entry_points.instanced.then(|| {
+            create_instanced_pipeline(
+                &self.device,
+                &self.render_pipeline_layout,
+                &self.shader,
+                self.shader_entry_points.color,
+                &self.vertex_layout,
+                self.surface_view_format,
+                self.depth_config,
+                self.sample_count,
+            )
+        });
+        self.skinned_mesh_pipeline = skinning::SkinnedMeshPipeline::new(
+            &self.device,
+            &self.camera_buffer,
+            &self.transform_buffer,
+            self.surface_view_format,
+            self.depth_config,
+            self.sample_count,
+        );
+        self.cursor_pipeline = create_cursor_pipeline(
+            &self.device,
+            &self.render_pipeline_layout,
+            &self.shader,
+            self.shader_entry_points.color,
+            &self.vertex_layout,
+            self.surface_view_format,
+            self.depth_config,
+            self.sample_count,
+        );
+        self.material_pipeline = create_render_pipeline(
+            &self.device,
+            "My material render pipeline",
+            &self.material_pipeline_layout,
+            &self.material_shader,
+            "fs_main",
+            &self.vertex_layout,
+            TRIANGLE_LIST,
+            self.surface_view_format,
+            self.material_blend_mode,
+            self.depth_config,
+            self.sample_count,
+        );
+        let (render_width, render_height) = self.render_size();
+        self.msaa_target = msaa::MsaaTarget::new(
+            &self.device,
+            render_width,
+            render_height,
+            self.sample_count,
+            self.surface_view_format,
+        );
+        self.wboit = wboit::WboitPass::new(
+            &self.device,
+            render_width,
+            render_height,
+            self.surface_view_format,
+            self.sample_count,
+        );
+        self.linked_list_oit = linked_list_oit::LinkedListOit::new(
+            &self.device,
+            render_width,
+            render_height,
+            MAX_TRANSPARENT_FRAGMENTS,
+            self.surface_view_format,
+        );
+        let fade = self.trails.fade();
+        self.trails = trails::TrailsPass::new(
+            &self.device,
+            render_width,
+            render_height,
+            self.surface_view_format,
+            self.depth_config.format(),
+            self.sample_count,
+        );
+        self.trails.set_fade(fade);
+        if let Some(upscale) = &mut self.upscale {
+            let (width, height) = upscale.size();
+            *upscale =
+                upscale::UpscalePass::new(&self.device, width, height, self.surface_view_format);
+        }
+        let (sheet_width, sheet_height, sheet_pixels) = walking_sheet();
+        self.sprites = sprite::SpriteRenderer::new(
+            &self.device,
+            &self.queue,
+            self.surface_view_format,
+            sheet_width,
+            sheet_height,
+            &sheet_pixels,
+        );
+        self.point_sprites = point_sprite::PointSpriteRenderer::new(
+            &self.device,
+            self.surface_view_format,
+            render_width,
+            render_height,
+        );
+        self.point_sprites.set_sprite_size(POINT_SPRITE_SIZE);
+        self.point_sprites.set_color(POINT_SPRITE_COLOR);
+        let selected = self.selection_highlight.selected().to_vec();
+        self.selection_highlight = selection_highlight::SelectionHighlight::new(
+            &self.device,
+            self.surface_view_format,
+            render_width,
+            render_height,
+            SELECTION_COLOR,
+            SELECTION_THICKNESS,
+        );
+        self.selection_highlight.set_selected(&selected);
+        self.blit = render_target::Blit::new(&self.device, self.surface_view_format);
+        self.wide_line = wide_line::WideLine::new(
+            &self.device,
+            self.surface_view_format,
+            render_width,
+            render_height,
+            &wavy_line(),
+            self.wide_line.style(),
+        );
+        self.hud = hud::HudLayer::new(
+            &self.device,
+            self.surface_config.width,
+            self.surface_config.height,
+            self.surface_view_format,
+        );
+    }
+
+    // The mesh of `StateConfig::mesh`, as drawn. Vertex colors can't be stored in OBJ,
+    // so only the positions are written
+    fn export_obj(&self, path: &str) -> Result<(), obj::ObjError> {
+        let vertices = &self.mesh_data.vertices;
+        let indices = match &self.mesh_data.indices {
+            Some(Indices::U16(indices)) => indices.iter().map(|&index| index as u32).collect(),
+            Some(Indices::U32(indices)) => indices.clone(),
+            None => (0..vertices.len() as u32).collect(),
+        };
+        let mesh = obj::ObjMesh {
+            positions: vertices.iter().map(|vertex| vertex.position).collect(),
+            indices,
+            ..Default::default()
+        };
+
+        obj::export_obj(path, &mesh)
+    }
+
+    // Loads the OBJ on another thread, so the window keeps responding. The progress is shown in the title.
+    // There is nothing to draw the mesh with yet, it's only loaded
+    fn import_obj(&mut self, path: &str) {
+        if self.import_events.is_some() {
+            return;
+        }
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let path = path.to_owned();
+
+        // The progress callback runs on the loading thread, so it's marshalled back through the channel
+        std::thread::spawn(move || {
+            let progress_sender = sender.clone();
+            let result = obj::load_obj_with_progress(&path, |progress| {
+                let _ = progress_sender.send(ImportEvent::Progress(progress));
+            });
+            let _ = sender.send(ImportEvent::Done(result));
+        });
+
+        self.import_events = Some(receiver);
+    }
+
+    // Keeps the previous scene when the new one fails to load, so a typo doesn't blank the screen
+    fn reload_scene(&mut self) {
+        let Some(path) = &self.scene_path else {
+            return;
+        };
+
+        self.scene_modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        match scene::Scene::from_json(path) {
+            Ok(scene) => {
+                log::info!(
+                    "Loaded {} objects from {}",
+                    scene.objects.len(),
+                    path.display()
+                );
+                self.scene = scene;
+                self.initial_camera = self.scene.camera;
+                self.reset_orbit_camera();
+                self.bake_scene();
+            }
+            Err(error) => log::error!("Can't load the scene {}: {}", path.display(), error),
+        }
+    }
+
+    // By the held `input_map::PAN_*` actions, slower with Ctrl and faster with Shift
+    fn pan_camera(&mut self, frame_time: f32) {
+        let [right, forward] = PAN_ACTIONS
+            .iter()
+            .filter(|(action, _)| self.input_map.is_action_pressed(action))
+            .fold([0., 0.], |[right, forward], (_, [dx, dz])| {
+                [right + dx, forward + dz]
+            });
+        if right == 0. && forward == 0. {
+            return;
+        }
+
+        let mut speed = self.pan_speed;
+        if self.modifiers.control_key() {
+            speed /= PAN_SPEED_MODIFIER;
+        }
+        if self.modifiers.shift_key() {
+            speed *= PAN_SPEED_MODIFIER;
+        }
+
+        let Some(camera) = &mut self.scene.camera else {
+            return;
+        };
+        camera.pan(right * speed * frame_time, forward * speed * frame_time);
+        self.bake_scene();
+    }
+
+    // Translates the transform by the held `input_map::MOVE_*` actions
+    fn move_by_actions(&mut self, frame_time: f32) {
+        let [dx, dy] = MOVE_ACTIONS
+            .iter()
+            .filter(|(action, _)| self.input_map.is_action_pressed(action))
+            .fold([0., 0.], |[x, y], (_, [dx, dy])| [x + dx, y + dy]);
+        if dx == 0. && dy == 0. {
+            return;
+        }
+
+        let mut transform = self.transform;
+        transform[3][0] += dx * MOVE_SPEED * frame_time;
+        transform[3][1] += dy * MOVE_SPEED * frame_time;
+        self.set_transform(transform);
+    }
+
+    // Speeds up along the view by the held `input_map::FLY_*` actions,
+    // coasting to a stop once they're released
+    fn fly_camera(&mut self, frame_time: f32) {
+        let [right, forward] = FLY_ACTIONS
+            .iter()
+            .filter(|(action, _)| self.input_map.is_action_pressed(action))
+            .fold([0., 0.], |[right, forward], (_, [dx, dz])| {
+                [right + dx, forward + dz]
+            });
+
+        let Some(camera) = &mut self.scene.camera else {
+            return;
+        };
+        let offset = self
+            .fly_camera
+            .update(camera.view_relative(right, forward), frame_time);
+        if !self.fly_camera.is_moving() {
+            return;
+        }
+
+        camera.translate(offset);
+        self.bake_scene();
+    }
+
+    /// Shakes the scene camera, replacing the shake going on if any
+    pub fn shake_camera(&mut self, shake: camera_shake::CameraShake) {
+        self.camera_shake = Some(shake);
+    }
+
+    // Bakes the scene seen from the shaken camera, leaving the camera where it was
+    fn bake_shaken_scene(&mut self, offset: [f32; 3]) {
+        let camera = self.scene.camera;
+        if let Some(camera) = &mut self.scene.camera {
+            camera.eye = std::array::from_fn(|i| camera.eye[i] + offset[i]);
+        }
+
+        self.bake_scene();
+        self.scene.camera = camera;
+    }
+
+    /// From the scene camera through `position` in the window, e.g. the cursor position.
+    /// None without a scene camera. Off by whatever `set_transform` does on top of the camera.
+    /// Positions in the bars around a letterboxed frame give rays outside of the view
+    pub fn screen_to_ray(&self, position: winit::dpi::PhysicalPosition<f64>) -> Option<ray::Ray> {
+        let camera = self.scene.camera?;
+        let (width, height) = self.render_size();
+        let view_projection =
+            camera.view_projection(width as f32 / height.max(1) as f32, self.reversed_z);
+        let inverse = cgmath::SquareMatrix::invert(&view_projection)?;
+
+        // At a fixed resolution, the frame is letterboxed in the middle of the surface
+        let (surface_width, surface_height) =
+            (self.surface_config.width, self.surface_config.height);
+        let (left, top, viewport_width, viewport_height) = match &self.upscale {
+            Some(upscale) => upscale.viewport(surface_width, surface_height),
+            None => (0., 0., surface_width as f32, surface_height as f32),
+        };
+        let x = (position.x as f32 - left) / viewport_width.max(1.) * 2. - 1.;
+        let y = 1. - (position.y as f32 - top) / viewport_height.max(1.) * 2.;
+        let (near_depth, far_depth) = if self.reversed_z { (1., 0.) } else { (0., 1.) };
+        let [near, far] = [near_depth, far_depth].map(|depth| {
+            let point = inverse * cgmath::Vector4::new(x, y, depth, 1.);
+            point.truncate() / point.w
+        });
+
+        Some(ray::Ray::new(near.into(), (far - near).into()))
+    }
+
+    // Puts the 3D cursor where the mouse points at, on the closest of the scene objects and
+    // the ground. The objects are hit at their bounding spheres
+    fn update_cursor(&mut self) {
+        let (Some(ray), Some(camera)) =
+            (self.screen_to_ray(self.cursor_position), self.scene.camera)
+        else {
+            self.cursor.set_position(None);
+            self.cursor
+                .update(&self.queue, cgmath::Matrix4::from_scale(1.));
+            return;
+        };
+
+        let ground = ray.intersect_plane([0.; 3], [0., 1., 0.]);
+        let closest = self
+            .scene
+            .objects
+            .iter()
+            .filter_map(|object| ray.intersect_sphere(self.scene.bounding_sphere(object)))
+            .chain(ground)
+            .min_by(f32::total_cmp);
+        self.cursor
+            .set_position(closest.map(|distance| ray.at(distance)));
+
+        let (width, height) = self.render_size();
+        self.cursor.update(
+            &self.queue,
+            camera.view_projection(width as f32 / height.max(1) as f32, self.reversed_z),
+        );
+    }
+
+    // The scene camera stays where the animation left it, the `camera_mode` moving it from there
+    fn stop_camera_animation(&mut self) {
+        if let Some(animation) = &mut self.camera_animation {
+            animation.stop();
+        }
+        self.fly_camera.stop();
+        self.reset_orbit_camera();
+    }
+
+    // Around where the scene camera looks, from where it is
+    fn reset_orbit_camera(&mut self) {
+        if let Some(camera) = self.scene.camera {
+            self.orbit_camera = orbit_camera::OrbitCamera::looking_at(camera.eye, camera.target);
+        }
+    }
+
+    // Moves the scene camera to the orbit camera, if it moved
+    fn follow_orbit_camera(&mut self) {
+        let Some(camera) = &mut self.scene.camera else {
+            return;
+        };
+
+        let (eye, target) = (self.orbit_camera.eye(), self.orbit_camera.center);
+        if camera.eye != eye || camera.target != target {
+            camera.eye = eye;
+            camera.target = target;
+            self.bake_scene();
+        }
+    }
+
+    // The scene is baked for the aspect ratio, so it's baked again when it changes
+    fn bake_scene(&mut self) {
+        let (width, height) = self.render_size();
+        // Objects were added or removed since the last sort, it's done again next frame
+        if self.draw_order.len() != self.scene.objects.len() {
+            self.draw_order = (0..self.scene.objects.len()).collect();
+        }
+        let vertices = self.scene.bake_in_order(
+            width as f32 / height.max(1) as f32,
+            self.reversed_z,
+            self.draw_order.iter().copied(),
+        );
+
+        self.scene_drawable = (!vertices.is_empty()).then(|| {
+            drawable::Drawable::new(
+                &self.device,
+                "My scene vertex buffer",
+                &vertices,
+                drawable::LAYER_OPAQUE,
+            )
+        });
+    }
+
+    /// Positions, scales and rotates the geometry of the main pipelines. Column major
+    pub fn set_transform(&mut self, transform: [[f32; 4]; 4]) {
+        self.transform = transform;
+        self.upload_transform();
+    }
+
+    fn upload_transform(&mut self) {
+        let mut transform = self.transform;
+
+        // Scaling X down by the aspect ratio of a wide frame, Y of a tall one
+        if self.correct_aspect_ratio {
+            let (width, height) = self.render_size();
+            let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+            let scale = [(height / width).min(1.), (width / height).min(1.)];
+
+            for column in &mut transform {
+                column[0] *= scale[0];
+                column[1] *= scale[1];
+            }
+        }
+
+        self.transform_buffer.set(transform);
+        self.transform_buffer.upload(&self.queue);
+    }
+
+    /// Takes effect from the next frame
+    pub fn set_blend_constant(&mut self, color: wgpu::Color) {
+        self.blend_constant = color;
+    }
+
+    // The material pipeline is created again with the new blend state
+    fn set_material_blend_mode(&mut self, blend_mode: blend::BlendMode) {
+        self.material_blend_mode = blend_mode;
+        self.material_pipeline = create_render_pipeline(
+            &self.device,
+            "My material render pipeline",
+            &self.material_pipeline_layout,
+            &self.material_shader,
+            "fs_main",
+            &self.vertex_layout,
+            TRIANGLE_LIST,
+            self.surface_view_format,
+            blend_mode,
+            self.depth_config,
+            self.sample_count,
+        );
+
+        if let Some(constant) = blend_mode.constant() {
+            self.set_blend_constant(constant);
+        }
+    }
+
+    /// The mesh is drawn with the material from now on, instead of its previous one
+    pub fn assign_material(&mut self, mesh: resources::MeshId, material: resources::MaterialId) {
+        self.material_table.assign(mesh, material);
+    }
+
+    /// The counts of the latest frame read back. None where they can't be counted
+    pub fn pipeline_stats(&self) -> Option<pipeline_stats::PipelineStats> {
+        self.pipeline_stat_query
+            .as_ref()
+            .and_then(pipeline_stats::PipelineStatQuery::latest)
+    }
+
+    // Drawables outside of the active layers are skipped by `render`
+    fn set_layer_mask(&mut self, layer_mask: u32) {
+        self.layer_mask = layer_mask;
+    }
+
+    /// Runs a window event through `StateConfig::event_filter`, the input map and `input`.
+    /// Whether it stops there: the filter consumed it, or it let it through and `input` handled it.
+    /// Otherwise the built-in handling, closing, the `input_map::QUIT` action and resizing,
+    /// is up to the event loop
+    pub fn filter_input(&mut self, event: &WindowEvent) -> bool {
+        match (self.event_filter)(event) {
+            EventDisposition::Consume => true,
+            EventDisposition::Ignore => {
+                self.input_map.handle_event(event);
+                false
+            }
+            EventDisposition::Passthrough => self.input(event),
+        }
+    }
+
+    /// The held keys and the actions they trigger
+    pub fn input_map(&self) -> &input_map::InputMap {
+        &self.input_map
+    }
+
+    /// To remap the keys while running
+    pub fn input_map_mut(&mut self) -> &mut input_map::InputMap {
+        &mut self.input_map
+    }
+
+    /// The interactive controls alone, bypassing the filter. Keys trigger them through
+    /// the actions of the input map, which sees the event first. Whether they handled it
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        self.input_map.handle_event(event);
+
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let (dx, dy) = (
+                    (position.x - self.cursor_position.x) as f32,
+                    (position.y - self.cursor_position.y) as f32,
+                );
+                match self.orbit_drag {
+                    Some(winit::event::MouseButton::Left) => self.orbit_camera.orbit(dx, dy),
+                    Some(winit::event::MouseButton::Middle) => self.orbit_camera.pan(dx, dy),
+                    _ => {}
+                }
+
+                self.cursor_position = *position;
+                let alpha_mode = self.surface_config.alpha_mode;
+                let alpha = if alpha_mode::AlphaModeSelector::is_transparent(alpha_mode) {
+                    TRANSPARENT_BACKGROUND_ALPHA
+                } else {
+                    1.
+                };
+                // The compositor expects the color already multiplied by the alpha
+                let scale = if alpha_mode == wgpu::CompositeAlphaMode::PreMultiplied {
+                    alpha
+                } else {
+                    1.
+                };
+
+                self.clear_color = wgpu::Color {
+                    r: position.x / self.window_size.width as f64 * scale,
+                    g: position.y / self.window_size.height as f64 * scale,
+                    b: scale,
+                    a: alpha,
+                };
+
+                true
+            }
+            // Dragging with the left button orbits, with the middle one pans, scrolling zooms
+            WindowEvent::MouseInput {
+                state,
+                button:
+                    button @ (winit::event::MouseButton::Left | winit::event::MouseButton::Middle),
+                ..
+            } if self.camera_mode == CameraMode::Orbit => {
+                self.orbit_drag = (*state == ElementState::Pressed).then_some(*button);
+
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } if self.camera_mode == CameraMode::Orbit => {
+                let lines = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(_, y) => *y,
+                    winit::event::MouseScrollDelta::PixelDelta(position) => {
+                        position.y as f32 / PIXELS_PER_SCROLL_LINE
+                    }
+                };
+                self.orbit_camera.zoom(lines);
+
+                true
+            }
+            // Clicking prints the color under the cursor
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: winit::event::MouseButton::Left,
+                ..
+            } => {
+                let (x, y) = (self.cursor_position.x, self.cursor_position.y);
+
+                if let Some([r, g, b, a]) = self.read_pixel(x as u32, y as u32) {
+                    log::info!(
+                        "The color at ({}, {}) is #{:02x}{:02x}{:02x}{:02x}",
+                        x,
+                        y,
+                        r,
+                        g,
+                        b,
+                        a
+                    );
+                }
+
+                true
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+
+                false
+            }
+            // The held pan actions pan the scene camera, see `pan_camera`,
+            // the fly ones fly it, see `fly_camera`
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key_code),
+                        ..
+                    },
+                ..
+            } if self.input_map.action(*key_code).is_some_and(|action| {
+                PAN_ACTIONS.iter().any(|(pan, _)| *pan == action)
+                    || self.camera_mode == CameraMode::Fly
+                        && FLY_ACTIONS.iter().any(|(fly, _)| *fly == action)
+            }) =>
+            {
+                true
+            }
+            // The other actions of the input map, see the constants of `input_map` for what they do
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key: PhysicalKey::Code(key_code),
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                let Some(action) = self.input_map.action(*key_code).map(str::to_owned) else {
+                    return false;
+                };
+
+                match action.as_str() {
+                    input_map::TOGGLE_OPAQUE_LAYER => {
+                        self.set_layer_mask(self.layer_mask ^ drawable::LAYER_OPAQUE)
+                    }
+                    input_map::TOGGLE_TRANSPARENT_LAYER => {
+                        self.set_layer_mask(self.layer_mask ^ drawable::LAYER_TRANSPARENT)
+                    }
+                    input_map::SWITCH_OIT => {
+                        self.oit_mode = match self.oit_mode {
+                            OitMode::Weighted => OitMode::LinkedList,
+                            OitMode::LinkedList => OitMode::Weighted,
+                        }
+                    }
+                    input_map::TOGGLE_SRGB_VIEW => {
+                        self.set_srgb_view(!self.surface_view_format.is_srgb())
+                    }
+                    input_map::TOGGLE_SLOW_FRAMES => self.slow_frames = !self.slow_frames,
+                    input_map::EXPORT_OBJ => match self.export_obj("mesh.obj") {
+                        Ok(()) => log::info!("Exported the mesh to mesh.obj"),
+                        Err(e) => log::error!("Failed to export the mesh: {}", e),
+                    },
+                    input_map::IMPORT_OBJ => self.import_obj("mesh.obj"),
+                    input_map::TOGGLE_TRAILS => {
+                        self.trails_enabled = !self.trails_enabled;
+
+                        if !self.trails_enabled {
+                            self.triangle
+                                .write_vertices(&self.queue, bytemuck::cast_slice(VERTICES));
+                        }
+                    }
+                    input_map::CYCLE_LINE_JOIN => {
+                        let mut style = self.wide_line.style();
+                        style.join = match style.join {
+                            wide_line::JoinStyle::Miter => wide_line::JoinStyle::Bevel,
+                            wide_line::JoinStyle::Bevel => wide_line::JoinStyle::Round,
+                            wide_line::JoinStyle::Round => wide_line::JoinStyle::Miter,
+                        };
+                        log::info!("Joining the line segments with {:?}", style.join);
+                        self.wide_line.set_style(style);
+                    }
+                    input_map::SCREENSHOT => {
+                        match self.screenshot(std::path::Path::new("screenshot.png")) {
+                            Ok(()) => log::info!("Saved the frame to screenshot.png"),
+                            Err(e) => log::error!("Failed to save a screenshot: {}", e),
+                        }
+                    }
+                    input_map::TOGGLE_FULLSCREEN => {
+                        let toggled = if self.window.fullscreen().is_some() {
+                            self.exit_fullscreen()
+                        } else {
+                            self.enter_exclusive_fullscreen(None)
+                        };
+                        if let Err(e) = toggled {
+                            log::error!("Failed to toggle fullscreen: {}", e);
+                        }
+                    }
+                    input_map::LOG_PIPELINE_STATS => match self.pipeline_stats() {
+                        Some(stats) => log::info!("Last frame: {:?}", stats),
+                        None => log::info!("No pipeline statistics, the GPU can't count them"),
+                    },
+                    input_map::RESET_CAMERA => {
+                        self.stop_camera_animation();
+                        self.scene.camera = self.initial_camera;
+                        self.fly_camera.stop();
+                        self.reset_orbit_camera();
+                        self.bake_scene();
+                    }
+                    input_map::CYCLE_CAMERA_MODE => {
+                        self.camera_mode = match self.camera_mode {
+                            CameraMode::Panning => CameraMode::Orbit,
+                            CameraMode::Orbit => CameraMode::Fly,
+                            CameraMode::Fly => CameraMode::Panning,
+                        };
+                        self.orbit_drag = None;
+                        self.fly_camera.stop();
+                        self.reset_orbit_camera();
+                        log::info!("Moving the scene camera in the {:?} mode", self.camera_mode);
+                    }
+                    input_map::TOGGLE_WIREFRAME => {
+                        if self.wireframe_pipelines.is_some() {
+                            self.wireframe = !self.wireframe;
+                        } else {
+                            log::warn!("The GPU can't draw a wireframe");
+                        }
+                    }
+                    input_map::SHAKE_CAMERA => {
+                        let (duration, intensity, frequency) = DEMO_SHAKE;
+                        self.camera_shakes_count = self.camera_shakes_count.wrapping_add(1);
+                        self.shake_camera(
+                            camera_shake::CameraShake::new(duration, intensity, frequency)
+                                .with_seed(self.camera_shakes_count),
+                        );
+                    }
+                    input_map::SWITCH_INSTANCES => {
+                        let scattered = self.instance_buffer.as_ref().is_some_and(|buffer| {
+                            buffer.vertex_count() as usize == DEMO_INSTANCES_COUNT
+                        });
+
+                        if scattered {
+                            self.set_instances(&instance_grid());
+                        } else {
+                            self.set_instances(&random_instances(DEMO_INSTANCES_COUNT));
+                        }
+                    }
+                    input_map::TOGGLE_SELECTION => {
+                        if self.selection_highlight.selected().is_empty() {
+                            // The squares sharing the first material
+                            let batches = self.material_table.batches();
+                            let meshes = batches.first().map_or(&[][..], |(_, meshes)| meshes);
+                            self.selection_highlight.set_selected(meshes);
+                        } else {
+                            self.selection_highlight.set_selected(&[]);
+                        }
+                    }
+                    input_map::TOGGLE_GRAYSCALE => {
+                        self.grayscale_target = match self.grayscale_target {
+                            Some(_) => None,
+                            None => Some(self.new_render_target(
+                                self.surface_config.width,
+                                self.surface_config.height,
+                                self.surface_view_format,
+                            )),
+                        };
+
+                        let mode = match self.grayscale_target {
+                            Some(_) => "GRAYSCALE",
+                            None => "COLOR",
+                        };
+                        self.update_label_text(self.render_mode_label, mode);
+                    }
+                    input_map::SWITCH_TEXTURE => {
+                        self.diffuse_texture_index =
+                            (self.diffuse_texture_index + 1) % self.diffuse_textures.len();
+                        // `bind_texture` can't borrow one of the textures from `self`
+                        self.diffuse_bind_group = create_diffuse_bind_group(
+                            &self.device,
+                            &self.shader_reflection,
+                            &self.diffuse_bind_group_layout,
+                            &self.diffuse_textures[self.diffuse_texture_index],
+                        )
+                        .expect("the bundled textures match the shader");
+                    }
+                    input_map::TOGGLE_CAMERA_TOUR => match &self.camera_animation {
+                        Some(animation) if animation.is_playing() => self.stop_camera_animation(),
+                        _ => {
+                            self.camera_animation = self.scene.camera.as_ref().map(camera_tour);
+                            if let Some(animation) = &mut self.camera_animation {
+                                animation.play(true);
+                            }
+                        }
+                    },
+                    input_map::TOGGLE_MATERIAL_BLEND => {
+                        self.set_material_blend_mode(match self.material_blend_mode {
+                            blend::BlendMode::Replace => blend::BlendMode::ConstantAlpha(0.5),
+                            _ => blend::BlendMode::Replace,
+                        })
+                    }
+                    input_map::TOGGLE_COLOR_CYCLE => {
+                        self.color_cycle_enabled = !self.color_cycle_enabled;
+
+                        if !self.color_cycle_enabled {
+                            self.transparent_triangle.write_vertices(
+                                &self.queue,
+                                bytemuck::cast_slice(TRANSPARENT_VERTICES),
+                            );
+                        }
+                    }
+                    _ => return false,
+                }
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Advances everything animated by the time since the last call, once per frame before `render`
+    pub fn update(&mut self) {
+        // Simulates a heavy frame
+        if self.slow_frames {
+            std::thread::sleep(std::time::Duration::from_millis(250));
+        }
+
+        self.camera_buffer
+            .set(CameraUniform::for_depth(self.reversed_z).0);
+        self.camera_buffer.upload(&self.queue);
+
+        // The culled geometry is drawn with the camera and the transform, see `vs_main`
+        let view_proj = cgmath::Matrix4::from(self.camera_buffer.value().view_proj)
+            * cgmath::Matrix4::from(*self.transform_buffer.value());
+        for culler in std::iter::once(&self.culler).chain(&self.mesh_culler) {
+            culler.update_frustum(&self.queue, view_proj.into());
+        }
+
+        // Front to back, so the depth test rejects the hidden fragments before they are shaded.
+        // The scene is baked again only when the order changes, the objects themselves stay in place
+        let (camera_pos, view_direction) = self.scene.camera.map_or(
+            // Without a camera the positions are in clip space, where the depth grows along Z
+            ([0., 0., 0.], [0., 0., 1.]),
+            |camera| (camera.eye, camera.view_direction()),
+        );
+        let mut draw_queue = draw_queue::DrawQueue::new(view_direction);
+        for (i, object) in self.scene.objects.iter().enumerate() {
+            draw_queue.push(self.scene.bounding_sphere(object), i);
+        }
+        draw_queue.sort_opaque_front_to_back(camera_pos);
+        if !draw_queue.items().eq(&self.draw_order) {
+            self.draw_order = draw_queue.items().copied().collect();
+            self.bake_scene();
+        }
+
+        // Something has to move for the trail to show
+        if self.trails_enabled {
+            let (sin, cos) = self.start_time.elapsed().as_secs_f32().sin_cos();
+
+            if let Some(mut vertices) =
+                self.write_buffer_with(self.triangle.vertex_buffer().buffer(), 0, VERTICES.len())
+            {
+                for (rotated, vertex) in vertices.iter_mut().zip(VERTICES) {
+                    let [x, y, z] = vertex.position;
+
+                    *rotated = Vertex {
+                        position: [x * cos - y * sin, x * sin + y * cos, z],
+                        ..*vertex
+                    };
+                }
+            }
+        }
+
+        // The compute pass writes the vertices the frame then draws
+        if self.color_cycle_enabled {
+            let command_buffer = self.color_cycle.record(
+                &self.device,
+                &self.queue,
+                self.start_time.elapsed().as_secs_f32(),
+                self.transparent_triangle.vertex_buffer().buffer(),
+            );
+            self.add_command_buffer(command_buffer, SubmitOrder::BeforeFrame);
+        }
+
+        let now = std::time::Instant::now();
+        let frame_duration = now.duration_since(self.last_frame);
+        let frame_time = frame_duration.as_secs_f32();
+        self.last_frame = now;
+
+        self.walker.advance(frame_duration);
+        if let Some((eye, target)) = self
+            .camera_animation
+            .as_mut()
+            .and_then(|animation| animation.advance(frame_time))
+        {
+            if let Some(camera) = &mut self.scene.camera {
+                camera.eye = eye;
+                camera.target = target;
+            }
+            self.bake_scene();
+
+            // Played to the end, the orbit camera goes on from there
+            if !self
+                .camera_animation
+                .as_ref()
+                .is_some_and(|a| a.is_playing())
+            {
+                self.reset_orbit_camera();
+            }
+        } else {
+            match self.camera_mode {
+                CameraMode::Panning => self.pan_camera(frame_time),
+                CameraMode::Orbit => self.follow_orbit_camera(),
+                CameraMode::Fly => self.fly_camera(frame_time),
+            }
+        }
+        // After the camera moved, shaking it from there. The last frame bakes it back in place
+        if let Some(shake) = &mut self.camera_shake {
+            let offset = shake.update(frame_time);
+            if shake.is_finished() {
+                self.camera_shake = None;
+            }
+            self.bake_shaken_scene(offset);
+        }
+        self.update_cursor();
+        self.move_by_actions(frame_time);
+        if frame_time > 0. {
+            self.fps += (1. / frame_time - self.fps) * 0.1;
+        }
+
+        // The crosshair and the counter stay in place whatever is drawn in the world
+        let (center_x, center_y) = (
+            self.surface_config.width as f32 / 2.,
+            self.surface_config.height as f32 / 2.,
+        );
+        let white = [1., 1., 1., 0.8];
+        self.hud.line(
+            [center_x - 10., center_y],
+            [center_x + 10., center_y],
+            2.,
+            white,
+        );
+        self.hud.line(
+            [center_x, center_y - 10.],
+            [center_x, center_y + 10.],
+            2.,
+            white,
+        );
+        self.hud.rect(8., 8., 120., 28., [0., 0., 0., 0.5]);
+        self.hud
+            .text(12., 12., 4., &format!("FPS {:.0}", self.fps), white);
+
+        for (_, label) in &self.viewport_labels {
+            label.draw(&mut self.hud);
+        }
+
+        let mut import_done = false;
+
+        if let Some(import_events) = &self.import_events {
+            for event in import_events.try_iter() {
+                match event {
+                    ImportEvent::Progress(progress) => {
+                        let filled = (progress * 20.) as usize;
+                        self.window.set_title(&format!(
+                            "Importing [{}{}] {:.0}%",
+                            "#".repeat(filled),
+                            "-".repeat(20 - filled),
+                            progress * 100.
+                        ));
+                    }
+                    ImportEvent::Done(result) => {
+                        match result {
+                            Ok(mesh) => log::info!(
+                                "Imported {} vertices and {} triangles",
+                                mesh.positions.len(),
+                                mesh.indices.len() / 3
+                            ),
+                            Err(e) => log::error!("Failed to import the mesh: {}", e),
+                        }
+
+                        self.window.set_title(WINDOW_TITLE);
+                        import_done = true;
+                    }
+                }
+            }
+        }
+
+        if import_done {
+            self.import_events = None;
+        }
+
+        if let Some(path) = &self.scene_path {
+            let modified = std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+
+            if modified.is_some() && modified != self.scene_modified {
+                self.reload_scene();
+            }
+        }
+    }
+
+    /// Presents a frame. Errors are reported according to `StateConfig::error_policy`,
+    /// the ones returned must end the rendering
+    pub fn render(&mut self) -> Result<(), error_policy::GpuError> {
+        let frame = match self.grayscale_target.take() {
+            Some(target) => {
+                self.render_to_target(&target);
+                let blitted = self.blit_to_screen(&target, render_target::BlitEffect::Grayscale);
+                self.grayscale_target = Some(target);
+
+                blitted
+            }
+            None => self.render_frame(None).map(|_| ()),
+        };
+
+        match frame {
+            Ok(()) => {}
+            // Reconfiguring the surface is enough
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.surface_config)
+            }
+            // The frame is skipped
+            Err(wgpu::SurfaceError::Timeout) => log::warn!("Timed out getting the frame"),
+            Err(e) => self.errors.report(error_policy::GpuError::Surface(e)),
+        }
+
+        self.errors.take_pending().map_or(Ok(()), Err)
+    }
+
+    /// An offscreen target the size of `width` and `height`, for `render_to_target`,
+    /// with a depth texture matching the pipelines
+    pub fn new_render_target(
+        &self,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> render_target::RenderTarget {
+        render_target::RenderTarget::new(
+            &self.device,
+            width,
+            height,
+            format,
+            self.depth_config.format(),
+        )
+    }
+
+    /// Draws the opaque geometry of the active layers into `target` instead of the surface,
+    /// without MSAA. Not the passes drawn over it: the transparent geometry, the sprites, the HUD
+    pub fn render_to_target(&mut self, target: &render_target::RenderTarget) {
+        if self
+            .offscreen_pipelines
+            .as_ref()
+            .is_none_or(|pipelines| pipelines.format != target.format())
+        {
+            let create = |label: &str, fragment_entry_point: &str| {
+                create_render_pipeline(
+                    &self.device,
+                    label,
+                    &self.render_pipeline_layout,
+                    &self.shader,
+                    fragment_entry_point,
+                    &self.vertex_layout,
+                    TRIANGLE_LIST,
+                    target.format(),
+                    blend::BlendMode::Replace,
+                    self.depth_config,
+                    1,
+                )
+            };
+
+            self.offscreen_pipelines = Some(OffscreenPipelines {
+                format: target.format(),
+                render: create(
+                    "My offscreen render pipeline",
+                    self.shader_entry_points.color,
+                ),
+                textured: create("My offscreen textured pipeline", "fs_main"),
+            });
+        }
+        let Some(pipelines) = &self.offscreen_pipelines else {
+            return;
+        };
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("My offscreen command encoder"),
+            });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My offscreen render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target.view(),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_color),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: target.depth_view(),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_depth),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                // Depth only formats can't have stencil operations
+                stencil_ops: self.depth_config.format().has_stencil_aspect().then_some(
+                    wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_stencil),
+                        store: wgpu::StoreOp::Discard,
+                    },
+                ),
             }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw, // ------ - Don't render triangles that are not visible
-                cull_mode: Some(wgpu::Face::Back), // ---/
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
         });
 
-        // 6. Create vertex buffer
-        let vertex_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("My vertex buffer"),
-                contents: bytemuck::cast_slice(VERTICES),
-                usage: wgpu::BufferUsages::VERTEX,
+        if let Some(stencil) = self.depth_config.stencil_config() {
+            render_pass.set_stencil_reference(stencil.reference);
+        }
+
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.transform_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.diffuse_bind_group, &[]);
+
+        // The culled triangle too, there is no culling pass before this one
+        render_pass.set_pipeline(&pipelines.textured);
+        for drawable in [&self.triangle, &self.textured_quad] {
+            if drawable.is_rendered(self.layer_mask) {
+                render_pass.set_vertex_buffer(0, drawable.vertex_buffer().slice());
+                render_pass.draw(0..drawable.vertices_count(), 0..1);
             }
-        );
+        }
 
-        State {
-            window,
-            surface,
-            device,
-            queue,
-            surface_config,
-            window_size,
-            clear_color: wgpu::Color::BLACK,
-            render_pipeline,
-            vertex_buffer,
-            vertices_count: VERTICES.len() as u32,
+        render_pass.set_pipeline(&pipelines.render);
+        for drawable in [&self.occlusion, &self.mesh] {
+            if drawable.is_rendered(self.layer_mask) {
+                render_pass.set_vertex_buffer(0, drawable.vertex_buffer().slice());
+                render_pass.draw(0..drawable.vertices_count(), 0..1);
+            }
+        }
+
+        if let Some(scene) = self
+            .scene_drawable
+            .as_ref()
+            .filter(|scene| scene.is_rendered(self.layer_mask))
+        {
+            render_pass.set_bind_group(0, &self.scene_camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, scene.vertex_buffer().slice());
+            render_pass.draw(0..scene.vertices_count(), 0..1);
         }
+
+        drop(render_pass);
+        self.queue.submit([encoder.finish()]);
     }
 
-    fn window(&self) -> &Window {
-        self.window
+    /// Presents a frame of `render_target` stretched over the whole surface, through `effect`,
+    /// with the HUD and the labels over it
+    pub fn blit_to_screen(
+        &mut self,
+        render_target: &render_target::RenderTarget,
+        effect: render_target::BlitEffect,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let texture = self.surface.get_current_texture()?;
+        let view = texture.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(self.surface_view_format),
+            ..Default::default()
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("My blit command encoder"),
+            });
+        self.blit
+            .draw(&self.device, &mut encoder, render_target, &view, effect);
+        // The HUD isn't part of the render target
+        self.hud
+            .render(&self.device, &self.queue, &mut encoder, &view);
+
+        self.queue.submit([encoder.finish()]);
+        texture.present();
+
+        Ok(())
     }
 
-    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.window_size = new_size;
-            self.surface_config.width = new_size.width;
-            self.surface_config.height = new_size.height;
-            self.surface.configure(&self.device, &self.surface_config);
+    // Renders a frame and returns the RGBA color of the pixel at the window coordinates.
+    // None if the surface can't be copied from or the frame failed
+    fn read_pixel(&mut self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if let Err(e) = self.check_readback() {
+            log::warn!("Can't read the pixel: {}", e);
+            return None;
         }
-    }
 
-    fn input(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::CursorMoved { position, .. } => {
-                self.clear_color = wgpu::Color {
-                    r: position.x / self.window_size.width as f64,
-                    g: position.y / self.window_size.height as f64,
-                    b: 1.,
-                    a: 1.,
-                };
+        // The window and the surface don't have to be of the same size
+        let x = (x as u64 * self.surface_config.width as u64 / self.window_size.width.max(1) as u64)
+            .min(self.surface_config.width as u64 - 1) as u32;
+        let y = (y as u64 * self.surface_config.height as u64
+            / self.window_size.height.max(1) as u64)
+            .min(self.surface_config.height as u64 - 1) as u32;
 
-                true
+        match self.render_frame(Some([x, y, 1, 1])) {
+            Ok(pixel) => pixel.map(|rgba| [rgba[0], rgba[1], rgba[2], rgba[3]]),
+            Err(e) => {
+                log::warn!("Failed to render the frame to read the pixel from: {}", e);
+                None
             }
-            _ => false,
         }
     }
 
-    fn update(&mut self) {}
+    /// Renders a frame and saves it as a PNG, e.g. as a reference image for regression tests.
+    /// The surface must allow copying from it, in an 8 bit RGBA or BGRA format
+    pub fn screenshot(
+        &mut self,
+        path: &std::path::Path,
+    ) -> Result<(), screenshot::ScreenshotError> {
+        self.check_readback()?;
+
+        let (width, height) = (self.surface_config.width, self.surface_config.height);
+        let rgba = self
+            .render_frame(Some([0, 0, width, height]))?
+            .ok_or(screenshot::ScreenshotError::Readback)?;
+
+        screenshot::save_png(path, width, height, &rgba)
+    }
+
+    // Whether `render_frame` can copy the frame out
+    fn check_readback(&self) -> Result<(), screenshot::ScreenshotError> {
+        if !self
+            .surface_config
+            .usage
+            .contains(wgpu::TextureUsages::COPY_SRC)
+        {
+            return Err(screenshot::ScreenshotError::NotCopyable);
+        }
+
+        screenshot::check_format(self.surface_config.format)
+    }
 
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    // `readback` is a rectangle of the surface, x, y, width and height, to copy out once the frame
+    // is rendered. Returned as RGBA rows, see `check_readback`
+    fn render_frame(
+        &mut self,
+        readback: Option<[u32; 4]>,
+    ) -> Result<Option<Vec<u8>>, wgpu::SurfaceError> {
         let texture = self.surface.get_current_texture()?;
-        let view = texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let view = texture.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(self.surface_view_format),
+            ..Default::default()
+        });
 
         let mut encoder = self
             .device
@@ -233,89 +3917,733 @@ impl<'a> State<'a> {
                 label: Some("My command encoder"),
             });
 
+        // Checked whenever the depth texture is created
+        debug_assert!(self.check_depth_texture().is_ok());
+
+        // The culling pass writes the instance counts used by `draw_indirect` and `draw_indexed_indirect`
+        {
+            let mut culling_scope = debug_scope::DebugScope::new(&mut encoder, "My culling");
+            for culler in std::iter::once(&self.culler).chain(&self.mesh_culler) {
+                culler.cull(&mut culling_scope);
+            }
+        }
+
+        // Once for every pass reading the skinned vertices
+        if let Some(skinning) = &self.skinning {
+            skinning.skin(&mut encoder);
+        }
+
+        // The jobs are dropped once recorded, the command buffer keeps what they use alive
+        let compute_jobs = std::mem::take(&mut self.compute_jobs);
+        if !compute_jobs.is_empty() {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("My compute jobs pass"),
+                timestamp_writes: None,
+            });
+
+            for job in &compute_jobs {
+                job.dispatch(&mut compute_pass);
+            }
+        }
+
+        // At a fixed resolution, the scene is rendered offscreen and scaled to the surface at the end
+        let scene_view = match &self.upscale {
+            Some(upscale) => upscale.frame_view(),
+            None => &view,
+        };
+
+        // With the trails, the frame is rendered over the previous one and copied to the scene view at the end
+        let target_view = if self.trails_enabled {
+            self.trails.frame_view()
+        } else {
+            scene_view
+        };
+
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("My render pass"),
-            depth_stencil_attachment: None,
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: self.depth_texture.view(),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_depth),
+                    store: wgpu::StoreOp::Store,
+                }),
+                // Depth only formats can't have stencil operations
+                stencil_ops: self.depth_config.format().has_stencil_aspect().then_some(
+                    wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_stencil),
+                        store: wgpu::StoreOp::Store,
+                    },
+                ),
+            }),
             occlusion_query_set: None,
             timestamp_writes: None,
             color_attachments: &[
-                // This is the 0 element. @location(0) in the shader tells to relate to this element
-                Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color),
-                        store: wgpu::StoreOp::Store,
+                // This is the 0 element. @location(0) in the shader tells to relate to this element.
+                // With MSAA, it's rendered multisampled and resolved into the target
+                Some(match &self.msaa_target {
+                    Some(msaa_target) => msaa_target
+                        .color_attachment(target_view, wgpu::LoadOp::Clear(self.clear_color)),
+                    None => wgpu::RenderPassColorAttachment {
+                        view: target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(self.clear_color),
+                            store: wgpu::StoreOp::Store,
+                        },
                     },
                 }),
             ],
         });
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.draw(0..self.vertices_count, 0..1); // @builtin(vertex_index) passes these values to the shader
+        if let Some(query) = &self.pipeline_stat_query {
+            query.begin(&mut render_pass);
+        }
+
+        if let Some(stencil) = self.depth_config.stencil_config() {
+            render_pass.set_stencil_reference(stencil.reference);
+        }
+
+        if self.trails_enabled {
+            self.trails.draw_history(&mut render_pass);
+        }
+
+        let (render_pipeline, textured_pipeline, strip_pipeline) =
+            match self.wireframe_pipelines.as_ref().filter(|_| self.wireframe) {
+                Some(wireframe) => (&wireframe.render, &wireframe.textured, &wireframe.strip),
+                None => (
+                    &self.render_pipeline,
+                    &self.textured_pipeline,
+                    &self.strip_pipeline,
+                ),
+            };
+
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.transform_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.diffuse_bind_group, &[]);
+
+        if self.triangle.is_rendered(self.layer_mask) {
+            let mut triangle_scope =
+                debug_scope::DebugScope::new(&mut render_pass, "My opaque triangle");
+            triangle_scope.set_pipeline(textured_pipeline);
+            triangle_scope.set_vertex_buffer(0, self.triangle.vertex_buffer().slice());
+            triangle_scope.draw_indirect(self.culler.draw_args(), 0); // @builtin(vertex_index) and @builtin(instance_index) get these values
+        }
+
+        if self.textured_quad.is_rendered(self.layer_mask) {
+            render_pass.set_pipeline(textured_pipeline);
+            render_pass.set_vertex_buffer(0, self.textured_quad.vertex_buffer().slice());
+            render_pass.draw(0..self.textured_quad.vertices_count(), 0..1);
+        }
+
+        if let (Some(instanced_pipeline), Some(instance_buffer)) = (
+            &self.instanced_pipeline,
+            self.instance_buffer
+                .as_ref()
+                .filter(|_| self.instanced_mesh.is_rendered(self.layer_mask)),
+        ) {
+            render_pass.set_pipeline(instanced_pipeline);
+            render_pass.set_vertex_buffer(0, self.instanced_mesh.vertex_buffer().slice());
+            render_pass.set_vertex_buffer(1, instance_buffer.slice());
+            render_pass.draw(
+                0..self.instanced_mesh.vertices_count(),
+                0..instance_buffer.vertex_count(),
+            );
+        }
+
+        if self.occlusion.is_rendered(self.layer_mask) {
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_vertex_buffer(0, self.occlusion.vertex_buffer().slice());
+            render_pass.draw(0..self.occlusion.vertices_count(), 0..1);
+        }
+
+        if self.mesh.is_rendered(self.layer_mask) {
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_vertex_buffer(0, self.mesh.vertex_buffer().slice());
+
+            match (&self.index_buffer, &self.mesh_culler) {
+                (Some(index_buffer), Some(mesh_culler)) => {
+                    index_buffer.set(&mut render_pass);
+                    render_pass.draw_indexed_indirect(mesh_culler.draw_indexed_args(), 0);
+                }
+                _ => render_pass.draw(0..self.mesh.vertices_count(), 0..1),
+            }
+        }
+
+        if let Some(scene) = self
+            .scene_drawable
+            .as_ref()
+            .filter(|scene| scene.is_rendered(self.layer_mask))
+        {
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_bind_group(0, &self.scene_camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, scene.vertex_buffer().slice());
+            render_pass.draw(0..scene.vertices_count(), 0..1);
+        }
+
+        if self.cursor.is_visible() {
+            render_pass.set_pipeline(&self.cursor_pipeline);
+            render_pass.set_bind_group(0, &self.scene_camera_bind_group, &[]);
+            self.cursor.draw(&mut render_pass);
+        }
+
+        // Each material is bound once, for all the meshes using it
+        render_pass.set_pipeline(&self.material_pipeline);
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        render_pass.set_blend_constant(self.blend_constant);
+        for (material, meshes) in self.material_table.batches() {
+            let Some(material) = self.resources.material(material) else {
+                continue;
+            };
+            render_pass.set_bind_group(material::MATERIAL_GROUP, &material.bind_group, &[]);
+
+            for mesh in meshes
+                .into_iter()
+                .filter_map(|mesh| self.resources.mesh(mesh))
+                .filter(|mesh| mesh.is_rendered(self.layer_mask))
+            {
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer().slice());
+                render_pass.draw(0..mesh.vertices_count(), 0..1);
+            }
+        }
+
+        if self.layer_mask & drawable::LAYER_OPAQUE != 0 {
+            render_pass.set_pipeline(strip_pipeline);
+            // The materials were bound to group 0 in the meantime
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.transform_bind_group, &[]);
+            render_pass.set_bind_group(2, &self.diffuse_bind_group, &[]);
+            self.strips.draw(&mut render_pass);
+
+            render_pass.set_vertex_buffer(0, self.sine_wave_buffer.slice(..));
+            render_pass.draw(0..SINE_WAVE.samples_count * 2, 0..1);
+
+            // Last, as it binds a group 0 of its own
+            if let Some(skinning) = &self.skinning {
+                self.skinned_mesh_pipeline.draw(&mut render_pass, skinning);
+            }
+        }
+
+        if let Some(query) = &self.pipeline_stat_query {
+            query.end(&mut render_pass);
+        }
 
         // encoder was mutably borrowed when creating `render_pass`
         drop(render_pass);
 
-        self.queue.submit([encoder.finish()]);
+        if let Some(query) = &mut self.pipeline_stat_query {
+            query.resolve(&mut encoder);
+        }
+
+        // Over the opaque geometry, but not through the transparent one
+        self.selection_highlight
+            .draw(&self.queue, &mut encoder, target_view, &self.resources);
+
+        // Transparent geometry goes after the opaque one, so it can be depth tested against it
+        let (mut transparent_pass, transparent_pipeline) = match self.oit_mode {
+            OitMode::Weighted => (
+                self.wboit
+                    .begin_accumulation(&mut encoder, self.depth_texture.view()),
+                self.transparent_pipeline.as_ref(),
+            ),
+            OitMode::LinkedList => (
+                self.linked_list_oit
+                    .begin_gather(&mut encoder, self.depth_texture.view(), 3),
+                self.linked_list_pipeline.as_ref(),
+            ),
+        };
+
+        if let Some(transparent_pipeline) =
+            transparent_pipeline.filter(|_| self.transparent_triangle.is_rendered(self.layer_mask))
+        {
+            transparent_pass.set_pipeline(transparent_pipeline);
+            transparent_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            transparent_pass.set_bind_group(1, &self.transform_bind_group, &[]);
+            transparent_pass.set_bind_group(2, &self.diffuse_bind_group, &[]);
+            transparent_pass
+                .set_vertex_buffer(0, self.transparent_triangle.vertex_buffer().slice());
+            transparent_pass.draw(0..self.transparent_triangle.vertices_count(), 0..1);
+        }
+
+        drop(transparent_pass);
+
+        match self.oit_mode {
+            OitMode::Weighted => self.wboit.composite(&mut encoder, target_view),
+            OitMode::LinkedList => self.linked_list_oit.resolve(&mut encoder, target_view),
+        }
+
+        self.wide_line
+            .render(&self.queue, &mut encoder, target_view);
+
+        self.sprites.draw(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            target_view,
+            &[sprite::SpriteInstance::new(
+                [-0.7, -0.6],
+                [0.3, 0.3],
+                self.walker.uv_rect(),
+            )],
+        );
+
+        self.point_sprites.draw(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            target_view,
+            &point_sprite_positions(self.start_time.elapsed().as_secs_f32()),
+        );
+
+        if self.trails_enabled {
+            self.trails.present(&mut encoder, scene_view);
+        }
+
+        if let Some(upscale) = &self.upscale {
+            upscale.present(
+                &mut encoder,
+                &view,
+                self.surface_config.width,
+                self.surface_config.height,
+            );
+        }
+
+        // After the trails, so the HUD doesn't leave any
+        self.hud
+            .render(&self.device, &self.queue, &mut encoder, &view);
+
+        // Even a single pixel is copied with the rows padded to 256 bytes
+        let readback_buffer = readback.map(|[x, y, width, height]| {
+            let bytes_per_row = screenshot::padded_bytes_per_row(width);
+            let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("My readback buffer"),
+                size: bytes_per_row as wgpu::BufferAddress * height as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+
+            encoder.copy_texture_to_buffer(
+                wgpu::ImageCopyTexture {
+                    texture: &texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            (buffer, width, height)
+        });
+
+        self.queue.submit(
+            self.command_buffers_before
+                .drain(..)
+                .chain([encoder.finish()])
+                .chain(self.command_buffers_after.drain(..)),
+        );
+
+        if let Some(query) = &mut self.pipeline_stat_query {
+            query.after_submit();
+        }
+
+        let pixels = readback_buffer.and_then(|(buffer, width, height)| {
+            let mapped = pollster::block_on(buffer_map::map_buffer_async(
+                &self.device,
+                &buffer,
+                wgpu::MapMode::Read,
+            ));
+            match mapped {
+                Ok(bytes) => Some(screenshot::rgba_rows(
+                    &bytes,
+                    width,
+                    height,
+                    self.surface_config.format,
+                )),
+                Err(error) => {
+                    log::error!("Can't read the frame back: {}", error);
+                    None
+                }
+            }
+        });
 
         texture.present();
 
-        Ok(())
+        Ok(pixels)
     }
 }
 
 pub async fn run() -> Result<(), String> {
+    run_with_config(StateConfig::default()).await
+}
+
+pub async fn run_with_config(config: StateConfig) -> Result<(), String> {
     env_logger::init();
 
     // Creating a window using just `winit`
+    // The window and the event loop always live on the main thread
     let event_loop = EventLoop::new().unwrap();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = WindowBuilder::new()
+        .with_title(WINDOW_TITLE)
+        .with_transparent(config.transparent_window)
+        .build(&event_loop)
+        .unwrap();
 
     // Creating our state
-    let mut state = State::new(&window).await;
+    // The surface is created here too, on the main thread, even when rendering happens elsewhere
+    let threading = config.threading;
+    let state = State::new(&window, config)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match threading {
+        Threading::SingleThreaded => run_single_threaded(event_loop, state),
+        Threading::RenderThread => run_render_thread(event_loop, state),
+    }
+}
+
+#[allow(clippy::collapsible_match)]
+fn run_single_threaded(event_loop: EventLoop<()>, mut state: State) -> Result<(), String> {
+    // The error ending the rendering, if any
+    let mut failure = None;
 
     // Running the event loop
-    event_loop
-        .run(move |event, control_flow| match event {
+    let result = event_loop.run(|event, control_flow| match event {
+        Event::WindowEvent {
+            window_id,
+            ref event,
+        } if window_id == state.window().id() => {
+            if !state.filter_input(event) {
+                match event {
+                    WindowEvent::CloseRequested => control_flow.exit(),
+                    event if state.input_map.triggers(event, input_map::QUIT) => {
+                        control_flow.exit()
+                    }
+                    WindowEvent::Resized(physical_size) => {
+                        if let Err(e) = state.resize(*physical_size) {
+                            failure = Some(e.to_string());
+                            control_flow.exit();
+                        }
+                    }
+                    WindowEvent::RedrawRequested => {
+                        state.update();
+
+                        if let Err(e) = state.render() {
+                            failure = Some(e.to_string());
+                            control_flow.exit();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Event::AboutToWait => {
+            state.window().request_redraw();
+        }
+        _ => {}
+    });
+
+    result.map_err(|e| e.to_string())?;
+    failure.map_or(Ok(()), Err)
+}
+
+// The event loop only forwards the window events to the render thread,
+// which renders as fast as the present mode lets it.
+// Closing the window is handled here, the quit action on the render thread, which has the input map
+fn run_render_thread(event_loop: EventLoop<()>, state: State) -> Result<(), String> {
+    let window_id = state.window().id();
+    let event_filter = state.event_filter;
+    // The render thread asks the event loop to exit with a user event
+    let exit_proxy = event_loop.create_proxy();
+    let (event_sender, event_receiver) = std::sync::mpsc::channel();
+
+    std::thread::scope(|scope| {
+        let render_thread = scope.spawn(move || render_loop(state, event_receiver, exit_proxy));
+
+        // The sender is dropped together with the closure once the loop exits,
+        // which stops the render thread
+        let result = event_loop.run(move |event, control_flow| match event {
             Event::WindowEvent {
-                window_id,
-                ref event,
-            } if window_id == state.window.id() => {
-                if !state.input(event) {
-                    match event {
-                        WindowEvent::CloseRequested
-                        | WindowEvent::KeyboardInput {
-                            event:
-                                KeyEvent {
-                                    state: ElementState::Pressed,
-                                    physical_key: PhysicalKey::Code(KeyCode::Escape),
-                                    ..
-                                },
-                            ..
-                        } => control_flow.exit(),
-                        WindowEvent::Resized(physical_size) => {
-                            state.resize(*physical_size);
+                window_id: id,
+                event,
+            } if id == window_id => match (event_filter(&event), event) {
+                (EventDisposition::Consume, _) => {}
+                (_, WindowEvent::CloseRequested) => control_flow.exit(),
+                (disposition, event) => {
+                    if event_sender.send((event, disposition)).is_err() {
+                        control_flow.exit();
+                    }
+                }
+            },
+            Event::UserEvent(()) => control_flow.exit(),
+            _ => {}
+        });
+
+        let failure = render_thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+        result.map_err(|e| e.to_string())?;
+        failure.map_or(Ok(()), Err)
+    })
+}
+
+// Returns the error that ended the rendering, if any
+fn render_loop(
+    mut state: State,
+    events: std::sync::mpsc::Receiver<(WindowEvent, EventDisposition)>,
+    exit_proxy: winit::event_loop::EventLoopProxy<()>,
+) -> Option<String> {
+    // The first `Resized` may have been sent before the thread started
+    if let Err(e) = state.resize(state.window().inner_size()) {
+        let _ = exit_proxy.send_event(());
+
+        return Some(e.to_string());
+    }
+
+    loop {
+        // Apply everything that happened since the last frame
+        loop {
+            match events.try_recv() {
+                // Filtered on the event loop thread already
+                Ok((event, disposition)) => {
+                    state.input_map.handle_event(&event);
+
+                    if !(disposition == EventDisposition::Passthrough && state.input(&event)) {
+                        if state.input_map.triggers(&event, input_map::QUIT) {
+                            let _ = exit_proxy.send_event(());
+
+                            return None;
                         }
-                        WindowEvent::RedrawRequested => {
-                            state.update();
-
-                            match state.render() {
-                                Ok(_) => {}
-                                Err(wgpu::SurfaceError::Lost) => state.resize(state.window_size),
-                                Err(wgpu::SurfaceError::OutOfMemory) => control_flow.exit(),
-                                Err(e) => eprintln!("{:#?}", e),
+
+                        if let WindowEvent::Resized(physical_size) = event {
+                            if let Err(e) = state.resize(physical_size) {
+                                let _ = exit_proxy.send_event(());
+
+                                return Some(e.to_string());
                             }
                         }
-                        _ => {}
                     }
                 }
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => return None,
             }
-            Event::AboutToWait => {
-                state.window.request_redraw();
-            }
-            _ => {}
-        })
-        .map_err(|op| op.to_string())
+        }
+
+        state.update();
+
+        if let Err(e) = state.render() {
+            let _ = exit_proxy.send_event(());
+
+            return Some(e.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) const SIZE: u32 = 64;
+
+    // None without an adapter, e.g. on a CI machine without a GPU. For the tests of the modules too
+    pub(crate) fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        device_with(wgpu::DownlevelFlags::empty())
+    }
+
+    // Also None when the adapter lacks some of `flags`, e.g. on GL
+    pub(crate) fn device_with(flags: wgpu::DownlevelFlags) -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))?;
+        if !adapter.get_downlevel_capabilities().flags.contains(flags) {
+            return None;
+        }
+
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).ok()
+    }
+
+    // A texture of `width` texels per row, RGBA, for the tests sampling known colors
+    pub(crate) fn texture_of(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        texels: &[[u8; 4]],
+    ) -> texture::Texture {
+        let image = image::RgbaImage::from_raw(width, texels.len() as u32 / width, texels.concat())
+            .expect("whole rows of texels");
+        let texture = texture::upload_image(
+            device,
+            queue,
+            &image.into(),
+            wgpu::TextureFormat::Rgba8Unorm,
+        );
+
+        texture::Texture::new(device, texture)
+    }
+
+    // The RGBA of the pixel at the column and row of texels read back from a `SIZE` wide target
+    pub(crate) fn texel(texels: &[u8], [column, row]: [u32; 2]) -> [u8; 4] {
+        let offset = ((row * SIZE + column) * 4) as usize;
+
+        texels[offset..offset + 4].try_into().unwrap()
+    }
+
+    // The RGBA of the pixel at the clip space position
+    fn pixel(texels: &[u8], [x, y]: [f32; 2]) -> [u8; 4] {
+        let column = ((x + 1.) / 2. * SIZE as f32) as usize;
+        let row = ((1. - y) / 2. * SIZE as f32) as usize;
+        let offset = (row * SIZE as usize + column) * 4;
+
+        texels[offset..offset + 4].try_into().unwrap()
+    }
+
+    // The opaque triangle through the main pipeline, offscreen
+    fn render_triangle(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        transform: [[f32; 4]; 4],
+        reversed_z: bool,
+    ) -> Vec<u8> {
+        let depth_config = if reversed_z {
+            depth::DepthConfig::reversed_z()
+        } else {
+            depth::DepthConfig::default()
+        };
+        let target = render_target::RenderTarget::new(
+            device,
+            SIZE,
+            SIZE,
+            wgpu::TextureFormat::Rgba8Unorm,
+            depth_config.format(),
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My test shader"),
+            source: wgpu::ShaderSource::Wgsl(DEFAULT_SHADER.into()),
+        });
+        let reflection = shader_reflection::ShaderReflection::from_wgsl(DEFAULT_SHADER).unwrap();
+        let camera_layout = reflection.create_bind_group_layout(device, 0);
+        let transform_layout = reflection.create_bind_group_layout(device, 1);
+
+        let camera_buffer = uniform_buffer::UniformBuffer::new(
+            device,
+            "My test camera buffer",
+            CameraUniform::for_depth(reversed_z).0,
+        );
+        let transform_buffer =
+            uniform_buffer::UniformBuffer::new(device, "My test transform buffer", transform);
+        let camera_bind_group =
+            bind_group_builder::BindGroupBuilder::from_reflection(&reflection, device)
+                .bind_resource(0, &camera_buffer)
+                .build(&camera_layout)
+                .unwrap();
+        let transform_bind_group =
+            bind_group_builder::BindGroupBuilder::from_reflection(&reflection, device)
+                .for_group(1)
+                .bind_resource(0, &transform_buffer)
+                .build(&transform_layout)
+                .unwrap();
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My test pipeline layout"),
+            bind_group_layouts: &[&camera_layout, &transform_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_render_pipeline(
+            device,
+            "My test pipeline",
+            &layout,
+            &shader,
+            "fs_color",
+            &Vertex::layout(),
+            TRIANGLE_LIST,
+            target.format(),
+            blend::BlendMode::Replace,
+            depth_config,
+            1,
+        );
+        let triangle = vertex_buffer::VertexBuffer::new(device, "My test vertex buffer", VERTICES);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My test encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("My test pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target.view(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: target.depth_view(),
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(depth_config.clear_depth()),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&pipeline);
+            render_pass.set_bind_group(0, &camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &transform_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, triangle.slice());
+            render_pass.draw(0..VERTICES.len() as u32, 0..1);
+        }
+        queue.submit([encoder.finish()]);
+
+        pollster::block_on(texture::readback(device, queue, target.texture(), 0, 0))
+    }
+
+    #[test]
+    fn transform_rotates_the_triangle() {
+        let Some((device, queue)) = device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        // Just below the red tip, and where a quarter turn counterclockwise takes it
+        let (tip, turned_tip) = ([0., 0.4], [-0.4, 0.]);
+
+        let texels = render_triangle(&device, &queue, IDENTITY_MATRIX, false);
+        assert!(pixel(&texels, tip)[0] > 200);
+        assert_eq!(pixel(&texels, turned_tip)[..3], [0, 0, 0]);
+
+        let quarter_turn: [[f32; 4]; 4] = cgmath::Matrix4::from_angle_z(cgmath::Deg(90f32)).into();
+        let texels = render_triangle(&device, &queue, quarter_turn, false);
+        assert_eq!(pixel(&texels, tip)[..3], [0, 0, 0]);
+        assert!(pixel(&texels, turned_tip)[0] > 200);
+    }
+
+    #[test]
+    fn reversed_z_keeps_the_clip_space_geometry() {
+        let Some((device, queue)) = device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        // At Z = 0, the triangle would fail `Greater` against the 0 clear value without the flip
+        let texels = render_triangle(&device, &queue, IDENTITY_MATRIX, true);
+        assert!(pixel(&texels, [0., 0.4])[0] > 200);
+    }
 }
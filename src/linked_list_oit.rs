@@ -0,0 +1,426 @@
+use wgpu::util::DeviceExt;
+
+pub const RESOLVED_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// Must match `MAX_FRAGMENTS` in linked_list_oit.wgsl
+const MAX_FRAGMENTS: u32 = 16;
+// vec4<f32> color, f32 depth, u32 next, padded to 16 bytes
+const NODE_SIZE: u64 = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct OitParams {
+    width: u32,
+    height: u32,
+    node_capacity: u32,
+    max_fragments_per_pixel: u32,
+}
+
+/// Exact order-independent transparency with per-pixel linked lists.
+/// Transparent fragments are pushed into the lists, then a compute pass sorts and blends every pixel.
+///
+/// WGSL has no atomic texture operations, so the head pointers live in a storage buffer
+/// with one `u32` per pixel rather than in an `R32Uint` texture.
+pub struct LinkedListOit {
+    max_fragments_per_pixel: u32,
+    width: u32,
+    height: u32,
+    heads_buffer: wgpu::Buffer,
+    counter_buffer: wgpu::Buffer,
+    gather_bind_group_layout: wgpu::BindGroupLayout,
+    gather_bind_group: wgpu::BindGroup,
+    resolve_bind_group_layout: wgpu::BindGroupLayout,
+    resolve_bind_group: wgpu::BindGroup,
+    resolve_pipeline: wgpu::ComputePipeline,
+    composite_bind_group_layout: wgpu::BindGroupLayout,
+    composite_bind_group: wgpu::BindGroup,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl LinkedListOit {
+    /// `max_fragments_per_pixel` is clamped to 16.
+    /// The node pool is clamped to the device's storage buffer binding size
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        max_fragments_per_pixel: u32,
+        target_format: wgpu::TextureFormat,
+    ) -> LinkedListOit {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My linked list OIT shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("linked_list_oit.wgsl").into()),
+        });
+
+        let buffer_entry = |binding: u32, visibility, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage = |read_only| wgpu::BufferBindingType::Storage { read_only };
+
+        let gather_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("My linked list OIT gather bind group layout"),
+                entries: &[
+                    buffer_entry(
+                        0,
+                        wgpu::ShaderStages::FRAGMENT,
+                        wgpu::BufferBindingType::Uniform,
+                    ),
+                    buffer_entry(1, wgpu::ShaderStages::FRAGMENT, storage(false)),
+                    buffer_entry(2, wgpu::ShaderStages::FRAGMENT, storage(false)),
+                    buffer_entry(3, wgpu::ShaderStages::FRAGMENT, storage(false)),
+                ],
+            });
+
+        let resolve_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("My linked list OIT resolve bind group layout"),
+                entries: &[
+                    buffer_entry(
+                        0,
+                        wgpu::ShaderStages::COMPUTE,
+                        wgpu::BufferBindingType::Uniform,
+                    ),
+                    buffer_entry(1, wgpu::ShaderStages::COMPUTE, storage(true)),
+                    buffer_entry(2, wgpu::ShaderStages::COMPUTE, storage(true)),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: RESOLVED_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let composite_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("My linked list OIT composite bind group layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+
+        let resolve_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("My linked list OIT resolve pipeline layout"),
+                bind_group_layouts: &[&resolve_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let resolve_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("My linked list OIT resolve pipeline"),
+            layout: Some(&resolve_pipeline_layout),
+            module: &shader,
+            entry_point: "cs_resolve",
+        });
+
+        let composite_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("My linked list OIT composite pipeline layout"),
+                bind_group_layouts: &[&composite_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let composite_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My linked list OIT composite pipeline"),
+            layout: Some(&composite_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_composite",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_composite",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let counter_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My linked list OIT counter buffer"),
+            size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let max_fragments_per_pixel = max_fragments_per_pixel.clamp(1, MAX_FRAGMENTS);
+        let resources = SizedResources::new(
+            device,
+            width,
+            height,
+            max_fragments_per_pixel,
+            &counter_buffer,
+            &gather_bind_group_layout,
+            &resolve_bind_group_layout,
+            &composite_bind_group_layout,
+        );
+
+        LinkedListOit {
+            max_fragments_per_pixel,
+            width: resources.width,
+            height: resources.height,
+            heads_buffer: resources.heads_buffer,
+            counter_buffer,
+            gather_bind_group_layout,
+            gather_bind_group: resources.gather_bind_group,
+            resolve_bind_group_layout,
+            resolve_bind_group: resources.resolve_bind_group,
+            resolve_pipeline,
+            composite_bind_group_layout,
+            composite_bind_group: resources.composite_bind_group,
+            composite_pipeline,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let resources = SizedResources::new(
+            device,
+            width,
+            height,
+            self.max_fragments_per_pixel,
+            &self.counter_buffer,
+            &self.gather_bind_group_layout,
+            &self.resolve_bind_group_layout,
+            &self.composite_bind_group_layout,
+        );
+
+        self.width = resources.width;
+        self.height = resources.height;
+        self.heads_buffer = resources.heads_buffer;
+        self.gather_bind_group = resources.gather_bind_group;
+        self.resolve_bind_group = resources.resolve_bind_group;
+        self.composite_bind_group = resources.composite_bind_group;
+    }
+
+    /// Layout of the bind group transparent pipelines push their fragments with
+    pub fn gather_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.gather_bind_group_layout
+    }
+
+    /// Empties the lists and starts the pass for transparent geometry.
+    /// The pass has no color targets, the opaque depth is only tested
+    pub fn begin_gather<'p>(
+        &'p self,
+        encoder: &'p mut wgpu::CommandEncoder,
+        depth_view: &'p wgpu::TextureView,
+        bind_group_index: u32,
+    ) -> wgpu::RenderPass<'p> {
+        crate::State::clear_buffer(encoder, &self.heads_buffer, None);
+        crate::State::clear_buffer(encoder, &self.counter_buffer, None);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My linked list OIT gather pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_bind_group(bind_group_index, &self.gather_bind_group, &[]);
+
+        render_pass
+    }
+
+    /// Sorts and blends the lists, then composites the result over `target_view`
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder, target_view: &wgpu::TextureView) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("My linked list OIT resolve pass"),
+            timestamp_writes: None,
+        });
+
+        compute_pass.set_pipeline(&self.resolve_pipeline);
+        compute_pass.set_bind_group(0, &self.resolve_bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.width.div_ceil(8), self.height.div_ceil(8), 1);
+
+        drop(compute_pass);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My linked list OIT composite pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, &self.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+// Everything that depends on the size of the screen
+struct SizedResources {
+    width: u32,
+    height: u32,
+    heads_buffer: wgpu::Buffer,
+    gather_bind_group: wgpu::BindGroup,
+    resolve_bind_group: wgpu::BindGroup,
+    composite_bind_group: wgpu::BindGroup,
+}
+
+impl SizedResources {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        max_fragments_per_pixel: u32,
+        counter_buffer: &wgpu::Buffer,
+        gather_bind_group_layout: &wgpu::BindGroupLayout,
+        resolve_bind_group_layout: &wgpu::BindGroupLayout,
+        composite_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> SizedResources {
+        let width = width.max(1);
+        let height = height.max(1);
+
+        let max_nodes = device.limits().max_storage_buffer_binding_size as u64 / NODE_SIZE;
+        let node_capacity =
+            (width as u64 * height as u64 * max_fragments_per_pixel as u64).min(max_nodes);
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My linked list OIT params buffer"),
+            contents: bytemuck::cast_slice(&[OitParams {
+                width,
+                height,
+                node_capacity: node_capacity as u32,
+                max_fragments_per_pixel,
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let heads_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My linked list OIT heads buffer"),
+            size: (width * height) as u64 * std::mem::size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let nodes_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My linked list OIT nodes buffer"),
+            size: node_capacity * NODE_SIZE,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let resolved_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("My linked list OIT resolved texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: RESOLVED_FORMAT,
+                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let gather_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My linked list OIT gather bind group"),
+            layout: gather_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: heads_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: nodes_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: counter_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let resolve_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My linked list OIT resolve bind group"),
+            layout: resolve_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: heads_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: nodes_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&resolved_view),
+                },
+            ],
+        });
+
+        let composite_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My linked list OIT composite bind group"),
+            layout: composite_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&resolved_view),
+            }],
+        });
+
+        SizedResources {
+            width,
+            height,
+            heads_buffer,
+            gather_bind_group,
+            resolve_bind_group,
+            composite_bind_group,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// Resolve of the per-pixel linked lists built by `fs_transparent_linked_list`
+
+// Upper bound of `max_fragments_per_pixel`, the size of the sort arrays
+const MAX_FRAGMENTS: u32 = 16u;
+
+struct OitParams {
+    width: u32,
+    height: u32,
+    node_capacity: u32,
+    max_fragments_per_pixel: u32,
+}
+
+struct OitNode {
+    color: vec4<f32>,
+    // From the near plane, growing with the distance even with reversed Z
+    depth: f32,
+    next: u32, // 0 terminates the list, otherwise it's the node index + 1
+}
+
+@group(0) @binding(0) var<uniform> params: OitParams;
+@group(0) @binding(1) var<storage, read> heads: array<u32>;
+@group(0) @binding(2) var<storage, read> nodes: array<OitNode>;
+@group(0) @binding(3) var resolved: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(8, 8) fn cs_resolve(
+    @builtin(global_invocation_id) id: vec3<u32>
+) {
+    if id.x >= params.width || id.y >= params.height {
+        return;
+    }
+
+    var colors: array<vec4<f32>, MAX_FRAGMENTS>;
+    var depths: array<f32, MAX_FRAGMENTS>;
+    var count = 0u;
+
+    var node = heads[id.y * params.width + id.x];
+
+    while node != 0u && count < params.max_fragments_per_pixel {
+        let current = nodes[node - 1u];
+
+        colors[count] = current.color;
+        depths[count] = current.depth;
+        count++;
+
+        node = current.next;
+    }
+
+    // Insertion sort, the farthest fragment, with the largest depth, goes first
+    for (var i = 1u; i < count; i++) {
+        let color = colors[i];
+        let depth = depths[i];
+        var j = i;
+
+        while j > 0u && depths[j - 1u] < depth {
+            colors[j] = colors[j - 1u];
+            depths[j] = depths[j - 1u];
+            j--;
+        }
+
+        colors[j] = color;
+        depths[j] = depth;
+    }
+
+    // Back to front "over" blending. The result is premultiplied by alpha
+    var result = vec4<f32>(0.);
+
+    for (var i = 0u; i < count; i++) {
+        let color = colors[i];
+
+        result = vec4<f32>(
+            color.rgb * color.a + result.rgb * (1. - color.a),
+            color.a + result.a * (1. - color.a),
+        );
+    }
+
+    textureStore(resolved, vec2<i32>(id.xy), result);
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// A single triangle covering the whole screen
+@vertex fn vs_composite(
+    @builtin(vertex_index) vertex_index: u32
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2. - 1., 0., 1.);
+
+    return out;
+}
+
+@group(0) @binding(4) var resolved_texture: texture_2d<f32>;
+
+@fragment fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(resolved_texture, vec2<i32>(in.clip_position.xy), 0);
+}
@@ -1,3 +1,89 @@
+use winit::{
+    event::{KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
 fn main() -> Result<(), String> {
-    pollster::block_on(wgpuing::run())
+    // `--render-thread` renders on a dedicated thread instead of the event loop one
+    let threading = if std::env::args().any(|arg| arg == "--render-thread") {
+        wgpuing::Threading::RenderThread
+    } else {
+        wgpuing::Threading::SingleThreaded
+    };
+
+    // `--allow-cpu` lets a software adapter be used when there is no GPU
+    let adapter = wgpuing::adapter::AdapterPreference {
+        allow_cpu: std::env::args().any(|arg| arg == "--allow-cpu"),
+        ..Default::default()
+    };
+
+    // `--retro` renders at 320x240, scaled up to the window with crisp pixels
+    let internal_resolution = std::env::args()
+        .any(|arg| arg == "--retro")
+        .then_some((320, 240));
+
+    // `--errors=panic` fails fast, `--errors=log` only logs the errors rendering can go on after
+    let error_policy =
+        std::env::args().find_map(|arg| arg.strip_prefix("--errors=").map(str::to_owned));
+    let error_policy = match error_policy.as_deref() {
+        Some("panic") => wgpuing::error_policy::ErrorPolicy::Panic,
+        Some("log") => {
+            wgpuing::error_policy::ErrorPolicy::Callback(|error| log::error!("{}", error))
+        }
+        _ => wgpuing::error_policy::ErrorPolicy::ReturnResult,
+    };
+
+    // `--transparent` lets the desktop show through the background
+    let transparent_window = std::env::args().any(|arg| arg == "--transparent");
+
+    // `--no-escape` keeps the window open on Escape, the filter taking the key before the built-in handling
+    let event_filter: fn(&WindowEvent) -> wgpuing::EventDisposition =
+        if std::env::args().any(|arg| arg == "--no-escape") {
+            |event| match event {
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key: PhysicalKey::Code(KeyCode::Escape),
+                            ..
+                        },
+                    ..
+                } => wgpuing::EventDisposition::Consume,
+                _ => wgpuing::EventDisposition::Passthrough,
+            }
+        } else {
+            |_| wgpuing::EventDisposition::Passthrough
+        };
+
+    // `--scene=scene.json` draws the scene described in the file, reloading it on every save
+    let scene_path =
+        std::env::args().find_map(|arg| arg.strip_prefix("--scene=").map(std::path::PathBuf::from));
+
+    // `--reversed-z` keeps the depth precise far away from the camera
+    let reversed_z = std::env::args().any(|arg| arg == "--reversed-z");
+
+    // `--correct-aspect` keeps the shapes from stretching with the window
+    let correct_aspect_ratio = std::env::args().any(|arg| arg == "--correct-aspect");
+
+    // `--msaa=8` smooths the edges with 8 samples per pixel instead of 4, `--msaa=1` turns it off
+    let defaults = wgpuing::StateConfig::default();
+    let sample_count = std::env::args()
+        .find_map(|arg| {
+            arg.strip_prefix("--msaa=")
+                .and_then(|count| count.parse().ok())
+        })
+        .unwrap_or(defaults.sample_count);
+
+    pollster::block_on(wgpuing::run_with_config(wgpuing::StateConfig {
+        threading,
+        adapter,
+        internal_resolution,
+        error_policy,
+        transparent_window,
+        event_filter,
+        scene_path,
+        reversed_z,
+        correct_aspect_ratio,
+        sample_count,
+        ..defaults
+    }))
 }
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use crate::resources::{MaterialId, MeshId};
+
+/// The bind group slot materials are bound to
+pub const MATERIAL_GROUP: u32 = 0;
+
+/// What a mesh is drawn with: its textures and uniforms, in a bind group for `MATERIAL_GROUP`
+pub struct Material {
+    pub bind_group: wgpu::BindGroup,
+}
+
+/// The uniform of the built-in material shader, tinting the vertex colors
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialParams {
+    pub color: [f32; 4],
+}
+
+/// Which material each mesh is drawn with
+#[derive(Default)]
+pub struct MaterialTable {
+    assignments: HashMap<MeshId, MaterialId>,
+}
+
+impl MaterialTable {
+    /// Replaces the previous material of the mesh, if any
+    pub fn assign(&mut self, mesh: MeshId, material: MaterialId) {
+        self.assignments.insert(mesh, material);
+    }
+
+    /// The mesh isn't drawn anymore
+    pub fn unassign(&mut self, mesh: MeshId) {
+        self.assignments.remove(&mesh);
+    }
+
+    pub fn material(&self, mesh: MeshId) -> Option<MaterialId> {
+        self.assignments.get(&mesh).copied()
+    }
+
+    /// The meshes grouped by material, so each material is bound once per frame
+    pub fn batches(&self) -> Vec<(MaterialId, Vec<MeshId>)> {
+        let mut assignments: Vec<(MaterialId, MeshId)> = self
+            .assignments
+            .iter()
+            .map(|(&mesh, &material)| (material, mesh))
+            .collect();
+        assignments.sort_unstable();
+
+        let mut batches: Vec<(MaterialId, Vec<MeshId>)> = Vec::new();
+        for (material, mesh) in assignments {
+            match batches.last_mut() {
+                Some((last, meshes)) if *last == material => meshes.push(mesh),
+                _ => batches.push((material, vec![mesh])),
+            }
+        }
+
+        batches
+    }
+}
@@ -0,0 +1,40 @@
+// The vertex colors tinted by the material of the mesh
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+}
+
+struct MaterialParams {
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> material: MaterialParams;
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    near_depth: f32,
+}
+
+// The one of the opaque pipelines, flipping the depth with reversed Z
+@group(1) @binding(0) var<uniform> camera: CameraUniform;
+
+@vertex fn vs_main(
+    model: VertexInput
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.color = model.color;
+    out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.);
+
+    return out;
+}
+
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.) * material.color;
+}
@@ -0,0 +1,246 @@
+use wgpu::util::DeviceExt;
+
+use crate::obj;
+
+const POSITION_ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![0 => Float32x3];
+const NORMAL_ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![1 => Float32x3];
+const UV_ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![2 => Float32x2];
+
+/// The vertex attributes of a mesh, each in its own buffer instead of interleaved:
+/// positions in slot 0, normals in slot 1 and UVs in slot 2, at the same shader locations.
+/// Passes needing only the positions, like the shadow ones, bind just the first buffer
+pub struct MeshStreams {
+    pub positions: wgpu::Buffer,
+    pub normals: wgpu::Buffer,
+    pub uvs: wgpu::Buffer,
+    vertices_count: u32,
+}
+
+impl MeshStreams {
+    pub const POSITION_SLOT: u32 = 0;
+    pub const NORMAL_SLOT: u32 = 1;
+    pub const UV_SLOT: u32 = 2;
+
+    /// The streams have one element per vertex each
+    pub fn new(
+        device: &wgpu::Device,
+        positions: &[[f32; 3]],
+        normals: &[[f32; 3]],
+        uvs: &[[f32; 2]],
+    ) -> MeshStreams {
+        assert!(
+            normals.len() == positions.len() && uvs.len() == positions.len(),
+            "the streams have different vertex counts"
+        );
+
+        let create_buffer = |label: &str, contents: &[u8]| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            })
+        };
+
+        MeshStreams {
+            positions: create_buffer("My mesh positions buffer", bytemuck::cast_slice(positions)),
+            normals: create_buffer("My mesh normals buffer", bytemuck::cast_slice(normals)),
+            uvs: create_buffer("My mesh UVs buffer", bytemuck::cast_slice(uvs)),
+            vertices_count: positions.len() as u32,
+        }
+    }
+
+    /// The missing normals point up the Z axis and the missing UVs are zero
+    pub fn from_obj(device: &wgpu::Device, mesh: &obj::ObjMesh) -> MeshStreams {
+        let normals = if mesh.normals.is_empty() {
+            vec![[0., 0., 1.]; mesh.positions.len()]
+        } else {
+            mesh.normals.clone()
+        };
+        let uvs = if mesh.tex_coords.is_empty() {
+            vec![[0.; 2]; mesh.positions.len()]
+        } else {
+            mesh.tex_coords.clone()
+        };
+
+        MeshStreams::new(device, &mesh.positions, &normals, &uvs)
+    }
+
+    /// For pipelines reading all the streams, in slot order
+    pub fn layouts() -> [wgpu::VertexBufferLayout<'static>; 3] {
+        [
+            Self::position_layout(),
+            wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &NORMAL_ATTRIBUTES,
+            },
+            wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &UV_ATTRIBUTES,
+            },
+        ]
+    }
+
+    /// For pipelines reading only the positions
+    pub fn position_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &POSITION_ATTRIBUTES,
+        }
+    }
+
+    pub fn vertices_count(&self) -> u32 {
+        self.vertices_count
+    }
+
+    pub fn set_vertex_buffers<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>) {
+        render_pass.set_vertex_buffer(Self::POSITION_SLOT, self.positions.slice(..));
+        render_pass.set_vertex_buffer(Self::NORMAL_SLOT, self.normals.slice(..));
+        render_pass.set_vertex_buffer(Self::UV_SLOT, self.uvs.slice(..));
+    }
+
+    pub fn set_position_buffer<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>) {
+        render_pass.set_vertex_buffer(Self::POSITION_SLOT, self.positions.slice(..));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{texel, SIZE};
+
+    // The normal and the UV of the vertices as a color, to check each stream reaches its location
+    const SHADER: &str = "
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(position, 1.0);
+    out.color = vec4<f32>(normal.z, normal.y, uv.y, uv.x);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
+";
+
+    // Covers the whole target
+    const POSITIONS: [[f32; 3]; 3] = [[-1., -1., 0.], [3., -1., 0.], [-1., 3., 0.]];
+
+    fn render(device: &wgpu::Device, queue: &wgpu::Queue, streams: &MeshStreams) -> [u8; 4] {
+        let target = crate::render_target::RenderTarget::new(
+            device,
+            SIZE,
+            SIZE,
+            wgpu::TextureFormat::Rgba8Unorm,
+            crate::depth::DEPTH_FORMAT,
+        );
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My test shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My test pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My test pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &MeshStreams::layouts(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(target.format().into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My test encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("My test pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target.view(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&pipeline);
+            streams.set_vertex_buffers(&mut render_pass);
+            render_pass.draw(0..streams.vertices_count(), 0..1);
+        }
+        queue.submit([encoder.finish()]);
+        let texels = pollster::block_on(crate::texture::readback(
+            device,
+            queue,
+            target.texture(),
+            0,
+            0,
+        ));
+
+        texel(&texels, [SIZE / 2, SIZE / 2])
+    }
+
+    #[test]
+    fn each_stream_reaches_its_location() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        let streams = MeshStreams::new(&device, &POSITIONS, &[[0., 1., 0.]; 3], &[[0., 1.]; 3]);
+
+        assert_eq!(streams.vertices_count(), 3);
+        assert_eq!(render(&device, &queue, &streams), [0, 255, 255, 0]);
+    }
+
+    #[test]
+    fn from_obj_fills_the_missing_streams() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        let mesh = obj::ObjMesh {
+            positions: POSITIONS.to_vec(),
+            normals: Vec::new(),
+            tex_coords: Vec::new(),
+            indices: vec![0, 1, 2],
+        };
+        let streams = MeshStreams::from_obj(&device, &mesh);
+
+        assert_eq!(streams.vertices_count(), 3);
+        assert_eq!(streams.normals.size(), 3 * 12);
+        assert_eq!(streams.uvs.size(), 3 * 8);
+        // Normals up the Z axis and zero UVs
+        assert_eq!(render(&device, &queue, &streams), [255, 0, 0, 0]);
+    }
+}
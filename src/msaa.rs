@@ -0,0 +1,164 @@
+/// The largest sample count `validate_sample_count` accepts
+pub const MAX_SAMPLE_COUNT: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleCountError {
+    /// Not a power of two up to `MAX_SAMPLE_COUNT`
+    Invalid(u32),
+    /// Valid, but not for every format rendered with it on this adapter
+    Unsupported(u32),
+}
+
+impl std::fmt::Display for SampleCountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SampleCountError::Invalid(count) => write!(
+                f,
+                "sample count {} isn't a power of two up to {}",
+                count, MAX_SAMPLE_COUNT
+            ),
+            SampleCountError::Unsupported(count) => {
+                write!(f, "sample count {} isn't supported by the adapter", count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SampleCountError {}
+
+/// 1, 2, 4 or 8. 1 turns MSAA off
+pub fn validate_sample_count(count: u32) -> Result<u32, SampleCountError> {
+    if count.is_power_of_two() && count <= MAX_SAMPLE_COUNT {
+        Ok(count)
+    } else {
+        Err(SampleCountError::Invalid(count))
+    }
+}
+
+/// The sample counts all of a set of formats can be rendered with on an adapter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SampleCountSupport {
+    flags: wgpu::TextureFormatFeatureFlags,
+}
+
+impl SampleCountSupport {
+    /// `color_formats` must also be resolvable. Without the device having
+    /// `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`, only the counts WebGPU guarantees are usable,
+    /// whatever the adapter supports
+    pub fn new(
+        adapter: &wgpu::Adapter,
+        device_features: wgpu::Features,
+        color_formats: &[wgpu::TextureFormat],
+        depth_format: wgpu::TextureFormat,
+    ) -> SampleCountSupport {
+        let format_flags = |format: wgpu::TextureFormat| {
+            if device_features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+                adapter.get_texture_format_features(format).flags
+            } else {
+                format.guaranteed_format_features(device_features).flags
+            }
+        };
+
+        let mut flags = format_flags(depth_format);
+        for &format in color_formats {
+            let color_flags = format_flags(format);
+            if !color_flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE) {
+                flags = wgpu::TextureFormatFeatureFlags::empty();
+            }
+            flags &= color_flags;
+        }
+
+        SampleCountSupport { flags }
+    }
+
+    /// 1 always is
+    pub fn supports(&self, count: u32) -> bool {
+        self.flags.sample_count_supported(count)
+    }
+
+    /// `count` if it's supported, otherwise the largest supported one below it
+    pub fn clamp(&self, count: u32) -> u32 {
+        (0..=count.clamp(1, MAX_SAMPLE_COUNT).ilog2())
+            .rev()
+            .map(|exponent| 1 << exponent)
+            .find(|&count| self.supports(count))
+            .unwrap_or(1)
+    }
+
+    /// Validated and supported
+    pub fn check(&self, count: u32) -> Result<u32, SampleCountError> {
+        let count = validate_sample_count(count)?;
+
+        if self.supports(count) {
+            Ok(count)
+        } else {
+            Err(SampleCountError::Unsupported(count))
+        }
+    }
+}
+
+/// The multisampled color texture a pass renders into, resolved into the single sampled target.
+/// Only its resolved image is kept, it must be recreated along with the depth texture
+pub struct MsaaTarget {
+    view: wgpu::TextureView,
+    sample_count: u32,
+}
+
+impl MsaaTarget {
+    /// None for a `sample_count` of 1, the target being rendered into directly
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        format: wgpu::TextureFormat,
+    ) -> Option<MsaaTarget> {
+        if sample_count <= 1 {
+            return None;
+        }
+
+        let view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("My multisampled color texture"),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        Some(MsaaTarget { view, sample_count })
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// A color attachment rendering into the multisampled texture, resolved into `resolve_target`
+    pub fn color_attachment<'a>(
+        &'a self,
+        resolve_target: &'a wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'a> {
+        wgpu::RenderPassColorAttachment {
+            view: &self.view,
+            resolve_target: Some(resolve_target),
+            ops: wgpu::Operations {
+                load,
+                // Only the resolved image is read afterwards
+                store: wgpu::StoreOp::Discard,
+            },
+        }
+    }
+}
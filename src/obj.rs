@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Indexed triangle mesh in the shape Wavefront OBJ can describe.
+/// `normals` and `tex_coords` are either empty or have one element per position
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl std::fmt::Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjError::Io(error) => write!(f, "{}", error),
+            ObjError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<std::io::Error> for ObjError {
+    fn from(error: std::io::Error) -> ObjError {
+        ObjError::Io(error)
+    }
+}
+
+/// Writes the mesh as positions, texture coordinates, normals and triangular faces.
+/// The mesh uses one index for all the attributes, so every face vertex is `i/i/i`
+pub fn export_obj(path: impl AsRef<Path>, mesh: &ObjMesh) -> Result<(), ObjError> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+    writeln!(file, "# Exported by wgpuing")?;
+
+    for [x, y, z] in &mesh.positions {
+        writeln!(file, "v {} {} {}", x, y, z)?;
+    }
+    for [u, v] in &mesh.tex_coords {
+        writeln!(file, "vt {} {}", u, v)?;
+    }
+    for [x, y, z] in &mesh.normals {
+        writeln!(file, "vn {} {} {}", x, y, z)?;
+    }
+
+    let has_tex_coords = !mesh.tex_coords.is_empty();
+    let has_normals = !mesh.normals.is_empty();
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        write!(file, "f")?;
+
+        for index in triangle {
+            // OBJ indices start at 1
+            let index = index + 1;
+
+            match (has_tex_coords, has_normals) {
+                (true, true) => write!(file, " {}/{}/{}", index, index, index)?,
+                (true, false) => write!(file, " {}/{}", index, index)?,
+                (false, true) => write!(file, " {}//{}", index, index)?,
+                (false, false) => write!(file, " {}", index)?,
+            }
+        }
+
+        writeln!(file)?;
+    }
+
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Reads positions, texture coordinates, normals and faces. Polygons are triangulated as fans.
+/// OBJ indexes every attribute separately, so the unique combinations become the vertices
+pub fn load_obj(path: impl AsRef<Path>) -> Result<ObjMesh, ObjError> {
+    load_obj_with_progress(path, |_| {})
+}
+
+/// `load_obj` reporting the share of the file parsed so far, from 0 to 1.
+/// `progress` is called on the loading thread, about every percent
+pub fn load_obj_with_progress(
+    path: impl AsRef<Path>,
+    progress: impl Fn(f32),
+) -> Result<ObjMesh, ObjError> {
+    let source = std::fs::read_to_string(path)?;
+    let total_bytes = source.len().max(1) as f32;
+    let mut parsed_bytes = 0;
+    let mut reported = 0.;
+
+    progress(0.);
+
+    let mut positions = Vec::new();
+    let mut tex_coords = Vec::new();
+    let mut normals = Vec::new();
+
+    let mut mesh = ObjMesh::default();
+    let mut vertices = HashMap::<(usize, Option<usize>, Option<usize>), u32>::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+
+        parsed_bytes += line.len() + 1;
+        let parsed = (parsed_bytes as f32 / total_bytes).min(1.);
+        if parsed - reported >= 0.01 {
+            progress(parsed);
+            reported = parsed;
+        }
+        let parse_error = |message: String| ObjError::Parse {
+            line: line_number,
+            message,
+        };
+
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => positions.push(parse_floats::<3>(tokens).map_err(parse_error)?),
+            Some("vt") => tex_coords.push(parse_floats::<2>(tokens).map_err(parse_error)?),
+            Some("vn") => normals.push(parse_floats::<3>(tokens).map_err(parse_error)?),
+            Some("f") => {
+                let mut face = Vec::new();
+
+                for token in tokens {
+                    let mut parts = token.split('/');
+
+                    let position = resolve_index(parts.next(), positions.len())
+                        .map_err(parse_error)?
+                        .ok_or_else(|| parse_error(format!("missing position in {}", token)))?;
+                    let tex_coord =
+                        resolve_index(parts.next(), tex_coords.len()).map_err(parse_error)?;
+                    let normal = resolve_index(parts.next(), normals.len()).map_err(parse_error)?;
+
+                    let key = (position, tex_coord, normal);
+                    let index = *vertices.entry(key).or_insert_with(|| {
+                        mesh.positions.push(positions[position]);
+                        if let Some(tex_coord) = tex_coord {
+                            mesh.tex_coords.push(tex_coords[tex_coord]);
+                        }
+                        if let Some(normal) = normal {
+                            mesh.normals.push(normals[normal]);
+                        }
+
+                        mesh.positions.len() as u32 - 1
+                    });
+
+                    face.push(index);
+                }
+
+                if face.len() < 3 {
+                    return Err(parse_error("a face needs at least 3 vertices".into()));
+                }
+
+                for i in 1..face.len() - 1 {
+                    mesh.indices
+                        .extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                }
+            }
+            // Groups, materials, comments etc. aren't needed
+            _ => {}
+        }
+    }
+
+    // Attributes present only on some of the faces can't be used
+    if mesh.tex_coords.len() != mesh.positions.len() {
+        mesh.tex_coords.clear();
+    }
+    if mesh.normals.len() != mesh.positions.len() {
+        mesh.normals.clear();
+    }
+
+    if reported < 1. {
+        progress(1.);
+    }
+
+    Ok(mesh)
+}
+
+fn parse_floats<'a, const N: usize>(
+    mut tokens: impl Iterator<Item = &'a str>,
+) -> Result<[f32; N], String> {
+    let mut values = [0.; N];
+
+    for value in values.iter_mut() {
+        let token = tokens.next().ok_or("not enough values")?;
+        *value = token
+            .parse()
+            .map_err(|_| format!("{} is not a number", token))?;
+    }
+
+    Ok(values)
+}
+
+// 1-based, negative indices are relative to the end. Returns a 0-based index
+fn resolve_index(token: Option<&str>, count: usize) -> Result<Option<usize>, String> {
+    let token = match token {
+        Some(token) if !token.is_empty() => token,
+        _ => return Ok(None),
+    };
+
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("{} is not an index", token))?;
+
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} is out of bounds", index));
+    }
+
+    Ok(Some(resolved as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_then_load_round_trips() {
+        // A quad sharing its corners, the vertices first used in order, as `load_obj` numbers them
+        let mesh = ObjMesh {
+            positions: vec![
+                [-0.5, -0.5, 0.],
+                [0.5, -0.5, 0.],
+                [0.5, 0.5, 0.25],
+                [-0.5, 0.5, -1.5],
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..Default::default()
+        };
+
+        let dir = std::env::temp_dir().join(format!("wgpuing-obj-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("quad.obj");
+
+        export_obj(&path, &mesh).unwrap();
+        let loaded = load_obj(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.positions, mesh.positions);
+        assert_eq!(loaded.indices, mesh.indices);
+    }
+}
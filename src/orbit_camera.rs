@@ -0,0 +1,104 @@
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+
+// Radians the camera turns per pixel dragged
+const ORBIT_SENSITIVITY: f32 = 0.01;
+// How far `center` moves per pixel dragged, in multiples of the distance
+const PAN_SENSITIVITY: f32 = 0.002;
+// The distance is multiplied by it per line scrolled
+const ZOOM_FACTOR: f32 = 0.9;
+// Zooming in never goes through `center`
+const MIN_DISTANCE: f32 = 0.01;
+
+/// Circles around `center`, always looking at it, for inspecting a model from every side
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitCamera {
+    pub center: [f32; 3],
+    pub distance: f32,
+    /// Radians around Y, 0 looking down -Z
+    pub yaw: f32,
+    /// Radians above the XZ plane
+    pub pitch: f32,
+    /// Within (-PI / 2, PI / 2), as looking straight up or down leaves no way to tell which way is up
+    pub pitch_limits: (f32, f32),
+}
+
+impl OrbitCamera {
+    pub fn new(center: [f32; 3], distance: f32) -> OrbitCamera {
+        let limit = std::f32::consts::FRAC_PI_2 - 0.01;
+
+        OrbitCamera {
+            center,
+            distance: distance.max(MIN_DISTANCE),
+            yaw: 0.,
+            pitch: 0.,
+            pitch_limits: (-limit, limit),
+        }
+    }
+
+    /// Placed at `eye`, looking at `center`
+    pub fn looking_at(eye: [f32; 3], center: [f32; 3]) -> OrbitCamera {
+        let offset = Point3::from(eye) - Point3::from(center);
+        let distance = offset.magnitude();
+
+        let mut camera = OrbitCamera::new(center, distance);
+        if distance > 0. {
+            camera.yaw = offset.x.atan2(offset.z);
+            camera.pitch = (offset.y / distance).asin();
+            camera.clamp_pitch();
+        }
+
+        camera
+    }
+
+    pub fn eye(&self) -> [f32; 3] {
+        (Point3::from(self.center) + self.offset()).into()
+    }
+
+    /// World to view space, column major
+    pub fn build_view_matrix(&self) -> [[f32; 4]; 4] {
+        Matrix4::look_at_rh(
+            Point3::from(self.eye()),
+            Point3::from(self.center),
+            Vector3::unit_y(),
+        )
+        .into()
+    }
+
+    /// By how far the mouse was dragged, in pixels
+    pub fn orbit(&mut self, dx: f32, dy: f32) {
+        self.yaw -= dx * ORBIT_SENSITIVITY;
+        self.pitch += dy * ORBIT_SENSITIVITY;
+        self.clamp_pitch();
+    }
+
+    /// Moves `center` along the view plane, by how far the mouse was dragged, in pixels.
+    /// Farther away, a pixel covers more of the world
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let forward = -self.offset().normalize();
+        let right = forward.cross(Vector3::unit_y()).normalize();
+        let up = right.cross(forward);
+
+        let scale = self.distance * PAN_SENSITIVITY;
+        let center = Point3::from(self.center) + (up * dy - right * dx) * scale;
+        self.center = center.into();
+    }
+
+    /// Positive `lines` scroll closer
+    pub fn zoom(&mut self, lines: f32) {
+        self.distance = (self.distance * ZOOM_FACTOR.powf(lines)).max(MIN_DISTANCE);
+    }
+
+    fn clamp_pitch(&mut self) {
+        let (min, max) = self.pitch_limits;
+
+        self.pitch = self.pitch.clamp(min, max);
+    }
+
+    // From `center` to the eye
+    fn offset(&self) -> Vector3<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+
+        Vector3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw) * self.distance
+    }
+}
@@ -0,0 +1,344 @@
+use crate::blend::BlendMode;
+use crate::resources::{ResourceManager, TextureId};
+use crate::uniform_buffer::UniformBuffer;
+
+/// A layer of a `ParallaxBackground`
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LayerId(u32);
+
+#[derive(Clone, Copy, Debug)]
+pub struct ParallaxLayer {
+    pub texture: TextureId,
+    /// How fast the layer moves with the camera: 0 stays put, like the sky, 1 moves along
+    pub scroll_factor: f32,
+    /// How far the layer has scrolled, in its texels
+    pub position: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ParallaxParams {
+    uv_offset: [f32; 2],
+    uv_scale: [f32; 2],
+}
+
+struct LayerEntry {
+    id: LayerId,
+    layer: ParallaxLayer,
+    texture_size: [f32; 2],
+    params: UniformBuffer<ParallaxParams>,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Textures repeated over the whole target, each scrolling at its own pace for an illusion of depth.
+/// The layers are drawn in the order they were added, the first one at the back
+pub struct ParallaxBackground {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    layers: Vec<LayerEntry>,
+    next_id: u32,
+    viewport: [f32; 2],
+}
+
+impl ParallaxBackground {
+    /// `blend_mode` combines every layer with the ones behind it
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        blend_mode: BlendMode,
+        width: u32,
+        height: u32,
+    ) -> ParallaxBackground {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My parallax shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("parallax.wgsl").into()),
+        });
+
+        // Repeating, for the layers to tile endlessly
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("My parallax sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My parallax bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My parallax pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My parallax pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend_mode.state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        ParallaxBackground {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            layers: Vec::new(),
+            next_id: 0,
+            viewport: [width.max(1) as f32, height.max(1) as f32],
+        }
+    }
+
+    /// In front of the layers added before. None when `texture` isn't in `resources`
+    pub fn add_layer(
+        &mut self,
+        device: &wgpu::Device,
+        resources: &ResourceManager,
+        texture: TextureId,
+        scroll_factor: f32,
+    ) -> Option<LayerId> {
+        let layer_texture = resources.texture(texture)?;
+        let size = layer_texture.texture.size();
+
+        let params = UniformBuffer::new(
+            device,
+            "My parallax layer params buffer",
+            ParallaxParams {
+                uv_offset: [0., 0.],
+                uv_scale: [1., 1.],
+            },
+        );
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My parallax layer bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&layer_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        let id = LayerId(self.next_id);
+        self.next_id += 1;
+
+        self.layers.push(LayerEntry {
+            id,
+            layer: ParallaxLayer {
+                texture,
+                scroll_factor,
+                position: [0., 0.],
+            },
+            texture_size: [size.width as f32, size.height as f32],
+            params,
+            bind_group,
+        });
+
+        Some(id)
+    }
+
+    /// None when the layer was already removed
+    pub fn remove_layer(&mut self, id: LayerId) -> Option<ParallaxLayer> {
+        let index = self.layers.iter().position(|entry| entry.id == id)?;
+
+        Some(self.layers.remove(index).layer)
+    }
+
+    pub fn layer(&self, id: LayerId) -> Option<&ParallaxLayer> {
+        self.layers
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| &entry.layer)
+    }
+
+    /// Scrolls every layer by how far the camera moved, in pixels, scaled by the layer's factor
+    pub fn update(&mut self, camera_delta: [f32; 2]) {
+        for entry in &mut self.layers {
+            let layer = &mut entry.layer;
+
+            layer.position[0] += camera_delta[0] * layer.scroll_factor;
+            layer.position[1] += camera_delta[1] * layer.scroll_factor;
+        }
+    }
+
+    /// The size of the target, for a texel to cover a pixel
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = [width.max(1) as f32, height.max(1) as f32];
+    }
+
+    /// Draws the layers over `view`, back to front
+    pub fn draw(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        if self.layers.is_empty() {
+            return;
+        }
+
+        for entry in &mut self.layers {
+            let [width, height] = entry.texture_size;
+
+            entry.params.set(ParallaxParams {
+                uv_offset: [
+                    entry.layer.position[0] / width,
+                    entry.layer.position[1] / height,
+                ],
+                uv_scale: [self.viewport[0] / width, self.viewport[1] / height],
+            });
+            entry.params.upload(queue);
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My parallax pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        for entry in &self.layers {
+            render_pass.set_bind_group(0, &entry.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{texel, SIZE};
+
+    #[test]
+    fn scrolls_the_layers_at_their_own_pace() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        // Stripes of red and green 2 texels wide, over a still blue sky
+        let (red, green, blue) = ([255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]);
+        let mut resources = ResourceManager::new();
+        let sky = resources.add_texture(crate::tests::texture_of(&device, &queue, 1, &[blue]));
+        let stripes = resources.add_texture(crate::tests::texture_of(
+            &device,
+            &queue,
+            4,
+            &[red, red, green, green],
+        ));
+        let target = crate::render_target::RenderTarget::new(
+            &device,
+            SIZE,
+            SIZE,
+            wgpu::TextureFormat::Rgba8Unorm,
+            crate::depth::DEPTH_FORMAT,
+        );
+
+        let mut background =
+            ParallaxBackground::new(&device, target.format(), BlendMode::Alpha, SIZE, SIZE);
+        let sky = background.add_layer(&device, &resources, sky, 0.).unwrap();
+        let stripes = background
+            .add_layer(&device, &resources, stripes, 0.5)
+            .unwrap();
+
+        let draw = |background: &mut ParallaxBackground| {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("My test encoder"),
+            });
+            background.draw(&queue, &mut encoder, target.view());
+            queue.submit([encoder.finish()]);
+
+            pollster::block_on(crate::texture::readback(
+                &device,
+                &queue,
+                target.texture(),
+                0,
+                0,
+            ))
+        };
+
+        let texels = draw(&mut background);
+        assert_eq!(texel(&texels, [0, 10]), red);
+        assert_eq!(texel(&texels, [2, 10]), green);
+        assert_eq!(texel(&texels, [4, 10]), red);
+
+        // Half of the 4 pixels the camera moved, the sky stays put
+        background.update([4., 0.]);
+        assert_eq!(background.layer(sky).unwrap().position, [0., 0.]);
+        assert_eq!(background.layer(stripes).unwrap().position, [2., 0.]);
+        let texels = draw(&mut background);
+        assert_eq!(texel(&texels, [0, 10]), green);
+        assert_eq!(texel(&texels, [2, 10]), red);
+
+        // Only the sky is left
+        assert!(background.remove_layer(stripes).is_some());
+        assert!(background.remove_layer(stripes).is_none());
+        let texels = draw(&mut background);
+        assert_eq!(texel(&texels, [0, 10]), blue);
+    }
+}
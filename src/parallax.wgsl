@@ -0,0 +1,34 @@
+// A background layer repeated over the whole target, scrolled by its own offset
+
+struct ParallaxParams {
+    uv_offset: vec2<f32>,
+    // The target's size in texture sizes, so the texels stay square and 1 pixel big
+    uv_scale: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: ParallaxParams;
+@group(0) @binding(1) var layer_texture: texture_2d<f32>;
+@group(0) @binding(2) var layer_sampler: sampler;
+
+// A single triangle covering the whole target
+@vertex fn vs_main(
+    @builtin(vertex_index) vertex_index: u32
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2. - 1., 0., 1.);
+    out.uv = params.uv_offset + vec2<f32>(uv.x, 1. - uv.y) * params.uv_scale;
+
+    return out;
+}
+
+// The sampler repeats the texture, so the layer never runs out
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(layer_texture, layer_sampler, in.uv);
+}
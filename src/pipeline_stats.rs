@@ -0,0 +1,156 @@
+use std::sync::{Arc, Mutex};
+
+// In the order the results are written, which is the order of the bits
+const STATISTICS: wgpu::PipelineStatisticsTypes =
+    wgpu::PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS
+        .union(wgpu::PipelineStatisticsTypes::CLIPPER_PRIMITIVES_OUT)
+        .union(wgpu::PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS);
+const RESULTS_SIZE: wgpu::BufferAddress = 3 * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+/// What the GPU did during a render pass
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineStats {
+    pub vertex_shader_invocations: u64,
+    pub fragment_shader_invocations: u64,
+    /// The primitives left after clipping, e.g. none for geometry fully off screen
+    pub clipper_primitives_out: u64,
+}
+
+enum ReadbackState {
+    Idle,
+    // The results were copied to the readback buffer, it's mapped after the submit
+    Copied,
+    // Set by the `map_async` callback
+    Mapping(Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>),
+}
+
+/// Counts the shader invocations of a render pass. The results are read back without waiting for the GPU,
+/// so they show up a frame or more later, and the frames recorded while reading are skipped
+pub struct PipelineStatQuery {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    state: ReadbackState,
+    latest: Option<PipelineStats>,
+}
+
+impl PipelineStatQuery {
+    /// None when the device wasn't created with `wgpu::Features::PIPELINE_STATISTICS_QUERY`
+    pub fn new(device: &wgpu::Device) -> Option<PipelineStatQuery> {
+        if !device
+            .features()
+            .contains(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+        {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("My pipeline statistics query set"),
+            ty: wgpu::QueryType::PipelineStatistics(STATISTICS),
+            count: 1,
+        });
+
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My pipeline statistics resolve buffer"),
+            size: RESULTS_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My pipeline statistics readback buffer"),
+            size: RESULTS_SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Some(PipelineStatQuery {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            state: ReadbackState::Idle,
+            latest: None,
+        })
+    }
+
+    /// Starts counting. Only one query can be running in a pass
+    pub fn begin<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>) {
+        render_pass.begin_pipeline_statistics_query(&self.query_set, 0);
+    }
+
+    pub fn end(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.end_pipeline_statistics_query();
+    }
+
+    /// Copies the results out, after the pass. Skipped while the previous ones are still being read
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.collect();
+
+        if !matches!(self.state, ReadbackState::Idle) {
+            return;
+        }
+
+        encoder.resolve_query_set(&self.query_set, 0..1, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            RESULTS_SIZE,
+        );
+
+        self.state = ReadbackState::Copied;
+    }
+
+    /// Starts reading the results resolved in the frame just submitted
+    pub fn after_submit(&mut self) {
+        if !matches!(self.state, ReadbackState::Copied) {
+            return;
+        }
+
+        let mapped = Arc::new(Mutex::new(None));
+        let callback_mapped = Arc::clone(&mapped);
+
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *callback_mapped.lock().unwrap() = Some(result);
+            });
+
+        self.state = ReadbackState::Mapping(mapped);
+    }
+
+    /// The most recent results read back, if any
+    pub fn latest(&self) -> Option<PipelineStats> {
+        self.latest
+    }
+
+    // Reads the results out of the buffer, if it's mapped by now
+    fn collect(&mut self) {
+        let mapped = match &self.state {
+            ReadbackState::Mapping(mapped) => mapped.lock().unwrap().take(),
+            _ => None,
+        };
+
+        match mapped {
+            Some(Ok(())) => {
+                let results: [u64; 3] = bytemuck::pod_read_unaligned(
+                    &self.readback_buffer.slice(..).get_mapped_range(),
+                );
+                self.readback_buffer.unmap();
+
+                self.latest = Some(PipelineStats {
+                    vertex_shader_invocations: results[0],
+                    clipper_primitives_out: results[1],
+                    fragment_shader_invocations: results[2],
+                });
+                self.state = ReadbackState::Idle;
+            }
+            Some(Err(error)) => {
+                log::error!("Can't read the pipeline statistics back: {}", error);
+                self.state = ReadbackState::Idle;
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,189 @@
+// The corners of the quad each point is expanded into, see the shader
+const VERTICES_PER_POINT: u32 = 4;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointSpriteParams {
+    color: [f32; 4],
+    viewport: [f32; 2],
+    size: f32,
+    _padding: f32,
+}
+
+/// Round dots of one size and color, e.g. for particles. Only the centers are uploaded,
+/// the vertex shader expands each of them into a screen aligned quad.
+/// wgpu draws points a single pixel wide, so the quads are instanced triangle strips instead
+pub struct PointSpriteRenderer {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    positions_buffer: wgpu::Buffer,
+    size: f32,
+    color: [f32; 4],
+    viewport: [f32; 2],
+}
+
+impl PointSpriteRenderer {
+    /// `width` and `height` are the target's, see `resize`
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> PointSpriteRenderer {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My point sprite shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("point_sprite.wgsl").into()),
+        });
+
+        // Written before every draw
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My point sprite params buffer"),
+            size: std::mem::size_of::<PointSpriteParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My point sprite bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My point sprite bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My point sprite pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My point sprite pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        PointSpriteRenderer {
+            pipeline,
+            bind_group,
+            params_buffer,
+            positions_buffer: create_positions_buffer(device, 0),
+            size: 8.,
+            color: [1., 1., 1., 1.],
+            viewport: [width.max(1) as f32, height.max(1) as f32],
+        }
+    }
+
+    /// The diameter, in pixels
+    pub fn set_sprite_size(&mut self, size: f32) {
+        self.size = size;
+    }
+
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    /// The size of the target, for the sprite size to be in its pixels
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = [width.max(1) as f32, height.max(1) as f32];
+    }
+
+    /// Draws a dot at each of the `positions` over `view`. They are in clip space
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        positions: &[[f32; 2]],
+    ) {
+        if positions.is_empty() {
+            return;
+        }
+
+        let size = std::mem::size_of_val(positions) as wgpu::BufferAddress;
+        if self.positions_buffer.size() < size {
+            self.positions_buffer = create_positions_buffer(device, size.next_power_of_two());
+        }
+        queue.write_buffer(&self.positions_buffer, 0, bytemuck::cast_slice(positions));
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::bytes_of(&PointSpriteParams {
+                color: self.color,
+                viewport: self.viewport,
+                size: self.size,
+                _padding: 0.,
+            }),
+        );
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My point sprite pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.positions_buffer.slice(..size));
+        render_pass.draw(0..VERTICES_PER_POINT, 0..positions.len() as u32);
+    }
+}
+
+fn create_positions_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("My point sprite positions buffer"),
+        size,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
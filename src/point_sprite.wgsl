@@ -0,0 +1,41 @@
+// Round screen aligned dots, one instance per point, expanded into a quad by the vertex shader
+
+struct PointSpriteParams {
+    color: vec4<f32>,
+    // In pixels, so the dots stay round whatever the aspect ratio
+    viewport: vec2<f32>,
+    size: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: PointSpriteParams;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // From -1 to 1 across the quad
+    @location(0) offset: vec2<f32>,
+}
+
+@vertex fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    // The center, in clip space
+    @location(0) position: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    // A triangle strip: (0, 0), (1, 0), (0, 1), (1, 1)
+    let corner = vertex_index % 4u;
+    let offset = vec2<f32>(f32(corner & 1u), f32(corner >> 1u)) * 2. - 1.;
+
+    out.clip_position = vec4<f32>(position + offset * params.size / params.viewport, 0., 1.);
+    out.offset = offset;
+
+    return out;
+}
+
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if dot(in.offset, in.offset) > 1. {
+        discard;
+    }
+
+    return params.color;
+}
@@ -0,0 +1,220 @@
+use rapier3d::prelude::{
+    ColliderBuilder, GenericJoint, PhysicsWorld, Pose, RevoluteJointBuilder, RigidBodyBuilder,
+    RigidBodyHandle, RigidBodyType, Rotation, Vector,
+};
+
+use crate::skeleton::{JointId, Skeleton};
+
+// How far a joint can bend either way from its bind pose, in radians
+const JOINT_LIMIT: f32 = std::f32::consts::FRAC_PI_3;
+
+/// One rigid body per skeleton joint, connected by revolute joints.
+/// While deactivated the bodies follow the animated skeleton, while activated the skeleton follows the bodies
+pub struct Ragdoll {
+    // Indexed by `JointId`
+    bodies: Vec<RigidBodyHandle>,
+    active: bool,
+}
+
+impl Ragdoll {
+    /// `collider_shapes` has one collider per joint, positioned in the joint's local space.
+    /// The bodies start out deactivated, in the skeleton's current pose
+    pub fn create(
+        skeleton: &Skeleton,
+        collider_shapes: &[ColliderBuilder],
+        physics_world: &mut PhysicsWorld,
+    ) -> Ragdoll {
+        assert_eq!(
+            collider_shapes.len(),
+            skeleton.joints_count(),
+            "a ragdoll needs one collider per joint"
+        );
+
+        let mut bodies = Vec::with_capacity(skeleton.joints_count());
+
+        for (index, collider) in collider_shapes.iter().enumerate() {
+            let joint = JointId(index);
+
+            let body = RigidBodyBuilder::kinematic_position_based()
+                .pose(world_pose(skeleton, joint))
+                .build();
+            let body = physics_world.bodies.insert(body);
+
+            physics_world.colliders.insert_with_parent(
+                collider.build(),
+                body,
+                &mut physics_world.bodies,
+            );
+
+            if let Some(parent) = skeleton.parent(joint) {
+                let anchor = Vector::from_array(skeleton.local_translation(joint));
+                let [x, y, z, w] = skeleton.local_rotation(joint);
+
+                // The bones bend around their local Z axis
+                let mut revolute: GenericJoint = RevoluteJointBuilder::new(Vector::Z)
+                    .local_anchor1(anchor)
+                    .limits([-JOINT_LIMIT, JOINT_LIMIT])
+                    .contacts_enabled(false)
+                    .build()
+                    .into();
+
+                // Keeps the bind pose rotation between the parent and the child as the rest position
+                let frame_rotation = revolute.local_frame1.rotation;
+                revolute.set_local_frame1(Pose::from_parts(
+                    anchor,
+                    Rotation::from_xyzw(x, y, z, w) * frame_rotation,
+                ));
+
+                physics_world
+                    .impulse_joints
+                    .insert(bodies[parent.0], body, revolute, true);
+            }
+
+            bodies.push(body);
+        }
+
+        Ragdoll {
+            bodies,
+            active: false,
+        }
+    }
+
+    /// Hands the pose over to the physics
+    pub fn activate(&mut self, physics_world: &mut PhysicsWorld) {
+        self.set_body_type(physics_world, RigidBodyType::Dynamic);
+        self.active = true;
+    }
+
+    /// Hands the pose back to the animation
+    pub fn deactivate(&mut self, physics_world: &mut PhysicsWorld) {
+        self.set_body_type(physics_world, RigidBodyType::KinematicPositionBased);
+        self.active = false;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Syncs the bodies and the skeleton. The caller steps the world, once for all of its bodies.
+    /// Active, writes the bodies' transforms into the skeleton, ready for `Skeleton::joint_matrices`.
+    /// Inactive, moves the bodies to the skeleton's pose on the next step,
+    /// so they still push other bodies around
+    pub fn update(&mut self, physics_world: &mut PhysicsWorld, skeleton: &mut Skeleton) {
+        if !self.active {
+            for (index, &body) in self.bodies.iter().enumerate() {
+                physics_world.bodies[body]
+                    .set_next_kinematic_position(world_pose(skeleton, JointId(index)));
+            }
+
+            return;
+        }
+
+        // Parents come before their children, so the local transforms can be derived in one go
+        for (index, &body) in self.bodies.iter().enumerate() {
+            let joint = JointId(index);
+            let pose = *physics_world.bodies[body].position();
+
+            let local_rotation = match skeleton.parent(joint) {
+                Some(parent) => {
+                    let parent_pose = physics_world.bodies[self.bodies[parent.0]].position();
+
+                    (parent_pose.rotation.inverse() * pose.rotation).normalize()
+                }
+                None => {
+                    skeleton.set_local_translation(joint, pose.translation.to_array());
+
+                    pose.rotation
+                }
+            };
+
+            skeleton.set_local_rotation(joint, local_rotation.to_array());
+        }
+    }
+
+    fn set_body_type(&self, physics_world: &mut PhysicsWorld, body_type: RigidBodyType) {
+        for &body in &self.bodies {
+            physics_world.bodies[body].set_body_type(body_type, true);
+        }
+    }
+}
+
+fn world_pose(skeleton: &Skeleton, joint: JointId) -> Pose {
+    let [x, y, z, w] = skeleton.world_orientation(joint);
+
+    Pose::from_parts(
+        Vector::from_array(skeleton.world_position(joint)),
+        Rotation::from_xyzw(x, y, z, w),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY: [f32; 4] = [0., 0., 0., 1.];
+
+    fn root_body_position(ragdoll: &Ragdoll, physics_world: &PhysicsWorld) -> [f32; 3] {
+        physics_world.bodies[ragdoll.bodies[0]]
+            .position()
+            .translation
+            .to_array()
+    }
+
+    #[test]
+    fn hands_the_pose_to_the_physics_and_back() {
+        // An arm sticking out sideways, tilted up by 45 degrees
+        let (sin, cos) = std::f32::consts::FRAC_PI_8.sin_cos();
+        let mut skeleton = Skeleton::new();
+        let shoulder = skeleton.add_joint(None, [0., 2., 0.], [0., 0., sin, cos]);
+        let hand = skeleton.add_joint(Some(shoulder), [1., 0., 0.], IDENTITY);
+
+        let mut physics_world = PhysicsWorld::new();
+        let mut ragdoll = Ragdoll::create(
+            &skeleton,
+            &[ColliderBuilder::ball(0.1), ColliderBuilder::ball(0.1)],
+            &mut physics_world,
+        );
+        assert!(!ragdoll.is_active());
+
+        // Inactive, the bodies follow the animated skeleton
+        skeleton.set_local_translation(shoulder, [0., 3., 0.]);
+        ragdoll.update(&mut physics_world, &mut skeleton);
+        physics_world.step();
+        assert_eq!(root_body_position(&ragdoll, &physics_world), [0., 3., 0.]);
+
+        // Held still for a step, otherwise the bodies would be activated with the speed of the move
+        ragdoll.update(&mut physics_world, &mut skeleton);
+        physics_world.step();
+
+        // Active, the skeleton falls with the bodies
+        ragdoll.activate(&mut physics_world);
+        for _ in 0..30 {
+            physics_world.step();
+            ragdoll.update(&mut physics_world, &mut skeleton);
+        }
+        assert!(ragdoll.is_active());
+        assert!(skeleton.local_translation(shoulder)[1] < 3.);
+        let hand_body = *physics_world.bodies[ragdoll.bodies[1]].position();
+        let hand_position = Vector::from_array(skeleton.world_position(hand));
+        let [x, y, z, w] = skeleton.world_orientation(hand);
+        assert!(hand_body.translation.distance(hand_position) < 1e-3);
+        assert!(
+            hand_body
+                .rotation
+                .dot(Rotation::from_xyzw(x, y, z, w))
+                .abs()
+                > 1. - 1e-4
+        );
+
+        // Inactive again, the skeleton is left alone and the bodies go back to following it
+        ragdoll.deactivate(&mut physics_world);
+        skeleton.set_local_translation(shoulder, [0., 5., 0.]);
+        skeleton.set_local_rotation(hand, IDENTITY);
+        ragdoll.update(&mut physics_world, &mut skeleton);
+        physics_world.step();
+        assert!(!ragdoll.is_active());
+        assert_eq!(skeleton.local_translation(shoulder), [0., 5., 0.]);
+        assert_eq!(skeleton.local_rotation(hand), IDENTITY);
+        assert_eq!(root_body_position(&ragdoll, &physics_world), [0., 5., 0.]);
+    }
+}
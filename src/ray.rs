@@ -0,0 +1,59 @@
+use cgmath::{InnerSpace, Vector3};
+
+// Rays closer than this to parallel with a plane miss it
+const PARALLEL_EPSILON: f32 = 1e-6;
+
+/// A half line in the world, e.g. from the camera through a pixel, see `State::screen_to_ray`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    /// Normalized
+    pub direction: [f32; 3],
+}
+
+impl Ray {
+    /// `direction` is normalized, it can't be zero
+    pub fn new(origin: [f32; 3], direction: [f32; 3]) -> Ray {
+        Ray {
+            origin,
+            direction: Vector3::from(direction).normalize().into(),
+        }
+    }
+
+    /// The point `distance` along the ray
+    pub fn at(&self, distance: f32) -> [f32; 3] {
+        (Vector3::from(self.origin) + Vector3::from(self.direction) * distance).into()
+    }
+
+    /// How far along the ray it crosses the plane through `point` facing `normal`, from either side.
+    /// None when it's parallel to the plane or points away from it
+    pub fn intersect_plane(&self, point: [f32; 3], normal: [f32; 3]) -> Option<f32> {
+        let normal = Vector3::from(normal);
+        let facing = normal.dot(self.direction.into());
+        if facing.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+
+        let distance = normal.dot(Vector3::from(point) - Vector3::from(self.origin)) / facing;
+
+        (distance >= 0.).then_some(distance)
+    }
+
+    /// How far along the ray it enters the sphere, xyz - center, w - radius, like `Scene::bounding_sphere`.
+    /// 0 when it starts inside
+    pub fn intersect_sphere(&self, [x, y, z, radius]: [f32; 4]) -> Option<f32> {
+        let to_center = Vector3::new(x, y, z) - Vector3::from(self.origin);
+        let along = to_center.dot(self.direction.into());
+        let off_ray2 = to_center.magnitude2() - along * along;
+        if off_ray2 > radius * radius {
+            return None;
+        }
+
+        let half_chord = (radius * radius - off_ray2).sqrt();
+        if along + half_chord < 0. {
+            return None;
+        }
+
+        Some((along - half_chord).max(0.))
+    }
+}
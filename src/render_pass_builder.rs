@@ -0,0 +1,470 @@
+/// What a render pipeline draws to. wgpu doesn't expose it on the pipeline,
+/// so it's kept next to it, with the same values as its descriptor
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipelineTargets {
+    pub color_formats: Vec<Option<wgpu::TextureFormat>>,
+    pub depth_format: Option<wgpu::TextureFormat>,
+    pub sample_count: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PassCompatibilityError {
+    ColorAttachmentCount {
+        attachments: usize,
+        targets: usize,
+    },
+    ColorFormat {
+        index: usize,
+        attachment: wgpu::TextureFormat,
+        target: Option<wgpu::TextureFormat>,
+    },
+    /// None when there is no depth attachment, or the pipeline doesn't use depth
+    DepthFormat {
+        attachment: Option<wgpu::TextureFormat>,
+        target: Option<wgpu::TextureFormat>,
+    },
+    SampleCount {
+        attachment: u32,
+        pipeline: u32,
+    },
+    /// A resolve target must be single sampled, and its color attachment multisampled
+    ResolveSampleCount {
+        index: usize,
+        attachment: u32,
+        resolve_target: u32,
+    },
+    /// The view formats of a color attachment and its resolve target differ
+    ResolveFormat {
+        index: usize,
+        attachment: wgpu::TextureFormat,
+        resolve_target: wgpu::TextureFormat,
+    },
+    /// The texture, or the resolve target of the color attachment, wasn't created
+    /// with `RENDER_ATTACHMENT`. None for the depth attachment
+    MissingUsage {
+        index: Option<usize>,
+    },
+    /// All the attachments must have the same size
+    SizeMismatch {
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+}
+
+impl std::fmt::Display for PassCompatibilityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PassCompatibilityError::ColorAttachmentCount {
+                attachments,
+                targets,
+            } => write!(
+                f,
+                "the pass has {} color attachments, but the pipeline has {} targets",
+                attachments, targets
+            ),
+            PassCompatibilityError::ColorFormat {
+                index,
+                attachment,
+                target,
+            } => write!(
+                f,
+                "color attachment {} is {:?}, but the pipeline's target is {:?}",
+                index, attachment, target
+            ),
+            PassCompatibilityError::DepthFormat { attachment, target } => write!(
+                f,
+                "the depth attachment is {:?}, but the pipeline's is {:?}",
+                attachment, target
+            ),
+            PassCompatibilityError::SampleCount {
+                attachment,
+                pipeline,
+            } => write!(
+                f,
+                "the attachments have {} samples, but the pipeline {}",
+                attachment, pipeline
+            ),
+            PassCompatibilityError::ResolveSampleCount {
+                index,
+                attachment,
+                resolve_target,
+            } => write!(
+                f,
+                "color attachment {} has {} samples and its resolve target {}, \
+                 instead of more than 1 and 1",
+                index, attachment, resolve_target
+            ),
+            PassCompatibilityError::ResolveFormat {
+                index,
+                attachment,
+                resolve_target,
+            } => write!(
+                f,
+                "color attachment {} is {:?}, but its resolve target is {:?}",
+                index, attachment, resolve_target
+            ),
+            PassCompatibilityError::MissingUsage { index: Some(index) } => write!(
+                f,
+                "color attachment {} or its resolve target isn't a RENDER_ATTACHMENT texture",
+                index
+            ),
+            PassCompatibilityError::MissingUsage { index: None } => {
+                write!(f, "the depth attachment isn't a RENDER_ATTACHMENT texture")
+            }
+            PassCompatibilityError::SizeMismatch { expected, found } => write!(
+                f,
+                "an attachment is {}x{}, but the others are {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PassCompatibilityError {}
+
+struct Attachment<'a, V> {
+    texture: &'a wgpu::Texture,
+    view: &'a wgpu::TextureView,
+    ops: wgpu::Operations<V>,
+}
+
+// A view and its format, which wgpu doesn't tell
+struct ColorView<'a> {
+    texture: &'a wgpu::Texture,
+    view: &'a wgpu::TextureView,
+    format: wgpu::TextureFormat,
+}
+
+struct ColorAttachment<'a> {
+    target: ColorView<'a>,
+    resolve_target: Option<ColorView<'a>>,
+    ops: wgpu::Operations<wgpu::Color>,
+}
+
+/// Collects the attachments of a render pass with their textures, so they can be checked against a pipeline
+/// and the mismatches reported as errors instead of wgpu panicking
+pub struct RenderPassBuilder<'a> {
+    label: Option<&'a str>,
+    color_attachments: Vec<ColorAttachment<'a>>,
+    depth_attachment: Option<Attachment<'a, f32>>,
+}
+
+impl<'a> RenderPassBuilder<'a> {
+    pub fn new(label: &'a str) -> RenderPassBuilder<'a> {
+        RenderPassBuilder {
+            label: Some(label),
+            color_attachments: Vec::new(),
+            depth_attachment: None,
+        }
+    }
+
+    /// `view` is a view of `texture` in `view_format`, the texture's format or one of its
+    /// `view_formats`, e.g. the sRGB view of a linear swapchain texture
+    pub fn color_attachment(
+        mut self,
+        texture: &'a wgpu::Texture,
+        view: &'a wgpu::TextureView,
+        view_format: wgpu::TextureFormat,
+        ops: wgpu::Operations<wgpu::Color>,
+    ) -> RenderPassBuilder<'a> {
+        self.color_attachments.push(ColorAttachment {
+            target: ColorView {
+                texture,
+                view,
+                format: view_format,
+            },
+            resolve_target: None,
+            ops,
+        });
+        self
+    }
+
+    /// Resolves the multisampled color attachment added last into `view` of `texture`,
+    /// in `view_format` like `color_attachment`. Panics without a color attachment
+    pub fn resolve_target(
+        mut self,
+        texture: &'a wgpu::Texture,
+        view: &'a wgpu::TextureView,
+        view_format: wgpu::TextureFormat,
+    ) -> RenderPassBuilder<'a> {
+        let attachment = self
+            .color_attachments
+            .last_mut()
+            .expect("a color attachment to resolve");
+        attachment.resolve_target = Some(ColorView {
+            texture,
+            view,
+            format: view_format,
+        });
+        self
+    }
+
+    /// The stencil, if any, is left untouched
+    pub fn depth_attachment(
+        mut self,
+        texture: &'a wgpu::Texture,
+        view: &'a wgpu::TextureView,
+        ops: wgpu::Operations<f32>,
+    ) -> RenderPassBuilder<'a> {
+        self.depth_attachment = Some(Attachment { texture, view, ops });
+        self
+    }
+
+    /// Checks what wgpu would panic on when drawing with the pipeline in the pass
+    pub fn validate_against_pipeline(
+        self,
+        pipeline: &PipelineTargets,
+    ) -> Result<RenderPassBuilder<'a>, PassCompatibilityError> {
+        if self.color_attachments.len() != pipeline.color_formats.len() {
+            return Err(PassCompatibilityError::ColorAttachmentCount {
+                attachments: self.color_attachments.len(),
+                targets: pipeline.color_formats.len(),
+            });
+        }
+
+        for (index, (attachment, &target)) in self
+            .color_attachments
+            .iter()
+            .zip(&pipeline.color_formats)
+            .enumerate()
+        {
+            let format = attachment.target.format;
+
+            if target != Some(format) {
+                return Err(PassCompatibilityError::ColorFormat {
+                    index,
+                    attachment: format,
+                    target,
+                });
+            }
+
+            let Some(resolve_target) = &attachment.resolve_target else {
+                continue;
+            };
+
+            let (samples, resolve_samples) = (
+                attachment.target.texture.sample_count(),
+                resolve_target.texture.sample_count(),
+            );
+            if samples == 1 || resolve_samples != 1 {
+                return Err(PassCompatibilityError::ResolveSampleCount {
+                    index,
+                    attachment: samples,
+                    resolve_target: resolve_samples,
+                });
+            }
+
+            if resolve_target.format != format {
+                return Err(PassCompatibilityError::ResolveFormat {
+                    index,
+                    attachment: format,
+                    resolve_target: resolve_target.format,
+                });
+            }
+        }
+
+        let depth_format = self
+            .depth_attachment
+            .as_ref()
+            .map(|attachment| attachment.texture.format());
+        if depth_format != pipeline.depth_format {
+            return Err(PassCompatibilityError::DepthFormat {
+                attachment: depth_format,
+                target: pipeline.depth_format,
+            });
+        }
+
+        let textures = self
+            .color_attachments
+            .iter()
+            .enumerate()
+            .map(|(index, attachment)| (Some(index), attachment.target.texture))
+            .chain(
+                self.depth_attachment
+                    .as_ref()
+                    .map(|attachment| (None, attachment.texture)),
+            );
+        // Single sampled, so they're left out of the sample count check
+        let resolve_textures =
+            self.color_attachments
+                .iter()
+                .enumerate()
+                .filter_map(|(index, attachment)| {
+                    let resolve_target = attachment.resolve_target.as_ref()?;
+
+                    Some((Some(index), resolve_target.texture))
+                });
+
+        let mut size = None;
+        for (resolving, (index, texture)) in textures
+            .map(|texture| (false, texture))
+            .chain(resolve_textures.map(|texture| (true, texture)))
+        {
+            if !texture
+                .usage()
+                .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+            {
+                return Err(PassCompatibilityError::MissingUsage { index });
+            }
+
+            if !resolving && texture.sample_count() != pipeline.sample_count {
+                return Err(PassCompatibilityError::SampleCount {
+                    attachment: texture.sample_count(),
+                    pipeline: pipeline.sample_count,
+                });
+            }
+
+            let found = (texture.width(), texture.height());
+            match size {
+                Some(expected) if expected != found => {
+                    return Err(PassCompatibilityError::SizeMismatch { expected, found })
+                }
+                _ => size = Some(found),
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn begin<'p>(&self, encoder: &'p mut wgpu::CommandEncoder) -> wgpu::RenderPass<'p>
+    where
+        'a: 'p,
+    {
+        let color_attachments: Vec<_> = self
+            .color_attachments
+            .iter()
+            .map(|attachment| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view: attachment.target.view,
+                    resolve_target: attachment
+                        .resolve_target
+                        .as_ref()
+                        .map(|resolve_target| resolve_target.view),
+                    ops: attachment.ops,
+                })
+            })
+            .collect();
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: self.label,
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: self.depth_attachment.as_ref().map(|attachment| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view: attachment.view,
+                    depth_ops: Some(attachment.ops),
+                    stencil_ops: None,
+                }
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPS: wgpu::Operations<wgpu::Color> = wgpu::Operations {
+        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+        store: wgpu::StoreOp::Store,
+    };
+
+    fn color_texture(
+        device: &wgpu::Device,
+        sample_count: u32,
+        view_formats: &[wgpu::TextureFormat],
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("My test color texture"),
+            size: wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats,
+        })
+    }
+
+    // Anything wgpu rejects in the pass panics here
+    fn begin_and_submit(device: &wgpu::Device, queue: &wgpu::Queue, builder: &RenderPassBuilder) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My test encoder"),
+        });
+        drop(builder.begin(&mut encoder));
+        queue.submit([encoder.finish()]);
+        device.poll(wgpu::Maintain::Wait);
+    }
+
+    #[test]
+    fn resolves_into_a_single_sampled_texture() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let pipeline = PipelineTargets {
+            color_formats: vec![Some(format)],
+            depth_format: None,
+            sample_count: 4,
+        };
+        let multisampled = color_texture(&device, 4, &[]);
+        let multisampled_view = multisampled.create_view(&Default::default());
+        let resolved = color_texture(&device, 1, &[]);
+        let resolved_view = resolved.create_view(&Default::default());
+
+        let builder = RenderPassBuilder::new("My test pass")
+            .color_attachment(&multisampled, &multisampled_view, format, OPS)
+            .resolve_target(&resolved, &resolved_view, format)
+            .validate_against_pipeline(&pipeline)
+            .unwrap();
+        begin_and_submit(&device, &queue, &builder);
+
+        // Resolving into the multisampled texture itself
+        assert_eq!(
+            RenderPassBuilder::new("My test pass")
+                .color_attachment(&multisampled, &multisampled_view, format, OPS)
+                .resolve_target(&multisampled, &multisampled_view, format)
+                .validate_against_pipeline(&pipeline)
+                .err(),
+            Some(PassCompatibilityError::ResolveSampleCount {
+                index: 0,
+                attachment: 4,
+                resolve_target: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn accepts_an_srgb_view_of_a_linear_texture() {
+        let Some((device, queue)) = crate::tests::device_with(wgpu::DownlevelFlags::VIEW_FORMATS)
+        else {
+            eprintln!("No adapter with view formats, skipping");
+            return;
+        };
+
+        let srgb = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let pipeline = PipelineTargets {
+            color_formats: vec![Some(srgb)],
+            depth_format: None,
+            sample_count: 1,
+        };
+        let texture = color_texture(&device, 1, &[srgb]);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(srgb),
+            ..Default::default()
+        });
+
+        let builder = RenderPassBuilder::new("My test pass")
+            .color_attachment(&texture, &view, srgb, OPS)
+            .validate_against_pipeline(&pipeline)
+            .unwrap();
+        begin_and_submit(&device, &queue, &builder);
+    }
+}
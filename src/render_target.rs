@@ -0,0 +1,215 @@
+use crate::depth::DepthTexture;
+
+/// An offscreen color texture with a depth texture of the same size, rendered into instead
+/// of the surface, then sampled by a later pass, e.g. with `Blit`, or read back
+pub struct RenderTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    depth: DepthTexture,
+}
+
+impl RenderTarget {
+    /// `depth_format` is `DepthConfig::format` of the pipelines drawing into it
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+    ) -> RenderTarget {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("My render target texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        RenderTarget {
+            depth: DepthTexture::new(device, width, height, 1, depth_format),
+            texture,
+            view,
+        }
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// Rendered into, and sampled afterwards
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn depth_view(&self) -> &wgpu::TextureView {
+        self.depth.view()
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.texture.format()
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.texture.width(), self.texture.height())
+    }
+}
+
+/// What `Blit` does to the colors on the way
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlitEffect {
+    Copy,
+    Grayscale,
+}
+
+impl BlitEffect {
+    fn fragment_entry_point(self) -> &'static str {
+        match self {
+            BlitEffect::Copy => "fs_copy",
+            BlitEffect::Grayscale => "fs_grayscale",
+        }
+    }
+}
+
+/// Draws a `RenderTarget` stretched over another target with a full screen triangle,
+/// e.g. onto the surface once the frame was rendered offscreen
+pub struct Blit {
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    copy_pipeline: wgpu::RenderPipeline,
+    grayscale_pipeline: wgpu::RenderPipeline,
+}
+
+impl Blit {
+    /// `format` is the one of the targets it draws onto
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Blit {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My blit shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("blit.wgsl").into()),
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("My blit sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My blit bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My blit pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |effect: BlitEffect| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("My blit pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: effect.fragment_entry_point(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        Blit {
+            copy_pipeline: create_pipeline(BlitEffect::Copy),
+            grayscale_pipeline: create_pipeline(BlitEffect::Grayscale),
+            bind_group_layout,
+            sampler,
+        }
+    }
+
+    /// Covers all of `view` with `source`
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &RenderTarget,
+        view: &wgpu::TextureView,
+        effect: BlitEffect,
+    ) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My blit bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source.view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My blit pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // Everything is drawn over
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(match effect {
+            BlitEffect::Copy => &self.copy_pipeline,
+            BlitEffect::Grayscale => &self.grayscale_pipeline,
+        });
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
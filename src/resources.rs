@@ -0,0 +1,156 @@
+use crate::drawable::Drawable;
+use crate::material::Material;
+use crate::texture::Texture;
+use crate::texture_atlas::TextureAtlas;
+
+/// A render pipeline in a `ResourceManager`
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PipelineId(u32);
+
+/// A texture in a `ResourceManager`
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextureId(u32);
+
+/// A buffer in a `ResourceManager`
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BufferId(u32);
+
+/// A mesh in a `ResourceManager`
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MeshId(u32);
+
+/// A material in a `ResourceManager`
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MaterialId(u32);
+
+/// A texture atlas in a `ResourceManager`
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AtlasId(u32);
+
+// Removed resources leave their slot empty, so a stale id finds nothing instead of another resource
+struct Slots<T> {
+    slots: Vec<Option<T>>,
+}
+
+impl<T> Slots<T> {
+    fn insert(&mut self, resource: T) -> u32 {
+        self.slots.push(Some(resource));
+        (self.slots.len() - 1) as u32
+    }
+
+    fn get(&self, index: u32) -> Option<&T> {
+        self.slots.get(index as usize)?.as_ref()
+    }
+
+    fn remove(&mut self, index: u32) -> Option<T> {
+        self.slots.get_mut(index as usize)?.take()
+    }
+}
+
+impl<T> Default for Slots<T> {
+    fn default() -> Slots<T> {
+        Slots { slots: Vec::new() }
+    }
+}
+
+/// Owns the resources handed out as ids. The ids of different kinds of resources are different types,
+/// so passing a texture where a buffer is expected doesn't compile
+#[derive(Default)]
+pub struct ResourceManager {
+    pipelines: Slots<wgpu::RenderPipeline>,
+    textures: Slots<Texture>,
+    buffers: Slots<wgpu::Buffer>,
+    meshes: Slots<Drawable>,
+    materials: Slots<Material>,
+    atlases: Slots<TextureAtlas>,
+}
+
+impl ResourceManager {
+    pub fn new() -> ResourceManager {
+        ResourceManager::default()
+    }
+
+    pub fn add_pipeline(&mut self, pipeline: wgpu::RenderPipeline) -> PipelineId {
+        PipelineId(self.pipelines.insert(pipeline))
+    }
+
+    /// None once removed
+    pub fn pipeline(&self, id: PipelineId) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(id.0)
+    }
+
+    pub fn remove_pipeline(&mut self, id: PipelineId) -> Option<wgpu::RenderPipeline> {
+        self.pipelines.remove(id.0)
+    }
+
+    pub fn add_texture(&mut self, texture: Texture) -> TextureId {
+        TextureId(self.textures.insert(texture))
+    }
+
+    /// None once removed
+    pub fn texture(&self, id: TextureId) -> Option<&Texture> {
+        self.textures.get(id.0)
+    }
+
+    pub fn remove_texture(&mut self, id: TextureId) -> Option<Texture> {
+        self.textures.remove(id.0)
+    }
+
+    pub fn add_buffer(&mut self, buffer: wgpu::Buffer) -> BufferId {
+        BufferId(self.buffers.insert(buffer))
+    }
+
+    /// None once removed
+    pub fn buffer(&self, id: BufferId) -> Option<&wgpu::Buffer> {
+        self.buffers.get(id.0)
+    }
+
+    pub fn remove_buffer(&mut self, id: BufferId) -> Option<wgpu::Buffer> {
+        self.buffers.remove(id.0)
+    }
+
+    pub fn add_mesh(&mut self, mesh: Drawable) -> MeshId {
+        MeshId(self.meshes.insert(mesh))
+    }
+
+    /// None once removed
+    pub fn mesh(&self, id: MeshId) -> Option<&Drawable> {
+        self.meshes.get(id.0)
+    }
+
+    pub fn remove_mesh(&mut self, id: MeshId) -> Option<Drawable> {
+        self.meshes.remove(id.0)
+    }
+
+    pub fn add_material(&mut self, material: Material) -> MaterialId {
+        MaterialId(self.materials.insert(material))
+    }
+
+    /// None once removed
+    pub fn material(&self, id: MaterialId) -> Option<&Material> {
+        self.materials.get(id.0)
+    }
+
+    pub fn remove_material(&mut self, id: MaterialId) -> Option<Material> {
+        self.materials.remove(id.0)
+    }
+
+    pub fn add_atlas(&mut self, atlas: TextureAtlas) -> AtlasId {
+        AtlasId(self.atlases.insert(atlas))
+    }
+
+    /// None once removed
+    pub fn atlas(&self, id: AtlasId) -> Option<&TextureAtlas> {
+        self.atlases.get(id.0)
+    }
+
+    pub fn remove_atlas(&mut self, id: AtlasId) -> Option<TextureAtlas> {
+        self.atlases.remove(id.0)
+    }
+}
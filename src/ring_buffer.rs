@@ -0,0 +1,264 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use wgpu::util::DeviceExt;
+
+/// Where `RingBuffer::push_slice` put the data, to get the slice for the render pass with `RingBuffer::slice`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RingSlice {
+    // None in the ring, otherwise the index of the separate buffer
+    overflow: Option<usize>,
+    offset: wgpu::BufferAddress,
+    size: wgpu::BufferAddress,
+}
+
+/// Streams data changing every frame, like particles or debug lines, into one buffer written front to back.
+/// The space is reused once the GPU is done with the frames that used it.
+/// When the ring is full, the data goes to a separate buffer instead of waiting for the GPU
+pub struct RingBuffer {
+    buffer: wgpu::Buffer,
+    capacity: wgpu::BufferAddress,
+    // Both count every byte ever used, the position in the ring is the remainder of the capacity
+    head: u64,
+    // Everything before it is done being read by the GPU. Moved by the `on_submitted_work_done` callbacks
+    tail: Arc<AtomicU64>,
+    // The separate buffers of the current frame
+    overflow: Vec<wgpu::Buffer>,
+    label: String,
+    usage: wgpu::BufferUsages,
+}
+
+impl RingBuffer {
+    /// `usage` is how the data is used, e.g. `VERTEX`
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        capacity: wgpu::BufferAddress,
+        usage: wgpu::BufferUsages,
+    ) -> RingBuffer {
+        let capacity = capacity
+            .max(1)
+            .next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: capacity,
+            usage,
+            mapped_at_creation: false,
+        });
+
+        RingBuffer {
+            buffer,
+            capacity,
+            head: 0,
+            tail: Arc::new(AtomicU64::new(0)),
+            overflow: Vec::new(),
+            label: label.to_owned(),
+            usage,
+        }
+    }
+
+    /// None for empty data, which can't be bound
+    pub fn push_slice<T: bytemuck::Pod>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[T],
+    ) -> Option<RingSlice> {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        if bytes.is_empty() {
+            return None;
+        }
+
+        // Writes must be whole multiples of 4 bytes, and start at one
+        let size =
+            (bytes.len() as wgpu::BufferAddress).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+        let position = self.head % self.capacity;
+        // The data isn't split between the end and the start, the end is skipped instead
+        let skipped = if position + size > self.capacity {
+            self.capacity - position
+        } else {
+            0
+        };
+        let used = self.head - self.tail.load(Ordering::Acquire);
+
+        if used + skipped + size > self.capacity {
+            self.overflow.push(
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&self.label),
+                    contents: bytes,
+                    usage: self.usage,
+                }),
+            );
+
+            return Some(RingSlice {
+                overflow: Some(self.overflow.len() - 1),
+                offset: 0,
+                size: bytes.len() as wgpu::BufferAddress,
+            });
+        }
+
+        let offset = (position + skipped) % self.capacity;
+        let padding = (size - bytes.len() as wgpu::BufferAddress) as usize;
+
+        if padding == 0 {
+            queue.write_buffer(&self.buffer, offset, bytes);
+        } else {
+            let mut padded = bytes.to_vec();
+            padded.resize(size as usize, 0);
+            queue.write_buffer(&self.buffer, offset, &padded);
+        }
+        self.head += skipped + size;
+
+        Some(RingSlice {
+            overflow: None,
+            offset,
+            size: bytes.len() as wgpu::BufferAddress,
+        })
+    }
+
+    /// The pushed data. Only valid until the end of the frame
+    pub fn slice(&self, ring_slice: RingSlice) -> wgpu::BufferSlice<'_> {
+        let buffer = match ring_slice.overflow {
+            Some(index) => &self.overflow[index],
+            None => &self.buffer,
+        };
+
+        buffer.slice(ring_slice.offset..ring_slice.offset + ring_slice.size)
+    }
+
+    /// Called after submitting the frame using the pushed data. Its space is reused once the GPU is done with it
+    pub fn finish_frame(&mut self, queue: &wgpu::Queue) {
+        let tail = Arc::clone(&self.tail);
+        let head = self.head;
+
+        queue.on_submitted_work_done(move || {
+            tail.fetch_max(head, Ordering::AcqRel);
+        });
+
+        // wgpu keeps them alive until the submitted work is done
+        self.overflow.clear();
+    }
+
+    /// The share of the ring the GPU may still be reading, from 0 to 1
+    pub fn utilization(&self) -> f32 {
+        (self.head - self.tail.load(Ordering::Acquire)) as f32 / self.capacity as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAPACITY: wgpu::BufferAddress = 16;
+
+    fn ring(device: &wgpu::Device) -> RingBuffer {
+        RingBuffer::new(
+            device,
+            "My test ring buffer",
+            CAPACITY,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC,
+        )
+    }
+
+    // Submits the frame and waits for the GPU to be done with it
+    fn finish_frame(device: &wgpu::Device, queue: &wgpu::Queue, ring: &mut RingBuffer) {
+        queue.submit([]);
+        ring.finish_frame(queue);
+        device.poll(wgpu::Maintain::Wait);
+    }
+
+    fn read(device: &wgpu::Device, queue: &wgpu::Queue, ring: &RingBuffer) -> Vec<u8> {
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My test readback buffer"),
+            size: CAPACITY,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My test encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&ring.buffer, 0, &readback, 0, CAPACITY);
+        queue.submit([encoder.finish()]);
+
+        let mapped = pollster::block_on(crate::buffer_map::map_buffer_async(
+            device,
+            &readback,
+            wgpu::MapMode::Read,
+        ))
+        .unwrap();
+
+        mapped.to_vec()
+    }
+
+    #[test]
+    fn pads_the_data_and_skips_the_end_when_wrapping() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let mut ring = ring(&device);
+
+        let padded = ring.push_slice(&device, &queue, &[1_u8; 3]).unwrap();
+        assert_eq!((padded.overflow, padded.offset, padded.size), (None, 0, 3));
+        let after = ring.push_slice(&device, &queue, &[2_u8; 8]).unwrap();
+        assert_eq!((after.overflow, after.offset), (None, 4));
+        assert_eq!(ring.utilization(), 0.75);
+
+        finish_frame(&device, &queue, &mut ring);
+        assert_eq!(ring.utilization(), 0.);
+
+        // 8 bytes don't fit in the 4 left before the end
+        let wrapped = ring.push_slice(&device, &queue, &[3_u8; 8]).unwrap();
+        assert_eq!((wrapped.overflow, wrapped.offset), (None, 0));
+        // The skipped end counts as used
+        assert_eq!(ring.utilization(), 0.75);
+
+        let mut expected = [3; 8].to_vec();
+        expected.extend([2; 4]);
+        expected.extend([0; 4]);
+        assert_eq!(read(&device, &queue, &ring), expected);
+    }
+
+    #[test]
+    fn overflows_until_the_gpu_is_done() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let mut ring = ring(&device);
+
+        ring.push_slice(&device, &queue, &[1_u8; 12]).unwrap();
+        let overflow = ring.push_slice(&device, &queue, &[2_u8; 8]).unwrap();
+        assert_eq!((overflow.overflow, overflow.offset), (Some(0), 0));
+        assert_eq!(overflow.size, 8);
+        // Still fits exactly
+        let last = ring.push_slice(&device, &queue, &[3_u8; 4]).unwrap();
+        assert_eq!((last.overflow, last.offset), (None, 12));
+        let next_overflow = ring.push_slice(&device, &queue, &[4_u8; 4]).unwrap();
+        assert_eq!(next_overflow.overflow, Some(1));
+
+        // Without waiting for the GPU, the ring is still full
+        ring.finish_frame(&queue);
+        assert_eq!(ring.overflow.len(), 0);
+        let still_full = ring.push_slice(&device, &queue, &[5_u8; 8]).unwrap();
+        assert_eq!(still_full.overflow, Some(0));
+
+        finish_frame(&device, &queue, &mut ring);
+        let reused = ring.push_slice(&device, &queue, &[6_u8; 8]).unwrap();
+        assert_eq!((reused.overflow, reused.offset), (None, 0));
+    }
+
+    #[test]
+    fn pushing_nothing_gives_no_slice() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let mut ring = ring(&device);
+
+        assert_eq!(ring.push_slice::<u32>(&device, &queue, &[]), None);
+        assert_eq!(ring.utilization(), 0.);
+    }
+}
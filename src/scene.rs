@@ -0,0 +1,542 @@
+use std::path::{Path, PathBuf};
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Vector3, Vector4};
+use serde::Deserialize;
+
+use crate::obj;
+
+// Lit surfaces are never darker than this
+const AMBIENT: f32 = 0.15;
+
+// cgmath's projections map the depth to [-1, 1], wgpu's clip space has it in [0, 1]
+#[rustfmt::skip]
+const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+// Flips the depth, 1.0 at the near plane and 0.0 at the far one
+#[rustfmt::skip]
+const REVERSED_Z_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, -1.0, 0.0,
+    0.0, 0.0, 1.0, 1.0,
+);
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// `entry` is where in the file it happened, e.g. `meshes[1]`
+    Mesh {
+        entry: String,
+        error: obj::ObjError,
+    },
+    UnknownMesh {
+        entry: String,
+        name: String,
+    },
+    UnknownMaterial {
+        entry: String,
+        name: String,
+    },
+    DuplicateName {
+        entry: String,
+        name: String,
+    },
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "{}", error),
+            SceneError::Json(error) => write!(f, "{}", error),
+            SceneError::Mesh { entry, error } => write!(f, "{}: {}", entry, error),
+            SceneError::UnknownMesh { entry, name } => {
+                write!(f, "{}: there is no mesh named {:?}", entry, name)
+            }
+            SceneError::UnknownMaterial { entry, name } => {
+                write!(f, "{}: there is no material named {:?}", entry, name)
+            }
+            SceneError::DuplicateName { entry, name } => {
+                write!(f, "{}: {:?} is already taken", entry, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> SceneError {
+        SceneError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(error: serde_json::Error) -> SceneError {
+        SceneError::Json(error)
+    }
+}
+
+/// The meshes generated instead of loaded
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Primitive {
+    Triangle,
+    /// A unit square facing +Z
+    Quad,
+    /// A unit cube centered on the origin
+    Cube,
+}
+
+impl Primitive {
+    fn mesh(self) -> SceneMesh {
+        let (positions, indices): (&[[f32; 3]], &[u32]) = match self {
+            Primitive::Triangle => (
+                &[[0., 0.5, 0.], [-0.5, -0.5, 0.], [0.5, -0.5, 0.]],
+                &[0, 1, 2],
+            ),
+            Primitive::Quad => (
+                &[
+                    [-0.5, -0.5, 0.],
+                    [0.5, -0.5, 0.],
+                    [0.5, 0.5, 0.],
+                    [-0.5, 0.5, 0.],
+                ],
+                &[0, 1, 2, 0, 2, 3],
+            ),
+            Primitive::Cube => (
+                &[
+                    [-0.5, -0.5, -0.5],
+                    [0.5, -0.5, -0.5],
+                    [0.5, 0.5, -0.5],
+                    [-0.5, 0.5, -0.5],
+                    [-0.5, -0.5, 0.5],
+                    [0.5, -0.5, 0.5],
+                    [0.5, 0.5, 0.5],
+                    [-0.5, 0.5, 0.5],
+                ],
+                // Counter-clockwise seen from outside
+                &[
+                    4, 5, 6, 4, 6, 7, // +Z
+                    1, 0, 3, 1, 3, 2, // -Z
+                    5, 1, 2, 5, 2, 6, // +X
+                    0, 4, 7, 0, 7, 3, // -X
+                    7, 6, 2, 7, 2, 3, // +Y
+                    0, 1, 5, 0, 5, 4, // -Y
+                ],
+            ),
+        };
+
+        SceneMesh {
+            positions: positions.to_vec(),
+            indices: indices.to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Transform {
+    pub translation: [f32; 3],
+    /// Euler angles in degrees, applied around X, then Y, then Z
+    pub rotation: [f32; 3],
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform {
+            translation: [0.; 3],
+            rotation: [0.; 3],
+            scale: 1.,
+        }
+    }
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Matrix4<f32> {
+        let [x, y, z] = self.rotation;
+
+        Matrix4::from_translation(self.translation.into())
+            * Matrix4::from_angle_z(cgmath::Deg(z))
+            * Matrix4::from_angle_y(cgmath::Deg(y))
+            * Matrix4::from_angle_x(cgmath::Deg(x))
+            * Matrix4::from_scale(self.scale)
+    }
+}
+
+/// A point light
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Light {
+    pub position: [f32; 3],
+    #[serde(default = "white")]
+    pub color: [f32; 3],
+    #[serde(default = "one")]
+    pub intensity: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Camera {
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    #[serde(default = "default_fov_y")]
+    pub fov_y_degrees: f32,
+    #[serde(default = "default_near")]
+    pub near: f32,
+    #[serde(default = "default_far")]
+    pub far: f32,
+}
+
+impl Camera {
+    /// `reversed_z` puts the near plane at 1.0, for `DepthConfig::reversed_z`
+    pub fn view_projection(&self, aspect: f32, reversed_z: bool) -> Matrix4<f32> {
+        let view = Matrix4::look_at_rh(
+            Point3::from(self.eye),
+            Point3::from(self.target),
+            Vector3::unit_y(),
+        );
+        let projection =
+            cgmath::perspective(cgmath::Deg(self.fov_y_degrees), aspect, self.near, self.far);
+
+        let projection = OPENGL_TO_WGPU_MATRIX * projection;
+
+        if reversed_z {
+            REVERSED_Z_MATRIX * projection * view
+        } else {
+            projection * view
+        }
+    }
+
+    /// Where the camera looks, normalized
+    pub fn view_direction(&self) -> [f32; 3] {
+        (Point3::from(self.target) - Point3::from(self.eye))
+            .normalize()
+            .into()
+    }
+
+    /// Moves the eye and the target together in the XZ plane, `right` and `forward`
+    /// being along the view direction flattened onto the plane. Looking straight down,
+    /// forward is -Z
+    pub fn pan(&mut self, right: f32, forward: f32) {
+        let view_direction = Vector3::from(self.view_direction());
+        let flat = Vector3::new(view_direction.x, 0., view_direction.z);
+        let forward_axis = if flat.magnitude2() > 1e-6 {
+            flat.normalize()
+        } else {
+            -Vector3::unit_z()
+        };
+        let right_axis = forward_axis.cross(Vector3::unit_y());
+
+        self.translate((right_axis * right + forward_axis * forward).into());
+    }
+
+    /// The world direction `right` and `forward` add up to, forward being the view direction.
+    /// Unlike `pan`, going forward climbs when looking up
+    pub fn view_relative(&self, right: f32, forward: f32) -> [f32; 3] {
+        let forward_axis = Vector3::from(self.view_direction());
+        let right_axis = forward_axis.cross(Vector3::unit_y());
+        // Looking straight up or down, right is +X
+        let right_axis = if right_axis.magnitude2() > 1e-6 {
+            right_axis.normalize()
+        } else {
+            Vector3::unit_x()
+        };
+
+        (right_axis * right + forward_axis * forward).into()
+    }
+
+    /// Moves the eye and the target together
+    pub fn translate(&mut self, offset: [f32; 3]) {
+        let offset = Vector3::from(offset);
+        self.eye = (Point3::from(self.eye) + offset).into();
+        self.target = (Point3::from(self.target) + offset).into();
+    }
+}
+
+fn white() -> [f32; 3] {
+    [1.; 3]
+}
+
+fn one() -> f32 {
+    1.
+}
+
+fn default_fov_y() -> f32 {
+    45.
+}
+
+fn default_near() -> f32 {
+    0.1
+}
+
+fn default_far() -> f32 {
+    100.
+}
+
+// The file as written, with the meshes and the materials referenced by name
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    #[serde(default)]
+    meshes: Vec<MeshDescription>,
+    #[serde(default)]
+    materials: Vec<MaterialDescription>,
+    #[serde(default)]
+    objects: Vec<ObjectDescription>,
+    #[serde(default)]
+    lights: Vec<Light>,
+    camera: Option<Camera>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshDescription {
+    name: String,
+    #[serde(flatten)]
+    source: MeshSource,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MeshSource {
+    Primitive(Primitive),
+    /// An OBJ file, relative to the scene file
+    Path(PathBuf),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDescription {
+    name: String,
+    color: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectDescription {
+    mesh: String,
+    /// White when None
+    material: Option<String>,
+    #[serde(default)]
+    transform: Transform,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SceneMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SceneObject {
+    /// Index into `Scene::meshes`
+    pub mesh: usize,
+    pub color: [f32; 3],
+    pub transform: Transform,
+}
+
+/// A vertex of `Scene::bake`: a clip space position and a color.
+/// The texture coordinates are always 0, for the layout of `crate::Vertex`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BakedVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub tex_coords: [f32; 2],
+}
+
+/// Meshes placed in the world, with lights and a camera, described in a JSON file
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    pub meshes: Vec<SceneMesh>,
+    pub objects: Vec<SceneObject>,
+    pub lights: Vec<Light>,
+    /// The positions are already in clip space when None
+    pub camera: Option<Camera>,
+}
+
+impl Scene {
+    /// Loads the meshes too. The first entry failing is reported
+    pub fn from_json(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+        let path = path.as_ref();
+        let description: SceneDescription = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        let mut mesh_names = Vec::with_capacity(description.meshes.len());
+        let mut meshes = Vec::with_capacity(description.meshes.len());
+
+        for (i, mesh) in description.meshes.into_iter().enumerate() {
+            let entry = format!("meshes[{}]", i);
+
+            if mesh_names.contains(&mesh.name) {
+                return Err(SceneError::DuplicateName {
+                    entry,
+                    name: mesh.name,
+                });
+            }
+
+            meshes.push(match mesh.source {
+                MeshSource::Primitive(primitive) => primitive.mesh(),
+                MeshSource::Path(mesh_path) => {
+                    let loaded = obj::load_obj(directory.join(mesh_path))
+                        .map_err(|error| SceneError::Mesh { entry, error })?;
+
+                    SceneMesh {
+                        positions: loaded.positions,
+                        indices: loaded.indices,
+                    }
+                }
+            });
+            mesh_names.push(mesh.name);
+        }
+
+        let mut materials: Vec<(String, [f32; 3])> = Vec::new();
+
+        for (i, material) in description.materials.into_iter().enumerate() {
+            if materials.iter().any(|(name, _)| *name == material.name) {
+                return Err(SceneError::DuplicateName {
+                    entry: format!("materials[{}]", i),
+                    name: material.name,
+                });
+            }
+
+            materials.push((material.name, material.color));
+        }
+
+        let objects = description
+            .objects
+            .into_iter()
+            .enumerate()
+            .map(|(i, object)| {
+                let entry = format!("objects[{}]", i);
+
+                let mesh = mesh_names
+                    .iter()
+                    .position(|name| *name == object.mesh)
+                    .ok_or_else(|| SceneError::UnknownMesh {
+                        entry: entry.clone(),
+                        name: object.mesh.clone(),
+                    })?;
+
+                let color = match object.material {
+                    Some(material) => materials
+                        .iter()
+                        .find(|(name, _)| *name == material)
+                        .map(|(_, color)| *color)
+                        .ok_or(SceneError::UnknownMaterial {
+                            entry,
+                            name: material,
+                        })?,
+                    None => white(),
+                };
+
+                Ok(SceneObject {
+                    mesh,
+                    color,
+                    transform: object.transform,
+                })
+            })
+            .collect::<Result<_, SceneError>>()?;
+
+        Ok(Scene {
+            meshes,
+            objects,
+            lights: description.lights,
+            camera: description.camera,
+        })
+    }
+
+    /// The triangles of all the objects seen through the camera and flat shaded on the CPU,
+    /// for a pipeline without any uniforms. Triangles reaching behind the camera are dropped
+    pub fn bake(&self, aspect: f32, reversed_z: bool) -> Vec<BakedVertex> {
+        self.bake_in_order(aspect, reversed_z, 0..self.objects.len())
+    }
+
+    /// Like `bake`, with the objects at the indices of `order` in that order, e.g. sorted by a `DrawQueue`
+    pub fn bake_in_order(
+        &self,
+        aspect: f32,
+        reversed_z: bool,
+        order: impl IntoIterator<Item = usize>,
+    ) -> Vec<BakedVertex> {
+        let view_projection = self.camera.map_or(Matrix4::from_scale(1.), |camera| {
+            camera.view_projection(aspect, reversed_z)
+        });
+
+        let mut vertices = Vec::new();
+
+        for object in order.into_iter().map(|i| &self.objects[i]) {
+            let mesh = &self.meshes[object.mesh];
+            let model = object.transform.matrix();
+
+            for triangle in mesh.indices.as_chunks::<3>().0 {
+                let world = triangle.map(|index| {
+                    let [x, y, z] = mesh.positions[index as usize];
+                    Point3::from_homogeneous(model * Vector4::new(x, y, z, 1.))
+                });
+                let clip = world.map(|point| view_projection * point.to_homogeneous());
+
+                if clip.iter().any(|position| position.w <= f32::EPSILON) {
+                    continue;
+                }
+
+                let color = self.shade(object.color, world);
+
+                vertices.extend(clip.map(|position| BakedVertex {
+                    position: (position.truncate() / position.w).into(),
+                    color,
+                    tex_coords: [0., 0.],
+                }));
+            }
+        }
+
+        vertices
+    }
+
+    /// Around the object in the world, xyz - center, w - radius. See `culling::bounding_sphere`
+    pub fn bounding_sphere(&self, object: &SceneObject) -> [f32; 4] {
+        let [x, y, z, radius] =
+            crate::culling::bounding_sphere(&self.meshes[object.mesh].positions);
+        let center = Point3::from_homogeneous(
+            object.transform.matrix() * Point3::new(x, y, z).to_homogeneous(),
+        );
+
+        [
+            center.x,
+            center.y,
+            center.z,
+            radius * object.transform.scale.abs(),
+        ]
+    }
+
+    // Lambert lighting of the whole triangle, lit from both sides. Unlit without lights
+    fn shade(&self, color: [f32; 3], [a, b, c]: [Point3<f32>; 3]) -> [f32; 3] {
+        if self.lights.is_empty() {
+            return color;
+        }
+
+        let normal = (b - a).cross(c - a).normalize();
+        let center = Point3::centroid(&[a, b, c]);
+
+        let mut light = Vector3::new(AMBIENT, AMBIENT, AMBIENT);
+        for source in &self.lights {
+            let direction = (Point3::from(source.position) - center).normalize();
+            let diffuse = normal.dot(direction).abs() * source.intensity;
+
+            light += Vector3::from(source.color) * diffuse;
+        }
+
+        [
+            (color[0] * light.x).min(1.),
+            (color[1] * light.y).min(1.),
+            (color[2] * light.z).min(1.),
+        ]
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ScreenshotError {
+    /// The surface wasn't configured with `COPY_SRC`
+    NotCopyable,
+    /// Only 8 bit RGBA and BGRA surfaces can be saved
+    UnsupportedFormat(wgpu::TextureFormat),
+    Surface(wgpu::SurfaceError),
+    /// The frame was rendered, but its pixels couldn't be mapped
+    Readback,
+    Image(image::ImageError),
+}
+
+impl std::fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScreenshotError::NotCopyable => {
+                write!(f, "the surface doesn't allow reading its pixels")
+            }
+            ScreenshotError::UnsupportedFormat(format) => {
+                write!(f, "{:?} surfaces can't be saved", format)
+            }
+            ScreenshotError::Surface(e) => write!(f, "failed to render the frame: {}", e),
+            ScreenshotError::Readback => write!(f, "failed to read the frame back"),
+            ScreenshotError::Image(e) => write!(f, "failed to write the image: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ScreenshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScreenshotError::Surface(e) => Some(e),
+            ScreenshotError::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<wgpu::SurfaceError> for ScreenshotError {
+    fn from(e: wgpu::SurfaceError) -> ScreenshotError {
+        ScreenshotError::Surface(e)
+    }
+}
+
+impl From<image::ImageError> for ScreenshotError {
+    fn from(e: image::ImageError) -> ScreenshotError {
+        ScreenshotError::Image(e)
+    }
+}
+
+/// The formats `rgba_rows` can convert
+pub fn check_format(format: wgpu::TextureFormat) -> Result<(), ScreenshotError> {
+    match format.remove_srgb_suffix() {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Bgra8Unorm => Ok(()),
+        _ => Err(ScreenshotError::UnsupportedFormat(format)),
+    }
+}
+
+/// The bytes per row of a texture copy `width` pixels of 4 bytes wide. `copy_texture_to_buffer`
+/// needs them to be a multiple of `COPY_BYTES_PER_ROW_ALIGNMENT`, even for a single row
+pub fn padded_bytes_per_row(width: u32) -> u32 {
+    (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// The pixels of a copy with `padded_bytes_per_row` as RGBA, row after row without the padding.
+/// sRGB formats stay sRGB encoded, which is what image files expect
+pub fn rgba_rows(padded: &[u8], width: u32, height: u32, format: wgpu::TextureFormat) -> Vec<u8> {
+    let row_size = width as usize * 4;
+    let mut rgba = Vec::with_capacity(row_size * height as usize);
+
+    for row in padded
+        .chunks(padded_bytes_per_row(width) as usize)
+        .take(height as usize)
+    {
+        rgba.extend_from_slice(&row[..row_size]);
+    }
+
+    if format.remove_srgb_suffix() == wgpu::TextureFormat::Bgra8Unorm {
+        for pixel in rgba.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    rgba
+}
+
+/// Writes RGBA rows, e.g. from `rgba_rows`, to a PNG file
+pub fn save_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<(), ScreenshotError> {
+    image::save_buffer_with_format(
+        path,
+        rgba,
+        width,
+        height,
+        image::ColorType::Rgba8,
+        image::ImageFormat::Png,
+    )?;
+
+    Ok(())
+}
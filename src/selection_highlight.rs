@@ -0,0 +1,222 @@
+use crate::depth::{DepthTexture, StencilConfig};
+use crate::resources::{MeshId, ResourceManager};
+use crate::uniform_buffer::UniformBuffer;
+
+// The copies of a mesh the outline is made of, see the shader
+const OUTLINE_DIRECTIONS: u32 = 8;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SelectionParams {
+    color: [f32; 4],
+    viewport: [f32; 2],
+    thickness: f32,
+    _padding: f32,
+}
+
+/// Outlines the selected meshes of a `ResourceManager`, drawn over a finished frame.
+/// The meshes are first drawn into a stencil of its own as a mask, then drawn again dilated,
+/// only where the mask isn't, leaving the border. Their vertices are in clip space, like
+/// those of the material meshes
+pub struct SelectionHighlight {
+    mask_pipeline: wgpu::RenderPipeline,
+    outline_pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    params: UniformBuffer<SelectionParams>,
+    stencil: DepthTexture,
+    selected: Vec<MeshId>,
+}
+
+impl SelectionHighlight {
+    /// `width` and `height` are the target's, see `resize`. `thickness` is in pixels
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        color: [f32; 4],
+        thickness: f32,
+    ) -> SelectionHighlight {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My selection highlight shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("selection_highlight.wgsl").into()),
+        });
+
+        let params = UniformBuffer::new(
+            device,
+            "My selection highlight params buffer",
+            SelectionParams {
+                color,
+                viewport: [width.max(1) as f32, height.max(1) as f32],
+                thickness,
+                _padding: 0.,
+            },
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My selection highlight bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My selection highlight bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My selection highlight pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label: &str,
+                               vertex_entry_point: &str,
+                               write_mask: wgpu::ColorWrites,
+                               stencil: StencilConfig| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vertex_entry_point,
+                    buffers: &[crate::Vertex::layout().buffer_layout()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_outline",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Stencil8,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: stencil.state(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        // Only the stencil is written
+        let mask_pipeline = create_pipeline(
+            "My selection mask pipeline",
+            "vs_mask",
+            wgpu::ColorWrites::empty(),
+            StencilConfig::write_one(),
+        );
+        let outline_pipeline = create_pipeline(
+            "My selection outline pipeline",
+            "vs_outline",
+            wgpu::ColorWrites::ALL,
+            StencilConfig::read_not_one(),
+        );
+
+        SelectionHighlight {
+            mask_pipeline,
+            outline_pipeline,
+            bind_group,
+            params,
+            stencil: create_stencil(device, width, height),
+            selected: Vec::new(),
+        }
+    }
+
+    /// Replaces the selection. Ids of removed meshes are skipped when drawing
+    pub fn set_selected(&mut self, ids: &[MeshId]) {
+        self.selected = ids.to_vec();
+    }
+
+    pub fn selected(&self) -> &[MeshId] {
+        &self.selected
+    }
+
+    /// The size of the target, for the thickness to be in its pixels
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.stencil = create_stencil(device, width, height);
+        self.params.set(SelectionParams {
+            viewport: [width.max(1) as f32, height.max(1) as f32],
+            ..*self.params.value()
+        });
+    }
+
+    /// Outlines the selected meshes over `view`, whatever is in front of them
+    pub fn draw(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        resources: &ResourceManager,
+    ) {
+        let meshes: Vec<_> = self
+            .selected
+            .iter()
+            .filter_map(|&id| resources.mesh(id))
+            .collect();
+        if meshes.is_empty() {
+            return;
+        }
+
+        self.params.upload(queue);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My selection highlight pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: self.stencil.view(),
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_stencil_reference(1);
+
+        // All the masks first, so the outline of one mesh doesn't cover another one
+        render_pass.set_pipeline(&self.mask_pipeline);
+        for mesh in &meshes {
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer().slice());
+            render_pass.draw(0..mesh.vertices_count(), 0..1);
+        }
+
+        render_pass.set_pipeline(&self.outline_pipeline);
+        for mesh in &meshes {
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer().slice());
+            render_pass.draw(0..mesh.vertices_count(), 0..OUTLINE_DIRECTIONS);
+        }
+    }
+}
+
+fn create_stencil(device: &wgpu::Device, width: u32, height: u32) -> DepthTexture {
+    DepthTexture::new(device, width, height, 1, wgpu::TextureFormat::Stencil8)
+}
@@ -0,0 +1,33 @@
+// Outlines meshes already in clip space. The outline is the mesh shifted `thickness` pixels
+// in 8 directions, one per instance, drawn where the stencil doesn't mask the mesh itself
+
+struct SelectionParams {
+    color: vec4<f32>,
+    viewport: vec2<f32>,
+    thickness: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: SelectionParams;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+@vertex fn vs_mask(in: VertexInput) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(in.position, 1.);
+}
+
+@vertex fn vs_outline(
+    in: VertexInput,
+    @builtin(instance_index) instance: u32,
+) -> @builtin(position) vec4<f32> {
+    let angle = f32(instance) * 0.785398;
+    // Clip space spans 2 across the viewport
+    let offset = vec2<f32>(cos(angle), sin(angle)) * params.thickness * 2. / params.viewport;
+
+    return vec4<f32>(in.position.xy + offset, in.position.z, 1.);
+}
+
+@fragment fn fs_outline() -> @location(0) vec4<f32> {
+    return params.color;
+}
@@ -1,25 +1,128 @@
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
 }
 
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    // 1 with reversed Z, where the depth shrinks with the distance
+    near_depth: f32,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+// Positions, scales and rotates the geometry before the camera sees it
+@group(1) @binding(0) var<uniform> transform: mat4x4<f32>;
+
+@group(2) @binding(0) var diffuse_texture: texture_2d<f32>;
+@group(2) @binding(1) var diffuse_sampler: sampler;
+
 @vertex fn vs_main(
     model: VertexInput
 ) -> VertexOutput {
     var out: VertexOutput;
 
     out.color = model.color;
-    out.clip_position = vec4<f32>(model.position, 1.);
+    out.tex_coords = model.tex_coords;
+    out.clip_position = camera.view_proj * transform * vec4<f32>(model.position, 1.);
+
+    return out;
+}
+
+// The model matrix of an instance, a column per location after those of the vertex
+struct InstanceInput {
+    @location(3) model_0: vec4<f32>,
+    @location(4) model_1: vec4<f32>,
+    @location(5) model_2: vec4<f32>,
+    @location(6) model_3: vec4<f32>,
+}
+
+@vertex fn vs_instanced(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let instance_model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    var out: VertexOutput;
+
+    out.color = model.color;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = camera.view_proj * transform * instance_model * vec4<f32>(model.position, 1.);
 
     return out;
 }
 
 // @location(0) tells wgpu to store the returned value in the first color target
 @fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(diffuse_texture, diffuse_sampler, in.tex_coords);
+}
+
+// For the geometry without a texture
+@fragment fn fs_color(in: VertexOutput) -> @location(0) vec4<f32> {
     return vec4<f32>(in.color, 1.);
 }
+
+struct TransparentOutput {
+    @location(0) accum: vec4<f32>,
+    @location(1) revealage: vec4<f32>,
+}
+
+// Transparent geometry doesn't carry alpha in the vertices yet
+const TRANSPARENT_ALPHA: f32 = 0.5;
+
+// Weighted, blended OIT. The weight favors the fragments closer to the camera
+@fragment fn fs_transparent(in: VertexOutput) -> TransparentOutput {
+    var out: TransparentOutput;
+
+    let alpha = TRANSPARENT_ALPHA;
+    // 0 at the near plane and 1 at the far one, whatever the depth direction
+    let z = abs(in.clip_position.z - camera.near_depth);
+    let weight = clamp(pow(min(1., alpha * 10.) + 0.01, 3.) * 1e8 * pow(1. - z * 0.9, 3.), 1e-2, 3e3);
+
+    out.accum = vec4<f32>(in.color * alpha, alpha) * weight;
+    out.revealage = vec4<f32>(alpha);
+
+    return out;
+}
+
+struct OitParams {
+    width: u32,
+    height: u32,
+    node_capacity: u32,
+    max_fragments_per_pixel: u32,
+}
+
+struct OitNode {
+    color: vec4<f32>,
+    // From the near plane, see the resolve in linked_list_oit.wgsl
+    depth: f32,
+    next: u32, // 0 terminates the list, otherwise it's the node index + 1
+}
+
+// After the camera, the transform and the texture
+@group(3) @binding(0) var<uniform> oit_params: OitParams;
+@group(3) @binding(1) var<storage, read_write> oit_heads: array<atomic<u32>>;
+@group(3) @binding(2) var<storage, read_write> oit_nodes: array<OitNode>;
+@group(3) @binding(3) var<storage, read_write> oit_counter: atomic<u32>;
+
+// Exact OIT. Every fragment is pushed to the front of its pixel's list, the lists are sorted later
+@fragment fn fs_transparent_linked_list(in: VertexOutput) {
+    let node = atomicAdd(&oit_counter, 1u) + 1u;
+
+    // Out of nodes, the fragment is dropped
+    if node > oit_params.node_capacity {
+        return;
+    }
+
+    let pixel = vec2<u32>(in.clip_position.xy);
+    let next = atomicExchange(&oit_heads[pixel.y * oit_params.width + pixel.x], node);
+
+    // The distance from the near plane, which the resolve sorts by, whatever the depth direction
+    let distance = abs(in.clip_position.z - camera.near_depth);
+    oit_nodes[node - 1u] = OitNode(vec4<f32>(in.color, TRANSPARENT_ALPHA), distance, next);
+}
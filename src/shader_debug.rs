@@ -0,0 +1,58 @@
+#[derive(Debug)]
+pub enum ShaderDebugError {
+    Parse(String),
+    Validation(String),
+    Translation(naga::back::glsl::Error),
+}
+
+impl std::fmt::Display for ShaderDebugError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderDebugError::Parse(message) | ShaderDebugError::Validation(message) => {
+                write!(f, "{}", message)
+            }
+            ShaderDebugError::Translation(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ShaderDebugError {}
+
+/// The GLSL (ES 3.10, like wgpu's GL backend) naga translates an entry point of a WGSL shader to.
+/// A `wgpu::ShaderModule` doesn't keep its source, so the WGSL is passed instead
+pub fn dump_glsl(
+    source: &str,
+    stage: naga::ShaderStage,
+    entry: &str,
+) -> Result<String, ShaderDebugError> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|error| ShaderDebugError::Parse(error.emit_to_string(source)))?;
+
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|error| ShaderDebugError::Validation(error.emit_to_string(source)))?;
+
+    let options = naga::back::glsl::Options::default();
+    let pipeline_options = naga::back::glsl::PipelineOptions {
+        shader_stage: stage,
+        entry_point: entry.to_owned(),
+        multiview: None,
+    };
+
+    let mut glsl = String::new();
+    naga::back::glsl::Writer::new(
+        &mut glsl,
+        &module,
+        &info,
+        &options,
+        &pipeline_options,
+        naga::proc::BoundsCheckPolicies::default(),
+    )
+    .and_then(|mut writer| writer.write())
+    .map_err(ShaderDebugError::Translation)?;
+
+    Ok(glsl)
+}
@@ -0,0 +1,303 @@
+use std::num::NonZeroU32;
+
+#[derive(Debug)]
+pub enum ShaderReflectionError {
+    Parse(String),
+    Validation(String),
+    /// A resource naga accepts, but wgpu can't bind
+    UnsupportedBinding {
+        group: u32,
+        binding: u32,
+    },
+}
+
+impl std::fmt::Display for ShaderReflectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderReflectionError::Parse(message) | ShaderReflectionError::Validation(message) => {
+                write!(f, "{}", message)
+            }
+            ShaderReflectionError::UnsupportedBinding { group, binding } => write!(
+                f,
+                "@group({}) @binding({}) can't be bound by wgpu",
+                group, binding
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShaderReflectionError {}
+
+/// A resource binding the shader declares
+#[derive(Clone, Debug)]
+pub struct ReflectedBinding {
+    pub name: Option<String>,
+    pub group: u32,
+    pub binding: u32,
+    pub ty: wgpu::BindingType,
+    /// Stages of the entry points using the binding
+    pub visibility: wgpu::ShaderStages,
+    /// Set for binding arrays
+    pub count: Option<NonZeroU32>,
+}
+
+impl ReflectedBinding {
+    pub fn layout_entry(&self) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding: self.binding,
+            visibility: self.visibility,
+            ty: self.ty,
+            count: self.count,
+        }
+    }
+}
+
+/// The bindings of a WGSL shader, read from the source instead of being repeated by hand
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    // Sorted by group, then by binding
+    bindings: Vec<ReflectedBinding>,
+    entry_points: Vec<String>,
+}
+
+impl ShaderReflection {
+    pub fn from_wgsl(source: &str) -> Result<ShaderReflection, ShaderReflectionError> {
+        let module = naga::front::wgsl::parse_str(source)
+            .map_err(|error| ShaderReflectionError::Parse(error.emit_to_string(source)))?;
+
+        // The validation also works out which entry points use which globals
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .map_err(|error| ShaderReflectionError::Validation(error.emit_to_string(source)))?;
+
+        let mut bindings = Vec::new();
+
+        for (handle, global) in module.global_variables.iter() {
+            let Some(resource_binding) = &global.binding else {
+                continue;
+            };
+
+            let mut visibility = wgpu::ShaderStages::NONE;
+            for (index, entry_point) in module.entry_points.iter().enumerate() {
+                if !info.get_entry_point(index)[handle].is_empty() {
+                    visibility |= match entry_point.stage {
+                        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+                    };
+                }
+            }
+
+            let unsupported = || ShaderReflectionError::UnsupportedBinding {
+                group: resource_binding.group,
+                binding: resource_binding.binding,
+            };
+
+            let (inner, count) = match module.types[global.ty].inner {
+                naga::TypeInner::BindingArray { base, size } => {
+                    let count = match size {
+                        naga::ArraySize::Constant(size) => NonZeroU32::new(size.get()),
+                        naga::ArraySize::Dynamic => return Err(unsupported()),
+                    };
+
+                    (&module.types[base].inner, count)
+                }
+                ref inner => (inner, None),
+            };
+
+            let ty = match global.space {
+                naga::AddressSpace::Uniform => wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(inner.size(module.to_ctx()) as u64),
+                },
+                // Runtime sized arrays have no fixed size, so it's checked when drawing
+                naga::AddressSpace::Storage { access } => wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage {
+                        read_only: !access.contains(naga::StorageAccess::STORE),
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                naga::AddressSpace::Handle => match *inner {
+                    naga::TypeInner::Sampler { comparison: true } => {
+                        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison)
+                    }
+                    naga::TypeInner::Sampler { comparison: false } => {
+                        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+                    }
+                    naga::TypeInner::Image {
+                        dim,
+                        arrayed,
+                        class,
+                    } => image_binding_type(dim, arrayed, class).ok_or_else(unsupported)?,
+                    _ => return Err(unsupported()),
+                },
+                _ => return Err(unsupported()),
+            };
+
+            bindings.push(ReflectedBinding {
+                name: global.name.clone(),
+                group: resource_binding.group,
+                binding: resource_binding.binding,
+                ty,
+                visibility,
+                count,
+            });
+        }
+
+        bindings.sort_by_key(|binding| (binding.group, binding.binding));
+
+        let entry_points = module
+            .entry_points
+            .iter()
+            .map(|entry_point| entry_point.name.clone())
+            .collect();
+
+        Ok(ShaderReflection {
+            bindings,
+            entry_points,
+        })
+    }
+
+    pub fn bindings(&self) -> &[ReflectedBinding] {
+        &self.bindings
+    }
+
+    /// Whether a pipeline can be created with `name` as its entry point, of whatever stage
+    pub fn has_entry_point(&self, name: &str) -> bool {
+        self.entry_points
+            .iter()
+            .any(|entry_point| entry_point == name)
+    }
+
+    /// How many bind groups a pipeline layout for the shader needs, including the unused ones in between
+    pub fn bind_groups_count(&self) -> u32 {
+        self.bindings.last().map_or(0, |binding| binding.group + 1)
+    }
+
+    pub fn bind_group_layout_entries(&self, group: u32) -> Vec<wgpu::BindGroupLayoutEntry> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.group == group)
+            .map(ReflectedBinding::layout_entry)
+            .collect()
+    }
+
+    /// The layout of `@group(group)`. Empty if the shader doesn't use the group
+    pub fn create_bind_group_layout(
+        &self,
+        device: &wgpu::Device,
+        group: u32,
+    ) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My reflected bind group layout"),
+            entries: &self.bind_group_layout_entries(group),
+        })
+    }
+
+    /// One layout per group, indexable by the group number
+    pub fn create_bind_group_layouts(&self, device: &wgpu::Device) -> Vec<wgpu::BindGroupLayout> {
+        (0..self.bind_groups_count())
+            .map(|group| self.create_bind_group_layout(device, group))
+            .collect()
+    }
+}
+
+fn image_binding_type(
+    dim: naga::ImageDimension,
+    arrayed: bool,
+    class: naga::ImageClass,
+) -> Option<wgpu::BindingType> {
+    let view_dimension = match (dim, arrayed) {
+        (naga::ImageDimension::D1, false) => wgpu::TextureViewDimension::D1,
+        (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+        (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+        (naga::ImageDimension::D3, false) => wgpu::TextureViewDimension::D3,
+        (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+        (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+        _ => return None,
+    };
+
+    Some(match class {
+        naga::ImageClass::Sampled { kind, multi } => wgpu::BindingType::Texture {
+            sample_type: match kind {
+                // Whether it's sampled with filtering isn't known, but multisampled textures can't be filtered
+                naga::ScalarKind::Float => wgpu::TextureSampleType::Float { filterable: !multi },
+                naga::ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                naga::ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                _ => return None,
+            },
+            view_dimension,
+            multisampled: multi,
+        },
+        naga::ImageClass::Depth { multi } => wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension,
+            multisampled: multi,
+        },
+        naga::ImageClass::Storage { format, access } => wgpu::BindingType::StorageTexture {
+            access: match (
+                access.contains(naga::StorageAccess::LOAD),
+                access.contains(naga::StorageAccess::STORE),
+            ) {
+                (true, true) => wgpu::StorageTextureAccess::ReadWrite,
+                (true, false) => wgpu::StorageTextureAccess::ReadOnly,
+                _ => wgpu::StorageTextureAccess::WriteOnly,
+            },
+            format: storage_texture_format(format),
+            view_dimension,
+        },
+    })
+}
+
+fn storage_texture_format(format: naga::StorageFormat) -> wgpu::TextureFormat {
+    use naga::StorageFormat as S;
+    use wgpu::TextureFormat as T;
+
+    match format {
+        S::R8Unorm => T::R8Unorm,
+        S::R8Snorm => T::R8Snorm,
+        S::R8Uint => T::R8Uint,
+        S::R8Sint => T::R8Sint,
+        S::R16Uint => T::R16Uint,
+        S::R16Sint => T::R16Sint,
+        S::R16Float => T::R16Float,
+        S::Rg8Unorm => T::Rg8Unorm,
+        S::Rg8Snorm => T::Rg8Snorm,
+        S::Rg8Uint => T::Rg8Uint,
+        S::Rg8Sint => T::Rg8Sint,
+        S::R32Uint => T::R32Uint,
+        S::R32Sint => T::R32Sint,
+        S::R32Float => T::R32Float,
+        S::Rg16Uint => T::Rg16Uint,
+        S::Rg16Sint => T::Rg16Sint,
+        S::Rg16Float => T::Rg16Float,
+        S::Rgba8Unorm => T::Rgba8Unorm,
+        S::Rgba8Snorm => T::Rgba8Snorm,
+        S::Rgba8Uint => T::Rgba8Uint,
+        S::Rgba8Sint => T::Rgba8Sint,
+        S::Bgra8Unorm => T::Bgra8Unorm,
+        S::Rgb10a2Uint => T::Rgb10a2Uint,
+        S::Rgb10a2Unorm => T::Rgb10a2Unorm,
+        S::Rg11b10Float => T::Rg11b10Float,
+        S::Rg32Uint => T::Rg32Uint,
+        S::Rg32Sint => T::Rg32Sint,
+        S::Rg32Float => T::Rg32Float,
+        S::Rgba16Uint => T::Rgba16Uint,
+        S::Rgba16Sint => T::Rgba16Sint,
+        S::Rgba16Float => T::Rgba16Float,
+        S::Rgba32Uint => T::Rgba32Uint,
+        S::Rgba32Sint => T::Rgba32Sint,
+        S::Rgba32Float => T::Rgba32Float,
+        S::R16Unorm => T::R16Unorm,
+        S::R16Snorm => T::R16Snorm,
+        S::Rg16Unorm => T::Rg16Unorm,
+        S::Rg16Snorm => T::Rg16Snorm,
+        S::Rgba16Unorm => T::Rgba16Unorm,
+        S::Rgba16Snorm => T::Rgba16Snorm,
+    }
+}
@@ -0,0 +1,265 @@
+use crate::Vertex;
+
+/// Vertices and the indices of their triangles, counter-clockwise with Y going up.
+/// The vertices are white, at z 0
+pub type Mesh = (Vec<Vertex>, Vec<u32>);
+
+// The longest a polyline miter may be, in multiples of half the width
+const MITER_LIMIT: f32 = 4.;
+
+/// `x` and `y` are the bottom left corner. `radius` is capped at half the shorter side,
+/// `segments` is per corner
+pub fn rounded_rect(x: f32, y: f32, w: f32, h: f32, radius: f32, segments: u32) -> Mesh {
+    let radius = radius.clamp(0., w.min(h) / 2.);
+    let segments = segments.max(1);
+
+    // Counter-clockwise from the bottom right corner
+    let corners = [
+        ([x + w - radius, y + radius], -0.25),
+        ([x + w - radius, y + h - radius], 0.),
+        ([x + radius, y + h - radius], 0.25),
+        ([x + radius, y + radius], 0.5),
+    ];
+
+    let mut outline = Vec::with_capacity(4 * (segments as usize + 1));
+    for (center, start_turn) in corners {
+        for i in 0..=segments {
+            let angle = (start_turn + 0.25 * i as f32 / segments as f32) * std::f32::consts::TAU;
+            let (sin, cos) = angle.sin_cos();
+
+            outline.push([center[0] + radius * cos, center[1] + radius * sin]);
+        }
+    }
+
+    fan([x + w / 2., y + h / 2.], &outline, true)
+}
+
+/// A pie slice, counter-clockwise from `start_angle` to `end_angle`, in radians
+pub fn arc(cx: f32, cy: f32, r: f32, start_angle: f32, end_angle: f32, segments: u32) -> Mesh {
+    let segments = segments.max(1);
+
+    let outline: Vec<_> = (0..=segments)
+        .map(|i| {
+            let angle = start_angle + (end_angle - start_angle) * i as f32 / segments as f32;
+            let (sin, cos) = angle.sin_cos();
+
+            [cx + r * cos, cy + r * sin]
+        })
+        .collect();
+
+    // A clockwise sweep would wind the triangles the other way
+    let (vertices, mut indices) = fan([cx, cy], &outline, false);
+    if end_angle < start_angle {
+        for triangle in indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+
+    (vertices, indices)
+}
+
+/// A line of `width` through the points, with mitered joins.
+/// Joins sharper than the miter limit are cut short instead of spiking. Consecutive points must differ
+pub fn polyline(points: &[[f32; 2]], width: f32) -> Mesh {
+    if points.len() < 2 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let half_width = width / 2.;
+    let normal = |a: [f32; 2], b: [f32; 2]| {
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let length = dx.hypot(dy);
+
+        [-dy / length, dx / length]
+    };
+
+    let mut vertices = Vec::with_capacity(points.len() * 2);
+    for (i, &point) in points.iter().enumerate() {
+        let before = (i > 0).then(|| normal(points[i - 1], point));
+        let after = (i + 1 < points.len()).then(|| normal(point, points[i + 1]));
+
+        let offset = match (before, after) {
+            (Some(n), None) | (None, Some(n)) => [n[0] * half_width, n[1] * half_width],
+            (Some(a), Some(b)) => {
+                let sum = [a[0] + b[0], a[1] + b[1]];
+                let length = sum[0].hypot(sum[1]);
+
+                if length < 1e-6 {
+                    // A U-turn, the miter would be infinite
+                    [b[0] * half_width, b[1] * half_width]
+                } else {
+                    let miter = [sum[0] / length, sum[1] / length];
+                    let scale = (1. / (miter[0] * b[0] + miter[1] * b[1])).min(MITER_LIMIT);
+
+                    [miter[0] * half_width * scale, miter[1] * half_width * scale]
+                }
+            }
+            (None, None) => unreachable!("there are at least 2 points"),
+        };
+
+        vertices.push(vertex([point[0] + offset[0], point[1] + offset[1]]));
+        vertices.push(vertex([point[0] - offset[0], point[1] - offset[1]]));
+    }
+
+    // The left side is at even indices, the right one at odd ones
+    let indices = (0..points.len() as u32 - 1)
+        .flat_map(|i| {
+            let (left, right) = (2 * i, 2 * i + 1);
+
+            [right, right + 2, left + 2, right, left + 2, left]
+        })
+        .collect();
+
+    (vertices, indices)
+}
+
+/// A simple polygon, convex or not, in either winding. Ear clipping, so it must not intersect itself
+pub fn polygon(points: &[[f32; 2]]) -> Mesh {
+    let vertices: Vec<_> = points.iter().copied().map(vertex).collect();
+    if points.len() < 3 {
+        return (vertices, Vec::new());
+    }
+
+    // Walked counter-clockwise, so the ears are the convex corners
+    let mut remaining: Vec<u32> = (0..points.len() as u32).collect();
+    if signed_area(points) < 0. {
+        remaining.reverse();
+    }
+
+    let point = |index: u32| points[index as usize];
+    let mut indices = Vec::with_capacity((points.len() - 2) * 3);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let [a, b, c] = [
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            ];
+
+            cross(point(a), point(b), point(c)) > 0.
+                && !remaining.iter().any(|&other| {
+                    ![a, b, c].contains(&other)
+                        && contains(point(a), point(b), point(c), point(other))
+                })
+        });
+
+        // Only degenerate polygons have no ear, the rest is clipped as it is
+        let i = ear.unwrap_or(0);
+        indices.extend([
+            remaining[(i + count - 1) % count],
+            remaining[i],
+            remaining[(i + 1) % count],
+        ]);
+        remaining.remove(i);
+    }
+    indices.extend(remaining);
+
+    (vertices, indices)
+}
+
+// Triangles from `center` to each pair of consecutive outline points
+fn fan(center: [f32; 2], outline: &[[f32; 2]], closed: bool) -> Mesh {
+    let mut vertices = Vec::with_capacity(outline.len() + 1);
+    vertices.push(vertex(center));
+    vertices.extend(outline.iter().copied().map(vertex));
+
+    let outline_count = outline.len() as u32;
+    let edges = if closed {
+        outline_count
+    } else {
+        outline_count - 1
+    };
+    let indices = (0..edges)
+        .flat_map(|i| [0, 1 + i, 1 + (i + 1) % outline_count])
+        .collect();
+
+    (vertices, indices)
+}
+
+fn vertex([x, y]: [f32; 2]) -> Vertex {
+    Vertex {
+        position: [x, y, 0.],
+        color: [1., 1., 1.],
+        tex_coords: [0., 0.],
+    }
+}
+
+// Positive for counter-clockwise points
+fn signed_area(points: &[[f32; 2]]) -> f32 {
+    let edges = points.iter().zip(points.iter().cycle().skip(1));
+
+    edges.map(|(a, b)| a[0] * b[1] - b[0] * a[1]).sum::<f32>() / 2.
+}
+
+// Positive when a, b, c turn left
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+// For the counter-clockwise triangle a, b, c, edges included
+fn contains(a: [f32; 2], b: [f32; 2], c: [f32; 2], point: [f32; 2]) -> bool {
+    cross(a, b, point) >= 0. && cross(b, c, point) >= 0. && cross(c, a, point) >= 0.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The areas of the triangles, checking every index is in range
+    fn triangle_areas((vertices, indices): &Mesh) -> Vec<f32> {
+        assert_eq!(indices.len() % 3, 0);
+
+        indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| {
+                    let position = vertices[triangle[i] as usize].position;
+
+                    [position[0], position[1]]
+                });
+
+                cross(a, b, c) / 2.
+            })
+            .collect()
+    }
+
+    #[test]
+    fn triangulates_a_convex_polygon() {
+        let square = [[0., 0.], [2., 0.], [2., 2.], [0., 2.]];
+
+        let areas = triangle_areas(&polygon(&square));
+        assert_eq!(areas.len(), 2);
+        assert!(areas.iter().all(|&area| area > 0.));
+        assert_eq!(areas.iter().sum::<f32>(), 4.);
+    }
+
+    #[test]
+    fn triangulates_a_concave_polygon_in_either_winding() {
+        // An L, its inner corner at (1, 1)
+        let mut l = vec![[0., 0.], [2., 0.], [2., 1.], [1., 1.], [1., 2.], [0., 2.]];
+
+        for _ in 0..2 {
+            let areas = triangle_areas(&polygon(&l));
+            assert_eq!(areas.len(), 4);
+            // Counter-clockwise whatever the winding of the points, none covering the notch
+            assert!(areas.iter().all(|&area| area > 0.));
+            assert_eq!(areas.iter().sum::<f32>(), 3.);
+
+            l.reverse();
+        }
+    }
+
+    #[test]
+    fn triangulates_degenerate_polygons() {
+        assert!(polygon(&[]).1.is_empty());
+        assert!(polygon(&[[0., 0.], [1., 1.]]).1.is_empty());
+
+        // Every point on a line, there are no ears but the indices still cover it
+        let line = [[0., 0.], [1., 0.], [2., 0.], [3., 0.]];
+        let areas = triangle_areas(&polygon(&line));
+        assert_eq!(areas.len(), 2);
+        assert!(areas.iter().all(|&area| area == 0.));
+    }
+}
@@ -0,0 +1,45 @@
+// Writes a wavy ribbon as a triangle strip, a vertex above and below each sample of a sine.
+// The vertices are packed as 3 floats of position, 3 of color and 2 of texture coordinates
+
+struct SineWaveParams {
+    // The left end, in clip space
+    origin: vec2<f32>,
+    length: f32,
+    amplitude: f32,
+    // Full periods along the length
+    periods: f32,
+    thickness: f32,
+    samples_count: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: SineWaveParams;
+@group(0) @binding(1) var<storage, read_write> vertices: array<f32>;
+
+const FLOATS_PER_VERTEX: u32 = 8u;
+const TAU: f32 = 6.283185;
+
+@compute @workgroup_size(64) fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let sample = id.x;
+    if sample >= params.samples_count {
+        return;
+    }
+
+    let t = f32(sample) / f32(max(params.samples_count, 2u) - 1u);
+    let x = params.origin.x + t * params.length;
+    let y = params.origin.y + params.amplitude * sin(t * params.periods * TAU);
+    let color = vec3<f32>(t, 0.4, 1. - t);
+
+    for (var side = 0u; side < 2u; side++) {
+        let base = (sample * 2u + side) * FLOATS_PER_VERTEX;
+        let offset = select(0.5, -0.5, side == 1u) * params.thickness;
+
+        vertices[base] = x;
+        vertices[base + 1u] = y + offset;
+        vertices[base + 2u] = 0.;
+        vertices[base + 3u] = color.r;
+        vertices[base + 4u] = color.g;
+        vertices[base + 5u] = color.b;
+        vertices[base + 6u] = t;
+        vertices[base + 7u] = f32(side);
+    }
+}
@@ -0,0 +1,314 @@
+use cgmath::{InnerSpace, Matrix4, Quaternion, Rotation, SquareMatrix, Vector3};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct JointId(pub usize);
+
+#[derive(Clone, Debug)]
+struct Joint {
+    parent: Option<JointId>,
+    translation: Vector3<f32>,
+    rotation: Quaternion<f32>,
+    inverse_bind: Matrix4<f32>,
+}
+
+/// A hierarchy of joints. Parents are always added before their children
+#[derive(Clone, Debug, Default)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    pub fn new() -> Skeleton {
+        Skeleton::default()
+    }
+
+    /// The joint's bind pose is its transform at the moment it's added
+    pub fn add_joint(
+        &mut self,
+        parent: Option<JointId>,
+        translation: [f32; 3],
+        rotation: [f32; 4],
+    ) -> JointId {
+        let [x, y, z, w] = rotation;
+        let joint = Joint {
+            parent,
+            translation: translation.into(),
+            rotation: Quaternion::new(w, x, y, z),
+            inverse_bind: Matrix4::identity(),
+        };
+
+        let parent_world = parent.map_or(Matrix4::identity(), |parent| self.world_matrix(parent));
+        let world = parent_world * local_matrix(&joint);
+
+        self.joints.push(Joint {
+            inverse_bind: world.invert().unwrap_or(Matrix4::identity()),
+            ..joint
+        });
+
+        JointId(self.joints.len() - 1)
+    }
+
+    pub fn joints_count(&self) -> usize {
+        self.joints.len()
+    }
+
+    pub fn parent(&self, joint: JointId) -> Option<JointId> {
+        self.joints[joint.0].parent
+    }
+
+    /// Translation relative to the parent
+    pub fn local_translation(&self, joint: JointId) -> [f32; 3] {
+        self.joints[joint.0].translation.into()
+    }
+
+    pub fn set_local_translation(&mut self, joint: JointId, translation: [f32; 3]) {
+        self.joints[joint.0].translation = translation.into();
+    }
+
+    /// Rotation relative to the parent, as `[x, y, z, w]`
+    pub fn local_rotation(&self, joint: JointId) -> [f32; 4] {
+        let rotation = self.joints[joint.0].rotation;
+
+        [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s]
+    }
+
+    /// Rotation relative to the parent, as `[x, y, z, w]`
+    pub fn set_local_rotation(&mut self, joint: JointId, rotation: [f32; 4]) {
+        let [x, y, z, w] = rotation;
+        self.joints[joint.0].rotation = Quaternion::new(w, x, y, z);
+    }
+
+    pub fn world_position(&self, joint: JointId) -> [f32; 3] {
+        self.world_matrix(joint).w.truncate().into()
+    }
+
+    /// As `[x, y, z, w]`
+    pub fn world_orientation(&self, joint: JointId) -> [f32; 4] {
+        let rotation = self.world_rotation(joint);
+
+        [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s]
+    }
+
+    /// The palette for `skinning::SkinningPass::update_joints`
+    pub fn joint_matrices(&self) -> Vec<[[f32; 4]; 4]> {
+        let mut world = Vec::<Matrix4<f32>>::with_capacity(self.joints.len());
+
+        for joint in &self.joints {
+            let parent_world = joint
+                .parent
+                .map_or(Matrix4::identity(), |parent| world[parent.0]);
+            world.push(parent_world * local_matrix(joint));
+        }
+
+        world
+            .iter()
+            .zip(&self.joints)
+            .map(|(world, joint)| (world * joint.inverse_bind).into())
+            .collect()
+    }
+
+    fn world_matrix(&self, joint: JointId) -> Matrix4<f32> {
+        let local = local_matrix(&self.joints[joint.0]);
+
+        match self.joints[joint.0].parent {
+            Some(parent) => self.world_matrix(parent) * local,
+            None => local,
+        }
+    }
+
+    fn world_rotation(&self, joint: JointId) -> Quaternion<f32> {
+        let rotation = self.joints[joint.0].rotation;
+
+        match self.joints[joint.0].parent {
+            Some(parent) => self.world_rotation(parent) * rotation,
+            None => rotation,
+        }
+    }
+}
+
+fn local_matrix(joint: &Joint) -> Matrix4<f32> {
+    Matrix4::from_translation(joint.translation) * Matrix4::from(joint.rotation)
+}
+
+/// A chain of joints from `root_joint` down to `tip_joint` solved with FABRIK
+/// (Forward And Backward Reaching Inverse Kinematics)
+#[derive(Clone, Debug)]
+pub struct IkChain {
+    // From the root to the tip
+    joints: Vec<JointId>,
+    // Distances between the neighboring joints
+    lengths: Vec<f32>,
+}
+
+impl IkChain {
+    // The tip is considered at the target when it's this close
+    const TOLERANCE: f32 = 1e-3;
+
+    /// `chain_length` is the number of bones between the root and the tip.
+    /// Panics if `tip_joint` isn't exactly that many bones below `root_joint`
+    pub fn new(
+        skeleton: &Skeleton,
+        tip_joint: JointId,
+        root_joint: JointId,
+        chain_length: usize,
+    ) -> IkChain {
+        let mut joints = vec![tip_joint];
+
+        while joints.len() <= chain_length {
+            let parent = skeleton
+                .parent(joints[joints.len() - 1])
+                .expect("the chain is longer than the hierarchy above the tip");
+            joints.push(parent);
+        }
+
+        assert_eq!(
+            joints[chain_length], root_joint,
+            "the root joint isn't {} bones above the tip",
+            chain_length
+        );
+
+        joints.reverse();
+
+        let positions = IkChain::positions(skeleton, &joints);
+        let lengths = positions
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).magnitude())
+            .collect();
+
+        IkChain { joints, lengths }
+    }
+
+    /// Rotates the chain's joints so the tip reaches `target` (in world space), if possible.
+    /// Returns whether the tip got close enough before running out of iterations
+    pub fn solve(&self, skeleton: &mut Skeleton, target: [f32; 3], iterations: u32) -> bool {
+        let target = Vector3::from(target);
+        let mut positions = IkChain::positions(skeleton, &self.joints);
+        let root = positions[0];
+        let tip = positions.len() - 1;
+
+        let reach: f32 = self.lengths.iter().sum();
+
+        if (target - root).magnitude() > reach {
+            // Unreachable, the best we can do is to point straight at the target
+            let direction = (target - root).normalize();
+
+            for i in 0..tip {
+                positions[i + 1] = positions[i] + direction * self.lengths[i];
+            }
+        } else {
+            for _ in 0..iterations {
+                if (positions[tip] - target).magnitude() < IkChain::TOLERANCE {
+                    break;
+                }
+
+                // Backward: drag the tip to the target, pulling the rest of the chain along
+                positions[tip] = target;
+                for i in (0..tip).rev() {
+                    let direction = (positions[i] - positions[i + 1]).normalize();
+                    positions[i] = positions[i + 1] + direction * self.lengths[i];
+                }
+
+                // Forward: put the root back, pushing the rest of the chain along
+                positions[0] = root;
+                for i in 0..tip {
+                    let direction = (positions[i + 1] - positions[i]).normalize();
+                    positions[i + 1] = positions[i] + direction * self.lengths[i];
+                }
+            }
+        }
+
+        self.apply(skeleton, &positions);
+
+        (IkChain::positions(skeleton, &self.joints)[tip] - target).magnitude() < IkChain::TOLERANCE
+    }
+
+    // Turns the solved positions back into joint rotations, from the root down
+    fn apply(&self, skeleton: &mut Skeleton, positions: &[Vector3<f32>]) {
+        for i in 0..self.joints.len() - 1 {
+            let current = IkChain::positions(skeleton, &self.joints);
+
+            let from = (current[i + 1] - current[i]).normalize();
+            let to = (positions[i + 1] - positions[i]).normalize();
+
+            let joint = self.joints[i];
+            let world_rotation =
+                Quaternion::between_vectors(from, to) * skeleton.world_rotation(joint);
+            let parent_rotation = skeleton
+                .parent(joint)
+                .map_or(Quaternion::new(1., 0., 0., 0.), |parent| {
+                    skeleton.world_rotation(parent)
+                });
+
+            skeleton.joints[joint.0].rotation =
+                (parent_rotation.invert() * world_rotation).normalize();
+        }
+    }
+
+    fn positions(skeleton: &Skeleton, joints: &[JointId]) -> Vec<Vector3<f32>> {
+        joints
+            .iter()
+            .map(|&joint| Vector3::from(skeleton.world_position(joint)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY: [f32; 4] = [0., 0., 0., 1.];
+
+    // Two bones of length 1 going up from the origin
+    fn arm() -> (Skeleton, IkChain, [JointId; 3]) {
+        let mut skeleton = Skeleton::new();
+        let shoulder = skeleton.add_joint(None, [0., 0., 0.], IDENTITY);
+        let elbow = skeleton.add_joint(Some(shoulder), [0., 1., 0.], IDENTITY);
+        let hand = skeleton.add_joint(Some(elbow), [0., 1., 0.], IDENTITY);
+        let chain = IkChain::new(&skeleton, hand, shoulder, 2);
+
+        (skeleton, chain, [shoulder, elbow, hand])
+    }
+
+    fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+        (Vector3::from(a) - Vector3::from(b)).magnitude()
+    }
+
+    #[test]
+    fn reaches_a_target_within_reach() {
+        let (mut skeleton, chain, [shoulder, _, hand]) = arm();
+
+        let target = [1., 1., 0.5];
+        assert!(chain.solve(&mut skeleton, target, 20));
+        assert!(distance(skeleton.world_position(hand), target) < IkChain::TOLERANCE);
+        assert_eq!(skeleton.world_position(shoulder), [0., 0., 0.]);
+    }
+
+    #[test]
+    fn points_straight_at_a_target_out_of_reach() {
+        let (mut skeleton, chain, [_, elbow, hand]) = arm();
+
+        assert!(!chain.solve(&mut skeleton, [5., 0., 0.], 20));
+        assert!(distance(skeleton.world_position(elbow), [1., 0., 0.]) < 1e-5);
+        assert!(distance(skeleton.world_position(hand), [2., 0., 0.]) < 1e-5);
+    }
+
+    #[test]
+    fn keeps_the_bone_lengths() {
+        let (mut skeleton, chain, [shoulder, elbow, hand]) = arm();
+
+        for target in [[1., 1., 0.5], [-0.5, 0.3, 1.2], [5., 0., 0.]] {
+            chain.solve(&mut skeleton, target, 20);
+
+            let upper = distance(
+                skeleton.world_position(shoulder),
+                skeleton.world_position(elbow),
+            );
+            let lower = distance(
+                skeleton.world_position(elbow),
+                skeleton.world_position(hand),
+            );
+            assert!((upper - 1.).abs() < 1e-5, "upper arm is {}", upper);
+            assert!((lower - 1.).abs() < 1e-5, "forearm is {}", lower);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+// Draws the output of the skinning pass, shaded by its normals
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+}
+
+// The uniforms of groups 0 and 1 of shader.wgsl
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    near_depth: f32,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraUniform;
+@group(0) @binding(1) var<uniform> transform: mat4x4<f32>;
+
+@vertex fn vs_main(
+    model: VertexInput
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.normal = (transform * vec4<f32>(model.normal, 0.)).xyz;
+    out.clip_position = camera.view_proj * transform * vec4<f32>(model.position, 1.);
+
+    return out;
+}
+
+// Lit from the viewer, the faces turned away being the darkest
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let light = 0.2 + 0.8 * abs(normalize(in.normal).z);
+
+    return vec4<f32>(vec3<f32>(light), 1.);
+}
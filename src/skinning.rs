@@ -0,0 +1,493 @@
+use wgpu::util::DeviceExt;
+
+use crate::bind_group_builder::BindGroupBuilder;
+use crate::bindable::Bindable;
+use crate::depth::DepthConfig;
+use crate::shader_reflection::ShaderReflection;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// How many joints can influence a single vertex
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MaxInfluences {
+    Two,
+    #[default]
+    Four,
+    Eight,
+}
+
+impl MaxInfluences {
+    pub fn count(self) -> usize {
+        match self {
+            MaxInfluences::Two => 2,
+            MaxInfluences::Four => 4,
+            MaxInfluences::Eight => 8,
+        }
+    }
+
+    /// Size of a packed `SkinVertex`: padded position and normal, joints, weights.
+    /// Always a multiple of 16 like the WGSL struct
+    pub fn vertex_size(self) -> usize {
+        let vec4_size = std::mem::size_of::<[f32; 4]>();
+
+        2 * vec4_size + 2 * self.count() * std::mem::size_of::<u32>()
+    }
+
+    /// Attributes of a vertex buffer of packed `SkinVertex`es.
+    /// Position and normal go first, then the joints and the weights, in groups of up to 4
+    pub fn vertex_attributes(self) -> Vec<wgpu::VertexAttribute> {
+        let vec4_size = std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress;
+        let joints_offset = 2 * vec4_size;
+        let weights_offset = joints_offset + (self.count() * std::mem::size_of::<u32>()) as u64;
+
+        let mut attributes = vec![
+            wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: wgpu::VertexFormat::Float32x3,
+            },
+            wgpu::VertexAttribute {
+                offset: vec4_size,
+                shader_location: 1,
+                format: wgpu::VertexFormat::Float32x3,
+            },
+        ];
+
+        let (joints_format, weights_format, groups) = match self {
+            MaxInfluences::Two => (
+                wgpu::VertexFormat::Uint32x2,
+                wgpu::VertexFormat::Float32x2,
+                1,
+            ),
+            MaxInfluences::Four => (
+                wgpu::VertexFormat::Uint32x4,
+                wgpu::VertexFormat::Float32x4,
+                1,
+            ),
+            MaxInfluences::Eight => (
+                wgpu::VertexFormat::Uint32x4,
+                wgpu::VertexFormat::Float32x4,
+                2,
+            ),
+        };
+
+        for (offset, format) in [
+            (joints_offset, joints_format),
+            (weights_offset, weights_format),
+        ] {
+            for group in 0..groups {
+                attributes.push(wgpu::VertexAttribute {
+                    offset: offset + group * vec4_size,
+                    shader_location: attributes.len() as u32,
+                    format,
+                });
+            }
+        }
+
+        attributes
+    }
+
+    /// The skinning shader with the `SkinVertex` struct sized for this many influences
+    fn shader_source(self) -> String {
+        include_str!("skinning.wgsl").replace(
+            "const INFLUENCES: u32 = 4u;",
+            &format!("const INFLUENCES: u32 = {}u;", self.count()),
+        )
+    }
+}
+
+/// Bind pose vertex with the joints influencing it
+#[derive(Clone, Debug)]
+pub struct SkinVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    // (joint, weight) pairs
+    influences: Vec<(u32, f32)>,
+}
+
+impl SkinVertex {
+    /// The weights of the influences should add up to 1
+    pub fn new(position: [f32; 3], normal: [f32; 3], influences: &[(u32, f32)]) -> SkinVertex {
+        SkinVertex {
+            position,
+            normal,
+            influences: influences.to_vec(),
+        }
+    }
+
+    // The layout of the WGSL `SkinVertex`. Missing influences get zero weights
+    fn pack(&self, max_influences: MaxInfluences, bytes: &mut Vec<u8>) {
+        let count = max_influences.count();
+
+        assert!(
+            self.influences.len() <= count,
+            "a vertex has {} influences, but at most {} are allowed",
+            self.influences.len(),
+            count
+        );
+
+        #[cfg(debug_assertions)]
+        {
+            let weights_sum: f32 = self.influences.iter().map(|(_, weight)| weight).sum();
+
+            debug_assert!(
+                (weights_sum - 1.).abs() < 1e-3,
+                "the weights of a vertex add up to {} instead of 1",
+                weights_sum
+            );
+        }
+
+        let [x, y, z] = self.position;
+        let [nx, ny, nz] = self.normal;
+        bytes.extend_from_slice(bytemuck::cast_slice(&[x, y, z, 1., nx, ny, nz, 0.]));
+
+        let joints = (0..count).map(|i| self.influences.get(i).map_or(0, |&(joint, _)| joint));
+        let weights = (0..count).map(|i| self.influences.get(i).map_or(0., |&(_, weight)| weight));
+
+        for joint in joints {
+            bytes.extend_from_slice(bytemuck::bytes_of(&joint));
+        }
+        for weight in weights {
+            bytes.extend_from_slice(bytemuck::bytes_of(&weight));
+        }
+    }
+}
+
+/// Output of the skinning pass, read directly as a vertex buffer
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkinnedVertex {
+    position: [f32; 4],
+    normal: [f32; 4],
+}
+
+impl SkinnedVertex {
+    /// Position at @location(0), normal at @location(1). The w components are skipped
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SkinnedVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
+/// Skins a mesh in a compute pre-pass, so every render pass drawing it
+/// (shadows, geometry, ...) reads the same pre-transformed vertices instead of skinning again
+pub struct SkinningPass {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    joint_matrices_buffer: wgpu::Buffer,
+    skin_vertices_buffer: wgpu::Buffer,
+    skin_vertex_attributes: Vec<wgpu::VertexAttribute>,
+    skinned_vertices_buffer: wgpu::Buffer,
+    vertices_count: u32,
+    max_influences: MaxInfluences,
+}
+
+impl SkinningPass {
+    pub fn new(
+        device: &wgpu::Device,
+        vertices: &[SkinVertex],
+        joints_count: u32,
+        max_influences: MaxInfluences,
+    ) -> SkinningPass {
+        let shader_source = max_influences.shader_source();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My skinning shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.as_str().into()),
+        });
+
+        // Identity matrices, i.e. the bind pose
+        let identity: [[f32; 4]; 4] = [
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ];
+        let joint_matrices_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My joint matrices buffer"),
+            contents: bytemuck::cast_slice(&vec![identity; joints_count.max(1) as usize]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mut skin_vertices = Vec::with_capacity(vertices.len() * max_influences.vertex_size());
+        for vertex in vertices {
+            vertex.pack(max_influences, &mut skin_vertices);
+        }
+
+        // Also usable as a vertex buffer, e.g. to draw the bind pose
+        let skin_vertices_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My skin vertices buffer"),
+            contents: &skin_vertices,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+        });
+
+        let skinned_vertices_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My skinned vertices buffer"),
+            size: (vertices.len() * std::mem::size_of::<SkinnedVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+
+        let reflection =
+            ShaderReflection::from_wgsl(&shader_source).expect("the skinning shader is valid");
+        let bind_group_layout = reflection.create_bind_group_layout(device, 0);
+
+        let bind_group = BindGroupBuilder::from_reflection(&reflection, device)
+            .bind_buffer(0, &joint_matrices_buffer)
+            .bind_buffer(1, &skin_vertices_buffer)
+            .bind_buffer(2, &skinned_vertices_buffer)
+            .build(&bind_group_layout)
+            .expect("the skinning buffers match the shader");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My skinning pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("My skinning pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        SkinningPass {
+            pipeline,
+            bind_group,
+            joint_matrices_buffer,
+            skin_vertices_buffer,
+            skin_vertex_attributes: max_influences.vertex_attributes(),
+            skinned_vertices_buffer,
+            vertices_count: vertices.len() as u32,
+            max_influences,
+        }
+    }
+
+    /// Column major joint matrices, already multiplied by the inverse bind matrices
+    pub fn update_joints(&self, queue: &wgpu::Queue, joint_matrices: &[[[f32; 4]; 4]]) {
+        queue.write_buffer(
+            &self.joint_matrices_buffer,
+            0,
+            bytemuck::cast_slice(joint_matrices),
+        );
+    }
+
+    /// Records the skinning. Must be called once per frame, before any render pass drawing the mesh
+    pub fn skin(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("My skinning pass"),
+            timestamp_writes: None,
+        });
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.vertices_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// `SkinnedVertex`es for `set_vertex_buffer`
+    pub fn skinned_vertices(&self) -> &wgpu::Buffer {
+        &self.skinned_vertices_buffer
+    }
+
+    /// The packed bind pose `SkinVertex`es
+    pub fn skin_vertices(&self) -> &wgpu::Buffer {
+        &self.skin_vertices_buffer
+    }
+
+    pub fn skin_vertices_desc(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.max_influences.vertex_size() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &self.skin_vertex_attributes,
+        }
+    }
+
+    pub fn vertices_count(&self) -> u32 {
+        self.vertices_count
+    }
+}
+
+/// Draws the skinned vertices of a `SkinningPass` with the opaque geometry, shaded by their normals
+pub struct SkinnedMeshPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl SkinnedMeshPipeline {
+    /// `camera` and `transform` are the uniforms of groups 0 and 1 of the built-in shader.
+    /// The rest is what the opaque pipelines are created with
+    pub fn new(
+        device: &wgpu::Device,
+        camera: &impl Bindable,
+        transform: &impl Bindable,
+        color_format: wgpu::TextureFormat,
+        depth_config: DepthConfig,
+        sample_count: u32,
+    ) -> SkinnedMeshPipeline {
+        let shader_source = include_str!("skinned_mesh.wgsl");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My skinned mesh shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        let reflection =
+            ShaderReflection::from_wgsl(shader_source).expect("the skinned mesh shader is valid");
+        let bind_group_layout = reflection.create_bind_group_layout(device, 0);
+
+        let bind_group = BindGroupBuilder::from_reflection(&reflection, device)
+            .bind_resource(0, camera)
+            .bind_resource(1, transform)
+            .build(&bind_group_layout)
+            .expect("the skinned mesh uniforms match the shader");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My skinned mesh pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My skinned mesh pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[SkinnedVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_config.format(),
+                depth_write_enabled: true,
+                depth_compare: depth_config.depth_compare(),
+                stencil: depth_config.stencil_state(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        SkinnedMeshPipeline {
+            pipeline,
+            bind_group,
+        }
+    }
+
+    /// The vertices `skinning` wrote this frame, as a triangle list. Binds its own group 0
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, skinning: &'a SkinningPass) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, skinning.skinned_vertices().slice(..));
+        render_pass.draw(0..skinning.vertices_count(), 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render_target::RenderTarget, texture, uniform_buffer::UniformBuffer};
+
+    const SIZE: u32 = 32;
+
+    #[test]
+    fn skinned_triangle_follows_its_joint() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        let depth_config = DepthConfig::default();
+        let target = RenderTarget::new(
+            &device,
+            SIZE,
+            SIZE,
+            wgpu::TextureFormat::Rgba8Unorm,
+            depth_config.format(),
+        );
+        let camera = UniformBuffer::new(&device, "My test camera", crate::CameraUniform::IDENTITY);
+        let transform = UniformBuffer::new(&device, "My test transform", crate::IDENTITY_MATRIX);
+        let pipeline = SkinnedMeshPipeline::new(
+            &device,
+            &camera,
+            &transform,
+            target.format(),
+            depth_config,
+            1,
+        );
+
+        // A triangle around the center, facing the camera and moved by its only joint
+        let vertices = [[-0.3, -0.3, 0.5], [0.3, -0.3, 0.5], [0., 0.3, 0.5]]
+            .map(|position| SkinVertex::new(position, [0., 0., 1.], &[(0, 1.)]));
+        let skinning = SkinningPass::new(&device, &vertices, 1, MaxInfluences::Two);
+        let moved_right: [[f32; 4]; 4] =
+            cgmath::Matrix4::from_translation(cgmath::vec3(0.6, 0., 0.)).into();
+        skinning.update_joints(&queue, &[moved_right]);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My test encoder"),
+        });
+        skinning.skin(&mut encoder);
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("My test pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target.view(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: target.depth_view(),
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(depth_config.clear_depth()),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            pipeline.draw(&mut render_pass, &skinning);
+        }
+        queue.submit([encoder.finish()]);
+
+        let texels = pollster::block_on(texture::readback(&device, &queue, target.texture(), 0, 0));
+        let red_at = |column: u32| texels[((SIZE / 2 * SIZE + column) * 4) as usize];
+
+        // The bind pose center is left empty, the skinned one is lit head on
+        assert_eq!(red_at(SIZE / 2), 0);
+        assert_eq!(red_at(SIZE * 4 / 5), 255);
+    }
+}
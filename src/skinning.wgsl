@@ -0,0 +1,45 @@
+// Linear blend skinning, done once per frame for every pass that draws the mesh
+
+// Replaced with `MaxInfluences::count` when the shader is created
+const INFLUENCES: u32 = 4u;
+
+struct SkinVertex {
+    position: vec4<f32>,
+    normal: vec4<f32>,
+    joints: array<u32, INFLUENCES>,
+    weights: array<f32, INFLUENCES>,
+}
+
+struct SkinnedVertex {
+    position: vec4<f32>,
+    normal: vec4<f32>,
+}
+
+@group(0) @binding(0) var<storage, read> joint_matrices: array<mat4x4<f32>>;
+@group(0) @binding(1) var<storage, read> vertices: array<SkinVertex>;
+@group(0) @binding(2) var<storage, read_write> skinned_vertices: array<SkinnedVertex>;
+
+@compute @workgroup_size(64) fn cs_main(
+    @builtin(global_invocation_id) id: vec3<u32>
+) {
+    let index = id.x;
+
+    if index >= arrayLength(&vertices) {
+        return;
+    }
+
+    // Accessed through the storage pointer, values of arrays can't be indexed dynamically
+    let vertex = &vertices[index];
+
+    var skin_matrix = mat4x4<f32>(vec4<f32>(0.), vec4<f32>(0.), vec4<f32>(0.), vec4<f32>(0.));
+    for (var i = 0u; i < INFLUENCES; i++) {
+        skin_matrix += joint_matrices[(*vertex).joints[i]] * (*vertex).weights[i];
+    }
+
+    var out: SkinnedVertex;
+    out.position = skin_matrix * vec4<f32>((*vertex).position.xyz, 1.);
+    // Fine as long as the joints aren't scaled non-uniformly
+    out.normal = vec4<f32>(normalize((skin_matrix * vec4<f32>((*vertex).normal.xyz, 0.)).xyz), 0.);
+
+    skinned_vertices[index] = out;
+}
@@ -0,0 +1,42 @@
+// Textured quads, placed and cut out of the texture per instance
+
+struct SpriteInstance {
+    // The center, in clip space
+    @location(0) position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    // The part of the texture shown, see `AnimatedSprite::uv_rect`
+    @location(2) uv_offset: vec2<f32>,
+    @location(3) uv_scale: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    instance: SpriteInstance,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Two triangles, the corners going from 0 to 1
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0., 0.), vec2<f32>(1., 0.), vec2<f32>(1., 1.),
+        vec2<f32>(0., 0.), vec2<f32>(1., 1.), vec2<f32>(0., 1.),
+    );
+    let corner = corners[vertex_index];
+
+    out.clip_position = vec4<f32>(instance.position + (corner - 0.5) * instance.size, 0., 1.);
+    // The texture's rows go down, clip space goes up
+    out.uv = instance.uv_offset + vec2<f32>(corner.x, 1. - corner.y) * instance.uv_scale;
+
+    return out;
+}
+
+@group(0) @binding(0) var sprite_texture: texture_2d<f32>;
+@group(0) @binding(1) var sprite_sampler: sampler;
+
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(sprite_texture, sprite_sampler, in.uv);
+}
@@ -0,0 +1,121 @@
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+
+/// An array in a storage buffer with a copy on the CPU. Only the elements changed since the last `flush`
+/// are uploaded, nothing when none were
+pub struct StorageBuffer<T: bytemuck::Pod> {
+    cpu: Vec<T>,
+    gpu: wgpu::Buffer,
+    // Covers all the elements changed since the last flush
+    dirty_range: Option<Range<usize>>,
+}
+
+impl<T: bytemuck::Pod> StorageBuffer<T> {
+    /// The size of `T` must be a multiple of 4 bytes, like any WGSL type
+    pub fn new(device: &wgpu::Device, label: &str, data: Vec<T>) -> StorageBuffer<T> {
+        assert!(
+            std::mem::size_of::<T>().is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT as usize),
+            "the elements must be a multiple of {} bytes",
+            wgpu::COPY_BUFFER_ALIGNMENT
+        );
+
+        let gpu = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&data),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        StorageBuffer {
+            cpu: data,
+            gpu,
+            dirty_range: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cpu.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpu.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &T {
+        &self.cpu[index]
+    }
+
+    /// The element is uploaded by the next `flush`, changed or not
+    pub fn get_mut(&mut self, index: usize) -> &mut T {
+        self.dirty_range = Some(match self.dirty_range.take() {
+            Some(range) => range.start.min(index)..range.end.max(index + 1),
+            None => index..index + 1,
+        });
+
+        &mut self.cpu[index]
+    }
+
+    /// Uploads the changed elements, and everything in between them
+    pub fn flush(&mut self, queue: &wgpu::Queue) {
+        let Some(range) = self.dirty_range.take() else {
+            return;
+        };
+
+        let offset = (range.start * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+        queue.write_buffer(&self.gpu, offset, bytemuck::cast_slice(&self.cpu[range]));
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.gpu
+    }
+
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        self.gpu.as_entire_binding()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_the_changed_elements_into_one_range() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let mut buffer = StorageBuffer::new(&device, "My test storage buffer", vec![0_u32; 8]);
+        assert_eq!(buffer.dirty_range, None);
+
+        *buffer.get_mut(3) = 1;
+        assert_eq!(buffer.dirty_range, Some(3..4));
+        // Inside the range, nothing changes
+        *buffer.get_mut(3) = 2;
+        assert_eq!(buffer.dirty_range, Some(3..4));
+        // Adjacent and before
+        *buffer.get_mut(2) = 3;
+        assert_eq!(buffer.dirty_range, Some(2..4));
+
+        buffer.flush(&queue);
+        assert_eq!(buffer.dirty_range, None);
+        assert_eq!(*buffer.get(3), 2);
+    }
+
+    #[test]
+    fn covers_disjoint_elements_and_everything_between() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let mut buffer = StorageBuffer::new(&device, "My test storage buffer", vec![0_u32; 8]);
+
+        *buffer.get_mut(6) = 1;
+        *buffer.get_mut(1) = 1;
+        assert_eq!(buffer.dirty_range, Some(1..7));
+        *buffer.get_mut(7) = 1;
+        assert_eq!(buffer.dirty_range, Some(1..8));
+
+        buffer.flush(&queue);
+        assert_eq!(buffer.dirty_range, None);
+    }
+}
@@ -0,0 +1,116 @@
+use wgpu::util::DeviceExt;
+
+use crate::vertex_buffer::VertexBuffer;
+
+/// An index type usable with triangle and line strips
+pub trait StripIndex: bytemuck::Pod + Into<u32> {
+    const FORMAT: wgpu::IndexFormat;
+    /// Ends the current strip and starts a new one when met in the index buffer.
+    /// Always the maximum value: 0xFFFF for `Uint16` and 0xFFFFFFFF for `Uint32`
+    const RESTART: Self;
+}
+
+impl StripIndex for u16 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint16;
+    const RESTART: u16 = u16::MAX;
+}
+
+impl StripIndex for u32 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32;
+    const RESTART: u32 = u32::MAX;
+}
+
+/// The primitive restart value of an index format
+pub fn restart_index(format: wgpu::IndexFormat) -> u32 {
+    match format {
+        wgpu::IndexFormat::Uint16 => u16::RESTART.into(),
+        wgpu::IndexFormat::Uint32 => u32::RESTART,
+    }
+}
+
+/// The primitive state of pipelines drawing strips indexed with `I`.
+/// The strip index format must match the index buffer, or the restart value isn't recognized
+pub fn primitive_state<I: StripIndex>() -> wgpu::PrimitiveState {
+    wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleStrip,
+        strip_index_format: Some(I::FORMAT),
+        ..Default::default()
+    }
+}
+
+/// Puts the strips into a single index buffer, separated by the restart value
+pub fn join_strips<I: StripIndex>(strips: &[&[I]]) -> Vec<I> {
+    let mut indices = Vec::with_capacity(strips.iter().map(|strip| strip.len() + 1).sum());
+
+    for (i, strip) in strips.iter().enumerate() {
+        assert!(
+            strip.iter().all(|&index| index.into() != I::RESTART.into()),
+            "strip {} contains the restart index",
+            i
+        );
+
+        if i > 0 {
+            indices.push(I::RESTART);
+        }
+        indices.extend_from_slice(strip);
+    }
+
+    indices
+}
+
+/// Several disjoint triangle strips drawn with one indexed draw
+pub struct StripMesh {
+    vertex_buffer: VertexBuffer,
+    index_buffer: wgpu::Buffer,
+    index_format: wgpu::IndexFormat,
+    indices_count: u32,
+}
+
+impl StripMesh {
+    pub fn new<V: bytemuck::Pod, I: StripIndex>(
+        device: &wgpu::Device,
+        label: &str,
+        vertices: &[V],
+        strips: &[&[I]],
+    ) -> StripMesh {
+        let indices = join_strips(strips);
+
+        if let Some(index) = indices
+            .iter()
+            .map(|&index| index.into())
+            .find(|&index| index != I::RESTART.into() && index as usize >= vertices.len())
+        {
+            panic!(
+                "index {} is out of bounds of {} vertices",
+                index,
+                vertices.len()
+            );
+        }
+
+        let vertex_buffer = VertexBuffer::new(device, label, vertices);
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        StripMesh {
+            vertex_buffer,
+            index_buffer,
+            index_format: I::FORMAT,
+            indices_count: indices.len() as u32,
+        }
+    }
+
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        self.index_format
+    }
+
+    /// The pipeline's strip index format must be `index_format`
+    pub fn draw<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice());
+        render_pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
+        render_pass.draw_indexed(0..self.indices_count, 0, 0..1);
+    }
+}
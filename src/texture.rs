@@ -0,0 +1,620 @@
+use wgpu::util::DeviceExt;
+
+use crate::buffer_map;
+
+#[derive(Debug)]
+pub enum DdsError {
+    Parse(ddsfile::Error),
+    /// Only BC1 to BC7 are supported. None when the file doesn't name its format
+    UnsupportedFormat(Option<ddsfile::DxgiFormat>),
+    /// The device wasn't created with `wgpu::Features::TEXTURE_COMPRESSION_BC`
+    CompressionNotSupported,
+    /// BCn textures are made of 4x4 blocks
+    UnalignedSize {
+        width: u32,
+        height: u32,
+    },
+    /// The file is shorter than its header says
+    MissingData {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl std::fmt::Display for DdsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DdsError::Parse(error) => write!(f, "{}", error),
+            DdsError::UnsupportedFormat(Some(format)) => {
+                write!(f, "the {:?} format isn't supported", format)
+            }
+            DdsError::UnsupportedFormat(None) => write!(f, "the format is unknown"),
+            DdsError::CompressionNotSupported => {
+                write!(f, "the device doesn't support BC compressed textures")
+            }
+            DdsError::UnalignedSize { width, height } => {
+                write!(f, "{}x{} isn't a multiple of the 4x4 blocks", width, height)
+            }
+            DdsError::MissingData { expected, found } => {
+                write!(f, "expected {} bytes of texels, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DdsError {}
+
+impl From<ddsfile::Error> for DdsError {
+    fn from(error: ddsfile::Error) -> DdsError {
+        DdsError::Parse(error)
+    }
+}
+
+#[derive(Debug)]
+pub enum Ktx2Error {
+    Parse(ktx2::ParseError),
+    /// Only ETC2, ASTC 4x4 and ASTC 6x6 are supported. None when the format is only in the data format descriptor
+    UnsupportedFormat(Option<ktx2::Format>),
+    /// Zstandard and Basis Universal compressed files need to be transcoded first
+    Supercompressed(ktx2::SupercompressionScheme),
+    /// The device wasn't created with the feature the format requires
+    CompressionNotSupported(wgpu::TextureFormat),
+    /// The file is shorter than its header says
+    MissingData {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl std::fmt::Display for Ktx2Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ktx2Error::Parse(error) => write!(f, "{}", error),
+            Ktx2Error::UnsupportedFormat(Some(format)) => {
+                write!(f, "the {:?} format isn't supported", format)
+            }
+            Ktx2Error::UnsupportedFormat(None) => write!(f, "the format is unknown"),
+            Ktx2Error::Supercompressed(scheme) => {
+                write!(f, "the {:?} supercompression isn't supported", scheme)
+            }
+            Ktx2Error::CompressionNotSupported(format) => {
+                write!(f, "the device doesn't support {:?} textures", format)
+            }
+            Ktx2Error::MissingData { expected, found } => {
+                write!(f, "expected {} bytes of texels, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Ktx2Error {}
+
+impl From<ktx2::ParseError> for Ktx2Error {
+    fn from(error: ktx2::ParseError) -> Ktx2Error {
+        Ktx2Error::Parse(error)
+    }
+}
+
+/// The families of compressed formats, each supported by different GPUs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureCompression {
+    /// Desktop GPUs, loaded by `load_dds`
+    Bc,
+    /// Recent mobile GPUs, loaded by `load_ktx2`
+    Astc,
+    /// OpenGL ES 3 and WebGL 2, loaded by `load_ktx2`
+    Etc2,
+}
+
+/// Which kind of compressed textures to ship to a device with `features`, the best looking one first.
+/// None when only uncompressed textures are supported
+pub fn preferred_compression(features: wgpu::Features) -> Option<TextureCompression> {
+    [
+        (
+            wgpu::Features::TEXTURE_COMPRESSION_BC,
+            TextureCompression::Bc,
+        ),
+        (
+            wgpu::Features::TEXTURE_COMPRESSION_ASTC,
+            TextureCompression::Astc,
+        ),
+        (
+            wgpu::Features::TEXTURE_COMPRESSION_ETC2,
+            TextureCompression::Etc2,
+        ),
+    ]
+    .into_iter()
+    .find(|(feature, _)| features.contains(*feature))
+    .map(|(_, compression)| compression)
+}
+
+/// A texture with a view of all of it and a sampler, ready to be bound
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    /// Clamps to the edges, magnifies linearly and minifies to the nearest texel
+    pub sampler: wgpu::Sampler,
+}
+
+impl Texture {
+    /// The view of a depth stencil texture only has the depth, the aspect the shaders can sample
+    pub fn new(device: &wgpu::Device, texture: wgpu::Texture) -> Texture {
+        let format = texture.format();
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            aspect: if format.has_depth_aspect() && format.has_stencil_aspect() {
+                wgpu::TextureAspect::DepthOnly
+            } else {
+                wgpu::TextureAspect::All
+            },
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("My texture sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Texture {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// A color image in an `Rgba8UnormSrgb` texture, PNG being the only format the `image` features decode.
+    /// Its colors are sRGB encoded, so the sampling decodes them to the linear values the shaders work with.
+    /// Data that isn't colors, such as normal maps, goes to `Rgba8Unorm` with `upload_image` instead
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+    ) -> Result<Texture, image::ImageError> {
+        let image = image::load_from_memory(bytes)?;
+
+        Ok(Texture::new(
+            device,
+            create_image_texture(
+                device,
+                queue,
+                &image,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                label,
+            ),
+        ))
+    }
+}
+
+/// A texture holding `image` converted to `format`, for sampling.
+/// Panics for formats other than `R8Unorm`, `Rg8Unorm`, `Rgba8Unorm`, `Bgra8Unorm`,
+/// their sRGB variants, `Rgba16Unorm` and `Rgba32Float`
+pub fn upload_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &image::DynamicImage,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    create_image_texture(device, queue, image, format, "My uploaded texture")
+}
+
+fn create_image_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &image::DynamicImage,
+    format: wgpu::TextureFormat,
+    label: &str,
+) -> wgpu::Texture {
+    let texels = match format {
+        wgpu::TextureFormat::R8Unorm => image.to_luma8().into_raw(),
+        wgpu::TextureFormat::Rg8Unorm => image.to_luma_alpha8().into_raw(),
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {
+            image.to_rgba8().into_raw()
+        }
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+            let mut texels = image.to_rgba8().into_raw();
+            texels
+                .chunks_exact_mut(4)
+                .for_each(|texel| texel.swap(0, 2));
+            texels
+        }
+        wgpu::TextureFormat::Rgba16Unorm => {
+            bytemuck::cast_slice(&image.to_rgba16().into_raw()).to_vec()
+        }
+        wgpu::TextureFormat::Rgba32Float => {
+            bytemuck::cast_slice(&image.to_rgba32f().into_raw()).to_vec()
+        }
+        _ => panic!("images can't be converted to {:?}", format),
+    };
+
+    let size = wgpu::Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    // The same layout as a buffer to texture copy, rows starting at multiples of 256 bytes
+    let row_size = texels.len() / size.height.max(1) as usize;
+    let padded_row_size = row_size.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+    let mut padded = vec![0; padded_row_size * size.height as usize];
+    for (padded_row, row) in padded
+        .chunks_exact_mut(padded_row_size)
+        .zip(texels.chunks_exact(row_size.max(1)))
+    {
+        padded_row[..row_size].copy_from_slice(row);
+    }
+
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &padded,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(padded_row_size as u32),
+            rows_per_image: Some(size.height),
+        },
+        size,
+    );
+
+    texture
+}
+
+/// The texels of one mip level of one layer, rows tightly packed.
+/// The texture needs `COPY_SRC` and an uncompressed color format
+pub async fn readback(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    mip_level: u32,
+    array_layer: u32,
+) -> Vec<u8> {
+    let format = texture.format();
+    let texel_size = format
+        .block_copy_size(Some(wgpu::TextureAspect::All))
+        .filter(|_| format.block_dimensions() == (1, 1))
+        .expect("only uncompressed color textures can be read back");
+
+    let size = texture
+        .size()
+        .mip_level_size(mip_level, texture.dimension());
+    let row_size = size.width * texel_size;
+    // Every row of the copy starts at a multiple of 256 bytes
+    let padded_row_size = row_size.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("My texture readback buffer"),
+        size: (padded_row_size * size.height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("My texture readback encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: array_layer,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: Some(size.height),
+            },
+        },
+        wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit([encoder.finish()]);
+
+    let texels = buffer_map::map_buffer_async(device, &buffer, wgpu::MapMode::Read)
+        .await
+        .expect("the readback buffer can be mapped");
+
+    strip_row_padding(&texels, row_size as usize, padded_row_size as usize)
+}
+
+fn strip_row_padding(padded: &[u8], row_size: usize, padded_row_size: usize) -> Vec<u8> {
+    padded
+        .chunks_exact(padded_row_size)
+        .flat_map(|row| &row[..row_size])
+        .copied()
+        .collect()
+}
+
+/// A BC1 to BC7 compressed texture, with all the mip levels and layers of the DDS file.
+/// The data is uploaded as is, without decompressing it
+pub fn load_dds(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    dds_bytes: &[u8],
+) -> Result<wgpu::Texture, DdsError> {
+    let dds = ddsfile::Dds::read(dds_bytes)?;
+    let dxgi_format = dds.get_dxgi_format();
+    let format = dxgi_format
+        .and_then(bc_format)
+        .ok_or(DdsError::UnsupportedFormat(dxgi_format))?;
+
+    if !device
+        .features()
+        .contains(wgpu::Features::TEXTURE_COMPRESSION_BC)
+    {
+        return Err(DdsError::CompressionNotSupported);
+    }
+
+    let (width, height) = (dds.get_width(), dds.get_height());
+    if width % 4 != 0 || height % 4 != 0 {
+        return Err(DdsError::UnalignedSize { width, height });
+    }
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: dds.get_num_array_layers(),
+    };
+    let mip_level_count = dds.get_num_mipmap_levels();
+
+    let expected = compressed_size(size, mip_level_count, format);
+    if dds.data.len() < expected {
+        return Err(DdsError::MissingData {
+            expected,
+            found: dds.data.len(),
+        });
+    }
+
+    Ok(device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("My DDS texture"),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        // DDS files store every mip level of a layer before the next layer
+        wgpu::util::TextureDataOrder::LayerMajor,
+        &dds.data[..expected],
+    ))
+}
+
+fn bc_format(format: ddsfile::DxgiFormat) -> Option<wgpu::TextureFormat> {
+    use ddsfile::DxgiFormat;
+
+    Some(match format {
+        DxgiFormat::BC1_UNorm => wgpu::TextureFormat::Bc1RgbaUnorm,
+        DxgiFormat::BC1_UNorm_sRGB => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+        DxgiFormat::BC2_UNorm => wgpu::TextureFormat::Bc2RgbaUnorm,
+        DxgiFormat::BC2_UNorm_sRGB => wgpu::TextureFormat::Bc2RgbaUnormSrgb,
+        DxgiFormat::BC3_UNorm => wgpu::TextureFormat::Bc3RgbaUnorm,
+        DxgiFormat::BC3_UNorm_sRGB => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+        DxgiFormat::BC4_UNorm => wgpu::TextureFormat::Bc4RUnorm,
+        DxgiFormat::BC4_SNorm => wgpu::TextureFormat::Bc4RSnorm,
+        DxgiFormat::BC5_UNorm => wgpu::TextureFormat::Bc5RgUnorm,
+        DxgiFormat::BC5_SNorm => wgpu::TextureFormat::Bc5RgSnorm,
+        DxgiFormat::BC6H_UF16 => wgpu::TextureFormat::Bc6hRgbUfloat,
+        DxgiFormat::BC6H_SF16 => wgpu::TextureFormat::Bc6hRgbFloat,
+        DxgiFormat::BC7_UNorm => wgpu::TextureFormat::Bc7RgbaUnorm,
+        DxgiFormat::BC7_UNorm_sRGB => wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+        _ => return None,
+    })
+}
+
+// The bytes of all the mip levels of all the layers of a block compressed texture
+fn compressed_size(
+    size: wgpu::Extent3d,
+    mip_level_count: u32,
+    format: wgpu::TextureFormat,
+) -> usize {
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format
+        .block_copy_size(None)
+        .expect("compressed formats have a single aspect");
+
+    let layer_size: u32 = (0..mip_level_count)
+        .map(|mip_level| {
+            let mip_size = size.mip_level_size(mip_level, wgpu::TextureDimension::D2);
+
+            mip_size.width.div_ceil(block_width)
+                * mip_size.height.div_ceil(block_height)
+                * block_size
+        })
+        .sum();
+
+    layer_size as usize * size.depth_or_array_layers as usize
+}
+
+/// An ETC2 or ASTC compressed texture, with all the mip levels and layers of the KTX2 file.
+/// Cube maps are loaded as arrays of 6 layers
+pub fn load_ktx2(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    ktx2_bytes: &[u8],
+) -> Result<wgpu::Texture, Ktx2Error> {
+    let reader = ktx2::Reader::new(ktx2_bytes)?;
+    let header = reader.header();
+
+    if let Some(scheme) = header.supercompression_scheme {
+        return Err(Ktx2Error::Supercompressed(scheme));
+    }
+
+    let format = header
+        .format
+        .and_then(mobile_format)
+        .ok_or(Ktx2Error::UnsupportedFormat(header.format))?;
+
+    if !device.features().contains(format.required_features()) {
+        return Err(Ktx2Error::CompressionNotSupported(format));
+    }
+
+    let size = wgpu::Extent3d {
+        width: header.pixel_width,
+        height: header.pixel_height.max(1),
+        depth_or_array_layers: header.layer_count.max(1) * header.face_count,
+    };
+    let mip_level_count = header.level_count.max(1);
+
+    // KTX2 files store every layer of a mip level before the next mip level
+    let data: Vec<u8> = reader
+        .levels()
+        .flat_map(|level| level.data)
+        .copied()
+        .collect();
+
+    let expected = compressed_size(size, mip_level_count, format);
+    if data.len() < expected {
+        return Err(Ktx2Error::MissingData {
+            expected,
+            found: data.len(),
+        });
+    }
+
+    Ok(device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("My KTX2 texture"),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::MipMajor,
+        &data[..expected],
+    ))
+}
+
+fn mobile_format(format: ktx2::Format) -> Option<wgpu::TextureFormat> {
+    use wgpu::{AstcBlock, AstcChannel};
+
+    let astc = |block, channel| wgpu::TextureFormat::Astc { block, channel };
+
+    Some(match format {
+        ktx2::Format::ETC2_R8G8B8_UNORM_BLOCK => wgpu::TextureFormat::Etc2Rgb8Unorm,
+        ktx2::Format::ETC2_R8G8B8_SRGB_BLOCK => wgpu::TextureFormat::Etc2Rgb8UnormSrgb,
+        ktx2::Format::ETC2_R8G8B8A1_UNORM_BLOCK => wgpu::TextureFormat::Etc2Rgb8A1Unorm,
+        ktx2::Format::ETC2_R8G8B8A1_SRGB_BLOCK => wgpu::TextureFormat::Etc2Rgb8A1UnormSrgb,
+        ktx2::Format::ETC2_R8G8B8A8_UNORM_BLOCK => wgpu::TextureFormat::Etc2Rgba8Unorm,
+        ktx2::Format::ETC2_R8G8B8A8_SRGB_BLOCK => wgpu::TextureFormat::Etc2Rgba8UnormSrgb,
+        ktx2::Format::ASTC_4x4_UNORM_BLOCK => astc(AstcBlock::B4x4, AstcChannel::Unorm),
+        ktx2::Format::ASTC_4x4_SRGB_BLOCK => astc(AstcBlock::B4x4, AstcChannel::UnormSrgb),
+        ktx2::Format::ASTC_6x6_UNORM_BLOCK => astc(AstcBlock::B6x6, AstcChannel::Unorm),
+        ktx2::Format::ASTC_6x6_SRGB_BLOCK => astc(AstcBlock::B6x6, AstcChannel::UnormSrgb),
+        _ => return None,
+    })
+}
+
+/// Overwrites a `width` by `height` region of the first mip level, e.g. a single glyph of an atlas.
+/// `data` is tightly packed in `format`, which for compressed formats needs the region aligned to the blocks
+#[allow(clippy::too_many_arguments)]
+pub fn update_region(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    x_offset: u32,
+    y_offset: u32,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    format: wgpu::TextureFormat,
+) {
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format
+        .block_copy_size(Some(wgpu::TextureAspect::All))
+        .expect("the format has a single aspect");
+
+    let bytes_per_row = width.div_ceil(block_width) * block_size;
+    let rows = height.div_ceil(block_height);
+    assert_eq!(
+        data.len(),
+        (bytes_per_row * rows) as usize,
+        "the data doesn't match the region size"
+    );
+
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: x_offset,
+                y: y_offset,
+                z: 0,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(bytes_per_row),
+            rows_per_image: Some(rows),
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_row_padding_of_a_non_power_of_two_row() {
+        // 13 RGBA8 texels are 52 bytes, padded to 256
+        let mut padded = vec![0xff; 256];
+        for (i, byte) in padded[..52].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        let texels = strip_row_padding(&padded, 52, 256);
+
+        assert_eq!(texels, (0..52).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn strip_row_padding_of_several_rows() {
+        let (row_size, padded_row_size, rows) = (52, 256, 3);
+        let mut padded = vec![0xff; padded_row_size * rows];
+        for row in 0..rows {
+            for i in 0..row_size {
+                padded[row * padded_row_size + i] = (row * row_size + i) as u8;
+            }
+        }
+
+        let texels = strip_row_padding(&padded, row_size, padded_row_size);
+
+        assert_eq!(texels.len(), row_size * rows);
+        assert_eq!(texels, (0..(row_size * rows) as u8).collect::<Vec<u8>>());
+    }
+}
@@ -0,0 +1,80 @@
+use crate::texture::Texture;
+
+/// A part of a texture, in UVs: the unit square scaled, then offset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub offset: [f32; 2],
+    pub scale: [f32; 2],
+}
+
+impl UvRect {
+    /// The whole texture
+    pub const FULL: UvRect = UvRect {
+        offset: [0., 0.],
+        scale: [1., 1.],
+    };
+}
+
+/// One texture holding many images, each one a region addressed by its index.
+/// Drawing them all from a single texture saves switching bind groups between the draws
+pub struct TextureAtlas {
+    texture: Texture,
+    regions: Vec<UvRect>,
+}
+
+impl TextureAtlas {
+    /// No regions yet, see `add_region`
+    pub fn new(texture: Texture) -> TextureAtlas {
+        TextureAtlas {
+            texture,
+            regions: Vec::new(),
+        }
+    }
+
+    /// Regions of equal size, left to right, then top to bottom, as in a sprite sheet
+    pub fn from_grid(texture: Texture, columns: u32, rows: u32) -> TextureAtlas {
+        assert!(
+            columns > 0 && rows > 0,
+            "the atlas has no regions: {}x{}",
+            columns,
+            rows
+        );
+
+        let scale = [1. / columns as f32, 1. / rows as f32];
+        let regions = (0..rows)
+            .flat_map(|row| {
+                (0..columns).map(move |column| UvRect {
+                    offset: [column as f32 * scale[0], row as f32 * scale[1]],
+                    scale,
+                })
+            })
+            .collect();
+
+        TextureAtlas { texture, regions }
+    }
+
+    /// The region's index. `x`, `y`, `width` and `height` are in texels
+    pub fn add_region(&mut self, x: u32, y: u32, width: u32, height: u32) -> usize {
+        let size = self.texture.texture.size();
+        let (texture_width, texture_height) = (size.width as f32, size.height as f32);
+
+        self.regions.push(UvRect {
+            offset: [x as f32 / texture_width, y as f32 / texture_height],
+            scale: [width as f32 / texture_width, height as f32 / texture_height],
+        });
+
+        self.regions.len() - 1
+    }
+
+    pub fn region(&self, index: usize) -> Option<UvRect> {
+        self.regions.get(index).copied()
+    }
+
+    pub fn regions(&self) -> &[UvRect] {
+        &self.regions
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+}
@@ -0,0 +1,63 @@
+/// Picks formats the adapter can actually render to, from the best to the most widely supported
+pub struct TextureFormatSelector;
+
+impl TextureFormatSelector {
+    const HDR_CANDIDATES: [wgpu::TextureFormat; 2] = [
+        wgpu::TextureFormat::Rgba32Float,
+        wgpu::TextureFormat::Rgba16Float,
+    ];
+
+    const DEPTH_CANDIDATES: [wgpu::TextureFormat; 3] = [
+        wgpu::TextureFormat::Depth32Float,
+        wgpu::TextureFormat::Depth24Plus,
+        wgpu::TextureFormat::Depth16Unorm,
+    ];
+
+    /// A float color target that can be rendered to and sampled afterwards.
+    /// `Rgba8Unorm` when there is none, so the colors must be tone mapped before being written,
+    /// see `needs_tone_mapping`
+    pub fn best_hdr(adapter: &wgpu::Adapter) -> wgpu::TextureFormat {
+        Self::first_supported(
+            adapter,
+            &Self::HDR_CANDIDATES,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        )
+        .unwrap_or(wgpu::TextureFormat::Rgba8Unorm)
+    }
+
+    /// The most precise depth format usable as a depth attachment
+    pub fn best_depth_format(adapter: &wgpu::Adapter) -> wgpu::TextureFormat {
+        // `Depth24Plus` is always supported, the last candidate is there for completeness
+        Self::first_supported(
+            adapter,
+            &Self::DEPTH_CANDIDATES,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        )
+        .unwrap_or(wgpu::TextureFormat::Depth24Plus)
+    }
+
+    /// Whether `format` clamps to [0, 1], so HDR colors must be tone mapped in the shader
+    /// instead of in a later pass
+    pub fn needs_tone_mapping(format: wgpu::TextureFormat) -> bool {
+        !Self::HDR_CANDIDATES.contains(&format)
+    }
+
+    fn first_supported(
+        adapter: &wgpu::Adapter,
+        candidates: &[wgpu::TextureFormat],
+        usages: wgpu::TextureUsages,
+    ) -> Option<wgpu::TextureFormat> {
+        candidates.iter().copied().find(|&format| {
+            let features = adapter.get_texture_format_features(format);
+            let supported = features.allowed_usages.contains(usages);
+
+            log::debug!(
+                "{:?} {} supported",
+                format,
+                if supported { "is" } else { "isn't" }
+            );
+
+            supported
+        })
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// How many unused textures of a format are kept, unless set otherwise
+pub const DEFAULT_MAX_PER_FORMAT: usize = 8;
+
+// Textures are only reused for the exact same request
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PoolKey {
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    usage: wgpu::TextureUsages,
+}
+
+struct PoolEntry {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+// Shared with the textures handed out, so they can come back on drop
+struct FreeTextures {
+    entries: HashMap<PoolKey, Vec<PoolEntry>>,
+    max_per_format: usize,
+}
+
+impl FreeTextures {
+    fn count(&self, format: wgpu::TextureFormat) -> usize {
+        self.entries
+            .iter()
+            .filter(|(key, _)| key.format == format)
+            .map(|(_, entries)| entries.len())
+            .sum()
+    }
+}
+
+/// Hands out the textures dropped earlier instead of creating new ones,
+/// e.g. for the intermediate targets of post-processing passes
+pub struct TexturePool<'a> {
+    device: &'a wgpu::Device,
+    free: Arc<Mutex<FreeTextures>>,
+}
+
+impl<'a> TexturePool<'a> {
+    pub fn new(device: &'a wgpu::Device) -> TexturePool<'a> {
+        TexturePool {
+            device,
+            free: Arc::new(Mutex::new(FreeTextures {
+                entries: HashMap::new(),
+                max_per_format: DEFAULT_MAX_PER_FORMAT,
+            })),
+        }
+    }
+
+    /// Unused textures of the same format beyond this are destroyed instead of kept
+    pub fn set_max_per_format(&mut self, max_per_format: usize) {
+        self.free.lock().unwrap().max_per_format = max_per_format;
+    }
+
+    /// A 2D texture with a single mip level and sample, returned to the pool when dropped.
+    /// Its content is whatever the previous user left
+    pub fn acquire(
+        &self,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+    ) -> PooledTexture {
+        let key = PoolKey {
+            width,
+            height,
+            format,
+            usage,
+        };
+
+        let reused = self
+            .free
+            .lock()
+            .unwrap()
+            .entries
+            .get_mut(&key)
+            .and_then(Vec::pop);
+
+        let entry = reused.unwrap_or_else(|| {
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("My pooled texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+            PoolEntry { texture, view }
+        });
+
+        PooledTexture {
+            key,
+            entry: Some(entry),
+            free: Arc::clone(&self.free),
+        }
+    }
+
+    /// Destroys all the unused textures, e.g. after a resize made their size useless
+    pub fn clear(&self) {
+        self.free.lock().unwrap().entries.clear();
+    }
+}
+
+/// A texture of a `TexturePool`, going back to it when dropped
+pub struct PooledTexture {
+    key: PoolKey,
+    // Only None while being dropped
+    entry: Option<PoolEntry>,
+    free: Arc<Mutex<FreeTextures>>,
+}
+
+impl PooledTexture {
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.entry.as_ref().unwrap().texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.entry.as_ref().unwrap().view
+    }
+}
+
+impl Drop for PooledTexture {
+    fn drop(&mut self) {
+        let mut free = self.free.lock().unwrap();
+
+        if free.count(self.key.format) < free.max_per_format {
+            let entry = self.entry.take().unwrap();
+            free.entries.entry(self.key).or_default().push(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+    const USAGE: wgpu::TextureUsages = wgpu::TextureUsages::RENDER_ATTACHMENT;
+
+    #[test]
+    fn reuses_a_dropped_texture_of_the_same_request() {
+        let Some((device, _)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let pool = TexturePool::new(&device);
+
+        let first = pool.acquire(16, 16, FORMAT, USAGE);
+        let id = first.texture().global_id();
+        drop(first);
+
+        let again = pool.acquire(16, 16, FORMAT, USAGE);
+        assert_eq!(again.texture().global_id(), id);
+
+        // Taken out of the pool while in use
+        let other = pool.acquire(16, 16, FORMAT, USAGE);
+        assert_ne!(other.texture().global_id(), id);
+    }
+
+    #[test]
+    fn does_not_reuse_a_texture_of_another_request() {
+        let Some((device, _)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let pool = TexturePool::new(&device);
+
+        let id = pool.acquire(16, 16, FORMAT, USAGE).texture().global_id();
+
+        let other_size = pool.acquire(32, 16, FORMAT, USAGE);
+        assert_ne!(other_size.texture().global_id(), id);
+        let other_format = pool.acquire(16, 16, wgpu::TextureFormat::Bgra8Unorm, USAGE);
+        assert_ne!(other_format.texture().global_id(), id);
+        let other_usage = pool.acquire(16, 16, FORMAT, USAGE | wgpu::TextureUsages::COPY_SRC);
+        assert_ne!(other_usage.texture().global_id(), id);
+    }
+
+    #[test]
+    fn keeps_at_most_max_per_format() {
+        let Some((device, _)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let mut pool = TexturePool::new(&device);
+        pool.set_max_per_format(1);
+
+        let small = pool.acquire(16, 16, FORMAT, USAGE);
+        let large = pool.acquire(32, 32, FORMAT, USAGE);
+        let other_format = pool.acquire(16, 16, wgpu::TextureFormat::Bgra8Unorm, USAGE);
+        drop(small);
+        drop(large);
+        drop(other_format);
+
+        let free = pool.free.lock().unwrap();
+        assert_eq!(free.count(FORMAT), 1);
+        assert_eq!(free.count(wgpu::TextureFormat::Bgra8Unorm), 1);
+    }
+
+    #[test]
+    fn clear_destroys_the_unused_textures() {
+        let Some((device, _)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let pool = TexturePool::new(&device);
+
+        let id = pool.acquire(16, 16, FORMAT, USAGE).texture().global_id();
+        pool.clear();
+
+        assert_eq!(pool.free.lock().unwrap().count(FORMAT), 0);
+        assert_ne!(
+            pool.acquire(16, 16, FORMAT, USAGE).texture().global_id(),
+            id
+        );
+    }
+}
@@ -0,0 +1,411 @@
+use wgpu::util::DeviceExt;
+
+use crate::index_buffer::IndexBuffer;
+use crate::texture_atlas::TextureAtlas;
+use crate::vertex_buffer::VertexBuffer;
+
+/// How many atlas regions the tiles can show, see the shader
+pub const MAX_TILE_IDS: usize = 256;
+
+const TILE_IDS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Uint;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TileVertex {
+    position: [f32; 2],
+    local: [f32; 2],
+    tile: [u32; 2],
+}
+
+impl TileVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Uint32x2,
+    ];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TileVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TileMapParams {
+    viewport: [f32; 2],
+    _padding: [f32; 2],
+}
+
+/// A grid of square tiles, each one showing the region of an atlas its id is the index of.
+/// The quads never change, only the ids do: they are texels of an `R16Uint` texture
+/// the fragment shader reads, so changing a tile uploads 2 bytes.
+/// The map is drawn from the top left corner of the target, in its pixels
+pub struct TileMap {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    vertex_buffer: VertexBuffer,
+    index_buffer: IndexBuffer,
+    tile_ids_texture: wgpu::Texture,
+    tiles: Vec<u16>,
+    tiles_x: u32,
+    tiles_y: u32,
+    viewport: [f32; 2],
+}
+
+impl TileMap {
+    /// Every tile starts with the id 0. Only the first `MAX_TILE_IDS` atlas regions can be shown.
+    /// `tile_size` is in pixels of the target, whose size `resize` sets before the first draw
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        tiles_x: u32,
+        tiles_y: u32,
+        tile_size: u32,
+        atlas: &TextureAtlas,
+    ) -> TileMap {
+        assert!(
+            tiles_x > 0 && tiles_y > 0,
+            "the map has no tiles: {}x{}",
+            tiles_x,
+            tiles_y
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My tile map shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("tile_map.wgsl").into()),
+        });
+
+        let (vertices, indices) = tile_quads(tiles_x, tiles_y, tile_size as f32);
+        let vertex_buffer = VertexBuffer::new(device, "My tile map vertex buffer", &vertices);
+        let index_buffer = IndexBuffer::new(device, &indices, vertices.len() as u32)
+            .expect("the tile quads index their own vertices");
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My tile map params buffer"),
+            size: std::mem::size_of::<TileMapParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut uv_rects = [[0.; 4]; MAX_TILE_IDS];
+        for (rect, region) in uv_rects.iter_mut().zip(atlas.regions()) {
+            *rect = [
+                region.offset[0],
+                region.offset[1],
+                region.scale[0],
+                region.scale[1],
+            ];
+        }
+        let uv_rects_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My tile map UV rects buffer"),
+            contents: bytemuck::cast_slice(&uv_rects),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        // wgpu zeroes new textures, which is the initial id of every tile
+        let tile_ids_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("My tile map ids texture"),
+            size: wgpu::Extent3d {
+                width: tiles_x,
+                height: tiles_y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TILE_IDS_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let tile_ids_view = tile_ids_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Nearest, so neighboring regions don't bleed into the tiles
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("My tile map sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let uniform_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let texture_entry = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My tile map bind group layout"),
+            entries: &[
+                uniform_entry(0, wgpu::ShaderStages::VERTEX),
+                uniform_entry(1, wgpu::ShaderStages::FRAGMENT),
+                texture_entry(2, wgpu::TextureSampleType::Uint),
+                texture_entry(3, wgpu::TextureSampleType::Float { filterable: true }),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My tile map bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uv_rects_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&tile_ids_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&atlas.texture().view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My tile map pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My tile map pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[TileVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        TileMap {
+            pipeline,
+            bind_group,
+            params_buffer,
+            vertex_buffer,
+            index_buffer,
+            tile_ids_texture,
+            tiles: vec![0; (tiles_x * tiles_y) as usize],
+            tiles_x,
+            tiles_y,
+            viewport: [1., 1.],
+        }
+    }
+
+    pub fn tiles_x(&self) -> u32 {
+        self.tiles_x
+    }
+
+    pub fn tiles_y(&self) -> u32 {
+        self.tiles_y
+    }
+
+    /// None outside of the map
+    pub fn tile(&self, x: u32, y: u32) -> Option<u16> {
+        (x < self.tiles_x && y < self.tiles_y).then(|| self.tiles[(y * self.tiles_x + x) as usize])
+    }
+
+    /// `id` is the index of the atlas region shown. The tile changes from the next submit
+    pub fn set_tile(&mut self, queue: &wgpu::Queue, x: u32, y: u32, id: u16) {
+        assert!(
+            x < self.tiles_x && y < self.tiles_y,
+            "the tile ({}, {}) is outside of the {}x{} map",
+            x,
+            y,
+            self.tiles_x,
+            self.tiles_y
+        );
+
+        self.tiles[(y * self.tiles_x + x) as usize] = id;
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.tile_ids_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::bytes_of(&id),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: None,
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// The size of the target, for the tile size to be in its pixels
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = [width.max(1) as f32, height.max(1) as f32];
+    }
+
+    /// Draws the map over `view`
+    pub fn draw(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::bytes_of(&TileMapParams {
+                viewport: self.viewport,
+                _padding: [0.; 2],
+            }),
+        );
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My tile map pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice());
+        self.index_buffer.set(&mut render_pass);
+        render_pass.draw_indexed(0..self.index_buffer.indices_count(), 0, 0..1);
+    }
+}
+
+// A quad per tile, so each one knows which tile it is without any division in the shader
+fn tile_quads(tiles_x: u32, tiles_y: u32, tile_size: f32) -> (Vec<TileVertex>, Vec<u32>) {
+    let mut vertices = Vec::with_capacity((tiles_x * tiles_y * 4) as usize);
+    let mut indices = Vec::with_capacity((tiles_x * tiles_y * 6) as usize);
+
+    for y in 0..tiles_y {
+        for x in 0..tiles_x {
+            let first = vertices.len() as u32;
+
+            for local in [[0., 0.], [1., 0.], [1., 1.], [0., 1.]] {
+                vertices.push(TileVertex {
+                    position: [
+                        (x as f32 + local[0]) * tile_size,
+                        (y as f32 + local[1]) * tile_size,
+                    ],
+                    local,
+                    tile: [x, y],
+                });
+            }
+
+            indices.extend([0, 1, 2, 0, 2, 3].map(|i| first + i));
+        }
+    }
+
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{texel, SIZE};
+
+    #[test]
+    fn shows_the_region_of_every_tile() {
+        let Some((device, queue)) = crate::tests::device() else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+
+        let (red, green) = ([255, 0, 0, 255], [0, 255, 0, 255]);
+        let atlas = TextureAtlas::from_grid(
+            crate::tests::texture_of(&device, &queue, 2, &[red, green]),
+            2,
+            1,
+        );
+        let target = crate::render_target::RenderTarget::new(
+            &device,
+            SIZE,
+            SIZE,
+            wgpu::TextureFormat::Rgba8Unorm,
+            crate::depth::DEPTH_FORMAT,
+        );
+
+        // 2x2 tiles over the top left quarter of the target
+        let mut tile_map = TileMap::new(&device, target.format(), 2, 2, SIZE / 4, &atlas);
+        tile_map.resize(SIZE, SIZE);
+        tile_map.set_tile(&queue, 1, 0, 1);
+        assert_eq!(tile_map.tile(1, 0), Some(1));
+        assert_eq!(tile_map.tile(2, 0), None);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("My test encoder"),
+        });
+        tile_map.draw(&queue, &mut encoder, target.view());
+        queue.submit([encoder.finish()]);
+        let texels = pollster::block_on(crate::texture::readback(
+            &device,
+            &queue,
+            target.texture(),
+            0,
+            0,
+        ));
+
+        let tile_center = |x: u32, y: u32| [x * SIZE / 4 + SIZE / 8, y * SIZE / 4 + SIZE / 8];
+        assert_eq!(texel(&texels, tile_center(0, 0)), red);
+        assert_eq!(texel(&texels, tile_center(1, 0)), green);
+        assert_eq!(texel(&texels, tile_center(1, 1)), red);
+        // Past the map, the target is left as it was
+        assert_eq!(texel(&texels, tile_center(2, 2)), [0; 4]);
+    }
+}
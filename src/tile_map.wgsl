@@ -0,0 +1,45 @@
+// A grid of tiles, each one showing the atlas region its id points to
+
+struct TileMapParams {
+    // In pixels, the map is drawn from the top left corner
+    viewport: vec2<f32>,
+}
+
+struct TileVertex {
+    // In the map's pixels, going down
+    @location(0) position: vec2<f32>,
+    // From 0 to 1 across the tile
+    @location(1) local: vec2<f32>,
+    @location(2) tile: vec2<u32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) tile: vec2<u32>,
+}
+
+@group(0) @binding(0) var<uniform> params: TileMapParams;
+// xy - offset, zw - scale, see `UvRect`
+@group(0) @binding(1) var<uniform> uv_rects: array<vec4<f32>, 256>;
+@group(0) @binding(2) var tile_ids: texture_2d<u32>;
+@group(0) @binding(3) var atlas_texture: texture_2d<f32>;
+@group(0) @binding(4) var atlas_sampler: sampler;
+
+@vertex fn vs_main(vertex: TileVertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let clip = vertex.position / params.viewport * 2. - 1.;
+    out.clip_position = vec4<f32>(clip.x, -clip.y, 0., 1.);
+    out.local = vertex.local;
+    out.tile = vertex.tile;
+
+    return out;
+}
+
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let id = textureLoad(tile_ids, in.tile, 0).r;
+    let rect = uv_rects[min(id, 255u)];
+
+    return textureSample(atlas_texture, atlas_sampler, rect.xy + in.local * rect.zw);
+}
@@ -0,0 +1,234 @@
+/// How much of the previous frame fades away every frame, unless set otherwise
+pub const DEFAULT_FADE: f32 = 0.1;
+
+/// Keeps the previous frames on screen, fading away, instead of clearing them.
+/// The frame is rendered into one of two textures while the other one holds the previous frame,
+/// then the textures switch roles
+pub struct TrailsPass {
+    // The frame being rendered is `frame_views[current]`
+    frame_views: [wgpu::TextureView; 2],
+    // `frame_bind_groups[i]` reads `frame_views[i]`
+    frame_bind_groups: [wgpu::BindGroup; 2],
+    current: usize,
+    bind_group_layout: wgpu::BindGroupLayout,
+    fade_pipeline: wgpu::RenderPipeline,
+    blit_pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    fade: f32,
+}
+
+impl TrailsPass {
+    /// `format` is the format of both the frame and the surface it's copied to.
+    /// `depth_format` and `sample_count` are the ones of the pass `draw_history` draws into
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> TrailsPass {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My trails shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("trails.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My trails bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My trails pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label: &str,
+                               blend: Option<wgpu::BlendState>,
+                               depth_stencil: Option<wgpu::DepthStencilState>,
+                               sample_count: u32| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        };
+
+        // previous * constant + clear color * (1 - constant), the constant being 1 - fade
+        let fade_component = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::OneMinusConstant,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let fade_pipeline = create_pipeline(
+            "My trails fade pipeline",
+            Some(wgpu::BlendState {
+                color: fade_component,
+                alpha: fade_component,
+            }),
+            // Behind everything drawn afterwards, whatever the depth test is
+            Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            sample_count,
+        );
+        let blit_pipeline = create_pipeline("My trails blit pipeline", None, None, 1);
+
+        let (frame_views, frame_bind_groups) =
+            create_frames(device, &bind_group_layout, width, height, format);
+
+        TrailsPass {
+            frame_views,
+            frame_bind_groups,
+            current: 0,
+            bind_group_layout,
+            fade_pipeline,
+            blit_pipeline,
+            format,
+            fade: DEFAULT_FADE,
+        }
+    }
+
+    /// The history is lost
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        (self.frame_views, self.frame_bind_groups) =
+            create_frames(device, &self.bind_group_layout, width, height, self.format);
+    }
+
+    pub fn fade(&self) -> f32 {
+        self.fade
+    }
+
+    /// 0 keeps the previous frames forever, 1 is the same as clearing every frame
+    pub fn set_fade(&mut self, fade: f32) {
+        self.fade = fade.clamp(0., 1.);
+    }
+
+    /// Where the frame is rendered, instead of the surface
+    pub fn frame_view(&self) -> &wgpu::TextureView {
+        &self.frame_views[self.current]
+    }
+
+    /// Draws the faded previous frame. Must come first in a pass targeting `frame_view`,
+    /// which is cleared to the color the history fades to
+    pub fn draw_history<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>) {
+        let history = 1. - self.fade as f64;
+
+        render_pass.set_pipeline(&self.fade_pipeline);
+        render_pass.set_bind_group(0, &self.frame_bind_groups[1 - self.current], &[]);
+        render_pass.set_blend_constant(wgpu::Color {
+            r: history,
+            g: history,
+            b: history,
+            a: history,
+        });
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Copies the finished frame to `surface_view`. It becomes the history of the next one
+    pub fn present(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        surface_view: &wgpu::TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My trails blit pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: surface_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.blit_pipeline);
+        render_pass.set_bind_group(0, &self.frame_bind_groups[self.current], &[]);
+        render_pass.draw(0..3, 0..1);
+
+        drop(render_pass);
+
+        self.current = 1 - self.current;
+    }
+}
+
+fn create_frames(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> ([wgpu::TextureView; 2], [wgpu::BindGroup; 2]) {
+    let create_view = || {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("My trails frame texture"),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    };
+    let views = [create_view(), create_view()];
+
+    let create_bind_group = |view: &wgpu::TextureView| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My trails bind group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            }],
+        })
+    };
+    let bind_groups = [create_bind_group(&views[0]), create_bind_group(&views[1])];
+
+    (views, bind_groups)
+}
@@ -0,0 +1,24 @@
+// Full-screen passes of the trails: fading the previous frame in, and copying the result to the surface
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// A single triangle covering the whole screen
+@vertex fn vs_main(
+    @builtin(vertex_index) vertex_index: u32
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2. - 1., 0., 1.);
+
+    return out;
+}
+
+@group(0) @binding(0) var frame_texture: texture_2d<f32>;
+
+// The fading itself is done by blending with the blend constant
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(frame_texture, vec2<i32>(in.clip_position.xy), 0);
+}
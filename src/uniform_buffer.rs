@@ -0,0 +1,58 @@
+use wgpu::util::DeviceExt;
+
+// The default `min_uniform_buffer_offset_alignment`. Buffers sized after it can be bound at dynamic offsets
+const UNIFORM_ALIGNMENT: wgpu::BufferAddress = 256;
+
+/// A uniform holding a single `T`, with a copy of the value on the CPU
+pub struct UniformBuffer<T: bytemuck::Pod> {
+    buffer: wgpu::Buffer,
+    value: T,
+}
+
+impl<T: bytemuck::Pod> UniformBuffer<T> {
+    pub fn new(device: &wgpu::Device, label: &str, initial: T) -> UniformBuffer<T> {
+        let size = (std::mem::size_of::<T>() as wgpu::BufferAddress)
+            .max(1)
+            .next_multiple_of(UNIFORM_ALIGNMENT);
+
+        let mut contents = vec![0; size as usize];
+        contents[..std::mem::size_of::<T>()].copy_from_slice(bytemuck::bytes_of(&initial));
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: &contents,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        UniformBuffer {
+            buffer,
+            value: initial,
+        }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Only on the CPU until `upload`
+    pub fn set(&mut self, value: T) {
+        self.value = value;
+    }
+
+    pub fn upload(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.value));
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Just the value, not the padding
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: wgpu::BufferSize::new(std::mem::size_of::<T>() as wgpu::BufferAddress),
+        })
+    }
+}
@@ -0,0 +1,185 @@
+/// Renders at a fixed resolution whatever the window size, for crisp pixel art.
+/// The frame is rendered into an offscreen texture, then scaled by a whole factor
+/// with nearest filtering and centered on the surface, the rest being black bars
+pub struct UpscalePass {
+    width: u32,
+    height: u32,
+    frame_view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl UpscalePass {
+    /// `format` is the format of both the frame and the surface it's scaled to
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> UpscalePass {
+        let (width, height) = (width.max(1), height.max(1));
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My upscale shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("upscale.wgsl").into()),
+        });
+
+        let frame_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("My low resolution frame texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Nearest keeps the pixels square instead of blurring them
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("My upscale sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("My upscale bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My upscale bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&frame_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My upscale pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My upscale pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        UpscalePass {
+            width,
+            height,
+            frame_view,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// The resolution everything is rendered at
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Where the frame is rendered, instead of the surface
+    pub fn frame_view(&self) -> &wgpu::TextureView {
+        &self.frame_view
+    }
+
+    /// The area of the surface the frame covers: x, y, width and height.
+    /// A surface smaller than the frame gets it shrunk, as no whole factor fits
+    pub fn viewport(&self, surface_width: u32, surface_height: u32) -> (f32, f32, f32, f32) {
+        let scale = (surface_width as f32 / self.width as f32)
+            .min(surface_height as f32 / self.height as f32);
+        let scale = if scale >= 1. { scale.floor() } else { scale };
+
+        let (width, height) = (self.width as f32 * scale, self.height as f32 * scale);
+
+        (
+            ((surface_width as f32 - width) / 2.).floor(),
+            ((surface_height as f32 - height) / 2.).floor(),
+            width,
+            height,
+        )
+    }
+
+    /// Scales the finished frame onto `surface_view`, clearing the bars around it
+    pub fn present(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        surface_view: &wgpu::TextureView,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My upscale pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: surface_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        let (x, y, width, height) = self.viewport(surface_width, surface_height);
+
+        render_pass.set_viewport(x, y, width, height, 0., 1.);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,71 @@
+use wgpu::util::DeviceExt;
+
+/// A buffer of vertices remembering how many there are, so draw calls can't get the count wrong
+pub struct VertexBuffer {
+    buffer: wgpu::Buffer,
+    vertex_count: u32,
+    stride: u32,
+}
+
+impl VertexBuffer {
+    pub fn new<V: bytemuck::Pod>(device: &wgpu::Device, label: &str, data: &[V]) -> VertexBuffer {
+        let stride = std::mem::size_of::<V>() as u32;
+
+        VertexBuffer::from_bytes(device, label, bytemuck::cast_slice(data), stride)
+    }
+
+    /// For vertices not described by a Rust struct. `data` must be a whole number of vertices
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        label: &str,
+        data: &[u8],
+        stride: u32,
+    ) -> VertexBuffer {
+        assert!(
+            stride > 0 && data.len().is_multiple_of(stride as usize),
+            "the data isn't a whole number of {} byte vertices",
+            stride
+        );
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: data,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        VertexBuffer {
+            buffer,
+            vertex_count: (data.len() / stride as usize) as u32,
+            stride,
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    /// The size of a vertex in bytes
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// All the vertices, for `set_vertex_buffer`
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..)
+    }
+
+    /// Replaces the vertices, e.g. to animate them. The count can't change
+    pub fn write(&self, queue: &wgpu::Queue, data: &[u8]) {
+        assert_eq!(
+            data.len() as u64,
+            self.buffer.size(),
+            "the vertex data doesn't match the buffer size"
+        );
+
+        queue.write_buffer(&self.buffer, 0, data);
+    }
+}
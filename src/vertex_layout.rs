@@ -0,0 +1,186 @@
+/// What a vertex attribute means. Also its name in the generated WGSL
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VertexAttributeKind {
+    Position,
+    Normal,
+    Uv,
+    Color,
+    Tangent,
+}
+
+impl VertexAttributeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            VertexAttributeKind::Position => "position",
+            VertexAttributeKind::Normal => "normal",
+            VertexAttributeKind::Uv => "uv",
+            VertexAttributeKind::Color => "color",
+            VertexAttributeKind::Tangent => "tangent",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VertexLayoutError {
+    MissingPosition,
+    DuplicateAttribute(VertexAttributeKind),
+    /// The format can't be a WGSL vertex input
+    UnsupportedFormat(wgpu::VertexFormat),
+    /// The mesh data isn't a whole number of vertices
+    MismatchedData {
+        data_size: usize,
+        vertex_size: u64,
+    },
+}
+
+impl std::fmt::Display for VertexLayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VertexLayoutError::MissingPosition => write!(f, "the layout has no position"),
+            VertexLayoutError::DuplicateAttribute(kind) => {
+                write!(f, "the layout declares the {} twice", kind.name())
+            }
+            VertexLayoutError::UnsupportedFormat(format) => {
+                write!(f, "{:?} can't be used as a shader input", format)
+            }
+            VertexLayoutError::MismatchedData {
+                data_size,
+                vertex_size,
+            } => write!(
+                f,
+                "{} bytes of mesh data aren't a multiple of the {} byte vertex",
+                data_size, vertex_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VertexLayoutError {}
+
+/// Declares which attributes a mesh's vertices have. The attributes are packed in the declaration
+/// order with just the padding wgpu requires, and get the shader locations in the same order
+#[derive(Clone, Debug)]
+pub struct VertexLayout {
+    kinds: Vec<VertexAttributeKind>,
+    attributes: Vec<wgpu::VertexAttribute>,
+    stride: wgpu::BufferAddress,
+}
+
+impl VertexLayout {
+    pub fn new(
+        attributes: &[(VertexAttributeKind, wgpu::VertexFormat)],
+    ) -> Result<VertexLayout, VertexLayoutError> {
+        let mut layout = VertexLayout {
+            kinds: Vec::with_capacity(attributes.len()),
+            attributes: Vec::with_capacity(attributes.len()),
+            stride: 0,
+        };
+
+        for &(kind, format) in attributes {
+            if layout.kinds.contains(&kind) {
+                return Err(VertexLayoutError::DuplicateAttribute(kind));
+            }
+            wgsl_type(format)?;
+
+            // wgpu wants the offsets aligned to the format size, up to 4 bytes
+            let offset = layout.stride.next_multiple_of(format.size().min(4));
+
+            layout.attributes.push(wgpu::VertexAttribute {
+                offset,
+                shader_location: layout.kinds.len() as u32,
+                format,
+            });
+            layout.kinds.push(kind);
+            layout.stride = offset + format.size();
+        }
+
+        layout.stride = layout
+            .stride
+            .next_multiple_of(wgpu::VERTEX_STRIDE_ALIGNMENT);
+
+        if !layout.kinds.contains(&VertexAttributeKind::Position) {
+            return Err(VertexLayoutError::MissingPosition);
+        }
+
+        Ok(layout)
+    }
+
+    pub fn stride(&self) -> wgpu::BufferAddress {
+        self.stride
+    }
+
+    pub fn has(&self, kind: VertexAttributeKind) -> bool {
+        self.kinds.contains(&kind)
+    }
+
+    pub fn attribute(&self, kind: VertexAttributeKind) -> Option<wgpu::VertexAttribute> {
+        self.kinds
+            .iter()
+            .position(|&other| other == kind)
+            .map(|index| self.attributes[index])
+    }
+
+    pub fn buffer_layout(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.stride,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &self.attributes,
+        }
+    }
+
+    /// A WGSL struct matching the layout, e.g. to prepend to a shader taking it as the vertex input
+    pub fn wgsl_input_struct(&self, name: &str) -> String {
+        let mut wgsl = format!("struct {} {{\n", name);
+
+        for (kind, attribute) in self.kinds.iter().zip(&self.attributes) {
+            wgsl.push_str(&format!(
+                "    @location({}) {}: {},\n",
+                attribute.shader_location,
+                kind.name(),
+                // Checked in `new`
+                wgsl_type(attribute.format).unwrap_or_default()
+            ));
+        }
+
+        wgsl.push_str("}\n");
+
+        wgsl
+    }
+
+    /// Checks that the mesh data is made of whole vertices of this layout. Returns the vertex count
+    pub fn validate(&self, vertex_data: &[u8]) -> Result<u32, VertexLayoutError> {
+        if !(vertex_data.len() as u64).is_multiple_of(self.stride) {
+            return Err(VertexLayoutError::MismatchedData {
+                data_size: vertex_data.len(),
+                vertex_size: self.stride,
+            });
+        }
+
+        Ok((vertex_data.len() as u64 / self.stride) as u32)
+    }
+}
+
+// Normalized formats are read as floats
+fn wgsl_type(format: wgpu::VertexFormat) -> Result<&'static str, VertexLayoutError> {
+    use wgpu::VertexFormat as F;
+
+    Ok(match format {
+        F::Float32 => "f32",
+        F::Uint32 => "u32",
+        F::Sint32 => "i32",
+        F::Float32x2 | F::Float16x2 | F::Unorm8x2 | F::Snorm8x2 | F::Unorm16x2 | F::Snorm16x2 => {
+            "vec2<f32>"
+        }
+        F::Float32x3 => "vec3<f32>",
+        F::Float32x4 | F::Float16x4 | F::Unorm8x4 | F::Snorm8x4 | F::Unorm16x4 | F::Snorm16x4 => {
+            "vec4<f32>"
+        }
+        F::Uint32x2 | F::Uint8x2 | F::Uint16x2 => "vec2<u32>",
+        F::Uint32x3 => "vec3<u32>",
+        F::Uint32x4 | F::Uint8x4 | F::Uint16x4 => "vec4<u32>",
+        F::Sint32x2 | F::Sint8x2 | F::Sint16x2 => "vec2<i32>",
+        F::Sint32x3 => "vec3<i32>",
+        F::Sint32x4 | F::Sint8x4 | F::Sint16x4 => "vec4<i32>",
+        _ => return Err(VertexLayoutError::UnsupportedFormat(format)),
+    })
+}
@@ -0,0 +1,72 @@
+use crate::hud::{HudLayer, GLYPH_SIZE};
+
+/// A label added to `State`
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LabelId(pub(crate) u32);
+
+/// Text at a fixed position of the viewport, whatever the camera. Drawn in the built-in font
+/// of the HUD, so characters it doesn't have leave a gap
+#[derive(Clone, Debug)]
+pub struct ViewportLabel {
+    position: [f32; 2],
+    text: String,
+    font_size: f32,
+    color: [f32; 4],
+}
+
+impl ViewportLabel {
+    /// `position` is the top left corner of the text in pixels from the top left of the viewport,
+    /// `font_size` the height of the glyphs in pixels
+    pub fn new(position: [f32; 2], text: String, font_size: f32, color: [f32; 4]) -> ViewportLabel {
+        ViewportLabel {
+            position,
+            text,
+            font_size,
+            color,
+        }
+    }
+
+    pub fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: [f32; 2]) {
+        self.position = position;
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        text.clone_into(&mut self.text);
+    }
+
+    pub fn font_size(&self) -> f32 {
+        self.font_size
+    }
+
+    pub fn set_font_size(&mut self, font_size: f32) {
+        self.font_size = font_size;
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    /// Collected by `hud`, drawn on its next `render`
+    pub fn draw(&self, hud: &mut HudLayer) {
+        hud.text(
+            self.position[0],
+            self.position[1],
+            self.font_size / GLYPH_SIZE.1 as f32,
+            &self.text,
+            self.color,
+        );
+    }
+}
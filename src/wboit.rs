@@ -0,0 +1,295 @@
+use crate::msaa::MsaaTarget;
+
+pub const ACCUM_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+pub const REVEALAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Weighted, blended order-independent transparency.
+/// Transparent fragments are accumulated in any order, then resolved over the opaque image
+pub struct WboitPass {
+    // Sum of color * alpha * weight (rgb) and alpha * weight (a)
+    accum_view: wgpu::TextureView,
+    // Product of (1 - alpha) in the red channel
+    revealage_view: wgpu::TextureView,
+    // Rendered into and resolved into the views above, with MSAA
+    multisampled: Option<(MsaaTarget, MsaaTarget)>,
+    sample_count: u32,
+    composite_bind_group_layout: wgpu::BindGroupLayout,
+    composite_bind_group: wgpu::BindGroup,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl WboitPass {
+    /// `target_format` is the format of the image the transparency is composited onto.
+    /// `sample_count` is the one of the depth texture `begin_accumulation` tests against
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        target_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> WboitPass {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My WBOIT composite shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("wboit.wgsl").into()),
+        });
+
+        let texture_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let composite_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("My WBOIT composite bind group layout"),
+                entries: &[texture_entry(0), texture_entry(1)],
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("My WBOIT composite pipeline layout"),
+            bind_group_layouts: &[&composite_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let composite_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My WBOIT composite pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (accum_view, revealage_view) = create_views(device, width, height);
+        let multisampled = create_multisampled(device, width, height, sample_count);
+        let composite_bind_group = create_composite_bind_group(
+            device,
+            &composite_bind_group_layout,
+            &accum_view,
+            &revealage_view,
+        );
+
+        WboitPass {
+            accum_view,
+            revealage_view,
+            multisampled,
+            sample_count,
+            composite_bind_group_layout,
+            composite_bind_group,
+            composite_pipeline,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        (self.accum_view, self.revealage_view) = create_views(device, width, height);
+        self.multisampled = create_multisampled(device, width, height, self.sample_count);
+        self.composite_bind_group = create_composite_bind_group(
+            device,
+            &self.composite_bind_group_layout,
+            &self.accum_view,
+            &self.revealage_view,
+        );
+    }
+
+    /// Color targets of pipelines drawing into `begin_accumulation`'s pass
+    pub fn color_targets() -> [Option<wgpu::ColorTargetState>; 2] {
+        [
+            Some(wgpu::ColorTargetState {
+                format: ACCUM_FORMAT,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            }),
+            Some(wgpu::ColorTargetState {
+                format: REVEALAGE_FORMAT,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Zero,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Zero,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            }),
+        ]
+    }
+
+    /// Starts the pass for transparent geometry. The opaque depth is only tested, never written.
+    /// The accumulation targets have the sample count given to `new`, which `depth_view` must match
+    pub fn begin_accumulation<'p>(
+        &'p self,
+        encoder: &'p mut wgpu::CommandEncoder,
+        depth_view: &'p wgpu::TextureView,
+    ) -> wgpu::RenderPass<'p> {
+        let attachment = |view: &'p wgpu::TextureView,
+                          multisampled: Option<&'p MsaaTarget>,
+                          clear: wgpu::Color| {
+            match multisampled {
+                Some(multisampled) => {
+                    multisampled.color_attachment(view, wgpu::LoadOp::Clear(clear))
+                }
+                None => wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(clear),
+                        store: wgpu::StoreOp::Store,
+                    },
+                },
+            }
+        };
+
+        let (accum, revealage) = match &self.multisampled {
+            Some((accum, revealage)) => (Some(accum), Some(revealage)),
+            None => (None, None),
+        };
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My WBOIT accumulation pass"),
+            color_attachments: &[
+                Some(attachment(
+                    &self.accum_view,
+                    accum,
+                    wgpu::Color::TRANSPARENT,
+                )),
+                Some(attachment(
+                    &self.revealage_view,
+                    revealage,
+                    wgpu::Color::WHITE,
+                )),
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+
+    /// Blends the accumulated transparency over `target_view`
+    pub fn composite(&self, encoder: &mut wgpu::CommandEncoder, target_view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My WBOIT composite pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, &self.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_views(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+) -> (wgpu::TextureView, wgpu::TextureView) {
+    let create_view = |label: &str, format: wgpu::TextureFormat| {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    };
+
+    (
+        create_view("My WBOIT accum texture", ACCUM_FORMAT),
+        create_view("My WBOIT revealage texture", REVEALAGE_FORMAT),
+    )
+}
+
+// None without MSAA, the views being rendered into directly
+fn create_multisampled(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> Option<(MsaaTarget, MsaaTarget)> {
+    Some((
+        MsaaTarget::new(device, width, height, sample_count, ACCUM_FORMAT)?,
+        MsaaTarget::new(device, width, height, sample_count, REVEALAGE_FORMAT)?,
+    ))
+}
+
+fn create_composite_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    accum_view: &wgpu::TextureView,
+    revealage_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("My WBOIT composite bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(accum_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(revealage_view),
+            },
+        ],
+    })
+}
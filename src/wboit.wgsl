@@ -0,0 +1,36 @@
+// Full-screen composite of the weighted, blended OIT accumulation targets
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// A single triangle covering the whole screen
+@vertex fn vs_main(
+    @builtin(vertex_index) vertex_index: u32
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2. - 1., 0., 1.);
+
+    return out;
+}
+
+@group(0) @binding(0) var accum_texture: texture_2d<f32>;
+@group(0) @binding(1) var revealage_texture: texture_2d<f32>;
+
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(in.clip_position.xy);
+
+    let accum = textureLoad(accum_texture, coords, 0);
+    let revealage = textureLoad(revealage_texture, coords, 0).r;
+
+    // Nothing transparent was drawn here
+    if revealage >= 1. {
+        discard;
+    }
+
+    let average_color = accum.rgb / clamp(accum.a, 1e-4, 5e4);
+
+    return vec4<f32>(average_color, 1. - revealage);
+}
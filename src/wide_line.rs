@@ -0,0 +1,313 @@
+use wgpu::util::DeviceExt;
+
+const WORKGROUP_SIZE: u32 = 64;
+// The vertices the compute pass writes per point, see the shader
+const VERTICES_PER_POINT: u32 = 54;
+
+/// How the segments meet at the inner points
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JoinStyle {
+    /// Extends the edges until they meet. Sharp angles past the miter limit get a bevel instead
+    #[default]
+    Miter,
+    /// Cuts the corner
+    Bevel,
+    Round,
+}
+
+impl JoinStyle {
+    fn shader_value(self) -> u32 {
+        match self {
+            JoinStyle::Miter => 0,
+            JoinStyle::Bevel => 1,
+            JoinStyle::Round => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WideLineStyle {
+    /// In pixels
+    pub width: f32,
+    pub join: JoinStyle,
+    /// The longest a miter may be, in multiples of half the width
+    pub miter_limit: f32,
+    pub color: [f32; 4],
+}
+
+impl Default for WideLineStyle {
+    fn default() -> WideLineStyle {
+        WideLineStyle {
+            width: 1.,
+            join: JoinStyle::default(),
+            miter_limit: 4.,
+            color: [1., 1., 1., 1.],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct WideLineParams {
+    color: [f32; 4],
+    viewport: [f32; 2],
+    half_width: f32,
+    miter_limit: f32,
+    join_style: u32,
+    points_count: u32,
+    _padding: [u32; 2],
+}
+
+/// A polyline of any width with joins and round caps. A compute pass expands the points
+/// into triangles every frame, so the points and the style can change freely.
+/// The points are in clip space, and consecutive ones must differ
+pub struct WideLine {
+    compute_pipeline: wgpu::ComputePipeline,
+    compute_bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    render_bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    points_buffer: wgpu::Buffer,
+    vertices_buffer: wgpu::Buffer,
+    points_count: u32,
+    style: WideLineStyle,
+    viewport: [f32; 2],
+}
+
+impl WideLine {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        points: &[[f32; 2]],
+        style: WideLineStyle,
+    ) -> WideLine {
+        assert!(!points.is_empty(), "a line needs at least one point");
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("My wide line shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("wide_line.wgsl").into()),
+        });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My wide line params buffer"),
+            size: std::mem::size_of::<WideLineParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let points_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("My wide line points buffer"),
+            contents: bytemuck::cast_slice(points),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let vertices_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("My wide line vertices buffer"),
+            size: (points.len() as u32 * VERTICES_PER_POINT) as wgpu::BufferAddress
+                * std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+
+        let params_entry = |visibility| wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        // The render pipeline only reads the color, it can't see the writable vertices
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("My wide line compute bind group layout"),
+                entries: &[
+                    params_entry(wgpu::ShaderStages::COMPUTE),
+                    storage_entry(1, true),
+                    storage_entry(2, false),
+                ],
+            });
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("My wide line render bind group layout"),
+                entries: &[params_entry(wgpu::ShaderStages::FRAGMENT)],
+            });
+
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My wide line compute bind group"),
+            layout: &compute_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: points_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: vertices_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("My wide line render bind group"),
+            layout: &render_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+        });
+
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("My wide line compute pipeline layout"),
+                bind_group_layouts: &[&compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("My wide line compute pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("My wide line render pipeline layout"),
+                bind_group_layouts: &[&render_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("My wide line render pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // The triangles are wound either way
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        WideLine {
+            compute_pipeline,
+            compute_bind_group,
+            render_pipeline,
+            render_bind_group,
+            params_buffer,
+            points_buffer,
+            vertices_buffer,
+            points_count: points.len() as u32,
+            style,
+            viewport: [width.max(1) as f32, height.max(1) as f32],
+        }
+    }
+
+    pub fn style(&self) -> WideLineStyle {
+        self.style
+    }
+
+    pub fn set_style(&mut self, style: WideLineStyle) {
+        self.style = style;
+    }
+
+    /// The size of the target, for the width to be in its pixels
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = [width.max(1) as f32, height.max(1) as f32];
+    }
+
+    /// As many points as the line was created with
+    pub fn write_points(&self, queue: &wgpu::Queue, points: &[[f32; 2]]) {
+        assert_eq!(
+            points.len(),
+            self.points_count as usize,
+            "the line has {} points",
+            self.points_count
+        );
+
+        queue.write_buffer(&self.points_buffer, 0, bytemuck::cast_slice(points));
+    }
+
+    /// Expands the line, then draws it over `view`
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::bytes_of(&WideLineParams {
+                color: self.style.color,
+                viewport: self.viewport,
+                half_width: self.style.width / 2.,
+                miter_limit: self.style.miter_limit,
+                join_style: self.style.join.shader_value(),
+                points_count: self.points_count,
+                _padding: [0; 2],
+            }),
+        );
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("My wide line expansion pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.points_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        drop(compute_pass);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("My wide line pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.render_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertices_buffer.slice(..));
+        render_pass.draw(0..self.points_count * VERTICES_PER_POINT, 0..1);
+    }
+}
@@ -0,0 +1,134 @@
+// Thick polylines: a compute pass expands the points into triangles, then they're drawn as a triangle list
+
+struct WideLineParams {
+    color: vec4<f32>,
+    // In pixels, so the width is the same in both directions
+    viewport: vec2<f32>,
+    half_width: f32,
+    miter_limit: f32,
+    join_style: u32,
+    points_count: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: WideLineParams;
+@group(0) @binding(1) var<storage, read> points: array<vec2<f32>>;
+@group(0) @binding(2) var<storage, read_write> vertices: array<vec2<f32>>;
+
+const JOIN_MITER: u32 = 0u;
+const JOIN_BEVEL: u32 = 1u;
+const JOIN_ROUND: u32 = 2u;
+
+const ROUND_SEGMENTS: u32 = 16u;
+// The segment starting at the point, then its join or cap. Unused vertices stay degenerate
+const VERTICES_PER_POINT: u32 = 54u;
+
+const TAU: f32 = 6.2831853;
+
+// Clip space to pixels from the center, and back
+fn point(i: u32) -> vec2<f32> {
+    return points[i] * params.viewport * 0.5;
+}
+
+fn to_clip(pixels: vec2<f32>) -> vec2<f32> {
+    return pixels / (params.viewport * 0.5);
+}
+
+// The left side of the segment
+fn normal(start: vec2<f32>, end: vec2<f32>) -> vec2<f32> {
+    let direction = normalize(end - start);
+    return vec2<f32>(-direction.y, direction.x);
+}
+
+// How much longer than the half width the miter at inner point j is. 0 when it isn't mitered:
+// with another join style, a U-turn, or past the miter limit, which falls back to a bevel
+fn miter_scale(j: u32) -> f32 {
+    if params.join_style != JOIN_MITER || j == 0u || j + 1u >= params.points_count {
+        return 0.;
+    }
+
+    let sum = normal(point(j - 1u), point(j)) + normal(point(j), point(j + 1u));
+    if length(sum) < 1e-3 {
+        return 0.;
+    }
+
+    let scale = 1. / dot(normalize(sum), normal(point(j), point(j + 1u)));
+    if scale > params.miter_limit {
+        return 0.;
+    }
+
+    return scale;
+}
+
+// The corner of a segment at point j. Mitered corners are shared with the adjacent segment
+fn corner_offset(j: u32, own_normal: vec2<f32>) -> vec2<f32> {
+    let scale = miter_scale(j);
+    if scale == 0. {
+        return own_normal * params.half_width;
+    }
+
+    let sum = normal(point(j - 1u), point(j)) + normal(point(j), point(j + 1u));
+    return normalize(sum) * params.half_width * scale;
+}
+
+@compute @workgroup_size(64) fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.points_count {
+        return;
+    }
+
+    let base = i * VERTICES_PER_POINT;
+    for (var k = 0u; k < VERTICES_PER_POINT; k++) {
+        vertices[base + k] = vec2<f32>(0.);
+    }
+
+    var cursor = base;
+    let p = point(i);
+
+    if i + 1u < params.points_count {
+        let q = point(i + 1u);
+        let own_normal = normal(p, q);
+        let start = corner_offset(i, own_normal);
+        let end = corner_offset(i + 1u, own_normal);
+
+        var quad = array<vec2<f32>, 6>(p + start, p - start, q + end, p - start, q - end, q + end);
+        for (var k = 0u; k < 6u; k++) {
+            vertices[cursor] = to_clip(quad[k]);
+            cursor++;
+        }
+    } else {
+        cursor += 6u;
+    }
+
+    let is_end = i == 0u || i + 1u == params.points_count;
+
+    // A disc makes both the round caps and the round joins
+    if is_end || params.join_style == JOIN_ROUND {
+        for (var s = 0u; s < ROUND_SEGMENTS; s++) {
+            let a0 = f32(s) / f32(ROUND_SEGMENTS) * TAU;
+            let a1 = f32(s + 1u) / f32(ROUND_SEGMENTS) * TAU;
+
+            vertices[cursor] = to_clip(p);
+            vertices[cursor + 1u] = to_clip(p + vec2<f32>(cos(a0), sin(a0)) * params.half_width);
+            vertices[cursor + 2u] = to_clip(p + vec2<f32>(cos(a1), sin(a1)) * params.half_width);
+            cursor += 3u;
+        }
+    } else if miter_scale(i) == 0. {
+        // Bevel: fills the wedge between the segments' corners, on whichever side it opens
+        let previous = normal(point(i - 1u), p) * params.half_width;
+        let next = normal(p, point(i + 1u)) * params.half_width;
+
+        var wedges = array<vec2<f32>, 6>(p, p + previous, p + next, p, p - previous, p - next);
+        for (var k = 0u; k < 6u; k++) {
+            vertices[cursor] = to_clip(wedges[k]);
+            cursor++;
+        }
+    }
+}
+
+@vertex fn vs_main(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(position, 0., 1.);
+}
+
+@fragment fn fs_main() -> @location(0) vec4<f32> {
+    return params.color;
+}